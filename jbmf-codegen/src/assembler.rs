@@ -0,0 +1,256 @@
+use std::collections::{HashMap, HashSet};
+
+use jbmf_error::CodegenError;
+use jbmf_parser::java_rs_pacific::attribute::Instruction;
+use jbmf_parser::java_rs_pacific::instruction_list::{InstructionList, JumpKind, Node};
+
+/// The conditional branch instructions
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BranchCondition {
+    IfEq,
+    IfNe,
+    IfLt,
+    IfGe,
+    IfGt,
+    IfLe,
+    IfICmpEq,
+    IfICmpNe,
+    IfICmpLt,
+    IfICmpGe,
+    IfICmpGt,
+    IfICmpLe,
+    IfACmpEq,
+    IfACmpNe,
+    IfNull,
+    IfNonNull,
+}
+
+impl BranchCondition {
+    pub fn negate(self) -> Self {
+        use BranchCondition::*;
+
+        match self {
+            IfEq => IfNe,
+            IfNe => IfEq,
+            IfLt => IfGe,
+            IfGe => IfLt,
+            IfGt => IfLe,
+            IfLe => IfGt,
+            IfICmpEq => IfICmpNe,
+            IfICmpNe => IfICmpEq,
+            IfICmpLt => IfICmpGe,
+            IfICmpGe => IfICmpLt,
+            IfICmpGt => IfICmpLe,
+            IfICmpLe => IfICmpGt,
+            IfACmpEq => IfACmpNe,
+            IfACmpNe => IfACmpEq,
+            IfNull => IfNonNull,
+            IfNonNull => IfNull,
+        }
+    }
+
+    /// Number of operand stack words the branch consumes
+    pub fn operands(self) -> u16 {
+        use BranchCondition::*;
+
+        match self {
+            IfEq | IfNe | IfLt | IfGe | IfGt | IfLe | IfNull | IfNonNull => 1,
+            _ => 2,
+        }
+    }
+
+    fn jump_kind(self) -> JumpKind {
+        match self {
+            BranchCondition::IfEq => JumpKind::IfEq,
+            BranchCondition::IfNe => JumpKind::IfNe,
+            BranchCondition::IfLt => JumpKind::IfLt,
+            BranchCondition::IfGe => JumpKind::IfGe,
+            BranchCondition::IfGt => JumpKind::IfGt,
+            BranchCondition::IfLe => JumpKind::IfLe,
+            BranchCondition::IfICmpEq => JumpKind::IfICmpEq,
+            BranchCondition::IfICmpNe => JumpKind::IfICmpNe,
+            BranchCondition::IfICmpLt => JumpKind::IfICmpLt,
+            BranchCondition::IfICmpGe => JumpKind::IfICmpGe,
+            BranchCondition::IfICmpGt => JumpKind::IfICmpGt,
+            BranchCondition::IfICmpLe => JumpKind::IfICmpLe,
+            BranchCondition::IfACmpEq => JumpKind::IfACmpEq,
+            BranchCondition::IfACmpNe => JumpKind::IfACmpNe,
+            BranchCondition::IfNull => JumpKind::IfNull,
+            BranchCondition::IfNonNull => JumpKind::IfNonNull,
+        }
+    }
+}
+
+/// An instruction whose branch offsets are not known yet. Branch targets are labels, which are the
+/// start positions of the blocks in the original code
+#[derive(Clone, Debug, PartialEq)]
+pub enum Item {
+    Label(u64),
+    Instruction(Instruction),
+    Goto(u64),
+    JumpSubroutine(u64),
+    Branch(BranchCondition, u64),
+    Switch { cases: Vec<(i32, u64)>, default: u64 },
+}
+
+pub struct Assembled {
+    pub instructions: Vec<Instruction>,
+    /// Byte position of every label
+    pub labels: HashMap<u64, u64>,
+    pub length: u64,
+}
+
+/// Lays out the items and resolves their branch offsets by lowering them as an instruction list, which
+/// widens far branches: `goto` and `jsr` become `goto_w` and `jsr_w`, a conditional branch is inverted to
+/// skip over a `goto_w` to its target
+pub fn assemble(items: &[Item]) -> Result<Assembled, CodegenError> {
+    let mut list = InstructionList::new();
+    let mut labels = HashMap::new();
+    let mut label = |list: &mut InstructionList, target: u64| {
+        *labels.entry(target).or_insert_with(|| list.new_label())
+    };
+
+    let placed: HashSet<u64> = items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Label(label) => Some(*label),
+            _ => None,
+        })
+        .collect();
+    for item in items {
+        let targets: Vec<u64> = match item {
+            Item::Goto(target) | Item::JumpSubroutine(target) | Item::Branch(_, target) => vec![*target],
+            Item::Switch { cases, default } => cases.iter().map(|(_, target)| *target).chain([*default]).collect(),
+            _ => continue,
+        };
+
+        if let Some(target) = targets.iter().find(|target| !placed.contains(target)) {
+            return Err(CodegenError::UndefinedLabel(*target));
+        }
+    }
+
+    for item in items {
+        let node = match item {
+            Item::Label(target) => Node::Label(label(&mut list, *target)),
+            Item::Instruction(instruction) => Node::Instruction(instruction.clone()),
+            Item::Goto(target) => Node::Jump {
+                kind: JumpKind::Goto,
+                target: label(&mut list, *target),
+            },
+            Item::JumpSubroutine(target) => Node::Jump {
+                kind: JumpKind::Jsr,
+                target: label(&mut list, *target),
+            },
+            Item::Branch(condition, target) => Node::Jump {
+                kind: condition.jump_kind(),
+                target: label(&mut list, *target),
+            },
+            Item::Switch { cases, default } if use_table_switch(cases) => {
+                let (low, high) = key_range(cases);
+                let targets = (low..=high)
+                    .map(|key| {
+                        let target = cases
+                            .iter()
+                            .find(|(case, _)| *case == key)
+                            .map(|(_, target)| target)
+                            .unwrap_or(default);
+                        label(&mut list, *target)
+                    })
+                    .collect();
+
+                Node::TableSwitch {
+                    default: label(&mut list, *default),
+                    low,
+                    targets,
+                }
+            }
+            Item::Switch { cases, default } => Node::LookUpSwitch {
+                default: label(&mut list, *default),
+                pairs: cases.iter().map(|(key, target)| (*key, label(&mut list, *target))).collect(),
+            },
+        };
+        list.nodes.push(node);
+    }
+
+    let lowered = list.lower()?;
+    Ok(Assembled {
+        instructions: lowered.instructions,
+        labels: labels
+            .into_iter()
+            .map(|(target, label)| (target, lowered.labels[&label]))
+            .collect(),
+        length: lowered.length,
+    })
+}
+
+fn key_range(cases: &[(i32, u64)]) -> (i32, i32) {
+    let low = cases.iter().map(|(key, _)| *key).min().unwrap_or(0);
+    let high = cases.iter().map(|(key, _)| *key).max().unwrap_or(0);
+    (low, high)
+}
+
+/// Picks the cheaper switch encoding, weighing size against lookup time like javac does
+fn use_table_switch(cases: &[(i32, u64)]) -> bool {
+    if cases.is_empty() {
+        return false;
+    }
+
+    let (low, high) = key_range(cases);
+    let table_cost = 4 + (high as i64 - low as i64 + 1) + 3 * 3;
+    let lookup_cost = 3 + 2 * cases.len() as i64 + 3 * cases.len() as i64;
+
+    table_cost <= lookup_cost
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn widens_far_branches() {
+        let mut items = vec![Item::Label(0), Item::Branch(BranchCondition::IfEq, 1)];
+        items.extend((0..40000).map(|_| Item::Instruction(Instruction::Nop)));
+        items.push(Item::Label(1));
+        items.push(Item::Instruction(Instruction::Return));
+
+        let assembled = assemble(&items).unwrap();
+
+        assert_eq!(assembled.instructions[0], Instruction::IfNe { offset: 8 });
+        assert_eq!(assembled.instructions[1], Instruction::GotoW { offset: 40005 });
+        assert_eq!(assembled.labels[&1], 40008);
+    }
+
+    #[test]
+    fn dense_switch_uses_table() {
+        let items = vec![
+            Item::Label(0),
+            Item::Switch {
+                cases: vec![(2, 2), (1, 1), (3, 2)],
+                default: 1,
+            },
+            Item::Label(1),
+            Item::Instruction(Instruction::Return),
+            Item::Label(2),
+            Item::Instruction(Instruction::Return),
+        ];
+
+        let assembled = assemble(&items).unwrap();
+
+        assert_eq!(
+            assembled.instructions[0],
+            Instruction::TableSwitch {
+                default: 28,
+                low: 1,
+                high: 3,
+                offsets: vec![28, 29, 29].into(),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_undefined_label() {
+        let items = vec![Item::Label(0), Item::Goto(5)];
+
+        assert!(matches!(assemble(&items), Err(CodegenError::UndefinedLabel(5))));
+    }
+}
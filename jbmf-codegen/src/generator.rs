@@ -0,0 +1,1301 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use jbmf_error::{CodegenError, Error};
+use jbmf_ir::descriptor::{parse_field_descriptor, MethodDescriptor};
+use jbmf_ir::expression::{ConstantValue, Expression, InvokeKind, MemberReference, Value};
+use jbmf_ir::flow_graph::EdgeKind;
+use jbmf_ir::function::Function;
+use jbmf_ir::statement::{
+    BinaryOperation, ComparisonOperation, Condition, FieldStatementKind, FlowStatementKind, Statement,
+    StatementKind, TypeSignature, UnaryOperation,
+};
+use jbmf_ir::variable::Variable;
+use jbmf_parser::java_rs_pacific::attribute::{
+    AlwaysZero, ArrayType, ExceptionTable, Instruction, SizedIndex, SmallConstantPoolIndex, SmallIndex, WideIndex,
+};
+use jbmf_parser::java_rs_pacific::{Constant, ConstantPool, ConstantPoolBuilder, ConstantPoolIndex};
+
+use crate::assembler::{assemble, BranchCondition, Item};
+
+/// The code attribute contents generated for a function
+#[derive(Clone, Debug)]
+pub struct GeneratedCode {
+    pub instructions: Vec<Instruction>,
+    pub exception_table: Vec<ExceptionTable>,
+    pub max_stack: u16,
+    pub max_locals: u16,
+}
+
+/// The computational type of a value, which selects the instruction variant operating on it
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+enum Kind {
+    Int,
+    Long,
+    Float,
+    Double,
+    Reference,
+}
+
+impl Kind {
+    fn size(self) -> u16 {
+        match self {
+            Kind::Long | Kind::Double => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Generates the instructions of a function, which must not be in SSA form.
+///
+/// Local variables keep their slot, every other variable receives its own slot after them. Blocks are
+/// laid out in their original order starting with the entry block. An operand stack value never lives
+/// across statements, except for the exception at the start of a handler and the return address at the
+/// start of a subroutine, which are stored right away.
+pub fn generate_code(
+    function: &Function,
+    is_static: bool,
+    constant_pool: &mut ConstantPool,
+) -> Result<GeneratedCode, Error> {
+    let mut generator = Generator::new(function, is_static, ConstantPoolBuilder::new(constant_pool))?;
+
+    let mut layout: Vec<u64> = vec![function.start];
+    layout.extend(function.blocks.keys().filter(|start| **start != function.start));
+
+    for (index, start) in layout.iter().enumerate() {
+        generator.generate_block(*start, layout.get(index + 1).copied())?;
+    }
+
+    let assembled = assemble(&generator.items).map_err(|error| function_error(function, error))?;
+
+    let mut exception_table = Vec::new();
+    for handler in function.handlers.iter() {
+        let position = |bound: u64| {
+            layout
+                .iter()
+                .filter(|start| **start >= bound)
+                .min()
+                .map(|start| assembled.labels[start])
+                .unwrap_or(assembled.length)
+        };
+        let handler_position = match assembled.labels.get(&handler.handler) {
+            Some(position) => *position,
+            None => continue,
+        };
+        let (start, end) = (position(handler.start), position(handler.end));
+
+        if start >= end {
+            continue;
+        }
+
+        let catch_type = match &handler.catch_type {
+            Some(name) => generator.constants.class(name)?,
+            None => ConstantPoolIndex(0),
+        };
+        exception_table.push(ExceptionTable {
+            start_pc: start as u16,
+            end_pc: end as u16,
+            handler_pc: handler_position as u16,
+            catch_type,
+        });
+    }
+
+    Ok(GeneratedCode {
+        instructions: assembled.instructions,
+        exception_table,
+        max_stack: generator.max_stack,
+        max_locals: generator.max_locals,
+    })
+}
+
+struct Generator<'a> {
+    function: &'a Function,
+    constants: ConstantPoolBuilder<'a>,
+    return_kind: Option<Kind>,
+    /// Kinds of the values assigned to every variable, including the parameters
+    kinds: HashMap<Variable, BTreeSet<Kind>>,
+    slots: HashMap<Variable, u16>,
+    next_slot: u16,
+    max_locals: u16,
+    /// Variable receiving the return address at the start of each subroutine
+    subroutines: HashMap<u64, Variable>,
+    items: Vec<Item>,
+    stack: u16,
+    max_stack: u16,
+    block: u64,
+}
+
+impl<'a> Generator<'a> {
+    fn new(function: &'a Function, is_static: bool, constants: ConstantPoolBuilder<'a>) -> Result<Self, Error> {
+        let MethodDescriptor {
+            parameters,
+            return_type,
+        } = MethodDescriptor::parse(&function.descriptor)?;
+        let mut kinds: HashMap<Variable, BTreeSet<Kind>> = HashMap::new();
+        let mut slot = 0;
+
+        if !is_static {
+            kinds.entry(Variable::Local(0)).or_default().insert(Kind::Reference);
+            slot = 1;
+        }
+        for parameter in parameters.iter() {
+            let kind = kind_of(parameter).ok_or_else(|| function_error(function, CodegenError::VoidParameter))?;
+            kinds.entry(Variable::Local(slot)).or_default().insert(kind);
+            slot += kind.size();
+        }
+
+        let mut subroutines = HashMap::new();
+
+        for block in function.blocks.values() {
+            for statement in block.statements.iter() {
+                if let StatementKind::Assign { target, ty, value } = statement.kind() {
+                    let kind = kind_of(ty).ok_or_else(|| function_error(function, CodegenError::VoidAssignment))?;
+                    kinds.entry(*target).or_default().insert(kind);
+
+                    if let Expression::Value(Value::Constant(ConstantValue::ReturnAddress(_))) = value {
+                        let subroutine = match block.statements.last().map(Statement::kind) {
+                            Some(StatementKind::Flow(FlowStatementKind::JumpSubroutine(subroutine))) => *subroutine,
+                            _ => return Err(function_error(function, CodegenError::ReturnAddressOutsideJsrBlock)),
+                        };
+
+                        if *subroutines.entry(subroutine).or_insert(*target) != *target {
+                            return Err(function_error(
+                                function,
+                                CodegenError::InconsistentReturnAddress,
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut slots = HashMap::new();
+        let mut next_slot = slot;
+
+        for (variable, variable_kinds) in kinds.iter() {
+            if let Variable::Local(index) = variable {
+                let size = variable_kinds.iter().map(|kind| kind.size()).max().unwrap_or(1);
+                slots.insert(*variable, *index);
+                next_slot = next_slot.max(index + size);
+            }
+        }
+
+        let mut generator = Self {
+            function,
+            constants,
+            return_kind: kind_of(&return_type),
+            kinds,
+            slots,
+            next_slot,
+            max_locals: next_slot,
+            subroutines,
+            items: Vec::new(),
+            stack: 0,
+            max_stack: 0,
+            block: function.start,
+        };
+
+        let variables: BTreeMap<Variable, u16> = generator
+            .kinds
+            .iter()
+            .filter(|(variable, _)| !matches!(variable, Variable::Local(_)))
+            .map(|(variable, kinds)| (*variable, kinds.iter().map(|kind| kind.size()).max().unwrap_or(1)))
+            .collect();
+        for (variable, size) in variables {
+            generator.allocate(variable, size);
+        }
+
+        Ok(generator)
+    }
+
+    fn error(&self, error: CodegenError) -> Error {
+        Error::CodegenError {
+            method: format!("{}.{}{}", self.function.owner, self.function.name, self.function.descriptor),
+            block: Some(self.block),
+            source: error,
+        }
+    }
+
+    fn allocate(&mut self, variable: Variable, size: u16) -> u16 {
+        let slot = match variable {
+            Variable::Local(index) => index,
+            _ => {
+                let slot = self.next_slot;
+                self.next_slot += size;
+                slot
+            }
+        };
+
+        self.slots.insert(variable, slot);
+        self.max_locals = self.max_locals.max(slot + size);
+        slot
+    }
+
+    /// The slot of a variable, variables which are never assigned are allocated on first use
+    fn slot(&mut self, variable: Variable, kind: Kind) -> u16 {
+        let slot = match self.slots.get(&variable) {
+            Some(slot) => *slot,
+            None => self.allocate(variable, kind.size()),
+        };
+
+        self.max_locals = self.max_locals.max(slot + kind.size());
+        slot
+    }
+
+    fn emit(&mut self, instruction: Instruction) {
+        self.items.push(Item::Instruction(instruction));
+    }
+
+    fn push(&mut self, words: u16) {
+        self.stack += words;
+        self.max_stack = self.max_stack.max(self.stack);
+    }
+
+    fn pop(&mut self, words: u16) {
+        self.stack -= words;
+    }
+
+    fn generate_block(&mut self, start: u64, next: Option<u64>) -> Result<(), Error> {
+        let function = self.function;
+        let block = &function.blocks[&start];
+        self.block = start;
+        self.stack = 0;
+        self.items.push(Item::Label(start));
+
+        let mut statements = block.statements.iter().peekable();
+
+        if let Some(variable) = self.subroutines.get(&start).copied() {
+            // A return address can be stored but not loaded, so a copy made right away is stored directly
+            let copy = statements.peek().and_then(|statement| match statement.kind() {
+                StatementKind::Assign {
+                    target,
+                    value: Expression::Value(Value::Variable(source)),
+                    ..
+                } if *source == variable => Some(*target),
+                _ => None,
+            });
+
+            self.push(1);
+            match copy {
+                Some(target) => {
+                    statements.next();
+                    self.store(target, Kind::Reference);
+                }
+                None => self.store(variable, Kind::Reference),
+            }
+        }
+
+        for (index, statement) in statements.enumerate() {
+            self.generate_statement(statement, index == 0)?;
+
+            // `ret` continues after the `jsr`, so the return site has to be laid out next
+            if let StatementKind::Assign {
+                value: Expression::Value(Value::Constant(ConstantValue::ReturnAddress(position))),
+                ..
+            } = statement.kind()
+            {
+                if function.blocks.contains_key(position) && next != Some(*position) {
+                    return Err(self.error(CodegenError::ReturnSiteNotAfterJsr {
+                        return_site: *position,
+                    }));
+                }
+            }
+        }
+
+        if !matches!(block.statements.last().map(Statement::kind), Some(StatementKind::Flow(_)))
+            || matches!(
+                block.statements.last().map(Statement::kind),
+                Some(StatementKind::Flow(FlowStatementKind::ConditionalJump { .. }))
+            )
+        {
+            let fall_through = function
+                .graph
+                .edges
+                .iter()
+                .find(|edge| edge.source == start && edge.kind == EdgeKind::FallThrough)
+                .map(|edge| edge.target)
+                .ok_or_else(|| self.error(CodegenError::MissingFallThrough))?;
+
+            if Some(fall_through) != next {
+                self.items.push(Item::Goto(fall_through));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn generate_statement(&mut self, statement: &Statement, first: bool) -> Result<(), Error> {
+        match statement.kind() {
+            StatementKind::Assign { target, ty, value } => {
+                let kind = kind_of(ty).ok_or_else(|| self.error(CodegenError::VoidAssignment))?;
+
+                match value {
+                    // Pushed by `jsr` itself and stored at the start of the subroutine
+                    Expression::Value(Value::Constant(ConstantValue::ReturnAddress(_))) => return Ok(()),
+                    Expression::CaughtException(_) if first => self.push(1),
+                    Expression::CaughtException(_) => {
+                        return Err(self.error(CodegenError::CaughtExceptionNotFirst))
+                    }
+                    Expression::Value(value) => self.value(value, kind)?,
+                    expression => {
+                        self.expression(expression)?;
+                    }
+                }
+
+                self.store(*target, kind);
+            }
+            StatementKind::Evaluate(expression) => {
+                if let Some(kind) = self.expression(expression)? {
+                    self.emit(match kind.size() {
+                        2 => Instruction::Pop2,
+                        _ => Instruction::Pop,
+                    });
+                    self.pop(kind.size());
+                }
+            }
+            StatementKind::Field(FieldStatementKind::Store { field, object, value }) => {
+                let kind = kind_of(&parse_field_descriptor(&field.descriptor)?)
+                    .ok_or_else(|| self.error(CodegenError::VoidField))?;
+                let index = self.field_ref(field)?;
+
+                match object {
+                    Some(object) => {
+                        self.value(object, Kind::Reference)?;
+                        self.value(value, kind)?;
+                        self.emit(Instruction::PutField { index });
+                        self.pop(1 + kind.size());
+                    }
+                    None => {
+                        self.value(value, kind)?;
+                        self.emit(Instruction::PutStatic { index });
+                        self.pop(kind.size());
+                    }
+                }
+            }
+            StatementKind::ArrayStore {
+                array,
+                index,
+                value,
+                ty,
+            } => {
+                let kind = kind_of(ty).ok_or_else(|| self.error(CodegenError::VoidArrayElement))?;
+                self.value(array, Kind::Reference)?;
+                self.value(index, Kind::Int)?;
+                self.value(value, kind)?;
+                self.emit(match ty {
+                    TypeSignature::Integer => Instruction::IAStore,
+                    TypeSignature::Long => Instruction::LAStore,
+                    TypeSignature::Float => Instruction::FAStore,
+                    TypeSignature::Double => Instruction::DAStore,
+                    TypeSignature::Byte | TypeSignature::Boolean => Instruction::BAStore,
+                    TypeSignature::Char => Instruction::CAStore,
+                    TypeSignature::Short => Instruction::SAStore,
+                    _ => Instruction::AAStore,
+                });
+                self.pop(2 + kind.size());
+            }
+            StatementKind::MonitorEnter(value) => {
+                self.value(value, Kind::Reference)?;
+                self.emit(Instruction::MonitorEnter);
+                self.pop(1);
+            }
+            StatementKind::MonitorExit(value) => {
+                self.value(value, Kind::Reference)?;
+                self.emit(Instruction::MonitorExit);
+                self.pop(1);
+            }
+            StatementKind::Flow(flow) => self.flow(flow)?,
+        }
+
+        Ok(())
+    }
+
+    fn flow(&mut self, flow: &FlowStatementKind) -> Result<(), Error> {
+        match flow {
+            FlowStatementKind::UnconditionalJump(target) => self.items.push(Item::Goto(*target)),
+            FlowStatementKind::ConditionalJump { condition, target } => {
+                let branch = self.condition(condition)?;
+                self.pop(branch.operands());
+                self.items.push(Item::Branch(branch, *target));
+            }
+            FlowStatementKind::Switch { value, cases, default } => {
+                self.value(value, Kind::Int)?;
+                self.pop(1);
+                self.items.push(Item::Switch {
+                    cases: cases.clone(),
+                    default: *default,
+                });
+            }
+            FlowStatementKind::Return(Some(value)) => {
+                let kind = self.return_kind.ok_or_else(|| self.error(CodegenError::ValueReturnedFromVoidMethod))?;
+                self.value(value, kind)?;
+                self.emit(match kind {
+                    Kind::Int => Instruction::IReturn,
+                    Kind::Long => Instruction::LReturn,
+                    Kind::Float => Instruction::FReturn,
+                    Kind::Double => Instruction::DReturn,
+                    Kind::Reference => Instruction::AReturn,
+                });
+                self.pop(kind.size());
+            }
+            FlowStatementKind::Return(None) => self.emit(Instruction::Return),
+            FlowStatementKind::Throw(value) => {
+                self.value(value, Kind::Reference)?;
+                self.emit(Instruction::AThrow);
+                self.pop(1);
+            }
+            FlowStatementKind::JumpSubroutine(target) => {
+                // The return address only exists on the operand stack of the jump
+                self.push(1);
+                self.pop(1);
+                self.items.push(Item::JumpSubroutine(*target));
+            }
+            FlowStatementKind::ReturnSubroutine(variable) => {
+                let slot = self.slot(*variable, Kind::Reference);
+                if slot > u8::MAX as u16 {
+                    self.emit(Instruction::Wide);
+                }
+                self.emit(Instruction::Ret {
+                    index: sized_index(slot),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pushes the operands of a condition and selects the branch instruction
+    fn condition(&mut self, condition: &Condition) -> Result<BranchCondition, Error> {
+        use BranchCondition::*;
+        use ComparisonOperation::*;
+
+        let kind = match (self.value_kind(&condition.left), self.value_kind(&condition.right)) {
+            (Some(left), Some(right)) if left == right => left,
+            (Some(kind), None) | (None, Some(kind)) => kind,
+            _ => return Err(self.error(CodegenError::UnknownComparisonType)),
+        };
+
+        let zero = |value: &Value| matches!(value, Value::Constant(ConstantValue::Integer(0)));
+        let null = |value: &Value| matches!(value, Value::Constant(ConstantValue::Null));
+
+        let branch = match kind {
+            Kind::Int if zero(&condition.right) => {
+                self.value(&condition.left, kind)?;
+                match condition.operation {
+                    Equal => IfEq,
+                    NotEqual => IfNe,
+                    Less => IfLt,
+                    GreaterEqual => IfGe,
+                    Greater => IfGt,
+                    LessEqual => IfLe,
+                }
+            }
+            Kind::Int => {
+                self.value(&condition.left, kind)?;
+                self.value(&condition.right, kind)?;
+                match condition.operation {
+                    Equal => IfICmpEq,
+                    NotEqual => IfICmpNe,
+                    Less => IfICmpLt,
+                    GreaterEqual => IfICmpGe,
+                    Greater => IfICmpGt,
+                    LessEqual => IfICmpLe,
+                }
+            }
+            Kind::Reference if null(&condition.left) || null(&condition.right) => {
+                let operand = if null(&condition.right) {
+                    &condition.left
+                } else {
+                    &condition.right
+                };
+                self.value(operand, kind)?;
+                match condition.operation {
+                    Equal => IfNull,
+                    NotEqual => IfNonNull,
+                    _ => return Err(self.error(CodegenError::OrderedReferenceComparison)),
+                }
+            }
+            Kind::Reference => {
+                self.value(&condition.left, kind)?;
+                self.value(&condition.right, kind)?;
+                match condition.operation {
+                    Equal => IfACmpEq,
+                    NotEqual => IfACmpNe,
+                    _ => return Err(self.error(CodegenError::OrderedReferenceComparison)),
+                }
+            }
+            _ => return Err(self.error(CodegenError::UnsupportedConditionalJump)),
+        };
+
+        Ok(branch)
+    }
+
+    /// The kind of a value if it can be told without context, i.e. for constants and variables which are
+    /// only ever assigned values of a single kind
+    fn value_kind(&self, value: &Value) -> Option<Kind> {
+        match value {
+            Value::Constant(constant) => Some(constant_kind(constant)),
+            Value::Variable(variable) => match self.kinds.get(variable) {
+                Some(kinds) if kinds.len() == 1 => kinds.iter().next().copied(),
+                _ => None,
+            },
+        }
+    }
+
+    fn store(&mut self, variable: Variable, kind: Kind) {
+        let slot = self.slot(variable, kind);
+        let index = sized_index(slot);
+
+        if slot > u8::MAX as u16 {
+            self.emit(Instruction::Wide);
+        }
+
+        self.emit(match (kind, slot) {
+            (Kind::Int, 0) => Instruction::IStore0,
+            (Kind::Int, 1) => Instruction::IStore1,
+            (Kind::Int, 2) => Instruction::IStore2,
+            (Kind::Int, 3) => Instruction::IStore3,
+            (Kind::Int, _) => Instruction::IStore { index },
+            (Kind::Long, 0) => Instruction::LStore0,
+            (Kind::Long, 1) => Instruction::LStore1,
+            (Kind::Long, 2) => Instruction::LStore2,
+            (Kind::Long, 3) => Instruction::LStore3,
+            (Kind::Long, _) => Instruction::LStore { index },
+            (Kind::Float, 0) => Instruction::FStore0,
+            (Kind::Float, 1) => Instruction::FStore1,
+            (Kind::Float, 2) => Instruction::FStore2,
+            (Kind::Float, 3) => Instruction::FStore3,
+            (Kind::Float, _) => Instruction::FStore { index },
+            (Kind::Double, 0) => Instruction::DStore0,
+            (Kind::Double, 1) => Instruction::DStore1,
+            (Kind::Double, 2) => Instruction::DStore2,
+            (Kind::Double, 3) => Instruction::DStore3,
+            (Kind::Double, _) => Instruction::DStore { index },
+            (Kind::Reference, 0) => Instruction::AStore0,
+            (Kind::Reference, 1) => Instruction::AStore1,
+            (Kind::Reference, 2) => Instruction::AStore2,
+            (Kind::Reference, 3) => Instruction::AStore3,
+            (Kind::Reference, _) => Instruction::AStore { index },
+        });
+        self.pop(kind.size());
+    }
+
+    fn load(&mut self, variable: Variable, kind: Kind) {
+        let slot = self.slot(variable, kind);
+        let index = sized_index(slot);
+
+        if slot > u8::MAX as u16 {
+            self.emit(Instruction::Wide);
+        }
+
+        self.emit(match (kind, slot) {
+            (Kind::Int, 0) => Instruction::ILoad0,
+            (Kind::Int, 1) => Instruction::ILoad1,
+            (Kind::Int, 2) => Instruction::ILoad2,
+            (Kind::Int, 3) => Instruction::ILoad3,
+            (Kind::Int, _) => Instruction::ILoad { index },
+            (Kind::Long, 0) => Instruction::LLoad0,
+            (Kind::Long, 1) => Instruction::LLoad1,
+            (Kind::Long, 2) => Instruction::LLoad2,
+            (Kind::Long, 3) => Instruction::LLoad3,
+            (Kind::Long, _) => Instruction::LLoad { index },
+            (Kind::Float, 0) => Instruction::FLoad0,
+            (Kind::Float, 1) => Instruction::FLoad1,
+            (Kind::Float, 2) => Instruction::FLoad2,
+            (Kind::Float, 3) => Instruction::FLoad3,
+            (Kind::Float, _) => Instruction::FLoad { index },
+            (Kind::Double, 0) => Instruction::DLoad0,
+            (Kind::Double, 1) => Instruction::DLoad1,
+            (Kind::Double, 2) => Instruction::DLoad2,
+            (Kind::Double, 3) => Instruction::DLoad3,
+            (Kind::Double, _) => Instruction::DLoad { index },
+            (Kind::Reference, 0) => Instruction::ALoad0,
+            (Kind::Reference, 1) => Instruction::ALoad1,
+            (Kind::Reference, 2) => Instruction::ALoad2,
+            (Kind::Reference, 3) => Instruction::ALoad3,
+            (Kind::Reference, _) => Instruction::ALoad { index },
+        });
+        self.push(kind.size());
+    }
+
+    /// Pushes a value, `kind` is the kind expected by the consumer, which decides how variables are loaded
+    fn value(&mut self, value: &Value, kind: Kind) -> Result<(), Error> {
+        match value {
+            Value::Variable(variable) => self.load(*variable, kind),
+            Value::Constant(constant) => self.constant(constant)?,
+        }
+        Ok(())
+    }
+
+    fn constant(&mut self, constant: &ConstantValue) -> Result<(), Error> {
+        let kind = constant_kind(constant);
+        let short = match constant {
+            ConstantValue::Null => Some(Instruction::AConstNull),
+            ConstantValue::Integer(value) => match *value {
+                -1 => Some(Instruction::IConstM1),
+                0 => Some(Instruction::IConst0),
+                1 => Some(Instruction::IConst1),
+                2 => Some(Instruction::IConst2),
+                3 => Some(Instruction::IConst3),
+                4 => Some(Instruction::IConst4),
+                5 => Some(Instruction::IConst5),
+                value if i8::try_from(value).is_ok() => Some(Instruction::BIPush { value: value as i8 }),
+                value if i16::try_from(value).is_ok() => Some(Instruction::SIPush { value: value as i16 }),
+                _ => None,
+            },
+            ConstantValue::Long(0) => Some(Instruction::LConst0),
+            ConstantValue::Long(1) => Some(Instruction::LConst1),
+            // Compared by bits, `-0.0` has no short form
+            ConstantValue::Float(value) if value.to_bits() == 0f32.to_bits() => Some(Instruction::FConst0),
+            ConstantValue::Float(value) if value.to_bits() == 1f32.to_bits() => Some(Instruction::FConst1),
+            ConstantValue::Float(value) if value.to_bits() == 2f32.to_bits() => Some(Instruction::FConst2),
+            ConstantValue::Double(value) if value.to_bits() == 0f64.to_bits() => Some(Instruction::DConst0),
+            ConstantValue::Double(value) if value.to_bits() == 1f64.to_bits() => Some(Instruction::DConst1),
+            ConstantValue::ReturnAddress(_) => return Err(self.error(CodegenError::ReturnAddressAsValue)),
+            _ => None,
+        };
+
+        let instruction = match short {
+            Some(instruction) => instruction,
+            None => {
+                let index = match constant {
+                    ConstantValue::Integer(value) => self.constants.integer(*value)?,
+                    ConstantValue::Long(value) => self.constants.long(*value)?,
+                    ConstantValue::Float(value) => self.constants.float(*value)?,
+                    ConstantValue::Double(value) => self.constants.double(*value)?,
+                    ConstantValue::String(value) => self.constants.string(value)?,
+                    ConstantValue::Class(ty) => {
+                        let name = class_name(ty).ok_or_else(|| self.error(CodegenError::PrimitiveClassReference))?;
+                        self.constants.class(&name)?
+                    }
+                    ConstantValue::MethodType(descriptor) => self.constants.method_type(descriptor)?,
+                    ConstantValue::MethodHandle {
+                        reference_kind,
+                        reference,
+                    } => self.constants.method_handle(
+                        *reference_kind,
+                        &reference.owner,
+                        &reference.name,
+                        &reference.descriptor,
+                    )?,
+                    ConstantValue::Dynamic {
+                        bootstrap_method,
+                        name,
+                        descriptor,
+                    } => self.constants.dynamic(*bootstrap_method, name, descriptor)?,
+                    ConstantValue::Null | ConstantValue::ReturnAddress(_) => unreachable!(),
+                };
+
+                match kind {
+                    Kind::Long | Kind::Double => Instruction::LDC2W { index },
+                    _ if index.0 <= u8::MAX as u16 => Instruction::LDC {
+                        index: SmallConstantPoolIndex(index.0 as u8),
+                    },
+                    _ => Instruction::LDCW { index },
+                }
+            }
+        };
+
+        self.emit(instruction);
+        self.push(kind.size());
+        Ok(())
+    }
+
+    /// Pushes the result of an expression, returns its kind or `None` for void invocations
+    fn expression(&mut self, expression: &Expression) -> Result<Option<Kind>, Error> {
+        let kind = match expression {
+            Expression::Value(value) => {
+                let kind = self
+                    .value_kind(value)
+                    .ok_or_else(|| self.error(CodegenError::UnknownValueType))?;
+                self.value(value, kind)?;
+                kind
+            }
+            Expression::Unary { operation, operand, ty } => {
+                let kind = kind_of(ty).ok_or_else(|| self.error(CodegenError::VoidOperand))?;
+                self.value(operand, kind)?;
+
+                match (operation, kind) {
+                    (UnaryOperation::ArithmeticNegate, Kind::Int) => self.emit(Instruction::INeg),
+                    (UnaryOperation::ArithmeticNegate, Kind::Long) => self.emit(Instruction::LNeg),
+                    (UnaryOperation::ArithmeticNegate, Kind::Float) => self.emit(Instruction::FNeg),
+                    (UnaryOperation::ArithmeticNegate, Kind::Double) => self.emit(Instruction::DNeg),
+                    (UnaryOperation::LogicalNegate, Kind::Int) => {
+                        self.emit(Instruction::IConst1);
+                        self.push(1);
+                        self.emit(Instruction::IXor);
+                        self.pop(1);
+                    }
+                    _ => return Err(self.error(CodegenError::UnsupportedUnaryOperation)),
+                }
+                kind
+            }
+            Expression::Binary {
+                operation,
+                left,
+                right,
+                ty,
+            } => self.binary(operation, left, right, ty)?,
+            Expression::Convert { value, from, to } => self.convert(value, from, to)?,
+            Expression::CheckCast { value, ty } => {
+                self.value(value, Kind::Reference)?;
+                let index = self.class_index(ty)?;
+                self.emit(Instruction::CheckCast { index });
+                Kind::Reference
+            }
+            Expression::InstanceOf { value, ty } => {
+                self.value(value, Kind::Reference)?;
+                let index = self.class_index(ty)?;
+                self.emit(Instruction::InstanceOf { index });
+                self.pop(1);
+                self.push(1);
+                Kind::Int
+            }
+            Expression::New(ty) => {
+                let index = self.class_index(ty)?;
+                self.emit(Instruction::New { index });
+                self.push(1);
+                Kind::Reference
+            }
+            Expression::NewArray { ty, dimensions } => {
+                for dimension in dimensions {
+                    self.value(dimension, Kind::Int)?;
+                }
+
+                match (ty, dimensions.len()) {
+                    (TypeSignature::Array(element), 1) => match array_type(element) {
+                        Some(array_type) => self.emit(Instruction::NewArray { ty: array_type }),
+                        None => {
+                            let index = self.class_index(element)?;
+                            self.emit(Instruction::ANewArray { index });
+                        }
+                    },
+                    (TypeSignature::Array(_), count) if count > 0 && count <= u8::MAX as usize => {
+                        let index = self.class_index(ty)?;
+                        self.emit(Instruction::MultiANewArray {
+                            index,
+                            dimensions: count as u8,
+                        });
+                    }
+                    _ => return Err(self.error(CodegenError::InvalidArrayAllocation)),
+                }
+
+                self.pop(dimensions.len() as u16);
+                self.push(1);
+                Kind::Reference
+            }
+            Expression::ArrayLength(array) => {
+                self.value(array, Kind::Reference)?;
+                self.emit(Instruction::ArrayLength);
+                Kind::Int
+            }
+            Expression::ArrayLoad { array, index, ty } => {
+                let kind = kind_of(ty).ok_or_else(|| self.error(CodegenError::VoidArrayElement))?;
+                self.value(array, Kind::Reference)?;
+                self.value(index, Kind::Int)?;
+                self.emit(match ty {
+                    TypeSignature::Integer => Instruction::IALoad,
+                    TypeSignature::Long => Instruction::LALoad,
+                    TypeSignature::Float => Instruction::FALoad,
+                    TypeSignature::Double => Instruction::DALoad,
+                    TypeSignature::Byte | TypeSignature::Boolean => Instruction::BALoad,
+                    TypeSignature::Char => Instruction::CALoad,
+                    TypeSignature::Short => Instruction::SALoad,
+                    _ => Instruction::AALoad,
+                });
+                self.pop(2);
+                self.push(kind.size());
+                kind
+            }
+            Expression::FieldLoad { field, object } => {
+                let kind = kind_of(&parse_field_descriptor(&field.descriptor)?)
+                    .ok_or_else(|| self.error(CodegenError::VoidField))?;
+                let index = self.field_ref(field)?;
+
+                match object {
+                    Some(object) => {
+                        self.value(object, Kind::Reference)?;
+                        self.emit(Instruction::GetField { index });
+                        self.pop(1);
+                    }
+                    None => self.emit(Instruction::GetStatic { index }),
+                }
+
+                self.push(kind.size());
+                kind
+            }
+            Expression::Invoke {
+                kind,
+                method,
+                receiver,
+                arguments,
+            } => {
+                let MethodDescriptor {
+                    parameters,
+                    return_type,
+                } = MethodDescriptor::parse(&method.descriptor)?;
+                if parameters.len() != arguments.len() {
+                    return Err(self.error(CodegenError::ArgumentCountMismatch {
+                        descriptor: method.descriptor.clone(),
+                        expected: parameters.len(),
+                        found: arguments.len(),
+                    }));
+                }
+
+                if let Some(receiver) = receiver {
+                    self.value(receiver, Kind::Reference)?;
+                }
+                let words = self.arguments(&parameters, arguments)? + receiver.iter().count() as u16;
+
+                let index = match kind {
+                    InvokeKind::Interface => {
+                        self.constants.interface_method_ref(&method.owner, &method.name, &method.descriptor)?
+                    }
+                    InvokeKind::Virtual => self.constants.method_ref(&method.owner, &method.name, &method.descriptor)?,
+                    InvokeKind::Special | InvokeKind::Static => self.static_method_ref(method)?,
+                };
+                self.emit(match kind {
+                    InvokeKind::Virtual => Instruction::InvokeVirtual { index },
+                    InvokeKind::Special => Instruction::InvokeSpecial { index },
+                    InvokeKind::Static => Instruction::InvokeStatic { index },
+                    InvokeKind::Interface => Instruction::InvokeInterface {
+                        index,
+                        count: words as u8,
+                        _zero: AlwaysZero,
+                    },
+                });
+
+                self.pop(words);
+                match kind_of(&return_type) {
+                    Some(kind) => {
+                        self.push(kind.size());
+                        kind
+                    }
+                    None => return Ok(None),
+                }
+            }
+            Expression::InvokeDynamic {
+                bootstrap_method,
+                name,
+                descriptor,
+                arguments,
+            } => {
+                let MethodDescriptor {
+                    parameters,
+                    return_type,
+                } = MethodDescriptor::parse(descriptor)?;
+                if parameters.len() != arguments.len() {
+                    return Err(self.error(CodegenError::ArgumentCountMismatch {
+                        descriptor: descriptor.clone(),
+                        expected: parameters.len(),
+                        found: arguments.len(),
+                    }));
+                }
+
+                let words = self.arguments(&parameters, arguments)?;
+                let index = self.constants.invoke_dynamic(*bootstrap_method, name, descriptor)?;
+                self.emit(Instruction::InvokeDynamic {
+                    index,
+                    _zero0: AlwaysZero,
+                    _zero1: AlwaysZero,
+                });
+
+                self.pop(words);
+                match kind_of(&return_type) {
+                    Some(kind) => {
+                        self.push(kind.size());
+                        kind
+                    }
+                    None => return Ok(None),
+                }
+            }
+            Expression::CaughtException(_) => {
+                return Err(self.error(CodegenError::CaughtExceptionNotAssigned))
+            }
+            Expression::Phi(_) => return Err(self.error(CodegenError::SsaForm)),
+        };
+
+        Ok(Some(kind))
+    }
+
+    /// Pushes call arguments, returns the number of words they occupy
+    fn arguments(&mut self, parameters: &[TypeSignature], arguments: &[Value]) -> Result<u16, Error> {
+        let mut words = 0;
+
+        for (parameter, argument) in parameters.iter().zip(arguments) {
+            let kind = kind_of(parameter).ok_or_else(|| self.error(CodegenError::VoidParameter))?;
+            self.value(argument, kind)?;
+            words += kind.size();
+        }
+
+        Ok(words)
+    }
+
+    fn binary(
+        &mut self,
+        operation: &BinaryOperation,
+        left: &Value,
+        right: &Value,
+        ty: &TypeSignature,
+    ) -> Result<Kind, Error> {
+        use BinaryOperation::*;
+
+        let kind = kind_of(ty).ok_or_else(|| self.error(CodegenError::VoidOperand))?;
+        let shift = matches!(operation, LeftShift | RightShift | RightShiftPadded);
+
+        self.value(left, kind)?;
+        self.value(right, if shift { Kind::Int } else { kind })?;
+
+        let instruction = match (operation, kind) {
+            (Addition, Kind::Int) => Instruction::IAdd,
+            (Addition, Kind::Long) => Instruction::LAdd,
+            (Addition, Kind::Float) => Instruction::FAdd,
+            (Addition, Kind::Double) => Instruction::DAdd,
+            (Subtraction, Kind::Int) => Instruction::ISub,
+            (Subtraction, Kind::Long) => Instruction::LSub,
+            (Subtraction, Kind::Float) => Instruction::FSub,
+            (Subtraction, Kind::Double) => Instruction::DSub,
+            (Multiplication, Kind::Int) => Instruction::IMul,
+            (Multiplication, Kind::Long) => Instruction::LMul,
+            (Multiplication, Kind::Float) => Instruction::FMul,
+            (Multiplication, Kind::Double) => Instruction::DMul,
+            (Division, Kind::Int) => Instruction::IDiv,
+            (Division, Kind::Long) => Instruction::LDiv,
+            (Division, Kind::Float) => Instruction::FDiv,
+            (Division, Kind::Double) => Instruction::DDiv,
+            (Modulo, Kind::Int) => Instruction::IRem,
+            (Modulo, Kind::Long) => Instruction::LRem,
+            (Modulo, Kind::Float) => Instruction::FRem,
+            (Modulo, Kind::Double) => Instruction::DRem,
+            (LeftShift, Kind::Int) => Instruction::IShl,
+            (LeftShift, Kind::Long) => Instruction::LShl,
+            (RightShift, Kind::Int) => Instruction::IShr,
+            (RightShift, Kind::Long) => Instruction::LShr,
+            (RightShiftPadded, Kind::Int) => Instruction::IUShr,
+            (RightShiftPadded, Kind::Long) => Instruction::LUShr,
+            (LAND, Kind::Int) => Instruction::IAnd,
+            (LAND, Kind::Long) => Instruction::LAnd,
+            (LOR, Kind::Int) => Instruction::IOr,
+            (LOR, Kind::Long) => Instruction::LOr,
+            (LXOR, Kind::Int) => Instruction::IXor,
+            (LXOR, Kind::Long) => Instruction::LXor,
+            (Compare, Kind::Long) => Instruction::LCmp,
+            (CompareNaNLess, Kind::Float) => Instruction::FCmpPL,
+            (CompareNaNLess, Kind::Double) => Instruction::DCmpL,
+            (CompareNaNGreater, Kind::Float) => Instruction::FCmpPG,
+            (CompareNaNGreater, Kind::Double) => Instruction::DCmpG,
+            _ => return Err(self.error(CodegenError::UnsupportedBinaryOperation)),
+        };
+        self.emit(instruction);
+
+        let right_size = if shift { 1 } else { kind.size() };
+        self.pop(kind.size() + right_size);
+
+        // Comparisons operate on `ty` but produce an int
+        let result = match operation {
+            Compare | CompareNaNLess | CompareNaNGreater => Kind::Int,
+            _ => kind,
+        };
+        self.push(result.size());
+        Ok(result)
+    }
+
+    fn convert(&mut self, value: &Value, from: &TypeSignature, to: &TypeSignature) -> Result<Kind, Error> {
+        let from_kind = kind_of(from).ok_or_else(|| self.error(CodegenError::VoidConversion))?;
+        let to_kind = kind_of(to).ok_or_else(|| self.error(CodegenError::VoidConversion))?;
+        self.value(value, from_kind)?;
+
+        let widening = match (from_kind, to_kind) {
+            (Kind::Int, Kind::Long) => Some(Instruction::I2L),
+            (Kind::Int, Kind::Float) => Some(Instruction::I2F),
+            (Kind::Int, Kind::Double) => Some(Instruction::I2D),
+            (Kind::Long, Kind::Int) => Some(Instruction::L2I),
+            (Kind::Long, Kind::Float) => Some(Instruction::L2F),
+            (Kind::Long, Kind::Double) => Some(Instruction::L2D),
+            (Kind::Float, Kind::Int) => Some(Instruction::F2I),
+            (Kind::Float, Kind::Long) => Some(Instruction::F2L),
+            (Kind::Float, Kind::Double) => Some(Instruction::F2D),
+            (Kind::Double, Kind::Int) => Some(Instruction::D2I),
+            (Kind::Double, Kind::Long) => Some(Instruction::D2L),
+            (Kind::Double, Kind::Float) => Some(Instruction::D2F),
+            (from, to) if from == to => None,
+            _ => return Err(self.error(CodegenError::ReferenceConversion)),
+        };
+        if let Some(instruction) = widening {
+            self.emit(instruction);
+        }
+
+        match to {
+            TypeSignature::Byte => self.emit(Instruction::I2B),
+            TypeSignature::Char => self.emit(Instruction::I2C),
+            TypeSignature::Short => self.emit(Instruction::I2S),
+            _ => {}
+        }
+
+        self.pop(from_kind.size());
+        self.push(to_kind.size());
+        Ok(to_kind)
+    }
+
+    fn class_index(&mut self, ty: &TypeSignature) -> Result<ConstantPoolIndex, Error> {
+        let name = class_name(ty).ok_or_else(|| self.error(CodegenError::PrimitiveClassReference))?;
+        Ok(self.constants.class(&name)?)
+    }
+
+    fn field_ref(&mut self, field: &MemberReference) -> Result<ConstantPoolIndex, Error> {
+        Ok(self.constants.field_ref(&field.owner, &field.name, &field.descriptor)?)
+    }
+
+    /// The method reference for `invokestatic` and `invokespecial`, which may refer to interface methods, so
+    /// an existing interface method reference is reused
+    fn static_method_ref(&mut self, method: &MemberReference) -> Result<ConstantPoolIndex, Error> {
+        let class = self.constants.class(&method.owner)?;
+        let name_and_type = self.constants.name_and_type(&method.name, &method.descriptor)?;
+
+        match self.constants.get(&Constant::InterfaceMethodRef { class, name_and_type }) {
+            Some(index) => Ok(index),
+            None => Ok(self.constants.insert(Constant::MethodRef { class, name_and_type })?),
+        }
+    }
+}
+
+pub(crate) fn function_error(function: &Function, error: CodegenError) -> Error {
+    Error::CodegenError {
+        method: format!("{}.{}{}", function.owner, function.name, function.descriptor),
+        block: None,
+        source: error,
+    }
+}
+
+fn kind_of(ty: &TypeSignature) -> Option<Kind> {
+    match ty {
+        TypeSignature::Boolean
+        | TypeSignature::Byte
+        | TypeSignature::Char
+        | TypeSignature::Short
+        | TypeSignature::Integer => Some(Kind::Int),
+        TypeSignature::Long => Some(Kind::Long),
+        TypeSignature::Float => Some(Kind::Float),
+        TypeSignature::Double => Some(Kind::Double),
+        TypeSignature::Class(_) | TypeSignature::Array(_) | TypeSignature::Arbitrary => Some(Kind::Reference),
+        TypeSignature::Void => None,
+    }
+}
+
+fn constant_kind(constant: &ConstantValue) -> Kind {
+    match constant {
+        ConstantValue::Integer(_) => Kind::Int,
+        ConstantValue::Long(_) => Kind::Long,
+        ConstantValue::Float(_) => Kind::Float,
+        ConstantValue::Double(_) => Kind::Double,
+        ConstantValue::Dynamic { descriptor, .. } => {
+            parse_field_descriptor(descriptor).ok().as_ref().and_then(kind_of).unwrap_or(Kind::Reference)
+        }
+        _ => Kind::Reference,
+    }
+}
+
+/// The name of a class as used by `CONSTANT_Class`, array classes are named by their descriptor
+fn class_name(ty: &TypeSignature) -> Option<String> {
+    match ty {
+        TypeSignature::Class(name) => Some(name.clone()),
+        TypeSignature::Array(_) => Some(ty.descriptor()),
+        TypeSignature::Arbitrary => Some("java/lang/Object".to_string()),
+        _ => None,
+    }
+}
+
+fn array_type(element: &TypeSignature) -> Option<ArrayType> {
+    match element {
+        TypeSignature::Boolean => Some(ArrayType::Boolean),
+        TypeSignature::Char => Some(ArrayType::Char),
+        TypeSignature::Float => Some(ArrayType::Float),
+        TypeSignature::Double => Some(ArrayType::Double),
+        TypeSignature::Byte => Some(ArrayType::Byte),
+        TypeSignature::Short => Some(ArrayType::Short),
+        TypeSignature::Integer => Some(ArrayType::Int),
+        TypeSignature::Long => Some(ArrayType::Long),
+        _ => None,
+    }
+}
+
+fn sized_index(slot: u16) -> SizedIndex {
+    match u8::try_from(slot) {
+        Ok(slot) => SizedIndex::Normal(SmallIndex(slot)),
+        Err(_) => SizedIndex::Wide(WideIndex(slot)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jbmf_ir::block::BasicBlock;
+    use jbmf_ir::flow_graph::{Edge, FlowGraph};
+    use jbmf_ir::function::ExceptionHandler;
+
+    fn function(descriptor: &str, blocks: Vec<(u64, Vec<StatementKind>)>, edges: Vec<(u64, u64, EdgeKind)>) -> Function {
+        let mut graph = FlowGraph::new();
+        graph.vertices.extend(blocks.iter().map(|(start, _)| *start));
+        graph.edges = edges
+            .into_iter()
+            .map(|(source, target, kind)| Edge { source, target, kind })
+            .collect();
+
+        Function {
+            owner: "Test".to_string(),
+            name: "test".to_string(),
+            descriptor: descriptor.to_string(),
+            blocks: blocks
+                .into_iter()
+                .map(|(beg_index, statements)| {
+                    (
+                        beg_index,
+                        BasicBlock {
+                            beg_index,
+                            end_index: beg_index,
+                            statements: statements.into_iter().map(Statement::new).collect(),
+                        },
+                    )
+                })
+                .collect(),
+            graph,
+            start: 0,
+            handlers: Vec::new(),
+        }
+    }
+
+    fn constant(value: ConstantValue) -> Value {
+        Value::Constant(value)
+    }
+
+    #[test]
+    fn conditional_return() {
+        let function = function(
+            "(I)I",
+            vec![
+                (
+                    0,
+                    vec![StatementKind::Flow(FlowStatementKind::ConditionalJump {
+                        condition: Condition {
+                            operation: ComparisonOperation::Equal,
+                            left: Value::Variable(Variable::Local(0)),
+                            right: constant(ConstantValue::Integer(0)),
+                        },
+                        target: 8,
+                    })],
+                ),
+                (
+                    4,
+                    vec![StatementKind::Flow(FlowStatementKind::Return(Some(constant(
+                        ConstantValue::Integer(1),
+                    ))))],
+                ),
+                (
+                    8,
+                    vec![StatementKind::Flow(FlowStatementKind::Return(Some(constant(
+                        ConstantValue::Integer(200),
+                    ))))],
+                ),
+            ],
+            vec![(0, 8, EdgeKind::Branch), (0, 4, EdgeKind::FallThrough)],
+        );
+
+        let code = generate_code(&function, true, &mut ConstantPool(Vec::new())).unwrap();
+
+        assert_eq!(
+            code.instructions,
+            vec![
+                Instruction::ILoad0,
+                Instruction::IfEq { offset: 5 },
+                Instruction::IConst1,
+                Instruction::IReturn,
+                Instruction::SIPush { value: 200 },
+                Instruction::IReturn,
+            ]
+        );
+        assert_eq!((code.max_stack, code.max_locals), (1, 1));
+    }
+
+    #[test]
+    fn handler_and_wide_temporaries() {
+        let mut function = function(
+            "(J)V",
+            vec![
+                (
+                    0,
+                    vec![
+                        StatementKind::Assign {
+                            target: Variable::Temporary(0),
+                            ty: TypeSignature::Long,
+                            value: Expression::Binary {
+                                operation: BinaryOperation::Addition,
+                                left: Value::Variable(Variable::Local(1)),
+                                right: constant(ConstantValue::Long(1)),
+                                ty: TypeSignature::Long,
+                            },
+                        },
+                        StatementKind::Assign {
+                            target: Variable::Local(1),
+                            ty: TypeSignature::Long,
+                            value: Expression::Value(Value::Variable(Variable::Temporary(0))),
+                        },
+                        StatementKind::Flow(FlowStatementKind::Return(None)),
+                    ],
+                ),
+                (
+                    9,
+                    vec![
+                        StatementKind::Assign {
+                            target: Variable::Stack(0),
+                            ty: TypeSignature::Class("java/lang/Exception".to_string()),
+                            value: Expression::CaughtException(TypeSignature::Class(
+                                "java/lang/Exception".to_string(),
+                            )),
+                        },
+                        StatementKind::Flow(FlowStatementKind::Throw(Value::Variable(Variable::Stack(0)))),
+                    ],
+                ),
+            ],
+            vec![(0, 9, EdgeKind::Exception(Some("java/lang/Exception".to_string())))],
+        );
+        function.handlers.push(ExceptionHandler {
+            start: 0,
+            end: 9,
+            handler: 9,
+            catch_type: Some("java/lang/Exception".to_string()),
+        });
+
+        let mut pool = ConstantPool(Vec::new());
+        let code = generate_code(&function, false, &mut pool).unwrap();
+
+        // this, the long parameter, then the stack variable and the long temporary
+        assert_eq!(
+            code.instructions,
+            vec![
+                Instruction::LLoad1,
+                Instruction::LConst1,
+                Instruction::LAdd,
+                Instruction::LStore {
+                    index: SizedIndex::Normal(SmallIndex(4))
+                },
+                Instruction::LLoad {
+                    index: SizedIndex::Normal(SmallIndex(4))
+                },
+                Instruction::LStore1,
+                Instruction::Return,
+                Instruction::AStore3,
+                Instruction::ALoad3,
+                Instruction::AThrow,
+            ]
+        );
+        assert_eq!(
+            code.exception_table,
+            vec![ExceptionTable {
+                start_pc: 0,
+                end_pc: 9,
+                handler_pc: 9,
+                catch_type: ConstantPoolIndex(2),
+            }]
+        );
+        assert_eq!((code.max_stack, code.max_locals), (4, 6));
+    }
+}
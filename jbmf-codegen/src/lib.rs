@@ -0,0 +1,141 @@
+use jbmf_error::{CodegenError, Error};
+use jbmf_ir::function::Function;
+use jbmf_parser::java_rs_base::io::SizedVec;
+use jbmf_parser::java_rs_pacific::attribute::{Attribute, Compatibility};
+use jbmf_parser::java_rs_pacific::{AccessFlags, ConstantPool, ConstantPoolBuilder, Method};
+
+use crate::generator::{function_error, generate_code};
+
+pub mod assembler;
+pub mod generator;
+
+/// Generates the code of a function and stores it as the code attribute of a method, adding the
+/// constants it refers to to the pool. Attributes nested in a previous code attribute describe the old
+/// instructions and are dropped, including the `StackMapTable`. Classes of version 50 and later therefore have
+/// to be written with `WriteOptions::compute_frames`
+pub fn write_method(function: &Function, constant_pool: &mut ConstantPool, method: &mut Method) -> Result<(), Error> {
+    let is_static = method.access_flags.contains(AccessFlags::STATIC);
+    let generated = generate_code(function, is_static, constant_pool)?;
+
+    let existing = method
+        .attributes
+        .iter()
+        .position(|attribute| matches!(attribute, Attribute::Code { .. }));
+    let (name, pre_java_1) = match existing.map(|index| &method.attributes[index]) {
+        Some(Attribute::Code { name, code, .. }) => (*name, matches!(code, Compatibility::PreJava1(_))),
+        _ => (ConstantPoolBuilder::new(constant_pool).utf8("Code")?, false),
+    };
+
+    let code = if pre_java_1 {
+        let max_stack = u8::try_from(generated.max_stack)
+            .map_err(|_| function_error(function, CodegenError::MaxStackTooLarge(generated.max_stack)))?;
+        let max_locals = u8::try_from(generated.max_locals)
+            .map_err(|_| function_error(function, CodegenError::MaxLocalsTooLarge(generated.max_locals)))?;
+        Attribute::Code {
+            name,
+            max_stack: Compatibility::PreJava1(max_stack),
+            max_locals: Compatibility::PreJava1(max_locals),
+            code: Compatibility::PreJava1(generated.instructions.into()),
+            exception_table: generated.exception_table.into(),
+            attributes: SizedVec::new(),
+        }
+    } else {
+        Attribute::Code {
+            name,
+            max_stack: Compatibility::Current(generated.max_stack),
+            max_locals: Compatibility::Current(generated.max_locals),
+            code: Compatibility::Current(generated.instructions.into()),
+            exception_table: generated.exception_table.into(),
+            attributes: SizedVec::new(),
+        }
+    };
+
+    match existing {
+        Some(index) => method.attributes[index] = code,
+        None => method.attributes.push(code),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use jbmf_lifter::lifter::lift_method;
+    use jbmf_parser::java_rs_pacific::analysis::frames::StaticHierarchy;
+    use jbmf_parser::java_rs_pacific::attribute::Instruction;
+    use jbmf_parser::java_rs_pacific::{Constant, ConstantPoolIndex, JavaClass, JavaVersion, MagicNumber, WriteOptions};
+
+    use super::*;
+
+    #[test]
+    fn frames_are_recomputed_on_write() -> Result<(), Error> {
+        // iload_0; ifeq 6; iconst_1; ireturn; iconst_0; ireturn
+        let code = vec![
+            Instruction::ILoad0,
+            Instruction::IfEq { offset: 5 },
+            Instruction::IConst1,
+            Instruction::IReturn,
+            Instruction::IConst0,
+            Instruction::IReturn,
+        ];
+        let mut class = JavaClass {
+            magic: MagicNumber::Cafebabe,
+            version: JavaVersion { major: 52, minor: 0 },
+            constant_pool: vec![
+                Constant::Class(ConstantPoolIndex(2)),
+                Constant::Utf8("a/A".into()),
+                Constant::Utf8("m".into()),
+                Constant::Utf8("(I)I".into()),
+                Constant::Utf8("Code".into()),
+            ]
+            .into(),
+            access_flags: AccessFlags::NONE,
+            this_class: ConstantPoolIndex(1),
+            super_class: ConstantPoolIndex(0),
+            interfaces: SizedVec::new(),
+            fields: SizedVec::new(),
+            methods: vec![Method {
+                access_flags: AccessFlags::STATIC,
+                name: ConstantPoolIndex(3),
+                descriptor: ConstantPoolIndex(4),
+                attributes: vec![Attribute::Code {
+                    name: ConstantPoolIndex(5),
+                    max_stack: Compatibility::Current(1),
+                    max_locals: Compatibility::Current(1),
+                    code: Compatibility::Current(code.into()),
+                    exception_table: SizedVec::new(),
+                    attributes: SizedVec::new(),
+                }]
+                .into(),
+            }]
+            .into(),
+            attributes: SizedVec::new(),
+        };
+
+        let function = lift_method(&class, &class.methods[0])?.unwrap();
+        write_method(&function, &mut class.constant_pool, &mut class.methods[0])?;
+
+        let hierarchy = StaticHierarchy {
+            superclasses: HashMap::new(),
+            interfaces: HashSet::new(),
+        };
+        let options = WriteOptions {
+            compute_max_values: false,
+            compute_frames: Some(&hierarchy),
+        };
+        let mut bytes = Vec::new();
+        class.write_with_options(&mut bytes, options)?;
+
+        let written = JavaClass::read(&mut bytes.as_slice())?;
+        let frames = match &written.methods[0].attributes[0] {
+            Attribute::Code { attributes, .. } => attributes
+                .iter()
+                .any(|attribute| matches!(attribute, Attribute::StackMapTable { .. })),
+            _ => false,
+        };
+        assert!(frames);
+        Ok(())
+    }
+}
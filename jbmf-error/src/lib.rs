@@ -1,10 +1,216 @@
 use thiserror::Error;
 
+use java_rs_base::descriptor::TypeSignature;
+pub use java_rs_base::error::{ConstantPoolError, DescriptorError, Error as ClassFileError};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("Failed to parse class file: {0}")]
-    ParserError(#[from] std::io::Error),
+    ParserError(#[from] ParserError),
+
+    #[error("Constant pool error: {0}")]
+    ConstantPoolError(#[from] ConstantPoolError),
+
+    #[error("Failed to analyse code: {0}")]
+    AnalysisError(#[from] AnalysisError),
+
+    #[error("Failed to lift {0}")]
+    LifterError(#[from] LifterError),
+
+    #[error("Invalid descriptor {0}")]
+    DescriptorError(#[from] DescriptorError),
+
+    #[error("Invalid signature {0}")]
+    SignatureError(#[from] SignatureError),
+
+    #[error(
+        "Failed to generate code for {method}{}: {source}",
+        block.map(|block| format!(" block {}", block)).unwrap_or_default()
+    )]
+    CodegenError {
+        method: String,
+        block: Option<u64>,
+        source: CodegenError,
+    },
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        ParserError::IO(error).into()
+    }
+}
+
+impl From<ClassFileError> for Error {
+    fn from(error: ClassFileError) -> Self {
+        ParserError::ClassFile(error).into()
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ParserError {
+    #[error("An IO error occurred: {0}")]
+    IO(#[from] std::io::Error),
+
+    #[error(transparent)]
+    ClassFile(#[from] ClassFileError),
+
+    #[error("Invalid archive: {0}")]
+    InvalidArchive(String),
+
+    #[error("Failed to parse archive entry {name}: {source}")]
+    ArchiveEntry { name: String, source: ClassFileError },
+}
+
+#[derive(Error, Debug)]
+pub enum AnalysisError {
+    #[error("Method has no instructions")]
+    EmptyCode,
+
+    #[error("Branch target of instruction at {position} lies before the start of the code")]
+    NegativeBranchTarget { position: u64 },
+
+    #[error("Branch target {target} of instruction at {position} is not the start of an instruction")]
+    InvalidBranchTarget { position: u64, target: u64 },
+
+    #[error("Position {position} of exception handler at {handler} is not the start of an instruction")]
+    InvalidHandlerPosition { handler: u64, position: u64 },
+
+    #[error("Exception handler at {handler} has invalid protected range {start}..{end}")]
+    InvalidProtectedRange { handler: u64, start: u64, end: u64 },
+
+    #[error("Execution falls off the end of the code after instruction at {position}")]
+    FallsOffEnd { position: u64 },
+}
+
+#[derive(Error, Debug)]
+pub enum LifterError {
+    #[error("instruction at {position}: operand stack underflow")]
+    StackUnderflow { position: u64 },
+
+    #[error("instruction at {position}: instruction splits a category 2 value")]
+    SplitsCategory2Value { position: u64 },
+
+    #[error("instruction at {position}: instruction follows a block terminator")]
+    FollowsTerminator { position: u64 },
+
+    #[error("block at {block}: inconsistent stack depth on entry, {expected} and {found}")]
+    InconsistentStackDepth { block: u64, expected: usize, found: usize },
+
+    #[error("block at {block}: stack entry {depth} is {expected} on one path and {found} on another")]
+    InconsistentStackType {
+        block: u64,
+        depth: usize,
+        expected: TypeSignature,
+        found: TypeSignature,
+    },
+
+    #[error("block at {block}: exception handler is also reached by normal control flow")]
+    FlowIntoHandler { block: u64 },
+}
+
+#[derive(Error, Debug)]
+pub enum CodegenError {
+    #[error("void parameter")]
+    VoidParameter,
+
+    #[error("void assignment")]
+    VoidAssignment,
+
+    #[error("void operand")]
+    VoidOperand,
+
+    #[error("void field")]
+    VoidField,
+
+    #[error("void array element")]
+    VoidArrayElement,
+
+    #[error("void conversion")]
+    VoidConversion,
+
+    #[error("return address outside of a jsr block")]
+    ReturnAddressOutsideJsrBlock,
+
+    #[error("callers of a subroutine pass the return address in different variables")]
+    InconsistentReturnAddress,
+
+    #[error("return site {return_site} of a jsr does not follow it")]
+    ReturnSiteNotAfterJsr { return_site: u64 },
+
+    #[error("return address used as a value")]
+    ReturnAddressAsValue,
+
+    #[error("block falls through without a successor")]
+    MissingFallThrough,
+
+    #[error("caught exception is not the first statement of a handler")]
+    CaughtExceptionNotFirst,
+
+    #[error("caught exception is only valid as an assignment")]
+    CaughtExceptionNotAssigned,
+
+    #[error("value returned from a void method")]
+    ValueReturnedFromVoidMethod,
+
+    #[error("cannot determine the operand type of a comparison")]
+    UnknownComparisonType,
+
+    #[error("cannot determine the type of a value")]
+    UnknownValueType,
+
+    #[error("ordered comparison of references")]
+    OrderedReferenceComparison,
+
+    #[error("conditional jump on a long or floating point value")]
+    UnsupportedConditionalJump,
+
+    #[error("unary operation on an unsupported type")]
+    UnsupportedUnaryOperation,
+
+    #[error("binary operation on an unsupported type")]
+    UnsupportedBinaryOperation,
+
+    #[error("conversion between reference and primitive types")]
+    ReferenceConversion,
+
+    #[error("invalid array allocation")]
+    InvalidArrayAllocation,
+
+    #[error("class reference to a primitive type")]
+    PrimitiveClassReference,
+
+    #[error("{found} arguments passed for {expected} parameters of {descriptor}")]
+    ArgumentCountMismatch { descriptor: String, expected: usize, found: usize },
+
+    #[error("function is still in SSA form")]
+    SsaForm,
+
+    #[error("undefined label {0}")]
+    UndefinedLabel(u64),
+
+    #[error("max stack {0} exceeds 255")]
+    MaxStackTooLarge(u16),
+
+    #[error("max locals {0} exceeds 255")]
+    MaxLocalsTooLarge(u16),
+
+    #[error("failed to lower the instructions: {0}")]
+    Lowering(#[from] ClassFileError),
+}
+
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum SignatureError {
+    #[error("{signature}: unexpected end")]
+    UnexpectedEnd { signature: String },
+
+    #[error("{signature}: unexpected character {found:?} at {position}")]
+    UnexpectedCharacter { signature: String, position: usize, found: char },
+
+    #[error("{signature}: array type at {position} has more than 255 dimensions")]
+    TooManyDimensions { signature: String, position: usize },
 
-    #[error("Failed to lift {0} because of {1}")]
-    LifterError(String, String),
+    #[error("{signature}: type arguments at {position} are nested more than 64 levels deep")]
+    TooDeeplyNested { signature: String, position: usize },
 }
@@ -0,0 +1,424 @@
+//! Structural analyses of a [`FlowGraph`]: traversal orders, dominance and natural loops.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::Hash;
+
+use crate::flow_graph::{FlowEdge, FlowGraph};
+
+/// Successor and predecessor lists of every vertex, computed once for repeated queries
+pub struct Adjacency<V> {
+    pub successors: HashMap<V, Vec<V>>,
+    pub predecessors: HashMap<V, Vec<V>>,
+}
+
+impl<V: Copy + Eq + Hash + Ord> Adjacency<V> {
+    pub fn new<E: FlowEdge<V>>(graph: &FlowGraph<V, E>) -> Self {
+        let mut successors: HashMap<V, Vec<V>> = graph.vertices.iter().map(|vertex| (*vertex, Vec::new())).collect();
+        let mut predecessors = successors.clone();
+
+        for edge in graph.edges.iter() {
+            successors.entry(edge.source()).or_default().push(edge.target());
+            predecessors.entry(edge.target()).or_default().push(edge.source());
+        }
+
+        for list in successors.values_mut().chain(predecessors.values_mut()) {
+            list.sort();
+            list.dedup();
+        }
+
+        Self {
+            successors,
+            predecessors,
+        }
+    }
+
+    pub fn successors(&self, vertex: V) -> &[V] {
+        self.successors.get(&vertex).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn predecessors(&self, vertex: V) -> &[V] {
+        self.predecessors.get(&vertex).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+/// Vertices reachable from `entry` in reverse postorder, so every vertex precedes its successors except
+/// along back edges
+pub fn reverse_postorder<V: Copy + Eq + Hash + Ord>(adjacency: &Adjacency<V>, entry: V) -> Vec<V> {
+    depth_first_postorder(&[entry], |vertex| adjacency.successors(vertex))
+        .into_iter()
+        .rev()
+        .collect()
+}
+
+fn depth_first_postorder<'a, V: Copy + Eq + Hash + 'a>(roots: &[V], next: impl Fn(V) -> &'a [V]) -> Vec<V> {
+    let mut visited = HashSet::new();
+    let mut postorder = Vec::new();
+
+    for root in roots {
+        if !visited.insert(*root) {
+            continue;
+        }
+
+        let mut stack = vec![(*root, next(*root).iter())];
+        while let Some((vertex, remaining)) = stack.last_mut() {
+            match remaining.next() {
+                Some(successor) => {
+                    if visited.insert(*successor) {
+                        stack.push((*successor, next(*successor).iter()));
+                    }
+                }
+                None => {
+                    postorder.push(*vertex);
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    postorder
+}
+
+/// A dominator or post-dominator tree. Post-dominators are computed relative to a virtual exit which succeeds
+/// every vertex without successors, so the tree can have several roots
+pub struct DominatorTree<V> {
+    roots: Vec<V>,
+    immediate_dominators: HashMap<V, V>,
+    children: BTreeMap<V, Vec<V>>,
+    post: bool,
+}
+
+impl<V: Copy + Eq + Hash + Ord> DominatorTree<V> {
+    /// Dominators of the vertices reachable from `entry`
+    pub fn dominators(adjacency: &Adjacency<V>, entry: V) -> Self {
+        Self::compute(
+            &[entry],
+            |vertex| adjacency.successors(vertex),
+            |vertex| adjacency.predecessors(vertex),
+            false,
+        )
+    }
+
+    /// Post-dominators of the vertices from which a vertex without successors is reachable. Vertices that
+    /// are only part of infinite loops are not contained in the tree
+    pub fn post_dominators(adjacency: &Adjacency<V>) -> Self {
+        let mut exits: Vec<V> = adjacency
+            .successors
+            .iter()
+            .filter(|(_, successors)| successors.is_empty())
+            .map(|(vertex, _)| *vertex)
+            .collect();
+        exits.sort();
+
+        Self::compute(
+            &exits,
+            |vertex| adjacency.predecessors(vertex),
+            |vertex| adjacency.successors(vertex),
+            true,
+        )
+    }
+
+    /// The iterative algorithm by Cooper, Harvey and Kennedy, run from a virtual root preceding all `roots`
+    fn compute<'a>(roots: &[V], next: impl Fn(V) -> &'a [V], previous: impl Fn(V) -> &'a [V], post: bool) -> Self
+    where
+        V: 'a,
+    {
+        let order: Vec<V> = depth_first_postorder(roots, &next).into_iter().rev().collect();
+        // Index 0 is the virtual root, every vertex is numbered by its reverse postorder position
+        let indices: HashMap<V, usize> = order.iter().enumerate().map(|(index, vertex)| (*vertex, index + 1)).collect();
+        let roots: HashSet<V> = roots.iter().copied().collect();
+
+        let mut dominators: Vec<Option<usize>> = vec![None; order.len() + 1];
+        dominators[0] = Some(0);
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for (position, vertex) in order.iter().enumerate() {
+                let index = position + 1;
+                let mut processed = previous(*vertex)
+                    .iter()
+                    .filter_map(|predecessor| indices.get(predecessor).copied())
+                    .chain(roots.contains(vertex).then_some(0))
+                    .filter(|predecessor| dominators[*predecessor].is_some());
+
+                let first = match processed.next() {
+                    Some(first) => first,
+                    None => continue,
+                };
+                let new_dominator = processed.fold(first, |mut a, mut b| {
+                    while a != b {
+                        while a > b {
+                            a = dominators[a].unwrap();
+                        }
+                        while b > a {
+                            b = dominators[b].unwrap();
+                        }
+                    }
+                    a
+                });
+
+                if dominators[index] != Some(new_dominator) {
+                    dominators[index] = Some(new_dominator);
+                    changed = true;
+                }
+            }
+        }
+
+        let mut tree = Self {
+            roots: Vec::new(),
+            immediate_dominators: HashMap::new(),
+            children: BTreeMap::new(),
+            post,
+        };
+
+        for (position, vertex) in order.iter().enumerate() {
+            match dominators[position + 1] {
+                Some(0) => tree.roots.push(*vertex),
+                Some(dominator) => {
+                    let dominator = order[dominator - 1];
+                    tree.immediate_dominators.insert(*vertex, dominator);
+                    tree.children.entry(dominator).or_default().push(*vertex);
+                }
+                None => unreachable!(),
+            }
+        }
+
+        tree.roots.sort();
+        for children in tree.children.values_mut() {
+            children.sort();
+        }
+
+        tree
+    }
+
+    /// Vertices without an immediate dominator, the entry for dominator trees
+    pub fn roots(&self) -> &[V] {
+        &self.roots
+    }
+
+    pub fn contains(&self, vertex: V) -> bool {
+        self.immediate_dominators.contains_key(&vertex) || self.roots.contains(&vertex)
+    }
+
+    pub fn immediate_dominator(&self, vertex: V) -> Option<V> {
+        self.immediate_dominators.get(&vertex).copied()
+    }
+
+    /// The vertices immediately dominated by `vertex`, in ascending order
+    pub fn children(&self, vertex: V) -> &[V] {
+        self.children.get(&vertex).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Whether `dominator` (post-)dominates `vertex`, every vertex dominates itself
+    pub fn dominates(&self, dominator: V, vertex: V) -> bool {
+        let mut current = Some(vertex);
+
+        while let Some(vertex) = current {
+            if vertex == dominator {
+                return true;
+            }
+            current = self.immediate_dominator(vertex);
+        }
+
+        false
+    }
+
+    /// The (post-)dominance frontier of every vertex in the tree: the vertices where its dominance ends
+    pub fn frontiers(&self, adjacency: &Adjacency<V>) -> HashMap<V, BTreeSet<V>> {
+        let mut frontiers: HashMap<V, BTreeSet<V>> = HashMap::new();
+
+        for vertex in self.roots.iter().chain(self.immediate_dominators.keys()) {
+            let joined = if self.post {
+                adjacency.successors(*vertex)
+            } else {
+                adjacency.predecessors(*vertex)
+            };
+            let joined: Vec<V> = joined.iter().copied().filter(|other| self.contains(*other)).collect();
+            // Roots are additionally joined from the virtual root
+            let joined_count = joined.len() + usize::from(self.roots.contains(vertex));
+            if joined_count < 2 {
+                continue;
+            }
+
+            let dominator = self.immediate_dominator(*vertex);
+            for other in joined {
+                let mut runner = Some(other);
+                while runner.is_some() && runner != dominator {
+                    let current = runner.unwrap();
+                    frontiers.entry(current).or_default().insert(*vertex);
+                    runner = self.immediate_dominator(current);
+                }
+            }
+        }
+
+        frontiers
+    }
+}
+
+/// A natural loop, formed by all back edges into the same header
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Loop<V> {
+    pub header: V,
+    /// Sources of the back edges into the header
+    pub latches: Vec<V>,
+    /// Every vertex of the loop including the header and the vertices of nested loops
+    pub body: BTreeSet<V>,
+    /// Index of the innermost enclosing loop
+    pub parent: Option<usize>,
+    /// Indices of the loops directly nested in this loop
+    pub children: Vec<usize>,
+    /// Nesting depth, one for outermost loops
+    pub depth: usize,
+}
+
+/// All natural loops of a graph together with their nesting. Loops are ordered such that enclosing loops
+/// precede the loops nested in them
+pub struct LoopForest<V> {
+    pub loops: Vec<Loop<V>>,
+}
+
+impl<V: Copy + Eq + Hash + Ord> LoopForest<V> {
+    /// Finds the natural loops of all back edges, i.e. edges whose target dominates their source
+    pub fn new(adjacency: &Adjacency<V>, dominators: &DominatorTree<V>) -> Self {
+        let mut latches: BTreeMap<V, Vec<V>> = BTreeMap::new();
+
+        for (source, successors) in adjacency.successors.iter() {
+            if !dominators.contains(*source) {
+                continue;
+            }
+            for target in successors {
+                if dominators.dominates(*target, *source) {
+                    latches.entry(*target).or_default().push(*source);
+                }
+            }
+        }
+
+        let mut loops: Vec<Loop<V>> = latches
+            .into_iter()
+            .map(|(header, mut latches)| {
+                latches.sort();
+
+                let mut body = BTreeSet::from([header]);
+                let mut worklist = latches.clone();
+                while let Some(vertex) = worklist.pop() {
+                    if body.insert(vertex) {
+                        worklist.extend(
+                            adjacency
+                                .predecessors(vertex)
+                                .iter()
+                                .filter(|predecessor| dominators.contains(**predecessor)),
+                        );
+                    }
+                }
+
+                Loop {
+                    header,
+                    latches,
+                    body,
+                    parent: None,
+                    children: Vec::new(),
+                    depth: 1,
+                }
+            })
+            .collect();
+
+        // Natural loops with different headers are either disjoint or nested, so the smaller one is inside
+        loops.sort_by(|a, b| b.body.len().cmp(&a.body.len()).then(a.header.cmp(&b.header)));
+
+        for index in 0..loops.len() {
+            let parent = (0..index)
+                .rev()
+                .find(|candidate| loops[*candidate].body.contains(&loops[index].header));
+
+            if let Some(parent) = parent {
+                loops[index].parent = Some(parent);
+                loops[index].depth = loops[parent].depth + 1;
+                loops[parent].children.push(index);
+            }
+        }
+
+        Self { loops }
+    }
+
+    /// Index of the innermost loop containing `vertex`
+    pub fn innermost(&self, vertex: V) -> Option<usize> {
+        (0..self.loops.len()).rev().find(|index| self.loops[*index].body.contains(&vertex))
+    }
+
+    /// Nesting depth of `vertex`, zero outside of all loops
+    pub fn depth(&self, vertex: V) -> usize {
+        self.innermost(vertex).map(|index| self.loops[index].depth).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(edges: &[(u64, u64)]) -> FlowGraph<u64, (u64, u64)> {
+        let mut graph = FlowGraph::new();
+        for (source, target) in edges {
+            graph.vertices.insert(*source);
+            graph.vertices.insert(*target);
+        }
+        graph.edges = edges.to_vec();
+        graph
+    }
+
+    #[test]
+    fn diamond_dominance() {
+        let adjacency = Adjacency::new(&graph(&[(0, 1), (0, 2), (1, 3), (2, 3)]));
+
+        assert_eq!(reverse_postorder(&adjacency, 0), vec![0, 2, 1, 3]);
+
+        let dominators = DominatorTree::dominators(&adjacency, 0);
+        assert_eq!(dominators.roots(), &[0]);
+        assert_eq!(dominators.children(0), &[1, 2, 3]);
+        assert!(dominators.dominates(0, 3));
+        assert!(!dominators.dominates(1, 3));
+        assert_eq!(dominators.frontiers(&adjacency)[&1], BTreeSet::from([3]));
+
+        let post_dominators = DominatorTree::post_dominators(&adjacency);
+        assert_eq!(post_dominators.roots(), &[3]);
+        assert_eq!(post_dominators.immediate_dominator(0), Some(3));
+        assert_eq!(post_dominators.frontiers(&adjacency)[&1], BTreeSet::from([0]));
+    }
+
+    #[test]
+    fn loop_to_entry() {
+        let adjacency = Adjacency::new(&graph(&[(0, 1), (1, 0), (1, 2)]));
+        let dominators = DominatorTree::dominators(&adjacency, 0);
+
+        assert_eq!(dominators.frontiers(&adjacency)[&1], BTreeSet::from([0]));
+    }
+
+    #[test]
+    fn multiple_exits() {
+        // 0 -> 1 -> return, 0 -> 2 -> throw
+        let adjacency = Adjacency::new(&graph(&[(0, 1), (0, 2)]));
+        let post_dominators = DominatorTree::post_dominators(&adjacency);
+
+        assert_eq!(post_dominators.roots(), &[0, 1, 2]);
+        assert_eq!(post_dominators.immediate_dominator(0), None);
+    }
+
+    #[test]
+    fn nested_loops() {
+        // 0 -> 1 -> 2 -> 3 -> 2, 3 -> 4 -> 1, 4 -> 5
+        let adjacency = Adjacency::new(&graph(&[(0, 1), (1, 2), (2, 3), (3, 2), (3, 4), (4, 1), (4, 5)]));
+        let dominators = DominatorTree::dominators(&adjacency, 0);
+        let forest = LoopForest::new(&adjacency, &dominators);
+
+        assert_eq!(forest.loops.len(), 2);
+        assert_eq!(forest.loops[0].header, 1);
+        assert_eq!(forest.loops[0].body, BTreeSet::from([1, 2, 3, 4]));
+        assert_eq!(forest.loops[0].children, vec![1]);
+        assert_eq!(forest.loops[1].header, 2);
+        assert_eq!(forest.loops[1].latches, vec![3]);
+        assert_eq!(forest.loops[1].parent, Some(0));
+        assert_eq!(forest.depth(3), 2);
+        assert_eq!(forest.depth(4), 1);
+        assert_eq!(forest.depth(5), 0);
+    }
+}
@@ -3,5 +3,7 @@ use crate::statement::Statement;
 #[derive(Clone, Debug, Eq, PartialEq, PartialOrd)]
 pub struct BasicBlock {
     pub beg_index: u64,
+    /// Byte position directly after the last instruction of the block
+    pub end_index: u64,
     pub statements: Vec<Statement>,
 }
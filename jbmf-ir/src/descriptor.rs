@@ -0,0 +1 @@
+pub use java_rs_base::descriptor::{parse_field_descriptor, MethodDescriptor};
@@ -0,0 +1,288 @@
+use std::fmt::{Display, Formatter};
+
+use crate::statement::{BinaryOperation, TypeSignature, UnaryOperation};
+use crate::variable::Variable;
+
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub enum ConstantValue {
+    Null,
+    Integer(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(String),
+    Class(TypeSignature),
+    MethodType(String),
+    MethodHandle {
+        reference_kind: u8,
+        reference: MemberReference,
+    },
+    Dynamic {
+        bootstrap_method: u16,
+        name: String,
+        descriptor: String,
+    },
+    /// The return address pushed by `jsr`, given as the byte position of the following instruction
+    ReturnAddress(u64),
+}
+
+impl Eq for ConstantValue {}
+
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd)]
+pub enum Value {
+    Constant(ConstantValue),
+    Variable(Variable),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd)]
+pub struct MemberReference {
+    pub owner: String,
+    pub name: String,
+    pub descriptor: String,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd)]
+pub enum InvokeKind {
+    Virtual,
+    Special,
+    Static,
+    Interface,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd)]
+pub enum Expression {
+    Value(Value),
+    Unary {
+        operation: UnaryOperation,
+        operand: Value,
+        ty: TypeSignature,
+    },
+    Binary {
+        operation: BinaryOperation,
+        left: Value,
+        right: Value,
+        ty: TypeSignature,
+    },
+    Convert {
+        value: Value,
+        from: TypeSignature,
+        to: TypeSignature,
+    },
+    CheckCast {
+        value: Value,
+        ty: TypeSignature,
+    },
+    InstanceOf {
+        value: Value,
+        ty: TypeSignature,
+    },
+    New(TypeSignature),
+    /// Allocates an array of type `ty`, one length per created dimension
+    NewArray {
+        ty: TypeSignature,
+        dimensions: Vec<Value>,
+    },
+    ArrayLength(Value),
+    ArrayLoad {
+        array: Value,
+        index: Value,
+        ty: TypeSignature,
+    },
+    /// Reads a field, `object` is `None` for static fields
+    FieldLoad {
+        field: MemberReference,
+        object: Option<Value>,
+    },
+    Invoke {
+        kind: InvokeKind,
+        method: MemberReference,
+        receiver: Option<Value>,
+        arguments: Vec<Value>,
+    },
+    InvokeDynamic {
+        bootstrap_method: u16,
+        name: String,
+        descriptor: String,
+        arguments: Vec<Value>,
+    },
+    /// The exception object received by a handler, always the first statement of a handler block
+    CaughtException(TypeSignature),
+    /// Selects a value by the predecessor block (given by its start position) control arrived from.
+    /// Only present while the function is in SSA form, always at the start of a block
+    Phi(Vec<(u64, Value)>),
+}
+
+impl Expression {
+    /// The values read by the expression
+    pub fn values(&self) -> Vec<&Value> {
+        match self {
+            Expression::Value(value)
+            | Expression::Unary { operand: value, .. }
+            | Expression::Convert { value, .. }
+            | Expression::CheckCast { value, .. }
+            | Expression::InstanceOf { value, .. }
+            | Expression::ArrayLength(value) => vec![value],
+            Expression::Binary { left, right, .. } => vec![left, right],
+            Expression::ArrayLoad { array, index, .. } => vec![array, index],
+            Expression::NewArray { dimensions, .. } => dimensions.iter().collect(),
+            Expression::FieldLoad { object, .. } => object.iter().collect(),
+            Expression::Invoke {
+                receiver, arguments, ..
+            } => receiver.iter().chain(arguments.iter()).collect(),
+            Expression::InvokeDynamic { arguments, .. } => arguments.iter().collect(),
+            Expression::Phi(operands) => operands.iter().map(|(_, value)| value).collect(),
+            Expression::New(_) | Expression::CaughtException(_) => Vec::new(),
+        }
+    }
+
+    pub fn values_mut(&mut self) -> Vec<&mut Value> {
+        match self {
+            Expression::Value(value)
+            | Expression::Unary { operand: value, .. }
+            | Expression::Convert { value, .. }
+            | Expression::CheckCast { value, .. }
+            | Expression::InstanceOf { value, .. }
+            | Expression::ArrayLength(value) => vec![value],
+            Expression::Binary { left, right, .. } => vec![left, right],
+            Expression::ArrayLoad { array, index, .. } => vec![array, index],
+            Expression::NewArray { dimensions, .. } => dimensions.iter_mut().collect(),
+            Expression::FieldLoad { object, .. } => object.iter_mut().collect(),
+            Expression::Invoke {
+                receiver, arguments, ..
+            } => receiver.iter_mut().chain(arguments.iter_mut()).collect(),
+            Expression::InvokeDynamic { arguments, .. } => arguments.iter_mut().collect(),
+            Expression::Phi(operands) => operands.iter_mut().map(|(_, value)| value).collect(),
+            Expression::New(_) | Expression::CaughtException(_) => Vec::new(),
+        }
+    }
+}
+
+impl From<Variable> for Value {
+    fn from(variable: Variable) -> Self {
+        Value::Variable(variable)
+    }
+}
+
+impl From<ConstantValue> for Value {
+    fn from(constant: ConstantValue) -> Self {
+        Value::Constant(constant)
+    }
+}
+
+impl From<Value> for Expression {
+    fn from(value: Value) -> Self {
+        Expression::Value(value)
+    }
+}
+
+impl Display for ConstantValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConstantValue::Null => f.write_str("null"),
+            ConstantValue::Integer(value) => write!(f, "{}", value),
+            ConstantValue::Long(value) => write!(f, "{}L", value),
+            ConstantValue::Float(value) => write!(f, "{:?}F", value),
+            ConstantValue::Double(value) => write!(f, "{:?}D", value),
+            ConstantValue::String(value) => write!(f, "{:?}", value),
+            ConstantValue::Class(ty) => write!(f, "{}.class", ty),
+            ConstantValue::MethodType(descriptor) => write!(f, "methodtype {}", descriptor),
+            ConstantValue::MethodHandle {
+                reference_kind,
+                reference,
+            } => write!(f, "methodhandle {} {}", reference_kind, reference),
+            ConstantValue::Dynamic {
+                bootstrap_method,
+                name,
+                descriptor,
+            } => write!(f, "dynamic #{} {}:{}", bootstrap_method, name, descriptor),
+            ConstantValue::ReturnAddress(position) => write!(f, "@{}", position),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Constant(constant) => constant.fmt(f),
+            Value::Variable(variable) => variable.fmt(f),
+        }
+    }
+}
+
+impl Display for MemberReference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}:{}", self.owner, self.name, self.descriptor)
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expression::Value(value) => value.fmt(f),
+            Expression::Unary { operation, operand, .. } => write!(f, "{}{}", operation, operand),
+            Expression::Binary {
+                operation, left, right, ..
+            } => write!(f, "{} {} {}", left, operation, right),
+            Expression::Convert { value, to, .. } => write!(f, "({}) {}", to, value),
+            Expression::CheckCast { value, ty } => write!(f, "checkcast({}) {}", ty, value),
+            Expression::InstanceOf { value, ty } => write!(f, "{} instanceof {}", value, ty),
+            Expression::New(ty) => write!(f, "new {}", ty),
+            Expression::NewArray { ty, dimensions } => {
+                write!(f, "newarray {}", ty)?;
+                for dimension in dimensions {
+                    write!(f, "[{}]", dimension)?;
+                }
+                Ok(())
+            }
+            Expression::ArrayLength(array) => write!(f, "{}.length", array),
+            Expression::ArrayLoad { array, index, .. } => write!(f, "{}[{}]", array, index),
+            Expression::FieldLoad { field, object } => match object {
+                Some(object) => write!(f, "{}.{}", object, field.name),
+                None => write!(f, "{}.{}", field.owner, field.name),
+            },
+            Expression::Invoke {
+                kind,
+                method,
+                receiver,
+                arguments,
+            } => {
+                match receiver {
+                    Some(receiver) => write!(f, "{:?} {}.{}", kind, receiver, method.name)?,
+                    None => write!(f, "{:?} {}.{}", kind, method.owner, method.name)?,
+                }
+                write_arguments(f, arguments)
+            }
+            Expression::InvokeDynamic {
+                bootstrap_method,
+                name,
+                arguments,
+                ..
+            } => {
+                write!(f, "Dynamic #{}.{}", bootstrap_method, name)?;
+                write_arguments(f, arguments)
+            }
+            Expression::CaughtException(ty) => write!(f, "caught {}", ty),
+            Expression::Phi(operands) => {
+                f.write_str("phi(")?;
+                for (index, (predecessor, value)) in operands.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", predecessor, value)?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+fn write_arguments(f: &mut Formatter<'_>, arguments: &[Value]) -> std::fmt::Result {
+    f.write_str("(")?;
+    for (index, argument) in arguments.iter().enumerate() {
+        if index > 0 {
+            f.write_str(", ")?;
+        }
+        argument.fmt(f)?;
+    }
+    f.write_str(")")
+}
@@ -1,6 +1,102 @@
 use std::collections::HashSet;
+use std::hash::Hash;
 
 pub struct FlowGraph<V, E> {
     pub vertices: HashSet<V>,
     pub edges: Vec<E>,
 }
+
+impl<V: Eq + Hash, E> FlowGraph<V, E> {
+    pub fn new() -> Self {
+        Self {
+            vertices: HashSet::new(),
+            edges: Vec::new(),
+        }
+    }
+}
+
+impl<V: Eq + Hash, E> Default for FlowGraph<V, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An edge between two basic blocks, identified by their start positions
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Edge {
+    pub source: u64,
+    pub target: u64,
+    pub kind: EdgeKind,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum EdgeKind {
+    /// Execution continues with the next block, including the untaken side of a conditional jump
+    FallThrough,
+    /// Unconditional jump
+    Jump,
+    /// Taken side of a conditional jump
+    Branch,
+    /// Switch case with its key, `None` for the default case
+    Switch(Option<i32>),
+    /// `jsr` into a subroutine
+    Subroutine,
+    /// `ret` back to a possible return site of a subroutine
+    SubroutineReturn,
+    /// Transfer to an exception handler with the caught class, `None` for catch-all handlers
+    Exception(Option<String>),
+}
+
+/// An edge of a [`FlowGraph`], directed from its source to its target vertex
+pub trait FlowEdge<V> {
+    fn source(&self) -> V;
+    fn target(&self) -> V;
+}
+
+impl FlowEdge<u64> for Edge {
+    fn source(&self) -> u64 {
+        self.source
+    }
+
+    fn target(&self) -> u64 {
+        self.target
+    }
+}
+
+impl<V: Copy> FlowEdge<V> for (V, V) {
+    fn source(&self) -> V {
+        self.0
+    }
+
+    fn target(&self) -> V {
+        self.1
+    }
+}
+
+impl<V: Copy + Eq + Hash + Ord, E: FlowEdge<V>> FlowGraph<V, E> {
+    /// The distinct targets of all edges leaving `vertex`, in ascending order
+    pub fn successors(&self, vertex: V) -> Vec<V> {
+        let mut successors: Vec<V> = self
+            .edges
+            .iter()
+            .filter(|edge| edge.source() == vertex)
+            .map(|edge| edge.target())
+            .collect();
+        successors.sort();
+        successors.dedup();
+        successors
+    }
+
+    /// The distinct sources of all edges entering `vertex`, in ascending order
+    pub fn predecessors(&self, vertex: V) -> Vec<V> {
+        let mut predecessors: Vec<V> = self
+            .edges
+            .iter()
+            .filter(|edge| edge.target() == vertex)
+            .map(|edge| edge.source())
+            .collect();
+        predecessors.sort();
+        predecessors.dedup();
+        predecessors
+    }
+}
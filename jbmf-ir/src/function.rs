@@ -1,10 +1,26 @@
+use std::collections::BTreeMap;
+
 use crate::block::BasicBlock;
-use crate::flow_graph::FlowGraph;
+use crate::flow_graph::{Edge, FlowGraph};
 
 pub struct Function {
     pub owner: String,
     pub name: String,
     pub descriptor: String,
-    pub blocks: FlowGraph<BasicBlock, (i64, i64)>,
-    pub start: BasicBlock,
+    /// Blocks keyed by their start position, which is also their vertex in `graph`
+    pub blocks: BTreeMap<u64, BasicBlock>,
+    pub graph: FlowGraph<u64, Edge>,
+    pub start: u64,
+    pub handlers: Vec<ExceptionHandler>,
+}
+
+/// A protected range of the method together with the handler that catches exceptions thrown within it
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExceptionHandler {
+    pub start: u64,
+    /// Exclusive end of the protected range
+    pub end: u64,
+    pub handler: u64,
+    /// Internal name of the caught class, `None` for handlers which catch every exception (e.g. `finally`)
+    pub catch_type: Option<String>,
 }
@@ -1,4 +1,10 @@
+pub mod analysis;
 pub mod block;
+pub mod descriptor;
+pub mod expression;
 pub mod function;
+pub mod signature;
 pub mod statement;
 pub mod flow_graph;
+pub mod ssa;
+pub mod variable;
@@ -0,0 +1,611 @@
+use std::fmt::{Display, Formatter};
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use jbmf_error::SignatureError;
+
+const MAX_DIMENSIONS: usize = 255;
+/// Type arguments are parsed recursively, so their nesting is limited to keep malformed signatures from
+/// exhausting the stack
+const MAX_TYPE_ARGUMENT_DEPTH: usize = 64;
+
+/// Generic signature of a class, e.g. `<T:Ljava/lang/Object;>Ljava/util/AbstractList<TT;>;Ljava/io/Serializable;`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ClassSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub superclass: ClassTypeSignature,
+    pub interfaces: Vec<ClassTypeSignature>,
+}
+
+/// Generic signature of a method, e.g. `<T:Ljava/lang/Object;>(Ljava/util/List<TT;>;)TT;^Ljava/io/IOException;`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MethodSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub parameters: Vec<JavaTypeSignature>,
+    /// `None` for void methods
+    pub return_type: Option<JavaTypeSignature>,
+    /// Class types or type variables
+    pub throws: Vec<ReferenceTypeSignature>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TypeParameter {
+    pub name: String,
+    pub class_bound: Option<ReferenceTypeSignature>,
+    pub interface_bounds: Vec<ReferenceTypeSignature>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum JavaTypeSignature {
+    Base(BaseType),
+    Reference(ReferenceTypeSignature),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BaseType {
+    Byte,
+    Char,
+    Double,
+    Float,
+    Integer,
+    Long,
+    Short,
+    Boolean,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ReferenceTypeSignature {
+    Class(ClassTypeSignature),
+    TypeVariable(String),
+    Array(Box<JavaTypeSignature>),
+}
+
+/// A possibly parameterized class type, inner classes of parameterized types are listed separately because each
+/// of them may have its own type arguments
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ClassTypeSignature {
+    /// Package names from the outermost, empty for the default package
+    pub package: Vec<String>,
+    pub class: SimpleClassTypeSignature,
+    pub inner: Vec<SimpleClassTypeSignature>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SimpleClassTypeSignature {
+    pub name: String,
+    pub type_arguments: Vec<TypeArgument>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TypeArgument {
+    /// `?`
+    Any,
+    Exact(ReferenceTypeSignature),
+    /// `? extends`
+    Extends(ReferenceTypeSignature),
+    /// `? super`
+    Super(ReferenceTypeSignature),
+}
+
+impl ClassSignature {
+    pub fn parse(signature: &str) -> Result<Self, SignatureError> {
+        let mut parser = Parser::new(signature);
+
+        let type_parameters = parser.type_parameters()?;
+        let superclass = parser.class_type()?;
+        let mut interfaces = Vec::new();
+        while parser.peek().is_some() {
+            interfaces.push(parser.class_type()?);
+        }
+
+        Ok(ClassSignature {
+            type_parameters,
+            superclass,
+            interfaces,
+        })
+    }
+
+    /// Java-like declaration of the class, e.g. `Foo<T> extends Bar<T> implements java.lang.Cloneable`
+    pub fn declaration(&self, name: &str) -> String {
+        let mut declaration = name.to_string();
+        if !self.type_parameters.is_empty() {
+            declaration += &format!("<{}>", List(&self.type_parameters, ", "));
+        }
+        declaration += &format!(" extends {}", self.superclass);
+        if !self.interfaces.is_empty() {
+            declaration += &format!(" implements {}", List(&self.interfaces, ", "));
+        }
+        declaration
+    }
+}
+
+impl MethodSignature {
+    pub fn parse(signature: &str) -> Result<Self, SignatureError> {
+        let mut parser = Parser::new(signature);
+
+        let type_parameters = parser.type_parameters()?;
+        parser.expect('(')?;
+        let mut parameters = Vec::new();
+        while !parser.consume(')') {
+            parameters.push(parser.java_type()?);
+        }
+        let return_type = match parser.consume('V') {
+            true => None,
+            false => Some(parser.java_type()?),
+        };
+        let mut throws = Vec::new();
+        while parser.consume('^') {
+            throws.push(match parser.peek() {
+                Some((_, 'L')) => ReferenceTypeSignature::Class(parser.class_type()?),
+                _ => parser.type_variable()?,
+            });
+        }
+        parser.finish()?;
+
+        Ok(MethodSignature {
+            type_parameters,
+            parameters,
+            return_type,
+            throws,
+        })
+    }
+
+    /// Java-like declaration of the method, e.g. `<T> T first(java.util.List<T>) throws java.io.IOException`
+    pub fn declaration(&self, name: &str) -> String {
+        let mut declaration = String::new();
+        if !self.type_parameters.is_empty() {
+            declaration += &format!("<{}> ", List(&self.type_parameters, ", "));
+        }
+        match &self.return_type {
+            Some(ty) => declaration += &format!("{} ", ty),
+            None => declaration += "void ",
+        }
+        declaration += &format!("{}({})", name, List(&self.parameters, ", "));
+        if !self.throws.is_empty() {
+            declaration += &format!(" throws {}", List(&self.throws, ", "));
+        }
+        declaration
+    }
+}
+
+/// Parses the signature of a field, which is always a reference type
+pub fn parse_field_signature(signature: &str) -> Result<ReferenceTypeSignature, SignatureError> {
+    let mut parser = Parser::new(signature);
+    let ty = parser.reference_type()?;
+    parser.finish()?;
+    Ok(ty)
+}
+
+impl ClassTypeSignature {
+    /// The binary name as used in descriptors, e.g. `java/util/Map$Entry`
+    pub fn binary_name(&self) -> String {
+        let mut name = String::new();
+        for package in self.package.iter() {
+            name += package;
+            name.push('/');
+        }
+        name += &self.class.name;
+        for inner in self.inner.iter() {
+            name.push('$');
+            name += &inner.name;
+        }
+        name
+    }
+}
+
+struct List<'a, T>(&'a [T], &'static str);
+
+impl<T: Display> Display for List<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (index, item) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_str(self.1)?;
+            }
+            write!(f, "{}", item)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for TypeParameter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)?;
+
+        // An implicit `extends java.lang.Object` is not shown
+        let class_bound = self.class_bound.iter().filter(|bound| match bound {
+            ReferenceTypeSignature::Class(class) => {
+                class.binary_name() != "java/lang/Object" || !class.class.type_arguments.is_empty()
+            }
+            _ => true,
+        });
+        let bounds: Vec<_> = class_bound.chain(self.interface_bounds.iter()).collect();
+        if !bounds.is_empty() {
+            write!(f, " extends {}", List(&bounds, " & "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for JavaTypeSignature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JavaTypeSignature::Base(ty) => write!(f, "{}", ty),
+            JavaTypeSignature::Reference(ty) => write!(f, "{}", ty),
+        }
+    }
+}
+
+impl Display for BaseType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            BaseType::Byte => "byte",
+            BaseType::Char => "char",
+            BaseType::Double => "double",
+            BaseType::Float => "float",
+            BaseType::Integer => "int",
+            BaseType::Long => "long",
+            BaseType::Short => "short",
+            BaseType::Boolean => "boolean",
+        })
+    }
+}
+
+impl Display for ReferenceTypeSignature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReferenceTypeSignature::Class(ty) => write!(f, "{}", ty),
+            ReferenceTypeSignature::TypeVariable(name) => f.write_str(name),
+            ReferenceTypeSignature::Array(element) => write!(f, "{}[]", element),
+        }
+    }
+}
+
+impl Display for ClassTypeSignature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for package in self.package.iter() {
+            write!(f, "{}.", package)?;
+        }
+        write!(f, "{}", self.class)?;
+        for inner in self.inner.iter() {
+            write!(f, ".{}", inner)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for SimpleClassTypeSignature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)?;
+        if !self.type_arguments.is_empty() {
+            write!(f, "<{}>", List(&self.type_arguments, ", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for TypeArgument {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeArgument::Any => f.write_str("?"),
+            TypeArgument::Exact(ty) => write!(f, "{}", ty),
+            TypeArgument::Extends(ty) => write!(f, "? extends {}", ty),
+            TypeArgument::Super(ty) => write!(f, "? super {}", ty),
+        }
+    }
+}
+
+struct Parser<'a> {
+    signature: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    /// Number of type argument lists being parsed
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(signature: &'a str) -> Self {
+        Parser {
+            signature,
+            chars: signature.char_indices().peekable(),
+            depth: 0,
+        }
+    }
+
+    fn peek(&mut self) -> Option<(usize, char)> {
+        self.chars.peek().copied()
+    }
+
+    fn next(&mut self) -> Result<(usize, char), SignatureError> {
+        self.chars.next().ok_or_else(|| SignatureError::UnexpectedEnd {
+            signature: self.signature.to_string(),
+        })
+    }
+
+    /// Skips the next character if it is `expected`
+    fn consume(&mut self, expected: char) -> bool {
+        self.chars.next_if(|(_, c)| *c == expected).is_some()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), SignatureError> {
+        match self.next()? {
+            (_, c) if c == expected => Ok(()),
+            (position, found) => Err(self.unexpected(position, found)),
+        }
+    }
+
+    fn finish(mut self) -> Result<(), SignatureError> {
+        match self.chars.next() {
+            Some((position, found)) => Err(self.unexpected(position, found)),
+            None => Ok(()),
+        }
+    }
+
+    fn unexpected(&self, position: usize, found: char) -> SignatureError {
+        SignatureError::UnexpectedCharacter {
+            signature: self.signature.to_string(),
+            position,
+            found,
+        }
+    }
+
+    /// Reads a non empty unqualified name
+    fn identifier(&mut self) -> Result<String, SignatureError> {
+        let mut identifier = String::new();
+        while let Some((position, c)) = self.peek() {
+            if matches!(c, '.' | ';' | '[' | '/' | '<' | '>' | ':') {
+                if identifier.is_empty() {
+                    return Err(self.unexpected(position, c));
+                }
+                return Ok(identifier);
+            }
+            identifier.push(c);
+            self.chars.next();
+        }
+        Err(SignatureError::UnexpectedEnd {
+            signature: self.signature.to_string(),
+        })
+    }
+
+    fn type_parameters(&mut self) -> Result<Vec<TypeParameter>, SignatureError> {
+        let mut type_parameters = Vec::new();
+        if !self.consume('<') {
+            return Ok(type_parameters);
+        }
+
+        loop {
+            let name = self.identifier()?;
+            self.expect(':')?;
+            let class_bound = match self.peek() {
+                Some((_, 'L' | 'T' | '[')) => Some(self.reference_type()?),
+                _ => None,
+            };
+            let mut interface_bounds = Vec::new();
+            while self.consume(':') {
+                interface_bounds.push(self.reference_type()?);
+            }
+            type_parameters.push(TypeParameter {
+                name,
+                class_bound,
+                interface_bounds,
+            });
+
+            if self.consume('>') {
+                return Ok(type_parameters);
+            }
+        }
+    }
+
+    fn java_type(&mut self) -> Result<JavaTypeSignature, SignatureError> {
+        let base = match self.peek() {
+            Some((_, 'B')) => BaseType::Byte,
+            Some((_, 'C')) => BaseType::Char,
+            Some((_, 'D')) => BaseType::Double,
+            Some((_, 'F')) => BaseType::Float,
+            Some((_, 'I')) => BaseType::Integer,
+            Some((_, 'J')) => BaseType::Long,
+            Some((_, 'S')) => BaseType::Short,
+            Some((_, 'Z')) => BaseType::Boolean,
+            _ => return Ok(JavaTypeSignature::Reference(self.reference_type()?)),
+        };
+        self.chars.next();
+        Ok(JavaTypeSignature::Base(base))
+    }
+
+    fn reference_type(&mut self) -> Result<ReferenceTypeSignature, SignatureError> {
+        match self.peek() {
+            Some((_, 'L')) => Ok(ReferenceTypeSignature::Class(self.class_type()?)),
+            Some((_, 'T')) => self.type_variable(),
+            Some((position, '[')) => {
+                let mut dimensions = 0;
+                while self.consume('[') {
+                    dimensions += 1;
+                }
+                if dimensions > MAX_DIMENSIONS {
+                    return Err(SignatureError::TooManyDimensions {
+                        signature: self.signature.to_string(),
+                        position,
+                    });
+                }
+
+                let mut ty = ReferenceTypeSignature::Array(Box::new(self.java_type()?));
+                for _ in 1..dimensions {
+                    ty = ReferenceTypeSignature::Array(Box::new(JavaTypeSignature::Reference(ty)));
+                }
+                Ok(ty)
+            }
+            _ => {
+                let (position, found) = self.next()?;
+                Err(self.unexpected(position, found))
+            }
+        }
+    }
+
+    fn type_variable(&mut self) -> Result<ReferenceTypeSignature, SignatureError> {
+        self.expect('T')?;
+        let name = self.identifier()?;
+        self.expect(';')?;
+        Ok(ReferenceTypeSignature::TypeVariable(name))
+    }
+
+    fn class_type(&mut self) -> Result<ClassTypeSignature, SignatureError> {
+        self.expect('L')?;
+
+        let mut package = Vec::new();
+        let mut name = self.identifier()?;
+        while self.consume('/') {
+            package.push(name);
+            name = self.identifier()?;
+        }
+        let class = self.simple_class_type(name)?;
+
+        let mut inner = Vec::new();
+        while self.consume('.') {
+            let name = self.identifier()?;
+            inner.push(self.simple_class_type(name)?);
+        }
+        self.expect(';')?;
+
+        Ok(ClassTypeSignature { package, class, inner })
+    }
+
+    fn simple_class_type(&mut self, name: String) -> Result<SimpleClassTypeSignature, SignatureError> {
+        let mut type_arguments = Vec::new();
+        if let Some((position, '<')) = self.peek() {
+            self.chars.next();
+            self.depth += 1;
+            if self.depth > MAX_TYPE_ARGUMENT_DEPTH {
+                return Err(SignatureError::TooDeeplyNested {
+                    signature: self.signature.to_string(),
+                    position,
+                });
+            }
+
+            loop {
+                type_arguments.push(match self.peek() {
+                    Some((_, '*')) => {
+                        self.chars.next();
+                        TypeArgument::Any
+                    }
+                    Some((_, '+')) => {
+                        self.chars.next();
+                        TypeArgument::Extends(self.reference_type()?)
+                    }
+                    Some((_, '-')) => {
+                        self.chars.next();
+                        TypeArgument::Super(self.reference_type()?)
+                    }
+                    _ => TypeArgument::Exact(self.reference_type()?),
+                });
+
+                if self.consume('>') {
+                    break;
+                }
+            }
+            self.depth -= 1;
+        }
+        Ok(SimpleClassTypeSignature { name, type_arguments })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_class_signature() {
+        let signature = ClassSignature::parse(concat!(
+            "<K::Ljava/lang/Comparable<-TK;>;V:Ljava/lang/Object;>",
+            "Ljava/util/AbstractMap<TK;TV;>;Ljava/io/Serializable;"
+        ))
+        .unwrap();
+
+        assert_eq!(signature.type_parameters.len(), 2);
+        assert_eq!(signature.type_parameters[0].class_bound, None);
+        assert_eq!(signature.superclass.binary_name(), "java/util/AbstractMap");
+        assert_eq!(
+            signature.declaration("Tree"),
+            "Tree<K extends java.lang.Comparable<? super K>, V> extends java.util.AbstractMap<K, V> \
+             implements java.io.Serializable"
+        );
+    }
+
+    #[test]
+    fn parse_method_signature() {
+        let signature = MethodSignature::parse(concat!(
+            "<E:Ljava/lang/Exception;>",
+            "([ILjava/util/Map<TK;*>.Entry<+[TK;Ljava/lang/String;>;)TE;^TE;^Ljava/io/IOException;"
+        ))
+        .unwrap();
+
+        match &signature.parameters[1] {
+            JavaTypeSignature::Reference(ReferenceTypeSignature::Class(class)) => {
+                assert_eq!(class.binary_name(), "java/util/Map$Entry")
+            }
+            parameter => panic!("Expected a class type, got {:?}", parameter),
+        }
+        assert_eq!(signature.throws[0], ReferenceTypeSignature::TypeVariable("E".to_string()));
+        assert_eq!(
+            signature.declaration("get"),
+            "<E extends java.lang.Exception> E get(int[], java.util.Map<K, ?>.Entry<? extends K[], java.lang.String>) \
+             throws E, java.io.IOException"
+        );
+
+        assert_eq!(MethodSignature::parse("()V").unwrap().declaration("run"), "void run()");
+        assert_eq!(
+            parse_field_signature("[Ljava/util/List<*>;").unwrap().to_string(),
+            "java.util.List<?>[]"
+        );
+    }
+
+    #[test]
+    fn reject_malformed_signatures() {
+        let unexpected = |signature: &str, position, found| SignatureError::UnexpectedCharacter {
+            signature: signature.to_string(),
+            position,
+            found,
+        };
+
+        assert_eq!(parse_field_signature("I").unwrap_err(), unexpected("I", 0, 'I'));
+        assert_eq!(parse_field_signature("Ljava/util/List<>;").unwrap_err(), unexpected("Ljava/util/List<>;", 16, '>'));
+        assert_eq!(parse_field_signature("Ljava//List;").unwrap_err(), unexpected("Ljava//List;", 6, '/'));
+        assert_eq!(MethodSignature::parse("(V)V").unwrap_err(), unexpected("(V)V", 1, 'V'));
+        assert_eq!(
+            ClassSignature::parse("<>Ljava/lang/Object;").unwrap_err(),
+            unexpected("<>Ljava/lang/Object;", 1, '>')
+        );
+        assert_eq!(
+            ClassSignature::parse("<T:>Ljava/lang/Object;").map(|signature| signature.type_parameters.len()),
+            Ok(1)
+        );
+        assert_eq!(
+            MethodSignature::parse("<T:Ljava/lang/Object;>(TT;"),
+            Err(SignatureError::UnexpectedEnd {
+                signature: "<T:Ljava/lang/Object;>(TT;".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn reject_deep_signatures() {
+        let array = format!("{}I", "[".repeat(65000));
+        assert_eq!(
+            parse_field_signature(&array),
+            Err(SignatureError::TooManyDimensions {
+                signature: array.clone(),
+                position: 0,
+            })
+        );
+        let array = format!("{}I", "[".repeat(255));
+        assert_eq!(parse_field_signature(&array).unwrap().to_string(), format!("int{}", "[]".repeat(255)));
+
+        let nested = format!("{}{}", "LA<".repeat(65000), ">;".repeat(65000));
+        assert_eq!(
+            parse_field_signature(&nested),
+            Err(SignatureError::TooDeeplyNested {
+                signature: nested.clone(),
+                position: 3 * 64 + 2,
+            })
+        );
+        let nested = format!("{}TT;{}", "LA<".repeat(64), ">;".repeat(64));
+        assert!(parse_field_signature(&nested).is_ok());
+    }
+}
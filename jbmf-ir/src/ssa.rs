@@ -0,0 +1,499 @@
+//! Conversion of functions into and out of static single assignment form.
+//!
+//! Local and stack variables are renamed to versions ([`Variable::LocalVersion`], [`Variable::StackVersion`]),
+//! temporaries are already assigned only once by the lifter. A read of a variable before any assignment
+//! (e.g. a method parameter) keeps the unversioned name.
+//!
+//! An exception can leave a block between any two of its statements, so a handler may observe every value a
+//! variable takes within a protected block. Variables assigned in a block with an exceptional successor are
+//! therefore left out of SSA form and keep their original name.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use crate::analysis::{Adjacency, DominatorTree};
+use crate::expression::{Expression, Value};
+use crate::flow_graph::EdgeKind;
+use crate::function::Function;
+use crate::statement::{Statement, StatementKind, TypeSignature};
+use crate::variable::Variable;
+
+/// Puts the function into SSA form by inserting phi functions at the iterated dominance frontiers of
+/// assignments and renaming every variable along the dominator tree
+pub fn construct_ssa(function: &mut Function) {
+    let adjacency = Adjacency::new(&function.graph);
+    let dominators = DominatorTree::dominators(&adjacency, function.start);
+    let frontiers = dominators.frontiers(&adjacency);
+
+    let pinned: HashSet<Variable> = function
+        .blocks
+        .iter()
+        .filter(|(start, _)| {
+            function
+                .graph
+                .edges
+                .iter()
+                .any(|edge| edge.source == **start && matches!(edge.kind, EdgeKind::Exception(_)))
+        })
+        .flat_map(|(_, block)| block.statements.iter().filter_map(Statement::target))
+        .collect();
+
+    // Only variables which are read before being assigned in some block can need a phi function
+    let mut live_across_blocks = HashSet::new();
+    let mut definitions: BTreeMap<Variable, (TypeSignature, BTreeSet<u64>)> = BTreeMap::new();
+
+    for (start, block) in function.blocks.iter() {
+        let mut assigned = HashSet::new();
+
+        for statement in block.statements.iter() {
+            for variable in statement.uses() {
+                if !assigned.contains(&variable) {
+                    live_across_blocks.insert(variable);
+                }
+            }
+
+            if let StatementKind::Assign { target, ty, .. } = statement.kind() {
+                if matches!(target, Variable::Local(_) | Variable::Stack(_)) && !pinned.contains(target) {
+                    definitions
+                        .entry(*target)
+                        .or_insert_with(|| (ty.clone(), BTreeSet::new()))
+                        .1
+                        .insert(*start);
+                }
+                assigned.insert(*target);
+            }
+        }
+    }
+
+    let mut phis: BTreeMap<u64, Vec<Statement>> = BTreeMap::new();
+
+    for (variable, (ty, blocks)) in definitions.iter() {
+        if !live_across_blocks.contains(variable) {
+            continue;
+        }
+
+        let mut worklist: Vec<u64> = blocks.iter().copied().collect();
+        let mut placed = HashSet::new();
+
+        while let Some(block) = worklist.pop() {
+            for frontier in frontiers.get(&block).into_iter().flatten() {
+                if placed.insert(*frontier) {
+                    phis.entry(*frontier).or_default().push(Statement::new(StatementKind::Assign {
+                        target: *variable,
+                        ty: ty.clone(),
+                        value: Expression::Phi(Vec::new()),
+                    }));
+
+                    if !blocks.contains(frontier) {
+                        worklist.push(*frontier);
+                    }
+                }
+            }
+        }
+    }
+
+    for (start, statements) in phis {
+        function.blocks.get_mut(&start).unwrap().statements.splice(0..0, statements);
+    }
+
+    let mut renamer = Renamer {
+        renamed: definitions.keys().copied().collect(),
+        versions: HashMap::new(),
+        stacks: HashMap::new(),
+    };
+    let mut walk = vec![(function.start, false)];
+
+    while let Some((start, leaving)) = walk.pop() {
+        if leaving {
+            renamer.leave(function, start);
+            continue;
+        }
+
+        renamer.enter(function, start, adjacency.successors(start));
+
+        walk.push((start, true));
+        for child in dominators.children(start).iter().rev() {
+            walk.push((*child, false));
+        }
+    }
+}
+
+/// Takes the function out of SSA form by replacing every phi function with copies at the end of its
+/// predecessors. Each phi function receives its own temporary, so copies of different phi functions never
+/// interfere with each other. Variables keep their versions
+pub fn destruct_ssa(function: &mut Function) {
+    let mut next_temporary = function
+        .blocks
+        .values()
+        .flat_map(|block| block.statements.iter())
+        .flat_map(|statement| statement.uses().into_iter().chain(statement.target()))
+        .filter_map(|variable| match variable {
+            Variable::Temporary(id) => Some(id + 1),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0);
+
+    let exceptional: HashSet<(u64, u64)> = function
+        .graph
+        .edges
+        .iter()
+        .filter(|edge| matches!(edge.kind, EdgeKind::Exception(_)))
+        .map(|edge| (edge.source, edge.target))
+        .collect();
+
+    let head_lengths: HashMap<u64, usize> = function
+        .blocks
+        .iter()
+        .map(|(start, block)| {
+            let length = block
+                .statements
+                .iter()
+                .take_while(|statement| {
+                    matches!(
+                        statement.kind(),
+                        StatementKind::Assign {
+                            value: Expression::Phi(_) | Expression::CaughtException(_),
+                            ..
+                        }
+                    )
+                })
+                .count();
+            (*start, length)
+        })
+        .collect();
+
+    let mut head_copies: BTreeMap<u64, Vec<Statement>> = BTreeMap::new();
+    let mut tail_copies: BTreeMap<u64, Vec<Statement>> = BTreeMap::new();
+
+    for (start, block) in function.blocks.iter_mut() {
+        for statement in block.statements.iter_mut() {
+            if let StatementKind::Assign {
+                ty,
+                value: value @ Expression::Phi(_),
+                ..
+            } = statement.kind_mut()
+            {
+                let temporary = Variable::Temporary(next_temporary);
+                next_temporary += 1;
+
+                let operands = match std::mem::replace(value, Value::Variable(temporary).into()) {
+                    Expression::Phi(operands) => operands,
+                    _ => unreachable!(),
+                };
+
+                for (predecessor, operand) in operands {
+                    let copy = Statement::new(StatementKind::Assign {
+                        target: temporary,
+                        ty: ty.clone(),
+                        value: operand.into(),
+                    });
+
+                    // A handler can be entered before the end of a protected block is reached. Phi operands
+                    // are never assigned within such a block, so the copy can be made at its start instead
+                    if exceptional.contains(&(predecessor, *start)) {
+                        head_copies.entry(predecessor).or_default().push(copy);
+                    } else {
+                        tail_copies.entry(predecessor).or_default().push(copy);
+                    }
+                }
+            }
+        }
+    }
+
+    for (predecessor, copies) in tail_copies {
+        let statements = &mut function.blocks.get_mut(&predecessor).unwrap().statements;
+        let index = match statements.last().map(Statement::kind) {
+            Some(StatementKind::Flow(_)) => statements.len() - 1,
+            _ => statements.len(),
+        };
+        statements.splice(index..index, copies);
+    }
+
+    for (predecessor, copies) in head_copies {
+        let index = head_lengths[&predecessor];
+        let statements = &mut function.blocks.get_mut(&predecessor).unwrap().statements;
+        statements.splice(index..index, copies);
+    }
+}
+
+struct Renamer {
+    renamed: HashSet<Variable>,
+    versions: HashMap<Variable, u32>,
+    /// The current version of every renamed variable on the path from the entry through the dominator tree
+    stacks: HashMap<Variable, Vec<Variable>>,
+}
+
+impl Renamer {
+    fn current(&self, variable: Variable) -> Variable {
+        self.stacks
+            .get(&variable)
+            .and_then(|versions| versions.last())
+            .copied()
+            .unwrap_or(variable)
+    }
+
+    fn enter(&mut self, function: &mut Function, start: u64, successors: &[u64]) {
+        for statement in function.blocks.get_mut(&start).unwrap().statements.iter_mut() {
+            if !is_phi(statement) {
+                for variable in statement.uses_mut() {
+                    if self.renamed.contains(variable) {
+                        *variable = self.current(*variable);
+                    }
+                }
+            }
+
+            if let Some(target) = statement.target_mut() {
+                if self.renamed.contains(target) {
+                    let version = self.versions.entry(*target).or_insert(0);
+                    *version += 1;
+
+                    let renamed = target.with_version(*version);
+                    self.stacks.entry(*target).or_default().push(renamed);
+                    *target = renamed;
+                }
+            }
+        }
+
+        for successor in successors {
+            for statement in function.blocks.get_mut(successor).unwrap().statements.iter_mut() {
+                match statement.kind_mut() {
+                    StatementKind::Assign {
+                        target,
+                        value: Expression::Phi(operands),
+                        ..
+                    } => operands.push((start, Value::Variable(self.current(target.base())))),
+                    _ => break,
+                }
+            }
+        }
+    }
+
+    fn leave(&mut self, function: &Function, start: u64) {
+        for statement in function.blocks[&start].statements.iter().rev() {
+            if let Some(target) = statement.target() {
+                if let Some(versions) = self.stacks.get_mut(&target.base()) {
+                    if versions.last() == Some(&target) {
+                        versions.pop();
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn is_phi(statement: &Statement) -> bool {
+    matches!(
+        statement.kind(),
+        StatementKind::Assign {
+            value: Expression::Phi(_),
+            ..
+        }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::BasicBlock;
+    use crate::expression::ConstantValue;
+    use crate::flow_graph::{Edge, FlowGraph};
+    use crate::statement::{ComparisonOperation, Condition, FlowStatementKind};
+
+    fn assign(target: Variable, value: Value) -> Statement {
+        Statement::new(StatementKind::Assign {
+            target,
+            ty: TypeSignature::Integer,
+            value: value.into(),
+        })
+    }
+
+    fn flow(kind: FlowStatementKind) -> Statement {
+        Statement::new(StatementKind::Flow(kind))
+    }
+
+    fn function(blocks: Vec<(u64, u64, Vec<Statement>)>, edges: Vec<(u64, u64, EdgeKind)>) -> Function {
+        let mut graph = FlowGraph::new();
+        graph.vertices.extend(blocks.iter().map(|(start, _, _)| *start));
+        graph.edges = edges
+            .into_iter()
+            .map(|(source, target, kind)| Edge { source, target, kind })
+            .collect();
+
+        Function {
+            owner: "Test".to_string(),
+            name: "test".to_string(),
+            descriptor: "(I)I".to_string(),
+            blocks: blocks
+                .into_iter()
+                .map(|(beg_index, end_index, statements)| {
+                    (
+                        beg_index,
+                        BasicBlock {
+                            beg_index,
+                            end_index,
+                            statements,
+                        },
+                    )
+                })
+                .collect(),
+            graph,
+            start: 0,
+            handlers: Vec::new(),
+        }
+    }
+
+    fn listing(function: &Function) -> Vec<String> {
+        function
+            .blocks
+            .values()
+            .flat_map(|block| block.statements.iter().map(|statement| format!("{}: {}", block.beg_index, statement)))
+            .collect()
+    }
+
+    fn integer(value: i32) -> Value {
+        Value::Constant(ConstantValue::Integer(value))
+    }
+
+    /// `l1 = 1; if l0 == 0 { l1 = 3 } else { l1 = 2 }; return l1`
+    fn diamond() -> Function {
+        function(
+            vec![
+                (
+                    0,
+                    4,
+                    vec![
+                        assign(Variable::Local(1), integer(1)),
+                        flow(FlowStatementKind::ConditionalJump {
+                            condition: Condition {
+                                operation: ComparisonOperation::Equal,
+                                left: Value::Variable(Variable::Local(0)),
+                                right: integer(0),
+                            },
+                            target: 8,
+                        }),
+                    ],
+                ),
+                (
+                    4,
+                    8,
+                    vec![
+                        assign(Variable::Local(1), integer(2)),
+                        flow(FlowStatementKind::UnconditionalJump(9)),
+                    ],
+                ),
+                (8, 9, vec![assign(Variable::Local(1), integer(3))]),
+                (
+                    9,
+                    10,
+                    vec![flow(FlowStatementKind::Return(Some(Value::Variable(Variable::Local(1)))))],
+                ),
+            ],
+            vec![
+                (0, 4, EdgeKind::FallThrough),
+                (0, 8, EdgeKind::Branch),
+                (4, 9, EdgeKind::Jump),
+                (8, 9, EdgeKind::FallThrough),
+            ],
+        )
+    }
+
+    #[test]
+    fn diamond_phi() {
+        let mut function = diamond();
+        construct_ssa(&mut function);
+
+        assert_eq!(
+            listing(&function),
+            vec![
+                "0: l1_1 = 1",
+                "0: if l0 == 0 goto 8",
+                "4: l1_2 = 2",
+                "4: goto 9",
+                "8: l1_3 = 3",
+                "9: l1_4 = phi(4: l1_2, 8: l1_3)",
+                "9: return l1_4",
+            ]
+        );
+
+        destruct_ssa(&mut function);
+
+        assert_eq!(
+            listing(&function),
+            vec![
+                "0: l1_1 = 1",
+                "0: if l0 == 0 goto 8",
+                "4: l1_2 = 2",
+                "4: t0 = l1_2",
+                "4: goto 9",
+                "8: l1_3 = 3",
+                "8: t0 = l1_3",
+                "9: l1_4 = t0",
+                "9: return l1_4",
+            ]
+        );
+    }
+
+    #[test]
+    fn loop_phi() {
+        // l1 = 0; while (l1 < l0) l1 = l1 + 1; return l1
+        let mut function = function(
+            vec![
+                (0, 2, vec![assign(Variable::Local(1), integer(0))]),
+                (
+                    2,
+                    7,
+                    vec![flow(FlowStatementKind::ConditionalJump {
+                        condition: Condition {
+                            operation: ComparisonOperation::GreaterEqual,
+                            left: Value::Variable(Variable::Local(1)),
+                            right: Value::Variable(Variable::Local(0)),
+                        },
+                        target: 13,
+                    })],
+                ),
+                (
+                    7,
+                    13,
+                    vec![
+                        Statement::new(StatementKind::Assign {
+                            target: Variable::Local(1),
+                            ty: TypeSignature::Integer,
+                            value: Expression::Binary {
+                                operation: crate::statement::BinaryOperation::Addition,
+                                left: Value::Variable(Variable::Local(1)),
+                                right: integer(1),
+                                ty: TypeSignature::Integer,
+                            },
+                        }),
+                        flow(FlowStatementKind::UnconditionalJump(2)),
+                    ],
+                ),
+                (
+                    13,
+                    15,
+                    vec![flow(FlowStatementKind::Return(Some(Value::Variable(Variable::Local(1)))))],
+                ),
+            ],
+            vec![
+                (0, 2, EdgeKind::FallThrough),
+                (2, 7, EdgeKind::FallThrough),
+                (2, 13, EdgeKind::Branch),
+                (7, 2, EdgeKind::Jump),
+            ],
+        );
+
+        construct_ssa(&mut function);
+
+        assert_eq!(
+            listing(&function),
+            vec![
+                "0: l1_1 = 0",
+                "2: l1_2 = phi(0: l1_1, 7: l1_3)",
+                "2: if l1_2 >= l0 goto 13",
+                "7: l1_3 = l1_2 + 1",
+                "7: goto 2",
+                "13: return l1_2",
+            ]
+        );
+    }
+}
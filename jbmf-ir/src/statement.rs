@@ -1,40 +1,183 @@
-use crate::block::BasicBlock;
+use std::fmt::{Display, Formatter};
+
+pub use java_rs_base::descriptor::TypeSignature;
+
+use crate::expression::{Expression, MemberReference, Value};
+use crate::variable::Variable;
 
 #[derive(Clone, Debug, Eq, PartialEq, PartialOrd)]
 pub struct Statement(pub Box<StatementKind>);
 
+impl Statement {
+    pub fn new(kind: StatementKind) -> Self {
+        Self(Box::new(kind))
+    }
+
+    pub fn kind(&self) -> &StatementKind {
+        &self.0
+    }
+
+    pub fn kind_mut(&mut self) -> &mut StatementKind {
+        &mut self.0
+    }
+
+    /// The variable assigned by the statement, if any
+    pub fn target(&self) -> Option<Variable> {
+        match self.kind() {
+            StatementKind::Assign { target, .. } => Some(*target),
+            _ => None,
+        }
+    }
+
+    pub fn target_mut(&mut self) -> Option<&mut Variable> {
+        match self.kind_mut() {
+            StatementKind::Assign { target, .. } => Some(target),
+            _ => None,
+        }
+    }
+
+    /// The variables read by the statement
+    pub fn uses(&self) -> Vec<Variable> {
+        let mut uses: Vec<Variable> = self
+            .values()
+            .into_iter()
+            .filter_map(|value| match value {
+                Value::Variable(variable) => Some(*variable),
+                Value::Constant(_) => None,
+            })
+            .collect();
+
+        if let StatementKind::Flow(FlowStatementKind::ReturnSubroutine(variable)) = self.kind() {
+            uses.push(*variable);
+        }
+        uses
+    }
+
+    pub fn uses_mut(&mut self) -> Vec<&mut Variable> {
+        if matches!(self.kind(), StatementKind::Flow(FlowStatementKind::ReturnSubroutine(_))) {
+            return match self.kind_mut() {
+                StatementKind::Flow(FlowStatementKind::ReturnSubroutine(variable)) => vec![variable],
+                _ => unreachable!(),
+            };
+        }
+
+        self.values_mut()
+            .into_iter()
+            .filter_map(|value| match value {
+                Value::Variable(variable) => Some(variable),
+                Value::Constant(_) => None,
+            })
+            .collect()
+    }
+
+    fn values(&self) -> Vec<&Value> {
+        match self.kind() {
+            StatementKind::Assign { value, .. } | StatementKind::Evaluate(value) => value.values(),
+            StatementKind::Field(FieldStatementKind::Store { object, value, .. }) => {
+                object.iter().chain(std::iter::once(value)).collect()
+            }
+            StatementKind::ArrayStore { array, index, value, .. } => vec![array, index, value],
+            StatementKind::MonitorEnter(value) | StatementKind::MonitorExit(value) => vec![value],
+            StatementKind::Flow(flow) => match flow {
+                FlowStatementKind::ConditionalJump { condition, .. } => vec![&condition.left, &condition.right],
+                FlowStatementKind::Switch { value, .. } | FlowStatementKind::Throw(value) => vec![value],
+                FlowStatementKind::Return(value) => value.iter().collect(),
+                FlowStatementKind::UnconditionalJump(_)
+                | FlowStatementKind::JumpSubroutine(_)
+                | FlowStatementKind::ReturnSubroutine(_) => Vec::new(),
+            },
+        }
+    }
+
+    fn values_mut(&mut self) -> Vec<&mut Value> {
+        match self.kind_mut() {
+            StatementKind::Assign { value, .. } | StatementKind::Evaluate(value) => value.values_mut(),
+            StatementKind::Field(FieldStatementKind::Store { object, value, .. }) => {
+                object.iter_mut().chain(std::iter::once(value)).collect()
+            }
+            StatementKind::ArrayStore { array, index, value, .. } => vec![array, index, value],
+            StatementKind::MonitorEnter(value) | StatementKind::MonitorExit(value) => vec![value],
+            StatementKind::Flow(flow) => match flow {
+                FlowStatementKind::ConditionalJump { condition, .. } => {
+                    vec![&mut condition.left, &mut condition.right]
+                }
+                FlowStatementKind::Switch { value, .. } | FlowStatementKind::Throw(value) => vec![value],
+                FlowStatementKind::Return(value) => value.iter_mut().collect(),
+                FlowStatementKind::UnconditionalJump(_)
+                | FlowStatementKind::JumpSubroutine(_)
+                | FlowStatementKind::ReturnSubroutine(_) => Vec::new(),
+            },
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, PartialOrd)]
 pub enum StatementKind {
-    Arithmetic(ArithmeticStatementKind),
-    Flow(FlowStatementKind),
+    /// `target = value`, where `ty` is the type of the assigned value
+    Assign {
+        target: Variable,
+        ty: TypeSignature,
+        value: Expression,
+    },
+    /// Evaluates an expression for its side effects only, e.g. a call returning void
+    Evaluate(Expression),
     Field(FieldStatementKind),
-    Variable(u32, TypeSignature),
+    ArrayStore {
+        array: Value,
+        index: Value,
+        value: Value,
+        ty: TypeSignature,
+    },
+    MonitorEnter(Value),
+    MonitorExit(Value),
+    Flow(FlowStatementKind),
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, PartialOrd)]
 pub enum FieldStatementKind {
-    Store(u16, TypeSignature),
-    Load(u16, TypeSignature),
+    /// Writes a field, `object` is `None` for static fields
+    Store {
+        field: MemberReference,
+        object: Option<Value>,
+        value: Value,
+    },
 }
 
+/// Control flow statements always terminate a block. Targets are absolute byte positions
+/// within the code attribute, which are also the start positions of the target blocks
 #[derive(Clone, Debug, Eq, PartialEq, PartialOrd)]
 pub enum FlowStatementKind {
-    MethodCall {
-        owner: String,
-        name: String,
-        descriptor: String,
-        arguments: Vec<TypeSignature>,
-        return_type: TypeSignature,
+    UnconditionalJump(u64),
+    /// Jumps to `target` if the condition holds and falls through otherwise
+    ConditionalJump { condition: Condition, target: u64 },
+    Switch {
+        value: Value,
+        cases: Vec<(i32, u64)>,
+        default: u64,
     },
-    UnconditionalJump(u16),
-    ConditionalJump { target: u16 },
-    Return,
+    Return(Option<Value>),
+    Throw(Value),
+    /// `jsr`, the return address has already been pushed onto the operand stack
+    JumpSubroutine(u64),
+    /// `ret`, jumps to the return address stored in the given variable
+    ReturnSubroutine(Variable),
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, PartialOrd)]
-pub enum ArithmeticStatementKind {
-    Unary(UnaryOperation),
-    Binary(BinaryOperation),
+pub struct Condition {
+    pub operation: ComparisonOperation,
+    pub left: Value,
+    pub right: Value,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd)]
+pub enum ComparisonOperation {
+    Equal,
+    NotEqual,
+    Less,
+    GreaterEqual,
+    Greater,
+    LessEqual,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, PartialOrd)]
@@ -56,55 +199,93 @@ pub enum BinaryOperation {
     LOR,
     LAND,
     LXOR,
+    /// `lcmp`, yields -1, 0 or 1
+    Compare,
+    /// `fcmpl` and `dcmpl`, yields -1 if either operand is NaN
+    CompareNaNLess,
+    /// `fcmpg` and `dcmpg`, yields 1 if either operand is NaN
+    CompareNaNGreater,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, PartialOrd)]
-pub enum TypeSignature {
-    Byte,
-    Char,
-    Integer,
-    Boolean,
-    Long,
-    Short,
-    Float,
-    Double,
-
-    Class(String),
+impl Display for UnaryOperation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnaryOperation::LogicalNegate => f.write_str("!"),
+            UnaryOperation::ArithmeticNegate => f.write_str("-"),
+        }
+    }
+}
 
-    Arbitrary,
+impl Display for BinaryOperation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            BinaryOperation::Addition => "+",
+            BinaryOperation::Multiplication => "*",
+            BinaryOperation::Subtraction => "-",
+            BinaryOperation::Modulo => "%",
+            BinaryOperation::Division => "/",
+            BinaryOperation::LeftShift => "<<",
+            BinaryOperation::RightShift => ">>",
+            BinaryOperation::RightShiftPadded => ">>>",
+            BinaryOperation::LOR => "|",
+            BinaryOperation::LAND => "&",
+            BinaryOperation::LXOR => "^",
+            BinaryOperation::Compare => "cmp",
+            BinaryOperation::CompareNaNLess => "cmpl",
+            BinaryOperation::CompareNaNGreater => "cmpg",
+        })
+    }
+}
 
-    Void,
+impl Display for ComparisonOperation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ComparisonOperation::Equal => "==",
+            ComparisonOperation::NotEqual => "!=",
+            ComparisonOperation::Less => "<",
+            ComparisonOperation::GreaterEqual => ">=",
+            ComparisonOperation::Greater => ">",
+            ComparisonOperation::LessEqual => "<=",
+        })
+    }
+}
 
-    Array(Box<TypeSignature>),
+impl Display for Condition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.left, self.operation, self.right)
+    }
 }
 
-impl From<String> for TypeSignature {
-    fn from(descriptor: String) -> Self {
-        let mut chars = descriptor.chars();
-        match chars.next() {
-            Some('Z') => TypeSignature::Boolean,
-            Some('B') => TypeSignature::Byte,
-            Some('C') => TypeSignature::Char,
-            Some('S') => TypeSignature::Short,
-            Some('I') => TypeSignature::Integer,
-            Some('J') => TypeSignature::Long,
-            Some('F') => TypeSignature::Float,
-            Some('D') => TypeSignature::Double,
-            Some('V') => TypeSignature::Void,
-            Some('L') => {
-                // Parse class name until ';' character
-                let class_name: String = chars
-                    .take_while(|&c| c != ';')
-                    .collect();
-                TypeSignature::Class(class_name)
-            }
-            Some('[') => {
-                // Parse array type recursively
-                let element_type = TypeSignature::from(descriptor[1..].to_string());
-                TypeSignature::Array(Box::new(element_type))
-            }
-            Some(_) => TypeSignature::Arbitrary, // Handle unknown characters
-            None => TypeSignature::Void, // Handle empty string (This might occur in this format: "()V")
+impl Display for Statement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.kind() {
+            StatementKind::Assign { target, value, .. } => write!(f, "{} = {}", target, value),
+            StatementKind::Evaluate(expression) => expression.fmt(f),
+            StatementKind::Field(FieldStatementKind::Store { field, object, value }) => match object {
+                Some(object) => write!(f, "{}.{} = {}", object, field.name, value),
+                None => write!(f, "{}.{} = {}", field.owner, field.name, value),
+            },
+            StatementKind::ArrayStore { array, index, value, .. } => write!(f, "{}[{}] = {}", array, index, value),
+            StatementKind::MonitorEnter(value) => write!(f, "monitorenter {}", value),
+            StatementKind::MonitorExit(value) => write!(f, "monitorexit {}", value),
+            StatementKind::Flow(flow) => match flow {
+                FlowStatementKind::UnconditionalJump(target) => write!(f, "goto {}", target),
+                FlowStatementKind::ConditionalJump { condition, target } => {
+                    write!(f, "if {} goto {}", condition, target)
+                }
+                FlowStatementKind::Switch { value, cases, default } => {
+                    write!(f, "switch {} {{", value)?;
+                    for (key, target) in cases {
+                        write!(f, " {} -> {},", key, target)?;
+                    }
+                    write!(f, " default -> {} }}", default)
+                }
+                FlowStatementKind::Return(Some(value)) => write!(f, "return {}", value),
+                FlowStatementKind::Return(None) => f.write_str("return"),
+                FlowStatementKind::Throw(value) => write!(f, "throw {}", value),
+                FlowStatementKind::JumpSubroutine(target) => write!(f, "jsr {}", target),
+                FlowStatementKind::ReturnSubroutine(variable) => write!(f, "ret {}", variable),
+            },
         }
     }
 }
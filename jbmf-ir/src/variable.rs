@@ -0,0 +1,47 @@
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum Variable {
+    /// A local variable slot of the method frame
+    Local(u16),
+    /// An operand stack entry that is live across a block boundary, indexed by its depth
+    Stack(u16),
+    /// A single assignment temporary introduced by the lifter
+    Temporary(u32),
+    /// A single assignment version of a local variable slot, introduced by SSA construction
+    LocalVersion(u16, u32),
+    /// A single assignment version of an operand stack entry, introduced by SSA construction
+    StackVersion(u16, u32),
+}
+
+impl Variable {
+    /// The variable without its SSA version
+    pub fn base(&self) -> Variable {
+        match *self {
+            Variable::LocalVersion(index, _) => Variable::Local(index),
+            Variable::StackVersion(depth, _) => Variable::Stack(depth),
+            variable => variable,
+        }
+    }
+
+    /// Creates the given SSA version of a local or stack variable, temporaries are returned unchanged
+    pub fn with_version(&self, version: u32) -> Variable {
+        match self.base() {
+            Variable::Local(index) => Variable::LocalVersion(index, version),
+            Variable::Stack(depth) => Variable::StackVersion(depth, version),
+            variable => variable,
+        }
+    }
+}
+
+impl Display for Variable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Variable::Local(index) => write!(f, "l{}", index),
+            Variable::Stack(depth) => write!(f, "s{}", depth),
+            Variable::Temporary(id) => write!(f, "t{}", id),
+            Variable::LocalVersion(index, version) => write!(f, "l{}_{}", index, version),
+            Variable::StackVersion(depth, version) => write!(f, "s{}_{}", depth, version),
+        }
+    }
+}
@@ -1,65 +1,274 @@
-use crate::instruction_info::is_flow_instruction;
-use crate::translate::Translate;
-use jbmf_ir::block::BasicBlock;
-use jbmf_ir::flow_graph::FlowGraph;
-use jbmf_ir::statement::Statement;
-use jbmf_parser::java_rs_pacific::attribute::{Attribute, Compatibility, Instruction};
-use jbmf_parser::java_rs_pacific::{Constant, JavaClass, Method, SizedVec};
-use jbmf_parser::parse_class_file;
-use std::collections::HashSet;
-
-pub fn generate_flow_graph(class: JavaClass) -> FlowGraph<BasicBlock, (i16, i16)> {
-    for method in class.methods.iter() {
-        let mut blocks = Vec::new();
-        let method_name = if let Some(Constant::Utf8(var)) = class.constant_pool.get(method.name) {
-            var
-        } else {
-            unreachable!()
-        };
-
-        method
-            .attributes
-            .iter()
-            .filter(|attribute| matches!(attribute, Attribute::Code { .. }))
-            .for_each(|attribute| {
-                if let Attribute::Code {
-                    code: Compatibility::Current(code),
-                    ..
-                } = attribute
-                {
-                    let mut instructions = Vec::new();
-                    for (index, instruction) in code.iter().enumerate() {
-                        let statement = Statement::translate(instruction.clone(), class.constant_pool.clone());
-                        instructions.push(statement);
-                        if is_flow_instruction(instruction) {
-                            blocks.push(BasicBlock {
-                                beg_index: index as u64,
-                                statements: instructions.clone(),
-                            });
-                            instructions.clear();
-                        }
-                    }
+use crate::instruction_info::{branch_targets, falls_through, is_flow_instruction, may_throw};
+use jbmf_error::{AnalysisError, Error};
+use jbmf_ir::flow_graph::{Edge, EdgeKind, FlowGraph};
+use jbmf_ir::function::ExceptionHandler;
+use jbmf_parser::java_rs_pacific::analysis::instruction_positions;
+use jbmf_parser::java_rs_pacific::attribute::Instruction;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// A maximal run of instructions which is only entered at its first and only left after its last instruction
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InstructionBlock {
+    pub start: u64,
+    /// Byte position directly after the last instruction of the block
+    pub end: u64,
+    pub instructions: Vec<(u64, Instruction)>,
+}
+
+/// The control flow graph of a single method, blocks and vertices are identified by their start position
+pub struct InstructionGraph {
+    pub blocks: BTreeMap<u64, InstructionBlock>,
+    pub graph: FlowGraph<u64, Edge>,
+}
+
+impl InstructionGraph {
+    pub fn successors(&self, block: u64) -> impl Iterator<Item = &Edge> {
+        self.graph.edges.iter().filter(move |edge| edge.source == block)
+    }
+}
+
+/// Splits the code of a method into basic blocks and connects them by their possible control transfers.
+/// Protected ranges are split off into their own blocks, so every block is either entirely inside or entirely
+/// outside of each range, and every block inside a range which may throw gets an edge to the handler
+pub fn generate_flow_graph(code: &[Instruction], handlers: &[ExceptionHandler]) -> Result<InstructionGraph, Error> {
+    if code.is_empty() {
+        return Err(AnalysisError::EmptyCode.into());
+    }
+
+    let (positions, code_length) = instruction_positions(code)?;
+    let indices: HashMap<u64, usize> = positions.iter().enumerate().map(|(index, position)| (*position, index)).collect();
+
+    let mut leaders = BTreeSet::from([0]);
+    let mut return_sites = Vec::new();
+
+    for (index, instruction) in code.iter().enumerate() {
+        for (_, target) in branch_targets(instruction, positions[index])? {
+            if !indices.contains_key(&target) {
+                return Err(AnalysisError::InvalidBranchTarget {
+                    position: positions[index],
+                    target,
                 }
-            });
-        println!("---------- Method {} ----------", method_name);
-        for block in blocks {
-            println!("Block at idx {}", block.beg_index);
-            for statement in block.statements {
-                println!("{:?}", statement)
+                .into());
             }
+            leaders.insert(target);
+        }
+
+        if is_flow_instruction(instruction) && index + 1 < code.len() {
+            leaders.insert(positions[index + 1]);
         }
+
+        if matches!(instruction, Instruction::JSR { .. } | Instruction::JSRW { .. }) && index + 1 < code.len() {
+            return_sites.push(positions[index + 1]);
+        }
+    }
+
+    for handler in handlers {
+        for position in [handler.start, handler.handler] {
+            if !indices.contains_key(&position) {
+                return Err(AnalysisError::InvalidHandlerPosition {
+                    handler: handler.handler,
+                    position,
+                }
+                .into());
+            }
+            leaders.insert(position);
+        }
+
+        if handler.end <= handler.start || (handler.end != code_length && !indices.contains_key(&handler.end)) {
+            return Err(AnalysisError::InvalidProtectedRange {
+                handler: handler.handler,
+                start: handler.start,
+                end: handler.end,
+            }
+            .into());
+        }
+        if handler.end != code_length {
+            leaders.insert(handler.end);
+        }
+    }
+
+    let mut blocks = BTreeMap::new();
+    let starts: Vec<u64> = leaders.into_iter().collect();
+
+    for (block_index, start) in starts.iter().enumerate() {
+        let end = starts.get(block_index + 1).copied().unwrap_or(code_length);
+        let first = indices[start];
+        let last = indices.get(&end).copied().unwrap_or(code.len());
+
+        let instructions = (first..last).map(|index| (positions[index], code[index].clone())).collect();
+        blocks.insert(
+            *start,
+            InstructionBlock {
+                start: *start,
+                end,
+                instructions,
+            },
+        );
     }
 
-    FlowGraph {
-        vertices: HashSet::new(),
-        edges: vec![],
+    let mut graph = FlowGraph::new();
+
+    for block in blocks.values() {
+        graph.vertices.insert(block.start);
+
+        let (position, instruction) = block.instructions.last().unwrap();
+
+        for (kind, target) in branch_targets(instruction, *position)? {
+            graph.edges.push(Edge {
+                source: block.start,
+                target,
+                kind,
+            });
+        }
+
+        if let Instruction::Ret { .. } = instruction {
+            for site in return_sites.iter() {
+                graph.edges.push(Edge {
+                    source: block.start,
+                    target: *site,
+                    kind: EdgeKind::SubroutineReturn,
+                });
+            }
+        }
+
+        if block.instructions.iter().any(|(_, instruction)| may_throw(instruction)) {
+            for handler in handlers {
+                if handler.start <= block.start && block.end <= handler.end {
+                    graph.edges.push(Edge {
+                        source: block.start,
+                        target: handler.handler,
+                        kind: EdgeKind::Exception(handler.catch_type.clone()),
+                    });
+                }
+            }
+        }
+
+        if falls_through(instruction) {
+            if block.end == code_length {
+                return Err(AnalysisError::FallsOffEnd { position: *position }.into());
+            }
+
+            graph.edges.push(Edge {
+                source: block.start,
+                target: block.end,
+                kind: EdgeKind::FallThrough,
+            });
+        }
     }
+
+    Ok(InstructionGraph { blocks, graph })
 }
 
-#[test]
-pub fn test_flow_graph() {
-    let class = parse_class_file("C:/Users/matth/Desktop/Scoreboard.class");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jbmf_parser::java_rs_base::io::SizedVec;
+    use jbmf_parser::java_rs_pacific::attribute::MatchOffsetPair;
+    use jbmf_parser::java_rs_pacific::ConstantPoolIndex;
 
-    let class1 = class.unwrap();
-    generate_flow_graph(class1);
+    fn edges(graph: &InstructionGraph) -> Vec<(u64, u64, EdgeKind)> {
+        let mut edges: Vec<_> = graph
+            .graph
+            .edges
+            .iter()
+            .map(|edge| (edge.source, edge.target, edge.kind.clone()))
+            .collect();
+        edges.sort_by_key(|(source, target, _)| (*source, *target));
+        edges
+    }
+
+    #[test]
+    fn conditional_split() {
+        // iload_0; ifeq 8; iconst_1; goto 9; iconst_0; ireturn
+        let code = vec![
+            Instruction::ILoad0,
+            Instruction::IfEq { offset: 7 },
+            Instruction::IConst1,
+            Instruction::Goto { offset: 4 },
+            Instruction::IConst0,
+            Instruction::IReturn,
+        ];
+
+        let graph = generate_flow_graph(&code, &[]).unwrap();
+
+        assert_eq!(graph.blocks.keys().copied().collect::<Vec<_>>(), vec![0, 4, 8, 9]);
+        assert_eq!(graph.blocks[&4].end, 8);
+        assert_eq!(
+            edges(&graph),
+            vec![
+                (0, 4, EdgeKind::FallThrough),
+                (0, 8, EdgeKind::Branch),
+                (4, 9, EdgeKind::Jump),
+                (8, 9, EdgeKind::FallThrough),
+            ]
+        );
+    }
+
+    #[test]
+    fn lookup_switch() {
+        // iload_0; lookupswitch { 1 -> 20, default -> 22 }; iconst_1; ireturn; iconst_0; ireturn
+        let code = vec![
+            Instruction::ILoad0,
+            Instruction::LookUpSwitch {
+                default: 21,
+                pairs: SizedVec::from(vec![MatchOffsetPair {
+                    match_value: 1,
+                    offset: 19,
+                }]),
+            },
+            Instruction::IConst1,
+            Instruction::IReturn,
+            Instruction::IConst0,
+            Instruction::IReturn,
+        ];
+
+        let graph = generate_flow_graph(&code, &[]).unwrap();
+
+        assert_eq!(graph.blocks.keys().copied().collect::<Vec<_>>(), vec![0, 20, 22]);
+        assert_eq!(
+            edges(&graph),
+            vec![
+                (0, 20, EdgeKind::Switch(Some(1))),
+                (0, 22, EdgeKind::Switch(None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn exception_handler() {
+        // aload_0; invokevirtual #1; areturn; astore_1; aconst_null; areturn, with 1..4 handled at 5
+        let code = vec![
+            Instruction::ALoad0,
+            Instruction::InvokeVirtual { index: ConstantPoolIndex(1) },
+            Instruction::AReturn,
+            Instruction::AStore1,
+            Instruction::AConstNull,
+            Instruction::AReturn,
+        ];
+        let handlers = [ExceptionHandler {
+            start: 1,
+            end: 4,
+            handler: 5,
+            catch_type: Some("java/lang/Exception".to_string()),
+        }];
+
+        let graph = generate_flow_graph(&code, &handlers).unwrap();
+
+        assert_eq!(graph.blocks.keys().copied().collect::<Vec<_>>(), vec![0, 1, 4, 5]);
+        assert_eq!(
+            edges(&graph),
+            vec![
+                (0, 1, EdgeKind::FallThrough),
+                (1, 4, EdgeKind::FallThrough),
+                (1, 5, EdgeKind::Exception(Some("java/lang/Exception".to_string()))),
+            ]
+        );
+    }
+
+    #[test]
+    fn target_inside_instruction() {
+        let code = vec![Instruction::BIPush { value: 1 }, Instruction::Goto { offset: -1 }];
+
+        assert!(generate_flow_graph(&code, &[]).is_err());
+    }
 }
@@ -1,39 +1,139 @@
+use jbmf_error::{AnalysisError, Error};
+use jbmf_ir::flow_graph::EdgeKind;
+use jbmf_parser::java_rs_pacific::analysis::{branch_offsets, BranchKind};
 use jbmf_parser::java_rs_pacific::attribute::Instruction;
 
 pub fn is_flow_instruction(instruction: &Instruction) -> bool {
-    match instruction {
+    matches!(
+        instruction,
         Instruction::AReturn
-        | Instruction::AThrow
-        | Instruction::DReturn
-        | Instruction::FReturn
-        | Instruction::Goto { .. }
-        | Instruction::GotoW { .. }
-        | Instruction::IfACmpEq { .. }
-        | Instruction::IfACmpNe { .. }
-        | Instruction::IfICmpEq { .. }
-        | Instruction::IfICmpNe { .. }
-        | Instruction::IfICmpLt { .. }
-        | Instruction::IfICmpGe { .. }
-        | Instruction::IfICmpGt { .. }
-        | Instruction::IfICmpLe { .. }
-        | Instruction::IfEq { .. }
-        | Instruction::IfNe { .. }
-        | Instruction::IfLt { .. }
-        | Instruction::IfGe { .. }
-        | Instruction::IfGt { .. }
-        | Instruction::IfLe { .. }
-        | Instruction::IfNonNull { .. }
-        | Instruction::IfNull { .. }
-        | Instruction::InvokeDynamic { .. }
-        | Instruction::InvokeInterface { .. }
-        | Instruction::InvokeSpecial { .. }
-        | Instruction::InvokeStatic { .. }
-        | Instruction::InvokeVirtual { .. }
-        | Instruction::LookUpSwitch { .. }
-        | Instruction::LReturn
-        | Instruction::Ret { .. }
-        | Instruction::Return
-        | Instruction::TableSwitch { .. } => true,
-        _ => false,
-    }
-}
\ No newline at end of file
+            | Instruction::AThrow
+            | Instruction::DReturn
+            | Instruction::FReturn
+            | Instruction::Goto { .. }
+            | Instruction::GotoW { .. }
+            | Instruction::IfACmpEq { .. }
+            | Instruction::IfACmpNe { .. }
+            | Instruction::IfICmpEq { .. }
+            | Instruction::IfICmpNe { .. }
+            | Instruction::IfICmpLt { .. }
+            | Instruction::IfICmpGe { .. }
+            | Instruction::IfICmpGt { .. }
+            | Instruction::IfICmpLe { .. }
+            | Instruction::IfEq { .. }
+            | Instruction::IfNe { .. }
+            | Instruction::IfLt { .. }
+            | Instruction::IfGe { .. }
+            | Instruction::IfGt { .. }
+            | Instruction::IfLe { .. }
+            | Instruction::IfNonNull { .. }
+            | Instruction::IfNull { .. }
+            | Instruction::IReturn
+            | Instruction::JSR { .. }
+            | Instruction::JSRW { .. }
+            | Instruction::LookUpSwitch { .. }
+            | Instruction::LReturn
+            | Instruction::Ret { .. }
+            | Instruction::Return
+            | Instruction::TableSwitch { .. }
+    )
+}
+
+/// Whether execution can continue with the next instruction after this one
+pub fn falls_through(instruction: &Instruction) -> bool {
+    !matches!(
+        instruction,
+        Instruction::AReturn
+            | Instruction::AThrow
+            | Instruction::DReturn
+            | Instruction::FReturn
+            | Instruction::Goto { .. }
+            | Instruction::GotoW { .. }
+            | Instruction::IReturn
+            | Instruction::JSR { .. }
+            | Instruction::JSRW { .. }
+            | Instruction::LookUpSwitch { .. }
+            | Instruction::LReturn
+            | Instruction::Ret { .. }
+            | Instruction::Return
+            | Instruction::TableSwitch { .. }
+    )
+}
+
+/// Resolves the relative branch offsets of an instruction at `position` to absolute byte positions
+pub fn branch_targets(instruction: &Instruction, position: u64) -> Result<Vec<(EdgeKind, u64)>, Error> {
+    branch_offsets(instruction)
+        .into_iter()
+        .map(|(kind, offset)| {
+            let kind = match kind {
+                BranchKind::Conditional => EdgeKind::Branch,
+                BranchKind::Jump => EdgeKind::Jump,
+                BranchKind::Subroutine => EdgeKind::Subroutine,
+                BranchKind::Switch(key) => EdgeKind::Switch(key),
+            };
+            let target = position as i64 + offset;
+
+            if target < 0 {
+                Err(AnalysisError::NegativeBranchTarget { position }.into())
+            } else {
+                Ok((kind, target as u64))
+            }
+        })
+        .collect()
+}
+
+/// Whether executing the instruction can raise an exception, either explicitly, through a failed runtime
+/// check or through the resolution of a constant pool entry
+pub fn may_throw(instruction: &Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::AALoad
+            | Instruction::AAStore
+            | Instruction::ANewArray { .. }
+            | Instruction::AReturn
+            | Instruction::ArrayLength
+            | Instruction::AThrow
+            | Instruction::BALoad
+            | Instruction::BAStore
+            | Instruction::CALoad
+            | Instruction::CAStore
+            | Instruction::CheckCast { .. }
+            | Instruction::DALoad
+            | Instruction::DAStore
+            | Instruction::DReturn
+            | Instruction::FALoad
+            | Instruction::FAStore
+            | Instruction::FReturn
+            | Instruction::GetField { .. }
+            | Instruction::GetStatic { .. }
+            | Instruction::IALoad
+            | Instruction::IAStore
+            | Instruction::IDiv
+            | Instruction::InstanceOf { .. }
+            | Instruction::InvokeDynamic { .. }
+            | Instruction::InvokeInterface { .. }
+            | Instruction::InvokeSpecial { .. }
+            | Instruction::InvokeStatic { .. }
+            | Instruction::InvokeVirtual { .. }
+            | Instruction::IRem
+            | Instruction::IReturn
+            | Instruction::LALoad
+            | Instruction::LAStore
+            | Instruction::LDC { .. }
+            | Instruction::LDCW { .. }
+            | Instruction::LDC2W { .. }
+            | Instruction::LDiv
+            | Instruction::LRem
+            | Instruction::LReturn
+            | Instruction::MonitorEnter
+            | Instruction::MonitorExit
+            | Instruction::MultiANewArray { .. }
+            | Instruction::New { .. }
+            | Instruction::NewArray { .. }
+            | Instruction::PutField { .. }
+            | Instruction::PutStatic { .. }
+            | Instruction::Return
+            | Instruction::SALoad
+            | Instruction::SAStore
+    )
+}
@@ -1,6 +1,314 @@
-use jbmf_ir::function::Function;
-use jbmf_parser::java_rs_pacific::Method;
-//
-// pub fn lift_method(method: Method) -> Function {
-//
-// }
+use crate::control_flow_graph::generate_flow_graph;
+use crate::translate::Translator;
+use jbmf_error::{Error, LifterError};
+use jbmf_ir::block::BasicBlock;
+use jbmf_ir::expression::Expression;
+use jbmf_ir::flow_graph::EdgeKind;
+use jbmf_ir::function::{ExceptionHandler, Function};
+use jbmf_ir::statement::{Statement, StatementKind, TypeSignature};
+use jbmf_ir::variable::Variable;
+use jbmf_parser::java_rs_pacific::attribute::{Attribute, Compatibility};
+use jbmf_parser::java_rs_pacific::{JavaClass, Method};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+
+const THROWABLE: &str = "java/lang/Throwable";
+
+/// Lifts every method of a class which has code
+pub fn lift_class(class: &JavaClass) -> Result<Vec<Function>, Error> {
+    let mut functions = Vec::new();
+
+    for method in class.methods.iter() {
+        if let Some(function) = lift_method(class, method)? {
+            functions.push(function);
+        }
+    }
+
+    Ok(functions)
+}
+
+/// Lifts a single method into a function, `None` if the method has no code (abstract and native methods).
+/// Blocks which are unreachable from the method entry are dropped
+pub fn lift_method(class: &JavaClass, method: &Method) -> Result<Option<Function>, Error> {
+    let code = method.attributes.iter().find_map(|attribute| match attribute {
+        Attribute::Code {
+            code: Compatibility::Current(code),
+            exception_table,
+            ..
+        } => Some((code, exception_table)),
+        _ => None,
+    });
+    let (code, exception_table) = match code {
+        Some(code) => code,
+        None => return Ok(None),
+    };
+
+    let cp = &class.constant_pool;
+    let owner = cp.resolve_class_name(class.this_class)?.to_string();
+    let name = cp.resolve_utf8(method.name)?.to_string();
+    let descriptor = cp.resolve_utf8(method.descriptor)?.to_string();
+
+    let handlers = exception_table
+        .iter()
+        .map(|entry| {
+            Ok(ExceptionHandler {
+                start: entry.start_pc as u64,
+                end: entry.end_pc as u64,
+                handler: entry.handler_pc as u64,
+                catch_type: match entry.catch_type.0 {
+                    0 => None,
+                    _ => Some(cp.resolve_class_name(entry.catch_type)?.to_string()),
+                },
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    // A handler shared by several entries receives the most general exception type
+    let mut caught_types: HashMap<u64, TypeSignature> = HashMap::new();
+    for handler in handlers.iter() {
+        let ty = TypeSignature::Class(handler.catch_type.clone().unwrap_or_else(|| THROWABLE.to_string()));
+        caught_types
+            .entry(handler.handler)
+            .and_modify(|existing| {
+                if *existing != ty {
+                    *existing = TypeSignature::Class(THROWABLE.to_string())
+                }
+            })
+            .or_insert(ty);
+    }
+
+    if caught_types.contains_key(&0) {
+        return Err(LifterError::FlowIntoHandler { block: 0 }.into());
+    }
+
+    let instruction_graph = generate_flow_graph(code, &handlers)?;
+    let mut translator = Translator::new(cp);
+    let mut entry_stacks: HashMap<u64, Vec<TypeSignature>> = HashMap::from([(0, Vec::new())]);
+    let mut worklist = vec![0];
+    let mut blocks = BTreeMap::new();
+
+    while let Some(start) = worklist.pop() {
+        let block = &instruction_graph.blocks[&start];
+        let mut translated = translator.translate_block(&block.instructions, &entry_stacks[&start])?;
+
+        if let Some(ty) = caught_types.get(&start) {
+            translated.statements.insert(
+                0,
+                Statement::new(StatementKind::Assign {
+                    target: Variable::Stack(0),
+                    ty: ty.clone(),
+                    value: Expression::CaughtException(ty.clone()),
+                }),
+            );
+        }
+
+        for edge in instruction_graph.successors(start) {
+            // The operand stack of a handler only holds the caught exception
+            if let EdgeKind::Exception(_) = edge.kind {
+                if let Entry::Vacant(entry) = entry_stacks.entry(edge.target) {
+                    entry.insert(vec![caught_types[&edge.target].clone()]);
+                    worklist.push(edge.target);
+                }
+                continue;
+            }
+            if caught_types.contains_key(&edge.target) {
+                return Err(LifterError::FlowIntoHandler { block: edge.target }.into());
+            }
+
+            let stack = match entry_stacks.get(&edge.target) {
+                Some(stack) => merge_stacks(edge.target, stack, &translated.exit_stack)?,
+                None => Some(translated.exit_stack.clone()),
+            };
+            // The target is translated again whenever its entry stack is widened
+            if let Some(stack) = stack {
+                entry_stacks.insert(edge.target, stack);
+                worklist.push(edge.target);
+            }
+        }
+
+        blocks.insert(
+            start,
+            BasicBlock {
+                beg_index: block.start,
+                end_index: block.end,
+                statements: translated.statements,
+            },
+        );
+    }
+
+    let mut graph = instruction_graph.graph;
+    graph.vertices.retain(|vertex| blocks.contains_key(vertex));
+    graph.edges.retain(|edge| blocks.contains_key(&edge.source));
+
+    Ok(Some(Function {
+        owner,
+        name,
+        descriptor,
+        blocks,
+        graph,
+        start: 0,
+        handlers,
+    }))
+}
+
+/// Merges the stack a block is entered with on one more path into its known entry stack. `None` if the known
+/// entry stack already covers the incoming one
+fn merge_stacks(
+    block: u64,
+    existing: &[TypeSignature],
+    incoming: &[TypeSignature],
+) -> Result<Option<Vec<TypeSignature>>, LifterError> {
+    if existing.len() != incoming.len() {
+        return Err(LifterError::InconsistentStackDepth {
+            block,
+            expected: existing.len(),
+            found: incoming.len(),
+        });
+    }
+
+    let merged = existing
+        .iter()
+        .zip(incoming)
+        .enumerate()
+        .map(|(depth, (expected, found))| {
+            merge_types(expected, found).ok_or_else(|| LifterError::InconsistentStackType {
+                block,
+                depth,
+                expected: expected.clone(),
+                found: found.clone(),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Some(merged).filter(|merged| merged != existing))
+}
+
+/// The type covering both types, int types smaller than int are widened to int and different reference types
+/// to an arbitrary reference
+fn merge_types(first: &TypeSignature, second: &TypeSignature) -> Option<TypeSignature> {
+    use TypeSignature::*;
+
+    match (first, second) {
+        _ if first == second => Some(first.clone()),
+        (Boolean | Byte | Char | Short | Integer, Boolean | Byte | Char | Short | Integer) => Some(Integer),
+        (Class(_) | Array(_) | Arbitrary, Class(_) | Array(_) | Arbitrary) => Some(Arbitrary),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jbmf_parser::java_rs_pacific::attribute::{ExceptionTable, Instruction};
+    use jbmf_parser::java_rs_pacific::{AccessFlags, Constant, ConstantPoolIndex, JavaVersion, MagicNumber, SizedVec};
+
+    use super::*;
+
+    fn class(code: Vec<Instruction>, exception_table: Vec<ExceptionTable>) -> JavaClass {
+        JavaClass {
+            magic: MagicNumber::Cafebabe,
+            version: JavaVersion { major: 52, minor: 0 },
+            constant_pool: vec![
+                Constant::Class(ConstantPoolIndex(2)),
+                Constant::Utf8("a/A".into()),
+                Constant::Utf8("m".into()),
+                Constant::Utf8("(I)Ljava/lang/Object;".into()),
+                Constant::Utf8("Code".into()),
+            ]
+            .into(),
+            access_flags: AccessFlags::NONE,
+            this_class: ConstantPoolIndex(1),
+            super_class: ConstantPoolIndex(0),
+            interfaces: SizedVec::new(),
+            fields: SizedVec::new(),
+            methods: vec![Method {
+                access_flags: AccessFlags::STATIC,
+                name: ConstantPoolIndex(3),
+                descriptor: ConstantPoolIndex(4),
+                attributes: vec![Attribute::Code {
+                    name: ConstantPoolIndex(5),
+                    max_stack: Compatibility::Current(1),
+                    max_locals: Compatibility::Current(1),
+                    code: Compatibility::Current(code.into()),
+                    exception_table: exception_table.into(),
+                    attributes: SizedVec::new(),
+                }]
+                .into(),
+            }]
+            .into(),
+            attributes: SizedVec::new(),
+        }
+    }
+
+    #[test]
+    fn reject_flow_into_handler() {
+        let class = class(
+            vec![
+                Instruction::IConst0,
+                Instruction::Pop,
+                Instruction::AStore0,
+                Instruction::AConstNull,
+                Instruction::AReturn,
+            ],
+            vec![ExceptionTable {
+                start_pc: 0,
+                end_pc: 2,
+                handler_pc: 2,
+                catch_type: ConstantPoolIndex(0),
+            }],
+        );
+
+        assert!(matches!(
+            lift_method(&class, &class.methods[0]),
+            Err(Error::LifterError(LifterError::FlowIntoHandler { block: 2 }))
+        ));
+    }
+
+    #[test]
+    fn reject_inconsistent_stack_types() {
+        // iload_0; ifeq 8; iconst_0; goto 9; fconst_0; ireturn
+        let class = class(
+            vec![
+                Instruction::ILoad0,
+                Instruction::IfEq { offset: 7 },
+                Instruction::IConst0,
+                Instruction::Goto { offset: 4 },
+                Instruction::FConst0,
+                Instruction::IReturn,
+            ],
+            Vec::new(),
+        );
+
+        assert!(matches!(
+            lift_method(&class, &class.methods[0]),
+            Err(Error::LifterError(LifterError::InconsistentStackType {
+                block: 9,
+                depth: 0,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn merge_entry_stacks() {
+        let class = TypeSignature::Class("a/A".to_string());
+
+        assert_eq!(
+            merge_stacks(
+                0,
+                &[class.clone(), TypeSignature::Boolean],
+                &[TypeSignature::Arbitrary, TypeSignature::Integer]
+            )
+            .unwrap(),
+            Some(vec![TypeSignature::Arbitrary, TypeSignature::Integer])
+        );
+        assert_eq!(merge_stacks(0, &[TypeSignature::Arbitrary], &[class]).unwrap(), None);
+        assert!(matches!(
+            merge_stacks(0, &[TypeSignature::Long], &[]),
+            Err(LifterError::InconsistentStackDepth {
+                expected: 1,
+                found: 0,
+                ..
+            })
+        ));
+    }
+}
@@ -1,367 +1,1073 @@
+use jbmf_error::{AnalysisError, Error, LifterError};
+use jbmf_ir::descriptor::{parse_field_descriptor, MethodDescriptor};
+use jbmf_ir::expression::{ConstantValue, Expression, InvokeKind, MemberReference, Value};
 use jbmf_ir::statement::{
-    ArithmeticStatementKind, BinaryOperation, FieldStatementKind, FlowStatementKind, Statement,
+    BinaryOperation, ComparisonOperation, Condition, FieldStatementKind, FlowStatementKind, Statement,
     StatementKind, TypeSignature, UnaryOperation,
 };
-use jbmf_parser::java_rs_pacific::attribute::Instruction;
-use jbmf_parser::java_rs_pacific::attribute::Instruction::{DLoad0, DLoad3};
-use jbmf_parser::java_rs_pacific::{Constant, ConstantPool, ConstantPoolIndex};
-use crate::extract_constant_fields;
-
-pub trait Translate {
-    fn translate(instructions: Instruction, cp: ConstantPool) -> Self
-        where
-            Self: Sized;
+use jbmf_ir::variable::Variable;
+use jbmf_parser::java_rs_pacific::attribute::{ArrayType, Instruction, SizedIndex};
+use jbmf_parser::java_rs_pacific::{ConstantPool, ConstantPoolIndex, LoadableConstant, MemberRef};
+
+/// A value on the simulated operand stack together with its type
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StackEntry {
+    pub value: Value,
+    pub ty: TypeSignature,
 }
 
-impl Translate for Statement {
-    fn translate(instruction: Instruction, cp: ConstantPool) -> Self
-        where
-            Self: Sized,
-    {
-        Self(Box::new(StatementKind::translate(instruction, cp)))
-    }
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TranslatedBlock {
+    pub statements: Vec<Statement>,
+    /// Types of the operand stack entries left at the end of the block. They are stored in
+    /// `Variable::Stack(depth)` and a successor block has to be translated with this as its entry stack
+    pub exit_stack: Vec<TypeSignature>,
 }
 
-impl Translate for StatementKind {
-    fn translate(instruction: Instruction, cp: ConstantPool) -> Self
-        where
-            Self: Sized,
-    {
-        match instruction {
-            // Integer arithmetics
-            Instruction::IAdd
-            | Instruction::ISub
-            | Instruction::IMul
-            | Instruction::IDiv
-            | Instruction::IRem
-            | Instruction::IOr
-            | Instruction::IXor
-            | Instruction::IAnd
-            | Instruction::INeg
-            | Instruction::IShl
-            | Instruction::IShr
-            | Instruction::IUShr
-
-            // Long arithmetics
-            | Instruction::LAdd
-            | Instruction::LSub
-            | Instruction::LMul
-            | Instruction::LDiv
-            | Instruction::LRem
-            | Instruction::LOr
-            | Instruction::LXor
-            | Instruction::LAnd
-            | Instruction::LNeg
-            | Instruction::LShl
-            | Instruction::LShr
-            | Instruction::LUShr
-
-            // Float Arithmetics
-            | Instruction::FAdd
-            | Instruction::FSub
-            | Instruction::FMul
-            | Instruction::FDiv
-            | Instruction::FRem
-
-            // Double arithmetics
-            | Instruction::DAdd
-            | Instruction::DSub
-            | Instruction::DMul
-            | Instruction::DDiv
-            | Instruction::DRem
-            => StatementKind::Arithmetic(ArithmeticStatementKind::translate(
-                instruction, cp
-            )),
-
-            // Conditionals
-            Instruction::IfEq { .. }
-            | Instruction::IfNe { .. }
-            | Instruction::IfGe { .. }
-            | Instruction::IfLe { .. }
-            | Instruction::IfGt { .. }
-            | Instruction::IfLt { .. }
-
-            //Arbitrary
-            | Instruction::IfACmpEq { .. }
-            | Instruction::IfACmpNe { .. }
-
-            //Integer
-            | Instruction::IfICmpEq { .. }
-            | Instruction::IfICmpNe { .. }
-            | Instruction::IfICmpGe { .. }
-            | Instruction::IfICmpLe { .. }
-            | Instruction::IfICmpGt { .. }
-            | Instruction::IfICmpLt { .. }
-
-            //Null
-            | Instruction::IfNonNull { .. }
-            | Instruction::IfNull { .. } => StatementKind::Flow(FlowStatementKind::translate(
-                instruction,
-                cp
-            )),
-
-            Instruction::InvokeDynamic { .. }
-            | Instruction::InvokeInterface { .. }
-            | Instruction::InvokeSpecial { .. }
-            | Instruction::InvokeStatic { .. }
-            | Instruction::InvokeVirtual { .. }
-
-            | Instruction::IReturn
-            | Instruction::LReturn
-            | Instruction::FReturn
-            | Instruction::DReturn
-            | Instruction::Ret { .. }
-            | Instruction::Return
-            | Instruction::AReturn
-            => StatementKind::Flow(FlowStatementKind::translate(
-                instruction,
-                cp
-            )),
-
-            Instruction::ALoad { .. } => StatementKind::Field(FieldStatementKind::Load(0, TypeSignature::Arbitrary)),
-            Instruction::ALoad0
-            | Instruction::ALoad1
-            | Instruction::ALoad2
-            | Instruction::ALoad3
-
-            | Instruction::ILoad0
-            | Instruction::ILoad1
-            | Instruction::ILoad2
-            | Instruction::ILoad3
-
-            | Instruction::LLoad0
-            | Instruction::LLoad1
-            | Instruction::LLoad2
-            | Instruction::LLoad3
-
-            | Instruction::FLoad0
-            | Instruction::FLoad1
-            | Instruction::FLoad2
-            | Instruction::FLoad3
-
-            | Instruction::DLoad0
-            | Instruction::DLoad1
-            | Instruction::DLoad2
-            | Instruction::DLoad3 => StatementKind::Field(FieldStatementKind::Load(0, TypeSignature::Double)),
-
-            _ => StatementKind::Field(FieldStatementKind::Load(0, TypeSignature::Arbitrary)),
+/// Translates the instructions of a method into statements by simulating the operand stack.
+///
+/// Every value producing instruction assigns its result to a fresh temporary, so the stack only ever
+/// holds temporaries, constants and the stack variables of the block entry. Temporaries are numbered
+/// per translator, so one translator should be used for all blocks of a method.
+pub struct Translator<'a> {
+    constant_pool: &'a ConstantPool,
+    next_temporary: u32,
+}
+
+struct BlockState {
+    position: u64,
+    stack: Vec<StackEntry>,
+    statements: Vec<Statement>,
+}
+
+impl BlockState {
+    fn push(&mut self, value: Value, ty: TypeSignature) {
+        self.stack.push(StackEntry { value, ty });
+    }
+
+    fn pop(&mut self) -> Result<StackEntry, Error> {
+        let position = self.position;
+        self.stack
+            .pop()
+            .ok_or_else(|| LifterError::StackUnderflow { position }.into())
+    }
+
+    fn pop_value(&mut self) -> Result<Value, Error> {
+        Ok(self.pop()?.value)
+    }
+
+    /// Pops `count` values and returns them in the order they were pushed
+    fn pop_values(&mut self, count: usize) -> Result<Vec<Value>, Error> {
+        if self.stack.len() < count {
+            return Err(LifterError::StackUnderflow { position: self.position }.into());
+        }
+
+        let values = self.stack.split_off(self.stack.len() - count);
+        Ok(values.into_iter().map(|entry| entry.value).collect())
+    }
+
+    /// Pops entries from the top of the stack until they occupy exactly `words` stack words
+    fn pop_words(&mut self, words: u16) -> Result<Vec<StackEntry>, Error> {
+        let mut entries = Vec::new();
+        let mut size = 0;
+
+        while size < words {
+            let entry = self.pop()?;
+            size += entry.ty.slot_size();
+            entries.insert(0, entry);
         }
+
+        if size != words {
+            return Err(LifterError::SplitsCategory2Value { position: self.position }.into());
+        }
+
+        Ok(entries)
+    }
+
+    fn emit(&mut self, kind: StatementKind) {
+        self.statements.push(Statement::new(kind));
     }
 }
 
-impl Translate for BinaryOperation {
-    fn translate(instruction: Instruction, cp: ConstantPool) -> Self
-        where
-            Self: Sized,
-    {
-        match instruction {
-            Instruction::LAdd | Instruction::DAdd | Instruction::FAdd | Instruction::IAdd => {
-                BinaryOperation::Addition
-            }
-            Instruction::IMul | Instruction::DMul | Instruction::FMul | Instruction::LMul => {
-                BinaryOperation::Multiplication
+impl<'a> Translator<'a> {
+    pub fn new(constant_pool: &'a ConstantPool) -> Self {
+        Self {
+            constant_pool,
+            next_temporary: 0,
+        }
+    }
+
+    /// Translates the instructions of a single basic block, given together with their byte positions.
+    /// `entry_stack` holds the types of the stack entries the block starts with
+    pub fn translate_block(
+        &mut self,
+        instructions: &[(u64, Instruction)],
+        entry_stack: &[TypeSignature],
+    ) -> Result<TranslatedBlock, Error> {
+        let mut state = BlockState {
+            position: 0,
+            stack: entry_stack
+                .iter()
+                .enumerate()
+                .map(|(depth, ty)| StackEntry {
+                    value: Value::Variable(Variable::Stack(depth as u16)),
+                    ty: ty.clone(),
+                })
+                .collect(),
+            statements: Vec::new(),
+        };
+
+        let mut terminated = false;
+
+        for (position, instruction) in instructions {
+            if terminated {
+                return Err(LifterError::FollowsTerminator { position: *position }.into());
             }
-            Instruction::IDiv | Instruction::DDiv | Instruction::FDiv | Instruction::LDiv => {
-                BinaryOperation::Division
+
+            state.position = *position;
+            terminated = self.translate_instruction(&mut state, instruction)?;
+        }
+
+        if !terminated {
+            self.spill(&mut state);
+        }
+
+        Ok(TranslatedBlock {
+            exit_stack: state.stack.into_iter().map(|entry| entry.ty).collect(),
+            statements: state.statements,
+        })
+    }
+
+    fn temporary(&mut self) -> Variable {
+        let variable = Variable::Temporary(self.next_temporary);
+        self.next_temporary += 1;
+        variable
+    }
+
+    /// Assigns `value` to a fresh temporary and pushes the temporary
+    fn push_expression(&mut self, state: &mut BlockState, ty: TypeSignature, value: Expression) {
+        let target = self.temporary();
+        state.emit(StatementKind::Assign {
+            target,
+            ty: ty.clone(),
+            value,
+        });
+        state.push(Value::Variable(target), ty);
+    }
+
+    fn load(&mut self, state: &mut BlockState, index: u16, ty: TypeSignature) {
+        self.push_expression(state, ty, Expression::Value(Value::Variable(Variable::Local(index))));
+    }
+
+    fn store(&mut self, state: &mut BlockState, index: u16, ty: TypeSignature) -> Result<(), Error> {
+        let value = state.pop_value()?;
+        state.emit(StatementKind::Assign {
+            target: Variable::Local(index),
+            ty,
+            value: Expression::Value(value),
+        });
+        Ok(())
+    }
+
+    /// Replaces every stack entry that refers to `variable` by a temporary holding its current value
+    fn materialize(&mut self, state: &mut BlockState, variable: Variable) {
+        let mut replacement = None;
+
+        for index in 0..state.stack.len() {
+            if state.stack[index].value != Value::Variable(variable) {
+                continue;
             }
-            Instruction::ISub | Instruction::DSub | Instruction::FSub | Instruction::LSub => {
-                BinaryOperation::Subtraction
+
+            let temporary = match replacement {
+                Some(temporary) => temporary,
+                None => {
+                    let temporary = self.temporary();
+                    state.emit(StatementKind::Assign {
+                        target: temporary,
+                        ty: state.stack[index].ty.clone(),
+                        value: Expression::Value(Value::Variable(variable)),
+                    });
+                    replacement = Some(temporary);
+                    temporary
+                }
+            };
+
+            state.stack[index].value = Value::Variable(temporary);
+        }
+    }
+
+    /// Copies an operand popped for a branch into a temporary if the spill before the branch overwrites the
+    /// stack variable it refers to
+    fn preserve(&mut self, state: &mut BlockState, operand: StackEntry) -> Value {
+        if let Value::Variable(Variable::Stack(depth)) = operand.value {
+            let overwritten = match state.stack.get(depth as usize) {
+                Some(entry) => entry.value != operand.value,
+                None => false,
+            };
+            if overwritten {
+                let temporary = self.temporary();
+                state.emit(StatementKind::Assign {
+                    target: temporary,
+                    ty: operand.ty,
+                    value: Expression::Value(operand.value),
+                });
+                return Value::Variable(temporary);
             }
-            Instruction::IRem | Instruction::DRem | Instruction::FRem | Instruction::LRem => {
-                BinaryOperation::Modulo
+        }
+
+        operand.value
+    }
+
+    /// Stores all remaining stack entries into their stack variables, so they survive the block boundary
+    fn spill(&mut self, state: &mut BlockState) {
+        for depth in 0..state.stack.len() {
+            if let Value::Variable(variable @ Variable::Stack(other)) = state.stack[depth].value {
+                if other as usize != depth {
+                    self.materialize(state, variable);
+                }
             }
-            Instruction::IAnd | Instruction::LAnd => BinaryOperation::LAND,
-            Instruction::IXor | Instruction::LXor => BinaryOperation::LXOR,
-            Instruction::IOr | Instruction::LOr => BinaryOperation::LOR,
-            Instruction::IShl | Instruction::LShl => BinaryOperation::LeftShift,
-            Instruction::IUShr | Instruction::LUShr => BinaryOperation::RightShiftPadded,
-            Instruction::IShr | Instruction::LShr => BinaryOperation::RightShift,
-            _ => {
-                unreachable!()
+        }
+
+        for depth in 0..state.stack.len() {
+            let target = Variable::Stack(depth as u16);
+            let entry = &state.stack[depth];
+
+            if entry.value == Value::Variable(target) {
+                continue;
             }
+
+            let kind = StatementKind::Assign {
+                target,
+                ty: entry.ty.clone(),
+                value: Expression::Value(entry.value.clone()),
+            };
+            state.emit(kind);
+            state.stack[depth].value = Value::Variable(target);
         }
     }
-}
 
-impl Translate for ArithmeticStatementKind {
-    fn translate(instruction: Instruction, cp: ConstantPool) -> Self
-        where
-            Self: Sized,
-    {
-        match instruction {
-            Instruction::IAdd
-            | Instruction::ISub
-            | Instruction::IMul
-            | Instruction::IDiv
-            | Instruction::IRem
-            | Instruction::IOr
-            | Instruction::IXor
-            | Instruction::IAnd
-            | Instruction::IShl
-            | Instruction::IShr
-            | Instruction::IUShr
-
-            // Long arithmetics
-            | Instruction::LAdd
-            | Instruction::LSub
-            | Instruction::LMul
-            | Instruction::LDiv
-            | Instruction::LRem
-            | Instruction::LOr
-            | Instruction::LXor
-            | Instruction::LAnd
-            | Instruction::LShl
-            | Instruction::LShr
-            | Instruction::LUShr
-
-            // Float Arithmetics
-            | Instruction::FAdd
-            | Instruction::FSub
-            | Instruction::FMul
-            | Instruction::FDiv
-            | Instruction::FRem
-
-            // Double arithmetics
-            | Instruction::DAdd
-            | Instruction::DSub
-            | Instruction::DMul
-            | Instruction::DDiv
-            | Instruction::DRem => ArithmeticStatementKind::Binary(
-                BinaryOperation::translate(instruction,cp),
-            ),
-            Instruction::INeg | Instruction::LNeg | Instruction::FNeg | Instruction::DNeg => ArithmeticStatementKind::Unary(
-                UnaryOperation::translate(instruction, cp),
-            ),
-            _ => ArithmeticStatementKind::Unary(
-                UnaryOperation::translate(instruction, cp),
-            ),
+    fn binary(&mut self, state: &mut BlockState, operation: BinaryOperation, ty: TypeSignature) -> Result<(), Error> {
+        let right = state.pop_value()?;
+        let left = state.pop_value()?;
+        self.push_expression(
+            state,
+            ty.clone(),
+            Expression::Binary {
+                operation,
+                left,
+                right,
+                ty,
+            },
+        );
+        Ok(())
+    }
+
+    fn compare(
+        &mut self,
+        state: &mut BlockState,
+        operation: BinaryOperation,
+        ty: TypeSignature,
+    ) -> Result<(), Error> {
+        let right = state.pop_value()?;
+        let left = state.pop_value()?;
+        self.push_expression(
+            state,
+            TypeSignature::Integer,
+            Expression::Binary {
+                operation,
+                left,
+                right,
+                ty,
+            },
+        );
+        Ok(())
+    }
+
+    fn unary(&mut self, state: &mut BlockState, operation: UnaryOperation, ty: TypeSignature) -> Result<(), Error> {
+        let operand = state.pop_value()?;
+        self.push_expression(state, ty.clone(), Expression::Unary { operation, operand, ty });
+        Ok(())
+    }
+
+    fn convert(&mut self, state: &mut BlockState, from: TypeSignature, to: TypeSignature) -> Result<(), Error> {
+        let value = state.pop_value()?;
+        self.push_expression(state, to.clone(), Expression::Convert { value, from, to });
+        Ok(())
+    }
+
+    fn array_load(&mut self, state: &mut BlockState, ty: TypeSignature) -> Result<(), Error> {
+        let index = state.pop_value()?;
+        let array = state.pop_value()?;
+        self.push_expression(state, ty.clone(), Expression::ArrayLoad { array, index, ty });
+        Ok(())
+    }
+
+    fn array_store(&mut self, state: &mut BlockState, ty: TypeSignature) -> Result<(), Error> {
+        let value = state.pop_value()?;
+        let index = state.pop_value()?;
+        let array = state.pop_value()?;
+        state.emit(StatementKind::ArrayStore {
+            array,
+            index,
+            value,
+            ty,
+        });
+        Ok(())
+    }
+
+    fn constant(&mut self, state: &mut BlockState, constant: ConstantValue, ty: TypeSignature) {
+        state.push(Value::Constant(constant), ty);
+    }
+
+    /// Implements the `dup` family, which duplicates the top `words` stack words and inserts them
+    /// below the `skip` words underneath
+    fn duplicate(&mut self, state: &mut BlockState, words: u16, skip: u16) -> Result<(), Error> {
+        let duplicated = state.pop_words(words)?;
+        let skipped = state.pop_words(skip)?;
+
+        state.stack.extend(duplicated.iter().cloned());
+        state.stack.extend(skipped);
+        state.stack.extend(duplicated);
+        Ok(())
+    }
+
+    fn conditional_jump(
+        &mut self,
+        state: &mut BlockState,
+        operation: ComparisonOperation,
+        offset: i64,
+        against: Option<ConstantValue>,
+    ) -> Result<(), Error> {
+        let (left, right) = match against {
+            Some(constant) => {
+                let left = state.pop()?;
+                let right = StackEntry {
+                    value: Value::Constant(constant),
+                    ty: left.ty.clone(),
+                };
+                (left, right)
+            }
+            None => {
+                let right = state.pop()?;
+                (state.pop()?, right)
+            }
+        };
+        let left = self.preserve(state, left);
+        let right = self.preserve(state, right);
+        let target = branch_target(state, offset)?;
+
+        self.spill(state);
+        state.emit(StatementKind::Flow(FlowStatementKind::ConditionalJump {
+            condition: Condition { operation, left, right },
+            target,
+        }));
+        Ok(())
+    }
+
+    fn invoke(&mut self, state: &mut BlockState, kind: InvokeKind, index: ConstantPoolIndex) -> Result<(), Error> {
+        let method = member_reference(self.constant_pool.resolve_member_ref(index)?);
+        let MethodDescriptor {
+            parameters,
+            return_type,
+        } = MethodDescriptor::parse(&method.descriptor)?;
+
+        let arguments = state.pop_values(parameters.len())?;
+        let receiver = match kind {
+            InvokeKind::Static => None,
+            _ => Some(state.pop_value()?),
+        };
+
+        let expression = Expression::Invoke {
+            kind,
+            method,
+            receiver,
+            arguments,
+        };
+
+        if return_type == TypeSignature::Void {
+            state.emit(StatementKind::Evaluate(expression));
+        } else {
+            self.push_expression(state, return_type, expression);
         }
+        Ok(())
     }
-}
 
-impl Translate for FlowStatementKind {
-    fn translate(instruction: Instruction, cp: ConstantPool) -> Self
-        where
-            Self: Sized,
-    {
+    /// Translates a single instruction, returns whether it terminated the block
+    fn translate_instruction(&mut self, state: &mut BlockState, instruction: &Instruction) -> Result<bool, Error> {
+        use TypeSignature::*;
+
         match instruction {
-            // Conditionals
-            Instruction::IfEq { offset }
-            | Instruction::IfNe { offset }
-            | Instruction::IfGe { offset }
-            | Instruction::IfLe { offset }
-            | Instruction::IfGt { offset }
-            | Instruction::IfLt { offset }
-
-            //Arbitrary
-            | Instruction::IfACmpEq { offset }
-            | Instruction::IfACmpNe { offset }
-
-            //Integer
-            | Instruction::IfICmpEq { offset }
-            | Instruction::IfICmpNe { offset }
-            | Instruction::IfICmpGe { offset }
-            | Instruction::IfICmpLe { offset }
-            | Instruction::IfICmpGt { offset }
-            | Instruction::IfICmpLt { offset }
-
-            //Null
-            | Instruction::IfNonNull { offset }
-            | Instruction::IfNull { offset } => FlowStatementKind::ConditionalJump { target: offset },
-
-            Instruction::InvokeDynamic { index, .. }
-            | Instruction::InvokeInterface { index, .. }
-            | Instruction::InvokeSpecial { index }
-            | Instruction::InvokeStatic { index }
-            | Instruction::InvokeVirtual { index } => {
-
-                let (name_and_type_index, class_index) = extract_constant_fields!(cp, ConstantPoolIndex(index.0), Constant::MethodRef { name_and_type, class });
-                let (name_index, descriptor_index) = extract_constant_fields!(cp, *name_and_type_index, Constant::NameAndType {name, descriptor});
-                let name = extract_constant_fields!(cp, *name_index, Constant::Utf8 => (name)).clone();
-                let descriptor = extract_constant_fields!(cp, *descriptor_index, Constant::Utf8 => (name)).clone();
-
-                let class_name_index = extract_constant_fields!(cp, *class_index, Constant::Class => (name));
-                let owner = extract_constant_fields!(cp, *class_name_index, Constant::Utf8 => (name)).clone();
-
-                let (arguments, return_type) = parse_method_signature(&descriptor);
-
-                FlowStatementKind::MethodCall {
-                    owner,
-                    name,
-                    descriptor,
-                    arguments,
+            Instruction::Nop | Instruction::Wide => {}
+
+            // Constants
+            Instruction::AConstNull => self.constant(state, ConstantValue::Null, Arbitrary),
+            Instruction::IConstM1 => self.constant(state, ConstantValue::Integer(-1), Integer),
+            Instruction::IConst0 => self.constant(state, ConstantValue::Integer(0), Integer),
+            Instruction::IConst1 => self.constant(state, ConstantValue::Integer(1), Integer),
+            Instruction::IConst2 => self.constant(state, ConstantValue::Integer(2), Integer),
+            Instruction::IConst3 => self.constant(state, ConstantValue::Integer(3), Integer),
+            Instruction::IConst4 => self.constant(state, ConstantValue::Integer(4), Integer),
+            Instruction::IConst5 => self.constant(state, ConstantValue::Integer(5), Integer),
+            Instruction::LConst0 => self.constant(state, ConstantValue::Long(0), Long),
+            Instruction::LConst1 => self.constant(state, ConstantValue::Long(1), Long),
+            Instruction::FConst0 => self.constant(state, ConstantValue::Float(0.0), Float),
+            Instruction::FConst1 => self.constant(state, ConstantValue::Float(1.0), Float),
+            Instruction::FConst2 => self.constant(state, ConstantValue::Float(2.0), Float),
+            Instruction::DConst0 => self.constant(state, ConstantValue::Double(0.0), Double),
+            Instruction::DConst1 => self.constant(state, ConstantValue::Double(1.0), Double),
+            Instruction::BIPush { value } => {
+                self.constant(state, ConstantValue::Integer(*value as i32), Integer)
+            }
+            Instruction::SIPush { value } => self.constant(state, ConstantValue::Integer(*value as i32), Integer),
+            Instruction::LDC { index } => {
+                let (constant, ty) = loadable_constant(self.constant_pool, (*index).into())?;
+                self.constant(state, constant, ty)
+            }
+            Instruction::LDCW { index } | Instruction::LDC2W { index } => {
+                let (constant, ty) = loadable_constant(self.constant_pool, *index)?;
+                self.constant(state, constant, ty)
+            }
+
+            // Loads
+            Instruction::ILoad { index } => self.load(state, local_index(index), Integer),
+            Instruction::LLoad { index } => self.load(state, local_index(index), Long),
+            Instruction::FLoad { index } => self.load(state, local_index(index), Float),
+            Instruction::DLoad { index } => self.load(state, local_index(index), Double),
+            Instruction::ALoad { index } => self.load(state, local_index(index), Arbitrary),
+            Instruction::ILoad0 => self.load(state, 0, Integer),
+            Instruction::ILoad1 => self.load(state, 1, Integer),
+            Instruction::ILoad2 => self.load(state, 2, Integer),
+            Instruction::ILoad3 => self.load(state, 3, Integer),
+            Instruction::LLoad0 => self.load(state, 0, Long),
+            Instruction::LLoad1 => self.load(state, 1, Long),
+            Instruction::LLoad2 => self.load(state, 2, Long),
+            Instruction::LLoad3 => self.load(state, 3, Long),
+            Instruction::FLoad0 => self.load(state, 0, Float),
+            Instruction::FLoad1 => self.load(state, 1, Float),
+            Instruction::FLoad2 => self.load(state, 2, Float),
+            Instruction::FLoad3 => self.load(state, 3, Float),
+            Instruction::DLoad0 => self.load(state, 0, Double),
+            Instruction::DLoad1 => self.load(state, 1, Double),
+            Instruction::DLoad2 => self.load(state, 2, Double),
+            Instruction::DLoad3 => self.load(state, 3, Double),
+            Instruction::ALoad0 => self.load(state, 0, Arbitrary),
+            Instruction::ALoad1 => self.load(state, 1, Arbitrary),
+            Instruction::ALoad2 => self.load(state, 2, Arbitrary),
+            Instruction::ALoad3 => self.load(state, 3, Arbitrary),
+
+            // Stores
+            Instruction::IStore { index } => self.store(state, local_index(index), Integer)?,
+            Instruction::LStore { index } => self.store(state, local_index(index), Long)?,
+            Instruction::FStore { index } => self.store(state, local_index(index), Float)?,
+            Instruction::DStore { index } => self.store(state, local_index(index), Double)?,
+            Instruction::AStore { index } => {
+                let ty = state.stack.last().map(|entry| entry.ty.clone()).unwrap_or(Arbitrary);
+                self.store(state, local_index(index), ty)?
+            }
+            Instruction::IStore0 => self.store(state, 0, Integer)?,
+            Instruction::IStore1 => self.store(state, 1, Integer)?,
+            Instruction::IStore2 => self.store(state, 2, Integer)?,
+            Instruction::IStore3 => self.store(state, 3, Integer)?,
+            Instruction::LStore0 => self.store(state, 0, Long)?,
+            Instruction::LStore1 => self.store(state, 1, Long)?,
+            Instruction::LStore2 => self.store(state, 2, Long)?,
+            Instruction::LStore3 => self.store(state, 3, Long)?,
+            Instruction::FStore0 => self.store(state, 0, Float)?,
+            Instruction::FStore1 => self.store(state, 1, Float)?,
+            Instruction::FStore2 => self.store(state, 2, Float)?,
+            Instruction::FStore3 => self.store(state, 3, Float)?,
+            Instruction::DStore0 => self.store(state, 0, Double)?,
+            Instruction::DStore1 => self.store(state, 1, Double)?,
+            Instruction::DStore2 => self.store(state, 2, Double)?,
+            Instruction::DStore3 => self.store(state, 3, Double)?,
+            Instruction::AStore0 | Instruction::AStore1 | Instruction::AStore2 | Instruction::AStore3 => {
+                let index = match instruction {
+                    Instruction::AStore0 => 0,
+                    Instruction::AStore1 => 1,
+                    Instruction::AStore2 => 2,
+                    _ => 3,
+                };
+                let ty = state.stack.last().map(|entry| entry.ty.clone()).unwrap_or(Arbitrary);
+                self.store(state, index, ty)?
+            }
+            Instruction::IInc { index, value } => {
+                let index = local_index(index);
+                let increment = value.value() as i32;
+                let local = Variable::Local(index);
+
+                state.emit(StatementKind::Assign {
+                    target: local,
+                    ty: Integer,
+                    value: Expression::Binary {
+                        operation: BinaryOperation::Addition,
+                        left: Value::Variable(local),
+                        right: Value::Constant(ConstantValue::Integer(increment)),
+                        ty: Integer,
+                    },
+                });
+            }
+
+            // Arrays
+            Instruction::IALoad => self.array_load(state, Integer)?,
+            Instruction::LALoad => self.array_load(state, Long)?,
+            Instruction::FALoad => self.array_load(state, Float)?,
+            Instruction::DALoad => self.array_load(state, Double)?,
+            Instruction::AALoad => self.array_load(state, Arbitrary)?,
+            Instruction::BALoad => self.array_load(state, Byte)?,
+            Instruction::CALoad => self.array_load(state, Char)?,
+            Instruction::SALoad => self.array_load(state, Short)?,
+            Instruction::IAStore => self.array_store(state, Integer)?,
+            Instruction::LAStore => self.array_store(state, Long)?,
+            Instruction::FAStore => self.array_store(state, Float)?,
+            Instruction::DAStore => self.array_store(state, Double)?,
+            Instruction::AAStore => self.array_store(state, Arbitrary)?,
+            Instruction::BAStore => self.array_store(state, Byte)?,
+            Instruction::CAStore => self.array_store(state, Char)?,
+            Instruction::SAStore => self.array_store(state, Short)?,
+            Instruction::ArrayLength => {
+                let array = state.pop_value()?;
+                self.push_expression(state, Integer, Expression::ArrayLength(array));
+            }
+            Instruction::NewArray { ty } => {
+                let length = state.pop_value()?;
+                let ty = Array(Box::new(array_type(ty)));
+                self.push_expression(
+                    state,
+                    ty.clone(),
+                    Expression::NewArray {
+                        ty,
+                        dimensions: vec![length],
+                    },
+                );
+            }
+            Instruction::ANewArray { index } => {
+                let length = state.pop_value()?;
+                let ty = Array(Box::new(class_type(self.constant_pool, *index)?));
+                self.push_expression(
+                    state,
+                    ty.clone(),
+                    Expression::NewArray {
+                        ty,
+                        dimensions: vec![length],
+                    },
+                );
+            }
+            Instruction::MultiANewArray { index, dimensions } => {
+                let dimensions = state.pop_values(*dimensions as usize)?;
+                let ty = class_type(self.constant_pool, *index)?;
+                self.push_expression(state, ty.clone(), Expression::NewArray { ty, dimensions });
+            }
+
+            // Stack manipulation
+            Instruction::Pop => {
+                state.pop_words(1)?;
+            }
+            Instruction::Pop2 => {
+                state.pop_words(2)?;
+            }
+            Instruction::Dup => self.duplicate(state, 1, 0)?,
+            Instruction::DupX1 => self.duplicate(state, 1, 1)?,
+            Instruction::DupX2 => self.duplicate(state, 1, 2)?,
+            Instruction::Dup2 => self.duplicate(state, 2, 0)?,
+            Instruction::Dup2X1 => self.duplicate(state, 2, 1)?,
+            Instruction::Dup2X2 => self.duplicate(state, 2, 2)?,
+            Instruction::Swap => {
+                let first = state.pop_words(1)?;
+                let second = state.pop_words(1)?;
+                state.stack.extend(first);
+                state.stack.extend(second);
+            }
+
+            // Arithmetic
+            Instruction::IAdd => self.binary(state, BinaryOperation::Addition, Integer)?,
+            Instruction::LAdd => self.binary(state, BinaryOperation::Addition, Long)?,
+            Instruction::FAdd => self.binary(state, BinaryOperation::Addition, Float)?,
+            Instruction::DAdd => self.binary(state, BinaryOperation::Addition, Double)?,
+            Instruction::ISub => self.binary(state, BinaryOperation::Subtraction, Integer)?,
+            Instruction::LSub => self.binary(state, BinaryOperation::Subtraction, Long)?,
+            Instruction::FSub => self.binary(state, BinaryOperation::Subtraction, Float)?,
+            Instruction::DSub => self.binary(state, BinaryOperation::Subtraction, Double)?,
+            Instruction::IMul => self.binary(state, BinaryOperation::Multiplication, Integer)?,
+            Instruction::LMul => self.binary(state, BinaryOperation::Multiplication, Long)?,
+            Instruction::FMul => self.binary(state, BinaryOperation::Multiplication, Float)?,
+            Instruction::DMul => self.binary(state, BinaryOperation::Multiplication, Double)?,
+            Instruction::IDiv => self.binary(state, BinaryOperation::Division, Integer)?,
+            Instruction::LDiv => self.binary(state, BinaryOperation::Division, Long)?,
+            Instruction::FDiv => self.binary(state, BinaryOperation::Division, Float)?,
+            Instruction::DDiv => self.binary(state, BinaryOperation::Division, Double)?,
+            Instruction::IRem => self.binary(state, BinaryOperation::Modulo, Integer)?,
+            Instruction::LRem => self.binary(state, BinaryOperation::Modulo, Long)?,
+            Instruction::FRem => self.binary(state, BinaryOperation::Modulo, Float)?,
+            Instruction::DRem => self.binary(state, BinaryOperation::Modulo, Double)?,
+            Instruction::IShl => self.binary(state, BinaryOperation::LeftShift, Integer)?,
+            Instruction::LShl => self.binary(state, BinaryOperation::LeftShift, Long)?,
+            Instruction::IShr => self.binary(state, BinaryOperation::RightShift, Integer)?,
+            Instruction::LShr => self.binary(state, BinaryOperation::RightShift, Long)?,
+            Instruction::IUShr => self.binary(state, BinaryOperation::RightShiftPadded, Integer)?,
+            Instruction::LUShr => self.binary(state, BinaryOperation::RightShiftPadded, Long)?,
+            Instruction::IAnd => self.binary(state, BinaryOperation::LAND, Integer)?,
+            Instruction::LAnd => self.binary(state, BinaryOperation::LAND, Long)?,
+            Instruction::IOr => self.binary(state, BinaryOperation::LOR, Integer)?,
+            Instruction::LOr => self.binary(state, BinaryOperation::LOR, Long)?,
+            Instruction::IXor => self.binary(state, BinaryOperation::LXOR, Integer)?,
+            Instruction::LXor => self.binary(state, BinaryOperation::LXOR, Long)?,
+            Instruction::INeg => self.unary(state, UnaryOperation::ArithmeticNegate, Integer)?,
+            Instruction::LNeg => self.unary(state, UnaryOperation::ArithmeticNegate, Long)?,
+            Instruction::FNeg => self.unary(state, UnaryOperation::ArithmeticNegate, Float)?,
+            Instruction::DNeg => self.unary(state, UnaryOperation::ArithmeticNegate, Double)?,
+
+            // Comparisons
+            Instruction::LCmp => self.compare(state, BinaryOperation::Compare, Long)?,
+            Instruction::FCmpPL => self.compare(state, BinaryOperation::CompareNaNLess, Float)?,
+            Instruction::FCmpPG => self.compare(state, BinaryOperation::CompareNaNGreater, Float)?,
+            Instruction::DCmpL => self.compare(state, BinaryOperation::CompareNaNLess, Double)?,
+            Instruction::DCmpG => self.compare(state, BinaryOperation::CompareNaNGreater, Double)?,
+
+            // Conversions
+            Instruction::I2L => self.convert(state, Integer, Long)?,
+            Instruction::I2F => self.convert(state, Integer, Float)?,
+            Instruction::I2D => self.convert(state, Integer, Double)?,
+            Instruction::L2I => self.convert(state, Long, Integer)?,
+            Instruction::L2F => self.convert(state, Long, Float)?,
+            Instruction::L2D => self.convert(state, Long, Double)?,
+            Instruction::F2I => self.convert(state, Float, Integer)?,
+            Instruction::F2L => self.convert(state, Float, Long)?,
+            Instruction::F2D => self.convert(state, Float, Double)?,
+            Instruction::D2I => self.convert(state, Double, Integer)?,
+            Instruction::D2L => self.convert(state, Double, Long)?,
+            Instruction::D2F => self.convert(state, Double, Float)?,
+            Instruction::I2B => self.convert(state, Integer, Byte)?,
+            Instruction::I2C => self.convert(state, Integer, Char)?,
+            Instruction::I2S => self.convert(state, Integer, Short)?,
+
+            // Objects
+            Instruction::New { index } => {
+                let ty = class_type(self.constant_pool, *index)?;
+                self.push_expression(state, ty.clone(), Expression::New(ty));
+            }
+            Instruction::CheckCast { index } => {
+                let value = state.pop_value()?;
+                let ty = class_type(self.constant_pool, *index)?;
+                self.push_expression(state, ty.clone(), Expression::CheckCast { value, ty });
+            }
+            Instruction::InstanceOf { index } => {
+                let value = state.pop_value()?;
+                let ty = class_type(self.constant_pool, *index)?;
+                self.push_expression(state, Integer, Expression::InstanceOf { value, ty });
+            }
+            Instruction::GetField { index } | Instruction::GetStatic { index } => {
+                let field = member_reference(self.constant_pool.resolve_member_ref(*index)?);
+                let ty = parse_field_descriptor(&field.descriptor)?;
+                let object = match instruction {
+                    Instruction::GetField { .. } => Some(state.pop_value()?),
+                    _ => None,
+                };
+                self.push_expression(state, ty, Expression::FieldLoad { field, object });
+            }
+            Instruction::PutField { index } | Instruction::PutStatic { index } => {
+                let field = member_reference(self.constant_pool.resolve_member_ref(*index)?);
+                let value = state.pop_value()?;
+                let object = match instruction {
+                    Instruction::PutField { .. } => Some(state.pop_value()?),
+                    _ => None,
+                };
+                state.emit(StatementKind::Field(FieldStatementKind::Store { field, object, value }));
+            }
+            Instruction::MonitorEnter => {
+                let value = state.pop_value()?;
+                state.emit(StatementKind::MonitorEnter(value));
+            }
+            Instruction::MonitorExit => {
+                let value = state.pop_value()?;
+                state.emit(StatementKind::MonitorExit(value));
+            }
+
+            // Invocations
+            Instruction::InvokeVirtual { index } => self.invoke(state, InvokeKind::Virtual, *index)?,
+            Instruction::InvokeSpecial { index } => self.invoke(state, InvokeKind::Special, *index)?,
+            Instruction::InvokeStatic { index } => self.invoke(state, InvokeKind::Static, *index)?,
+            Instruction::InvokeInterface { index, .. } => {
+                self.invoke(state, InvokeKind::Interface, *index)?
+            }
+            Instruction::InvokeDynamic { index, .. } => {
+                let call_site = self.constant_pool.resolve_invoke_dynamic(*index)?;
+                let MethodDescriptor {
+                    parameters,
                     return_type,
+                } = MethodDescriptor::parse(call_site.descriptor)?;
+                let arguments = state.pop_values(parameters.len())?;
+
+                let expression = Expression::InvokeDynamic {
+                    bootstrap_method: call_site.bootstrap_method,
+                    name: call_site.name.to_string(),
+                    descriptor: call_site.descriptor.to_string(),
+                    arguments,
+                };
+
+                if return_type == Void {
+                    state.emit(StatementKind::Evaluate(expression));
+                } else {
+                    self.push_expression(state, return_type, expression);
                 }
             }
 
+            // Control flow
+            Instruction::IfEq { offset } => self.conditional_jump(
+                state,
+                ComparisonOperation::Equal,
+                *offset as i64,
+                Some(ConstantValue::Integer(0)),
+            )?,
+            Instruction::IfNe { offset } => self.conditional_jump(
+                state,
+                ComparisonOperation::NotEqual,
+                *offset as i64,
+                Some(ConstantValue::Integer(0)),
+            )?,
+            Instruction::IfLt { offset } => self.conditional_jump(
+                state,
+                ComparisonOperation::Less,
+                *offset as i64,
+                Some(ConstantValue::Integer(0)),
+            )?,
+            Instruction::IfGe { offset } => self.conditional_jump(
+                state,
+                ComparisonOperation::GreaterEqual,
+                *offset as i64,
+                Some(ConstantValue::Integer(0)),
+            )?,
+            Instruction::IfGt { offset } => self.conditional_jump(
+                state,
+                ComparisonOperation::Greater,
+                *offset as i64,
+                Some(ConstantValue::Integer(0)),
+            )?,
+            Instruction::IfLe { offset } => self.conditional_jump(
+                state,
+                ComparisonOperation::LessEqual,
+                *offset as i64,
+                Some(ConstantValue::Integer(0)),
+            )?,
+            Instruction::IfNull { offset } => self.conditional_jump(
+                state,
+                ComparisonOperation::Equal,
+                *offset as i64,
+                Some(ConstantValue::Null),
+            )?,
+            Instruction::IfNonNull { offset } => self.conditional_jump(
+                state,
+                ComparisonOperation::NotEqual,
+                *offset as i64,
+                Some(ConstantValue::Null),
+            )?,
+            Instruction::IfICmpEq { offset } | Instruction::IfACmpEq { offset } => {
+                self.conditional_jump(state, ComparisonOperation::Equal, *offset as i64, None)?
+            }
+            Instruction::IfICmpNe { offset } | Instruction::IfACmpNe { offset } => {
+                self.conditional_jump(state, ComparisonOperation::NotEqual, *offset as i64, None)?
+            }
+            Instruction::IfICmpLt { offset } => {
+                self.conditional_jump(state, ComparisonOperation::Less, *offset as i64, None)?
+            }
+            Instruction::IfICmpGe { offset } => {
+                self.conditional_jump(state, ComparisonOperation::GreaterEqual, *offset as i64, None)?
+            }
+            Instruction::IfICmpGt { offset } => {
+                self.conditional_jump(state, ComparisonOperation::Greater, *offset as i64, None)?
+            }
+            Instruction::IfICmpLe { offset } => {
+                self.conditional_jump(state, ComparisonOperation::LessEqual, *offset as i64, None)?
+            }
+            Instruction::Goto { offset } => {
+                let target = branch_target(state, *offset as i64)?;
+                self.spill(state);
+                state.emit(StatementKind::Flow(FlowStatementKind::UnconditionalJump(target)));
+            }
+            Instruction::GotoW { offset } => {
+                let target = branch_target(state, *offset as i64)?;
+                self.spill(state);
+                state.emit(StatementKind::Flow(FlowStatementKind::UnconditionalJump(target)));
+            }
+            Instruction::JSR { .. } | Instruction::JSRW { .. } => {
+                let (offset, length) = match instruction {
+                    Instruction::JSR { offset } => (*offset as i64, 3),
+                    Instruction::JSRW { offset } => (*offset as i64, 5),
+                    _ => unreachable!(),
+                };
+                let target = branch_target(state, offset)?;
+                let return_address = ConstantValue::ReturnAddress(state.position + length);
+
+                self.constant(state, return_address, Arbitrary);
+                self.spill(state);
+                state.emit(StatementKind::Flow(FlowStatementKind::JumpSubroutine(target)));
+            }
+            Instruction::Ret { index } => {
+                self.spill(state);
+                state.emit(StatementKind::Flow(FlowStatementKind::ReturnSubroutine(Variable::Local(
+                    local_index(index),
+                ))));
+            }
+            Instruction::TableSwitch {
+                default, low, offsets, ..
+            } => {
+                let value = state.pop()?;
+                let value = self.preserve(state, value);
+                let cases = offsets
+                    .iter()
+                    .enumerate()
+                    .map(|(index, offset)| Ok((low + index as i32, branch_target(state, *offset as i64)?)))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                let default = branch_target(state, *default as i64)?;
+
+                self.spill(state);
+                state.emit(StatementKind::Flow(FlowStatementKind::Switch { value, cases, default }));
+            }
+            Instruction::LookUpSwitch { default, pairs } => {
+                let value = state.pop()?;
+                let value = self.preserve(state, value);
+                let cases = pairs
+                    .iter()
+                    .map(|pair| Ok((pair.match_value, branch_target(state, pair.offset as i64)?)))
+                    .collect::<Result<Vec<_>, Error>>()?;
+                let default = branch_target(state, *default as i64)?;
+
+                self.spill(state);
+                state.emit(StatementKind::Flow(FlowStatementKind::Switch { value, cases, default }));
+            }
             Instruction::IReturn
             | Instruction::LReturn
             | Instruction::FReturn
             | Instruction::DReturn
-            | Instruction::Ret { .. }
-            | Instruction::Return
-            | Instruction::AReturn
-            => FlowStatementKind::Return,
-
-            _ => unreachable!()
+            | Instruction::AReturn => {
+                let value = state.pop_value()?;
+                state.emit(StatementKind::Flow(FlowStatementKind::Return(Some(value))));
+            }
+            Instruction::Return => state.emit(StatementKind::Flow(FlowStatementKind::Return(None))),
+            Instruction::AThrow => {
+                let value = state.pop_value()?;
+                state.emit(StatementKind::Flow(FlowStatementKind::Throw(value)));
+            }
         }
+
+        Ok(matches!(
+            state.statements.last().map(Statement::kind),
+            Some(StatementKind::Flow(_))
+        ))
     }
 }
 
-fn parse_method_signature(signature: &str) -> (Vec<TypeSignature>, TypeSignature) {
-    let mut chars = signature.chars();
-    let mut arguments = Vec::new();
-    let mut return_type = None;
-    let mut state = ParseState::Arguments;
-
-    while let Some(ch) = chars.next() {
-        match state {
-            ParseState::Arguments => {
-                if ch == ')' {
-                    state = ParseState::ReturnType;
-                } else {
+fn branch_target(state: &BlockState, offset: i64) -> Result<u64, Error> {
+    let target = state.position as i64 + offset;
 
-                    let argument_type = TypeSignature::from(ch.to_string());
-                    arguments.push(argument_type);
-                }
-            }
-            ParseState::ReturnType => {
-                let return_type_descriptor = format!("{}{}", ch, chars.collect::<String>());
-                return_type = Some(TypeSignature::from(return_type_descriptor));
-                break;
-            }
+    if target < 0 {
+        return Err(AnalysisError::NegativeBranchTarget {
+            position: state.position,
         }
+        .into());
+    }
+
+    Ok(target as u64)
+}
+
+fn local_index(index: &SizedIndex) -> u16 {
+    match index {
+        SizedIndex::Normal(index) => index.0 as u16,
+        SizedIndex::Wide(index) => index.0,
     }
+}
 
-    let return_type = return_type.unwrap_or(TypeSignature::Void);
-    (arguments, return_type)
+fn array_type(ty: &ArrayType) -> TypeSignature {
+    match ty {
+        ArrayType::Boolean => TypeSignature::Boolean,
+        ArrayType::Char => TypeSignature::Char,
+        ArrayType::Float => TypeSignature::Float,
+        ArrayType::Double => TypeSignature::Double,
+        ArrayType::Byte => TypeSignature::Byte,
+        ArrayType::Short => TypeSignature::Short,
+        ArrayType::Int => TypeSignature::Integer,
+        ArrayType::Long => TypeSignature::Long,
+    }
 }
 
-enum ParseState {
-    Arguments,
-    ReturnType,
+/// Resolves a `CONSTANT_Class` entry. Array classes are named by their descriptor
+fn class_type(cp: &ConstantPool, index: ConstantPoolIndex) -> Result<TypeSignature, Error> {
+    class_name_type(cp.resolve_class_name(index)?)
 }
 
-impl Translate for UnaryOperation {
-    fn translate(instruction: Instruction, cp: ConstantPool) -> Self
-        where
-            Self: Sized,
-    {
-        match instruction {
-            Instruction::INeg | Instruction::FNeg | Instruction::DNeg | Instruction::LNeg => {
-                UnaryOperation::ArithmeticNegate
-            }
-            _ => UnaryOperation::LogicalNegate,
-        }
+fn class_name_type(name: &str) -> Result<TypeSignature, Error> {
+    if name.starts_with('[') {
+        Ok(parse_field_descriptor(name)?)
+    } else {
+        Ok(TypeSignature::Class(name.to_string()))
+    }
+}
+
+fn member_reference(reference: MemberRef) -> MemberReference {
+    MemberReference {
+        owner: reference.class.to_string(),
+        name: reference.name.to_string(),
+        descriptor: reference.descriptor.to_string(),
     }
 }
 
+fn loadable_constant(cp: &ConstantPool, index: ConstantPoolIndex) -> Result<(ConstantValue, TypeSignature), Error> {
+    Ok(match cp.resolve_loadable_constant(index)? {
+        LoadableConstant::Integer(value) => (ConstantValue::Integer(value), TypeSignature::Integer),
+        LoadableConstant::Float(value) => (ConstantValue::Float(value), TypeSignature::Float),
+        LoadableConstant::Long(value) => (ConstantValue::Long(value), TypeSignature::Long),
+        LoadableConstant::Double(value) => (ConstantValue::Double(value), TypeSignature::Double),
+        LoadableConstant::String(value) => (
+            ConstantValue::String(value.to_string()),
+            TypeSignature::Class("java/lang/String".to_string()),
+        ),
+        LoadableConstant::Class(name) => (
+            ConstantValue::Class(class_name_type(name)?),
+            TypeSignature::Class("java/lang/Class".to_string()),
+        ),
+        LoadableConstant::MethodType(descriptor) => (
+            ConstantValue::MethodType(descriptor.to_string()),
+            TypeSignature::Class("java/lang/invoke/MethodType".to_string()),
+        ),
+        LoadableConstant::MethodHandle(handle) => (
+            ConstantValue::MethodHandle {
+                reference_kind: handle.reference_kind,
+                reference: member_reference(handle.reference),
+            },
+            TypeSignature::Class("java/lang/invoke/MethodHandle".to_string()),
+        ),
+        LoadableConstant::Dynamic(constant) => (
+            ConstantValue::Dynamic {
+                bootstrap_method: constant.bootstrap_method,
+                name: constant.name.to_string(),
+                descriptor: constant.descriptor.to_string(),
+            },
+            parse_field_descriptor(constant.descriptor)?,
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use jbmf_error::{ConstantPoolError, Error, LifterError};
+    use jbmf_ir::statement::TypeSignature;
+    use jbmf_parser::java_rs_pacific::attribute::{Instruction, MatchOffsetPair};
+    use jbmf_parser::java_rs_pacific::{Constant, ConstantPool, ConstantPoolIndex};
+
+    use crate::translate::Translator;
+
+    fn render(instructions: Vec<Instruction>, entry_stack: &[TypeSignature]) -> (Vec<String>, Vec<TypeSignature>) {
+        let cp = ConstantPool(Vec::new());
+        let instructions: Vec<(u64, Instruction)> = instructions
+            .into_iter()
+            .enumerate()
+            .map(|(position, instruction)| (position as u64, instruction))
+            .collect();
+
+        let block = Translator::new(&cp).translate_block(&instructions, entry_stack).unwrap();
+        (
+            block.statements.iter().map(|statement| statement.to_string()).collect(),
+            block.exit_stack,
+        )
+    }
+
+    #[test]
+    fn translate_arithmetic() {
+        let (statements, exit_stack) = render(
+            vec![
+                Instruction::ILoad0,
+                Instruction::ILoad1,
+                Instruction::IAdd,
+                Instruction::IConst2,
+                Instruction::IMul,
+                Instruction::IReturn,
+            ],
+            &[],
+        );
+
+        assert_eq!(statements, vec!["t0 = l0", "t1 = l1", "t2 = t0 + t1", "t3 = t2 * 2", "return t3"]);
+        assert!(exit_stack.is_empty());
+    }
+
+    #[test]
+    fn translate_store_keeps_loaded_value() {
+        let (statements, _) = render(
+            vec![Instruction::ILoad0, Instruction::IConst1, Instruction::IStore0, Instruction::IReturn],
+            &[],
+        );
+
+        assert_eq!(statements, vec!["t0 = l0", "l0 = 1", "return t0"]);
+    }
+
+    #[test]
+    fn translate_spills_stack_at_block_end() {
+        let (statements, exit_stack) = render(
+            vec![Instruction::Swap, Instruction::LConst1, Instruction::Dup2X1],
+            &[TypeSignature::Integer, TypeSignature::Integer],
+        );
+
+        assert_eq!(statements, vec!["t0 = s1", "t1 = s0", "s0 = t0", "s1 = 1L", "s2 = t1", "s3 = 1L"]);
+        assert_eq!(
+            exit_stack,
+            vec![
+                TypeSignature::Integer,
+                TypeSignature::Long,
+                TypeSignature::Integer,
+                TypeSignature::Long
+            ]
+        );
+    }
+
+    #[test]
+    fn translate_branch_keeps_operand_overwritten_by_spill() {
+        let (statements, _) = render(
+            vec![Instruction::Swap, Instruction::IfEq { offset: 10 }],
+            &[TypeSignature::Integer, TypeSignature::Integer],
+        );
+
+        assert_eq!(statements, vec!["t0 = s0", "t1 = s1", "s0 = t1", "if t0 == 0 goto 11"]);
+    }
+
+    #[test]
+    fn translate_switch_keeps_key_overwritten_by_spill() {
+        let (statements, _) = render(
+            vec![
+                Instruction::Swap,
+                Instruction::LookUpSwitch {
+                    default: 10,
+                    pairs: vec![MatchOffsetPair {
+                        match_value: 1,
+                        offset: 20,
+                    }]
+                    .into(),
+                },
+            ],
+            &[TypeSignature::Integer, TypeSignature::Integer],
+        );
+
+        assert_eq!(statements, vec!["t0 = s0", "t1 = s1", "s0 = t1", "switch t0 { 1 -> 21, default -> 11 }"]);
+    }
+
+    #[test]
+    fn reject_malformed_input() {
+        let cp = ConstantPool(vec![Constant::Utf8("a".into())]);
+        let mut translator = Translator::new(&cp);
+
+        let result = translator.translate_block(&[(4, Instruction::Pop)], &[]);
+        assert!(matches!(
+            result,
+            Err(Error::LifterError(LifterError::StackUnderflow { position: 4 }))
+        ));
+
+        let result = translator.translate_block(&[(0, Instruction::New { index: ConstantPoolIndex(1) })], &[]);
+        assert!(matches!(
+            result,
+            Err(Error::ConstantPoolError(ConstantPoolError::UnexpectedConstant { index: 1, .. }))
+        ));
 
-impl Translate for TypeSignature {
-    fn translate(instruction: Instruction, cp: ConstantPool) -> Self
-        where
-            Self: Sized,
-    {
-        TypeSignature::Arbitrary
+        let result = translator.translate_block(&[(0, Instruction::New { index: ConstantPoolIndex(0) })], &[]);
+        assert!(matches!(
+            result,
+            Err(Error::ConstantPoolError(ConstantPoolError::InvalidIndex(0)))
+        ));
     }
 }
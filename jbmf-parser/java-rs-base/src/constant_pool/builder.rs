@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+
+use crate::constant_pool::{Constant, ConstantPool, ConstantPoolIndex};
+use crate::error::ConstantPoolError;
+
+/// Highest number of slots a pool can use, the count written before it is one more
+const MAX_SLOTS: usize = u16::MAX as usize - 1;
+
+/// The identity of an internable constant, floating point values are compared by their bits
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+enum Key {
+    Utf8(String),
+    Integer(i32),
+    Float(u32),
+    Long(i64),
+    Double(u64),
+    Class(u16),
+    String(u16),
+    FieldRef(u16, u16),
+    MethodRef(u16, u16),
+    InterfaceMethodRef(u16, u16),
+    NameAndType(u16, u16),
+    MethodHandle(u8, u16),
+    MethodType(u16),
+    Dynamic(u16, u16),
+    InvokeDynamic(u16, u16),
+    Module(u16),
+    Package(u16),
+}
+
+impl Key {
+    fn of(constant: &Constant) -> Option<Self> {
+        Some(match constant {
+            Constant::Utf8(value) => Key::Utf8(value.clone()),
+            Constant::Integer(value) => Key::Integer(*value),
+            Constant::Float(value) => Key::Float(value.to_bits()),
+            Constant::Long(value) => Key::Long(*value),
+            Constant::Double(value) => Key::Double(value.to_bits()),
+            Constant::Class(name) => Key::Class(name.0),
+            Constant::String(value) => Key::String(value.0),
+            Constant::FieldRef { class, name_and_type } => Key::FieldRef(class.0, name_and_type.0),
+            Constant::MethodRef { class, name_and_type } => Key::MethodRef(class.0, name_and_type.0),
+            Constant::InterfaceMethodRef { class, name_and_type } => {
+                Key::InterfaceMethodRef(class.0, name_and_type.0)
+            }
+            Constant::NameAndType { name, descriptor } => Key::NameAndType(name.0, descriptor.0),
+            Constant::MethodHandle {
+                reference_kind,
+                reference,
+            } => Key::MethodHandle(*reference_kind, reference.0),
+            Constant::MethodType(descriptor) => Key::MethodType(descriptor.0),
+            Constant::Dynamic {
+                bootstrap_method_attribute,
+                name_and_type,
+            } => Key::Dynamic(*bootstrap_method_attribute, name_and_type.0),
+            Constant::InvokeDynamic {
+                bootstrap_method_attribute,
+                name_and_type,
+            } => Key::InvokeDynamic(*bootstrap_method_attribute, name_and_type.0),
+            Constant::Module(name) => Key::Module(name.0),
+            Constant::Package(name) => Key::Package(name.0),
+            Constant::Raw { .. } | Constant::Unsupported(_) | Constant::InvalidUtf8(_) | Constant::Unusable => {
+                return None
+            }
+        })
+    }
+}
+
+/// Adds constants to a pool, reusing equal constants which are already in it. `Long` and `Double`
+/// constants are followed by the unusable slot they occupy
+pub struct ConstantPoolBuilder<'a> {
+    pool: &'a mut ConstantPool,
+    indices: HashMap<Key, ConstantPoolIndex>,
+}
+
+impl<'a> ConstantPoolBuilder<'a> {
+    pub fn new(pool: &'a mut ConstantPool) -> Self {
+        let mut indices = HashMap::new();
+
+        for (position, constant) in pool.0.iter().enumerate() {
+            if let Some(key) = Key::of(constant) {
+                indices.entry(key).or_insert(ConstantPoolIndex(position as u16 + 1));
+            }
+        }
+
+        Self { pool, indices }
+    }
+
+    pub fn pool(&self) -> &ConstantPool {
+        self.pool
+    }
+
+    /// Number of used slots, including the unusable slots after long and double constants
+    pub fn len(&self) -> usize {
+        self.pool.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pool.0.is_empty()
+    }
+
+    /// The index of an equal constant in the pool
+    pub fn get(&self, constant: &Constant) -> Option<ConstantPoolIndex> {
+        self.indices.get(&Key::of(constant)?).copied()
+    }
+
+    /// Returns the index of an equal constant or appends it. Constants which can't be compared, like
+    /// `Raw` ones, are always appended
+    pub fn insert(&mut self, constant: Constant) -> Result<ConstantPoolIndex, ConstantPoolError> {
+        let key = Key::of(&constant);
+        if let Some(index) = key.as_ref().and_then(|key| self.indices.get(key)) {
+            return Ok(*index);
+        }
+
+        let wide = matches!(constant, Constant::Long(_) | Constant::Double(_));
+        let slots = if wide { 2 } else { 1 };
+        if self.pool.0.len() + slots > MAX_SLOTS {
+            return Err(ConstantPoolError::Full);
+        }
+
+        let index = ConstantPoolIndex(self.pool.0.len() as u16 + 1);
+        self.pool.0.push(constant);
+        if wide {
+            self.pool.0.push(Constant::Unusable);
+        }
+        if let Some(key) = key {
+            self.indices.insert(key, index);
+        }
+
+        Ok(index)
+    }
+
+    pub fn utf8(&mut self, value: &str) -> Result<ConstantPoolIndex, ConstantPoolError> {
+        match self.indices.get(&Key::Utf8(value.to_string())) {
+            Some(index) => Ok(*index),
+            None => self.insert(Constant::Utf8(value.to_string())),
+        }
+    }
+
+    pub fn integer(&mut self, value: i32) -> Result<ConstantPoolIndex, ConstantPoolError> {
+        self.insert(Constant::Integer(value))
+    }
+
+    pub fn float(&mut self, value: f32) -> Result<ConstantPoolIndex, ConstantPoolError> {
+        self.insert(Constant::Float(value))
+    }
+
+    pub fn long(&mut self, value: i64) -> Result<ConstantPoolIndex, ConstantPoolError> {
+        self.insert(Constant::Long(value))
+    }
+
+    pub fn double(&mut self, value: f64) -> Result<ConstantPoolIndex, ConstantPoolError> {
+        self.insert(Constant::Double(value))
+    }
+
+    /// A class by its internal name, or an array class by its descriptor
+    pub fn class(&mut self, name: &str) -> Result<ConstantPoolIndex, ConstantPoolError> {
+        let name = self.utf8(name)?;
+        self.insert(Constant::Class(name))
+    }
+
+    pub fn string(&mut self, value: &str) -> Result<ConstantPoolIndex, ConstantPoolError> {
+        let value = self.utf8(value)?;
+        self.insert(Constant::String(value))
+    }
+
+    pub fn name_and_type(&mut self, name: &str, descriptor: &str) -> Result<ConstantPoolIndex, ConstantPoolError> {
+        let name = self.utf8(name)?;
+        let descriptor = self.utf8(descriptor)?;
+        self.insert(Constant::NameAndType { name, descriptor })
+    }
+
+    pub fn field_ref(
+        &mut self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<ConstantPoolIndex, ConstantPoolError> {
+        let class = self.class(class)?;
+        let name_and_type = self.name_and_type(name, descriptor)?;
+        self.insert(Constant::FieldRef { class, name_and_type })
+    }
+
+    pub fn method_ref(
+        &mut self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<ConstantPoolIndex, ConstantPoolError> {
+        let class = self.class(class)?;
+        let name_and_type = self.name_and_type(name, descriptor)?;
+        self.insert(Constant::MethodRef { class, name_and_type })
+    }
+
+    pub fn interface_method_ref(
+        &mut self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<ConstantPoolIndex, ConstantPoolError> {
+        let class = self.class(class)?;
+        let name_and_type = self.name_and_type(name, descriptor)?;
+        self.insert(Constant::InterfaceMethodRef { class, name_and_type })
+    }
+
+    /// A method handle to the member, which is referenced as a field for the kinds 1 to 4, as an
+    /// interface method for kind 9 and as a method otherwise
+    pub fn method_handle(
+        &mut self,
+        reference_kind: u8,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<ConstantPoolIndex, ConstantPoolError> {
+        let reference = match reference_kind {
+            1..=4 => self.field_ref(class, name, descriptor)?,
+            9 => self.interface_method_ref(class, name, descriptor)?,
+            _ => self.method_ref(class, name, descriptor)?,
+        };
+        self.insert(Constant::MethodHandle {
+            reference_kind,
+            reference,
+        })
+    }
+
+    pub fn method_type(&mut self, descriptor: &str) -> Result<ConstantPoolIndex, ConstantPoolError> {
+        let descriptor = self.utf8(descriptor)?;
+        self.insert(Constant::MethodType(descriptor))
+    }
+
+    pub fn dynamic(
+        &mut self,
+        bootstrap_method: u16,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<ConstantPoolIndex, ConstantPoolError> {
+        let name_and_type = self.name_and_type(name, descriptor)?;
+        self.insert(Constant::Dynamic {
+            bootstrap_method_attribute: bootstrap_method,
+            name_and_type,
+        })
+    }
+
+    pub fn invoke_dynamic(
+        &mut self,
+        bootstrap_method: u16,
+        name: &str,
+        descriptor: &str,
+    ) -> Result<ConstantPoolIndex, ConstantPoolError> {
+        let name_and_type = self.name_and_type(name, descriptor)?;
+        self.insert(Constant::InvokeDynamic {
+            bootstrap_method_attribute: bootstrap_method,
+            name_and_type,
+        })
+    }
+
+    pub fn module(&mut self, name: &str) -> Result<ConstantPoolIndex, ConstantPoolError> {
+        let name = self.utf8(name)?;
+        self.insert(Constant::Module(name))
+    }
+
+    pub fn package(&mut self, name: &str) -> Result<ConstantPoolIndex, ConstantPoolError> {
+        let name = self.utf8(name)?;
+        self.insert(Constant::Package(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interns_constants() {
+        let mut pool = ConstantPool(vec![
+            Constant::Utf8("java/lang/Object".to_string()),
+            Constant::Class(ConstantPoolIndex(1)),
+            Constant::Long(5),
+            Constant::Unusable,
+        ]);
+        let mut builder = ConstantPoolBuilder::new(&mut pool);
+
+        assert_eq!(builder.class("java/lang/Object"), Ok(ConstantPoolIndex(2)));
+        assert_eq!(builder.long(5), Ok(ConstantPoolIndex(3)));
+        assert_eq!(builder.double(f64::NAN), Ok(ConstantPoolIndex(5)));
+        assert_eq!(builder.double(f64::NAN), Ok(ConstantPoolIndex(5)));
+        assert_eq!(builder.integer(1), Ok(ConstantPoolIndex(7)));
+
+        let method = builder.method_ref("java/lang/Object", "<init>", "()V");
+        assert_eq!(method, Ok(ConstantPoolIndex(11)));
+        assert_eq!(builder.method_ref("java/lang/Object", "<init>", "()V"), method);
+        assert_eq!(builder.method_handle(7, "java/lang/Object", "<init>", "()V"), Ok(ConstantPoolIndex(12)));
+        assert_eq!(builder.len(), 12);
+        assert_eq!(pool.0[5], Constant::Unusable);
+    }
+
+    #[test]
+    fn reports_full_pool() {
+        let mut pool = ConstantPool((0..MAX_SLOTS - 1).map(|value| Constant::Integer(value as i32)).collect());
+        let mut builder = ConstantPoolBuilder::new(&mut pool);
+
+        assert_eq!(builder.integer(0), Ok(ConstantPoolIndex(1)));
+        assert_eq!(builder.long(0), Err(ConstantPoolError::Full));
+        assert_eq!(builder.integer(-1), Ok(ConstantPoolIndex(MAX_SLOTS as u16)));
+        assert_eq!(builder.integer(-2), Err(ConstantPoolError::Full));
+    }
+}
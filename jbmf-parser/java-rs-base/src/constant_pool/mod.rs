@@ -2,13 +2,17 @@ use std::io::{Read, Write};
 
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 
+pub use builder::ConstantPoolBuilder;
 pub use constant::Constant;
+pub use resolve::{DynamicRef, LoadableConstant, MemberRef, MemberRefKind, MethodHandle, NameAndType};
 
-use crate::error::Error;
-use crate::io::{ClassFilePart, ReadContext, WriteContext};
+use crate::error::{ConstantPoolError, Error};
+use crate::io::{ClassFilePart, PositionReader, ReadContext, WriteContext};
 use crate::version::JavaVersion;
 
+mod builder;
 mod constant;
+mod resolve;
 
 // https://docs.oracle.com/javase/specs/jvms/se15/html/jvms-4.html#jvms-4.4
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -18,16 +22,24 @@ pub struct ConstantPool(pub Vec<Constant>);
 pub struct ConstantPoolIndex(pub u16);
 
 impl ConstantPool {
+    /// Errors are located relative to the start of the constant pool
     pub fn read<R: Read>(reader: &mut R, version: &JavaVersion) -> Result<Self, Error>
     where
         Self: Sized,
     {
-        let count = reader.read_u16::<BigEndian>()? - 1;
+        let mut reader = PositionReader::new(reader);
+        let count = reader
+            .read_u16::<BigEndian>()
+            .map_err(|error| Error::from(error).located(0, "constant_pool"))?
+            .checked_sub(1)
+            .ok_or_else(|| Error::from(ConstantPoolError::ZeroCount).located(0, "constant_pool"))?;
         let mut constants = Vec::with_capacity(count as usize);
         let mut i = 0;
 
         while i < count {
-            let result = Constant::read(reader, version)?;
+            let offset = reader.position();
+            let result = Constant::read(&mut reader, version)
+                .map_err(|error| error.located(offset, format!("constant_pool[{}]", i + 1)))?;
             let length = &(result.len() as u16);
 
             for constant in result {
@@ -52,12 +64,9 @@ impl ConstantPool {
 }
 
 impl ConstantPool {
+    /// The constant at `index`, `None` for index 0 and indices outside of the pool
     pub fn get(&self, index: ConstantPoolIndex) -> Option<&Constant> {
-        if let Some(value) = self.0.get(index.0 as usize - 1) {
-            Some(value)
-        } else {
-            None
-        }
+        self.0.get((index.0 as usize).checked_sub(1)?)
     }
 }
 
@@ -80,3 +89,20 @@ impl ClassFilePart for ConstantPoolIndex {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_zero_count() {
+        let version = JavaVersion { major: 52, minor: 0 };
+
+        let error = ConstantPool::read(&mut [0u8, 0].as_slice(), &version).unwrap_err();
+        assert_eq!(error.location(), Some((0, "constant_pool", None)));
+        assert!(matches!(
+            error,
+            Error::Located { source, .. } if matches!(*source, Error::ConstantPool(ConstantPoolError::ZeroCount))
+        ));
+    }
+}
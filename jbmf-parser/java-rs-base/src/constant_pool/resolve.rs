@@ -0,0 +1,314 @@
+use crate::constant_pool::{Constant, ConstantPool, ConstantPoolIndex};
+use crate::error::ConstantPoolError;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct NameAndType<'a> {
+    pub name: &'a str,
+    pub descriptor: &'a str,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MemberRefKind {
+    Field,
+    Method,
+    InterfaceMethod,
+}
+
+/// A `CONSTANT_Fieldref`, `CONSTANT_Methodref` or `CONSTANT_InterfaceMethodref`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct MemberRef<'a> {
+    pub kind: MemberRefKind,
+    pub class: &'a str,
+    pub name: &'a str,
+    pub descriptor: &'a str,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct MethodHandle<'a> {
+    pub reference_kind: u8,
+    pub reference: MemberRef<'a>,
+}
+
+/// A `CONSTANT_InvokeDynamic` or `CONSTANT_Dynamic`, `bootstrap_method` indexes the
+/// `BootstrapMethods` attribute
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct DynamicRef<'a> {
+    pub bootstrap_method: u16,
+    pub name: &'a str,
+    pub descriptor: &'a str,
+}
+
+/// A constant which can be loaded by `ldc`, `ldc_w` or `ldc2_w`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LoadableConstant<'a> {
+    Integer(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    /// The internal name of the class, or the descriptor of an array class
+    Class(&'a str),
+    String(&'a str),
+    MethodType(&'a str),
+    MethodHandle(MethodHandle<'a>),
+    Dynamic(DynamicRef<'a>),
+}
+
+impl ConstantPool {
+    /// Looks up a constant, looking through constants which are unsupported by the class file version
+    pub fn resolve(&self, index: ConstantPoolIndex) -> Result<&Constant, ConstantPoolError> {
+        match index.0.checked_sub(1).and_then(|position| self.0.get(position as usize)) {
+            Some(Constant::Unsupported(constant)) => Ok(constant),
+            Some(constant) => Ok(constant),
+            None => Err(ConstantPoolError::InvalidIndex(index.0)),
+        }
+    }
+
+    pub fn resolve_utf8(&self, index: ConstantPoolIndex) -> Result<&str, ConstantPoolError> {
+        match self.resolve(index)? {
+            Constant::Utf8(value) => Ok(value),
+            constant => Err(unexpected(index, "Utf8", constant)),
+        }
+    }
+
+    pub fn resolve_integer(&self, index: ConstantPoolIndex) -> Result<i32, ConstantPoolError> {
+        match self.resolve(index)? {
+            Constant::Integer(value) => Ok(*value),
+            constant => Err(unexpected(index, "Integer", constant)),
+        }
+    }
+
+    pub fn resolve_float(&self, index: ConstantPoolIndex) -> Result<f32, ConstantPoolError> {
+        match self.resolve(index)? {
+            Constant::Float(value) => Ok(*value),
+            constant => Err(unexpected(index, "Float", constant)),
+        }
+    }
+
+    pub fn resolve_long(&self, index: ConstantPoolIndex) -> Result<i64, ConstantPoolError> {
+        match self.resolve(index)? {
+            Constant::Long(value) => Ok(*value),
+            constant => Err(unexpected(index, "Long", constant)),
+        }
+    }
+
+    pub fn resolve_double(&self, index: ConstantPoolIndex) -> Result<f64, ConstantPoolError> {
+        match self.resolve(index)? {
+            Constant::Double(value) => Ok(*value),
+            constant => Err(unexpected(index, "Double", constant)),
+        }
+    }
+
+    pub fn resolve_class_name(&self, index: ConstantPoolIndex) -> Result<&str, ConstantPoolError> {
+        match self.resolve(index)? {
+            Constant::Class(name) => self.resolve_utf8(*name),
+            constant => Err(unexpected(index, "Class", constant)),
+        }
+    }
+
+    pub fn resolve_module_name(&self, index: ConstantPoolIndex) -> Result<&str, ConstantPoolError> {
+        match self.resolve(index)? {
+            Constant::Module(name) => self.resolve_utf8(*name),
+            constant => Err(unexpected(index, "Module", constant)),
+        }
+    }
+
+    /// The internal name of a package, e.g. `java/lang`
+    pub fn resolve_package_name(&self, index: ConstantPoolIndex) -> Result<&str, ConstantPoolError> {
+        match self.resolve(index)? {
+            Constant::Package(name) => self.resolve_utf8(*name),
+            constant => Err(unexpected(index, "Package", constant)),
+        }
+    }
+
+    pub fn resolve_name_and_type(&self, index: ConstantPoolIndex) -> Result<NameAndType<'_>, ConstantPoolError> {
+        match self.resolve(index)? {
+            Constant::NameAndType { name, descriptor } => Ok(NameAndType {
+                name: self.resolve_utf8(*name)?,
+                descriptor: self.resolve_utf8(*descriptor)?,
+            }),
+            constant => Err(unexpected(index, "NameAndType", constant)),
+        }
+    }
+
+    pub fn resolve_member_ref(&self, index: ConstantPoolIndex) -> Result<MemberRef<'_>, ConstantPoolError> {
+        let (kind, class, name_and_type) = match self.resolve(index)? {
+            Constant::FieldRef { class, name_and_type } => (MemberRefKind::Field, class, name_and_type),
+            Constant::MethodRef { class, name_and_type } => (MemberRefKind::Method, class, name_and_type),
+            Constant::InterfaceMethodRef { class, name_and_type } => {
+                (MemberRefKind::InterfaceMethod, class, name_and_type)
+            }
+            constant => return Err(unexpected(index, "FieldRef, MethodRef or InterfaceMethodRef", constant)),
+        };
+        let NameAndType { name, descriptor } = self.resolve_name_and_type(*name_and_type)?;
+
+        Ok(MemberRef {
+            kind,
+            class: self.resolve_class_name(*class)?,
+            name,
+            descriptor,
+        })
+    }
+
+    pub fn resolve_method_handle(&self, index: ConstantPoolIndex) -> Result<MethodHandle<'_>, ConstantPoolError> {
+        match self.resolve(index)? {
+            Constant::MethodHandle {
+                reference_kind,
+                reference,
+            } => Ok(MethodHandle {
+                reference_kind: *reference_kind,
+                reference: self.resolve_member_ref(*reference)?,
+            }),
+            constant => Err(unexpected(index, "MethodHandle", constant)),
+        }
+    }
+
+    pub fn resolve_invoke_dynamic(&self, index: ConstantPoolIndex) -> Result<DynamicRef<'_>, ConstantPoolError> {
+        match self.resolve(index)? {
+            Constant::InvokeDynamic {
+                bootstrap_method_attribute,
+                name_and_type,
+            } => self.dynamic_ref(*bootstrap_method_attribute, *name_and_type),
+            constant => Err(unexpected(index, "InvokeDynamic", constant)),
+        }
+    }
+
+    pub fn resolve_loadable_constant(
+        &self,
+        index: ConstantPoolIndex,
+    ) -> Result<LoadableConstant<'_>, ConstantPoolError> {
+        Ok(match self.resolve(index)? {
+            Constant::Integer(value) => LoadableConstant::Integer(*value),
+            Constant::Float(value) => LoadableConstant::Float(*value),
+            Constant::Long(value) => LoadableConstant::Long(*value),
+            Constant::Double(value) => LoadableConstant::Double(*value),
+            Constant::Class(name) => LoadableConstant::Class(self.resolve_utf8(*name)?),
+            Constant::String(value) => LoadableConstant::String(self.resolve_utf8(*value)?),
+            Constant::MethodType(descriptor) => LoadableConstant::MethodType(self.resolve_utf8(*descriptor)?),
+            Constant::MethodHandle { .. } => LoadableConstant::MethodHandle(self.resolve_method_handle(index)?),
+            Constant::Dynamic {
+                bootstrap_method_attribute,
+                name_and_type,
+            } => LoadableConstant::Dynamic(self.dynamic_ref(*bootstrap_method_attribute, *name_and_type)?),
+            constant => return Err(unexpected(index, "loadable constant", constant)),
+        })
+    }
+
+    fn dynamic_ref(
+        &self,
+        bootstrap_method: u16,
+        name_and_type: ConstantPoolIndex,
+    ) -> Result<DynamicRef<'_>, ConstantPoolError> {
+        let NameAndType { name, descriptor } = self.resolve_name_and_type(name_and_type)?;
+
+        Ok(DynamicRef {
+            bootstrap_method,
+            name,
+            descriptor,
+        })
+    }
+}
+
+fn unexpected(index: ConstantPoolIndex, expected: &'static str, found: &Constant) -> ConstantPoolError {
+    ConstantPoolError::UnexpectedConstant {
+        index: index.0,
+        expected,
+        found: kind(found),
+    }
+}
+
+fn kind(constant: &Constant) -> &'static str {
+    match constant {
+        Constant::Utf8(_) => "Utf8",
+        Constant::Integer(_) => "Integer",
+        Constant::Float(_) => "Float",
+        Constant::Long(_) => "Long",
+        Constant::Double(_) => "Double",
+        Constant::Class(_) => "Class",
+        Constant::String(_) => "String",
+        Constant::FieldRef { .. } => "FieldRef",
+        Constant::MethodRef { .. } => "MethodRef",
+        Constant::InterfaceMethodRef { .. } => "InterfaceMethodRef",
+        Constant::NameAndType { .. } => "NameAndType",
+        Constant::MethodHandle { .. } => "MethodHandle",
+        Constant::MethodType(_) => "MethodType",
+        Constant::Dynamic { .. } => "Dynamic",
+        Constant::InvokeDynamic { .. } => "InvokeDynamic",
+        Constant::Module(_) => "Module",
+        Constant::Package(_) => "Package",
+        Constant::Raw { .. } => "Raw",
+        Constant::Unsupported(constant) => kind(constant),
+        Constant::InvalidUtf8(_) => "InvalidUtf8",
+        Constant::Unusable => "Unusable",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_references() {
+        let constant_pool = ConstantPool(vec![
+            Constant::MethodRef {
+                class: ConstantPoolIndex(2),
+                name_and_type: ConstantPoolIndex(4),
+            },
+            Constant::Class(ConstantPoolIndex(3)),
+            Constant::Utf8("a/A".into()),
+            Constant::NameAndType {
+                name: ConstantPoolIndex(5),
+                descriptor: ConstantPoolIndex(6),
+            },
+            Constant::Utf8("run".into()),
+            Constant::Utf8("()V".into()),
+            Constant::MethodHandle {
+                reference_kind: 6,
+                reference: ConstantPoolIndex(1),
+            },
+            Constant::InvokeDynamic {
+                bootstrap_method_attribute: 0,
+                name_and_type: ConstantPoolIndex(4),
+            },
+        ]);
+        let reference = MemberRef {
+            kind: MemberRefKind::Method,
+            class: "a/A",
+            name: "run",
+            descriptor: "()V",
+        };
+
+        assert_eq!(constant_pool.resolve_member_ref(ConstantPoolIndex(1)), Ok(reference));
+        assert_eq!(
+            constant_pool.resolve_loadable_constant(ConstantPoolIndex(7)),
+            Ok(LoadableConstant::MethodHandle(MethodHandle {
+                reference_kind: 6,
+                reference
+            }))
+        );
+        assert_eq!(
+            constant_pool.resolve_invoke_dynamic(ConstantPoolIndex(8)),
+            Ok(DynamicRef {
+                bootstrap_method: 0,
+                name: "run",
+                descriptor: "()V"
+            })
+        );
+        assert_eq!(constant_pool.resolve_class_name(ConstantPoolIndex(2)), Ok("a/A"));
+        assert_eq!(
+            constant_pool.resolve_class_name(ConstantPoolIndex(3)),
+            Err(ConstantPoolError::UnexpectedConstant {
+                index: 3,
+                expected: "Class",
+                found: "Utf8"
+            })
+        );
+        assert_eq!(
+            constant_pool.resolve_member_ref(ConstantPoolIndex(0)),
+            Err(ConstantPoolError::InvalidIndex(0))
+        );
+        assert_eq!(
+            constant_pool.resolve_utf8(ConstantPoolIndex(9)),
+            Err(ConstantPoolError::InvalidIndex(9))
+        );
+    }
+}
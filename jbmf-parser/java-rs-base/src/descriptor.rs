@@ -0,0 +1,328 @@
+use std::fmt::{Display, Formatter};
+use std::iter::Peekable;
+use std::str::{CharIndices, FromStr};
+
+use crate::error::DescriptorError;
+
+const MAX_DIMENSIONS: usize = 255;
+const MAX_PARAMETER_SLOTS: u32 = 255;
+
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd)]
+pub enum TypeSignature {
+    Byte,
+    Char,
+    Integer,
+    Boolean,
+    Long,
+    Short,
+    Float,
+    Double,
+
+    Class(String),
+
+    Arbitrary,
+
+    Void,
+
+    Array(Box<TypeSignature>),
+}
+
+impl TypeSignature {
+    /// Number of local variable slots (or operand stack words) a value of this type occupies
+    pub fn slot_size(&self) -> u16 {
+        match self {
+            TypeSignature::Long | TypeSignature::Double => 2,
+            TypeSignature::Void => 0,
+            _ => 1,
+        }
+    }
+
+    /// The field descriptor of the type, e.g. `I` or `[Ljava/lang/String;`. Arbitrary references are
+    /// described as `java/lang/Object`
+    pub fn descriptor(&self) -> String {
+        match self {
+            TypeSignature::Byte => "B".to_string(),
+            TypeSignature::Char => "C".to_string(),
+            TypeSignature::Integer => "I".to_string(),
+            TypeSignature::Boolean => "Z".to_string(),
+            TypeSignature::Long => "J".to_string(),
+            TypeSignature::Short => "S".to_string(),
+            TypeSignature::Float => "F".to_string(),
+            TypeSignature::Double => "D".to_string(),
+            TypeSignature::Class(name) => format!("L{};", name),
+            TypeSignature::Arbitrary => "Ljava/lang/Object;".to_string(),
+            TypeSignature::Void => "V".to_string(),
+            TypeSignature::Array(element) => format!("[{}", element.descriptor()),
+        }
+    }
+}
+
+impl Display for TypeSignature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TypeSignature::Byte => f.write_str("byte"),
+            TypeSignature::Char => f.write_str("char"),
+            TypeSignature::Integer => f.write_str("int"),
+            TypeSignature::Boolean => f.write_str("boolean"),
+            TypeSignature::Long => f.write_str("long"),
+            TypeSignature::Short => f.write_str("short"),
+            TypeSignature::Float => f.write_str("float"),
+            TypeSignature::Double => f.write_str("double"),
+            TypeSignature::Class(name) => f.write_str(name),
+            TypeSignature::Arbitrary => f.write_str("?"),
+            TypeSignature::Void => f.write_str("void"),
+            TypeSignature::Array(element) => write!(f, "{}[]", element),
+        }
+    }
+}
+
+/// Parameter and return types of a method descriptor such as `(Ljava/lang/String;[I)V`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MethodDescriptor {
+    pub parameters: Vec<TypeSignature>,
+    pub return_type: TypeSignature,
+}
+
+impl MethodDescriptor {
+    /// Parses and validates a method descriptor. The parameters may take at most 255 slots, the receiver of
+    /// instance methods is not counted
+    pub fn parse(descriptor: &str) -> Result<Self, DescriptorError> {
+        let mut parser = Parser::new(descriptor);
+
+        parser.expect('(')?;
+        let mut parameters = Vec::new();
+        while !parser.consume(')')? {
+            parameters.push(parser.field_type()?);
+        }
+        let return_type = match parser.consume('V')? {
+            true => TypeSignature::Void,
+            false => parser.field_type()?,
+        };
+        parser.finish()?;
+
+        let parsed = MethodDescriptor {
+            parameters,
+            return_type,
+        };
+        if parsed.parameter_slots() > MAX_PARAMETER_SLOTS {
+            return Err(DescriptorError::TooManyParameterSlots {
+                descriptor: descriptor.to_string(),
+            });
+        }
+        Ok(parsed)
+    }
+
+    /// Number of local variable slots taken by the parameters, without the receiver
+    pub fn parameter_slots(&self) -> u32 {
+        self.parameters.iter().map(|parameter| u32::from(parameter.slot_size())).sum()
+    }
+
+    pub fn descriptor(&self) -> String {
+        let parameters: String = self.parameters.iter().map(TypeSignature::descriptor).collect();
+        format!("({}){}", parameters, self.return_type.descriptor())
+    }
+}
+
+impl FromStr for MethodDescriptor {
+    type Err = DescriptorError;
+
+    fn from_str(descriptor: &str) -> Result<Self, Self::Err> {
+        Self::parse(descriptor)
+    }
+}
+
+/// Parses and validates a field descriptor such as `I` or `[Ljava/lang/String;`
+pub fn parse_field_descriptor(descriptor: &str) -> Result<TypeSignature, DescriptorError> {
+    let mut parser = Parser::new(descriptor);
+    let ty = parser.field_type()?;
+    parser.finish()?;
+    Ok(ty)
+}
+
+struct Parser<'a> {
+    descriptor: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(descriptor: &'a str) -> Self {
+        Parser {
+            descriptor,
+            chars: descriptor.char_indices().peekable(),
+        }
+    }
+
+    fn next(&mut self) -> Result<(usize, char), DescriptorError> {
+        self.chars.next().ok_or_else(|| DescriptorError::UnexpectedEnd {
+            descriptor: self.descriptor.to_string(),
+        })
+    }
+
+    /// Skips the next character if it is `expected`
+    fn consume(&mut self, expected: char) -> Result<bool, DescriptorError> {
+        match self.chars.peek() {
+            Some((_, c)) if *c == expected => {
+                self.chars.next();
+                Ok(true)
+            }
+            Some(_) => Ok(false),
+            None => Err(DescriptorError::UnexpectedEnd {
+                descriptor: self.descriptor.to_string(),
+            }),
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), DescriptorError> {
+        match self.next()? {
+            (_, c) if c == expected => Ok(()),
+            (position, found) => Err(self.unexpected(position, found)),
+        }
+    }
+
+    fn finish(mut self) -> Result<(), DescriptorError> {
+        match self.chars.next() {
+            Some((position, found)) => Err(self.unexpected(position, found)),
+            None => Ok(()),
+        }
+    }
+
+    fn unexpected(&self, position: usize, found: char) -> DescriptorError {
+        DescriptorError::UnexpectedCharacter {
+            descriptor: self.descriptor.to_string(),
+            position,
+            found,
+        }
+    }
+
+    fn field_type(&mut self) -> Result<TypeSignature, DescriptorError> {
+        let (position, c) = self.next()?;
+        Ok(match c {
+            'B' => TypeSignature::Byte,
+            'C' => TypeSignature::Char,
+            'D' => TypeSignature::Double,
+            'F' => TypeSignature::Float,
+            'I' => TypeSignature::Integer,
+            'J' => TypeSignature::Long,
+            'S' => TypeSignature::Short,
+            'Z' => TypeSignature::Boolean,
+            'L' => TypeSignature::Class(self.class_name(position)?),
+            '[' => {
+                let mut dimensions = 1;
+                while self.consume('[')? {
+                    dimensions += 1;
+                }
+                if dimensions > MAX_DIMENSIONS {
+                    return Err(DescriptorError::TooManyDimensions {
+                        descriptor: self.descriptor.to_string(),
+                        position,
+                    });
+                }
+
+                let mut ty = self.field_type()?;
+                for _ in 0..dimensions {
+                    ty = TypeSignature::Array(Box::new(ty));
+                }
+                ty
+            }
+            found => return Err(self.unexpected(position, found)),
+        })
+    }
+
+    /// Reads a binary class name up to its terminating `;`, `start` is the position of the leading `L`
+    fn class_name(&mut self, start: usize) -> Result<String, DescriptorError> {
+        let mut name = String::new();
+        loop {
+            match self.next()? {
+                (_, ';') => break,
+                (_, c) => name.push(c),
+            }
+        }
+
+        // Each package and the simple name must be a non empty unqualified name
+        let valid = name
+            .split('/')
+            .all(|part| !part.is_empty() && !part.contains(['.', '[']));
+        match valid {
+            true => Ok(name),
+            false => Err(DescriptorError::InvalidClassName {
+                descriptor: self.descriptor.to_string(),
+                position: start,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_method_descriptor() {
+        let descriptor = MethodDescriptor::parse("(Ljava/lang/String;[IJ[[D)V").unwrap();
+
+        assert_eq!(
+            descriptor.parameters,
+            vec![
+                TypeSignature::Class("java/lang/String".to_string()),
+                TypeSignature::Array(Box::new(TypeSignature::Integer)),
+                TypeSignature::Long,
+                TypeSignature::Array(Box::new(TypeSignature::Array(Box::new(TypeSignature::Double)))),
+            ]
+        );
+        assert_eq!(descriptor.return_type, TypeSignature::Void);
+        assert_eq!(descriptor.parameter_slots(), 5);
+        assert_eq!(descriptor.descriptor(), "(Ljava/lang/String;[IJ[[D)V");
+
+        assert_eq!(
+            parse_field_descriptor("[Ljava/util/List;"),
+            Ok(TypeSignature::Array(Box::new(TypeSignature::Class("java/util/List".to_string()))))
+        );
+    }
+
+    #[test]
+    fn reject_invalid_descriptors() {
+        let descriptor = |descriptor: &str| descriptor.to_string();
+
+        assert_eq!(
+            MethodDescriptor::parse("(IV)V"),
+            Err(DescriptorError::UnexpectedCharacter {
+                descriptor: descriptor("(IV)V"),
+                position: 2,
+                found: 'V'
+            })
+        );
+        assert_eq!(
+            MethodDescriptor::parse("(Ljava/lang/String"),
+            Err(DescriptorError::UnexpectedEnd {
+                descriptor: descriptor("(Ljava/lang/String"),
+            })
+        );
+        assert_eq!(
+            parse_field_descriptor("Ljava.lang.String;"),
+            Err(DescriptorError::InvalidClassName {
+                descriptor: descriptor("Ljava.lang.String;"),
+                position: 0
+            })
+        );
+        assert_eq!(
+            parse_field_descriptor("II"),
+            Err(DescriptorError::UnexpectedCharacter {
+                descriptor: descriptor("II"),
+                position: 1,
+                found: 'I'
+            })
+        );
+        assert!(matches!(
+            parse_field_descriptor(&format!("{}I", "[".repeat(256))),
+            Err(DescriptorError::TooManyDimensions { position: 0, .. })
+        ));
+        assert!(matches!(
+            MethodDescriptor::parse(&format!("({})V", "J".repeat(128))),
+            Err(DescriptorError::TooManyParameterSlots { .. })
+        ));
+        assert!(matches!(
+            MethodDescriptor::parse(&format!("({})V", "J".repeat(32768))),
+            Err(DescriptorError::TooManyParameterSlots { .. })
+        ));
+    }
+}
@@ -14,6 +14,9 @@ pub enum Error {
     #[error("Unknown Java target type {0:X}")]
     UnknownTargetType(u8),
 
+    #[error("Unknown Java type path kind {0:X}")]
+    UnknownTypePathKind(u8),
+
     #[error("Unknown Java verification type {0:X}")]
     UnknownVerificationType(u8),
 
@@ -28,6 +31,153 @@ pub enum Error {
 
     #[error("Expected value {} to {}, but got {found}", expected.0, expected.1)]
     UnexpectedOpCodeValue { expected: (u8, u8), found: u8 },
+
+    #[error("Invalid code at byte {position}: {reason}")]
+    InvalidCode { position: u64, reason: String },
+
+    #[error("Invalid descriptor used at byte {position} of the code: {source}")]
+    InvalidDescriptor { position: u64, source: DescriptorError },
+
+    #[error("Max stack or max locals of {0} exceeds the 8 bit limit of pre Java 1 code")]
+    PreJava1MaxValue(u16),
+
+    #[error(transparent)]
+    ConstantPool(#[from] ConstantPoolError),
+
+    /// An error together with where it occurred, e.g. `methods[3].attributes[0].code[17]`
+    #[error("{}{path} at byte {offset}: {source}", class.as_ref().map(|class| format!("{}: ", class)).unwrap_or_default())]
+    Located {
+        offset: u64,
+        path: String,
+        class: Option<String>,
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Marks the error as raised by the part at `offset` named `path`, an already located error keeps
+    /// its offset and gets `path` prepended
+    pub fn located(self, offset: u64, path: impl Into<String>) -> Self {
+        let path = path.into();
+
+        match self {
+            Error::Located {
+                offset,
+                path: inner,
+                class,
+                source,
+            } => {
+                let path = if path.is_empty() {
+                    inner
+                } else if inner.is_empty() || inner.starts_with('[') {
+                    path + &inner
+                } else {
+                    path + "." + &inner
+                };
+                Error::Located {
+                    offset,
+                    path,
+                    class,
+                    source,
+                }
+            }
+            error => Error::Located {
+                offset,
+                path,
+                class: None,
+                source: Box::new(error),
+            },
+        }
+    }
+
+    /// Moves the offset of a located error by `base`, for errors of parts read from a nested buffer
+    pub fn offset_by(self, base: u64) -> Self {
+        match self {
+            Error::Located {
+                offset,
+                path,
+                class,
+                source,
+            } => Error::Located {
+                offset: base + offset,
+                path,
+                class,
+                source,
+            },
+            error => error.located(base, ""),
+        }
+    }
+
+    /// Names the class in which a located error occurred
+    pub fn in_class(self, name: &str) -> Self {
+        match self {
+            Error::Located {
+                offset,
+                path,
+                class: None,
+                source,
+            } => Error::Located {
+                offset,
+                path,
+                class: Some(name.to_string()),
+                source,
+            },
+            error => error,
+        }
+    }
+
+    /// The offset, path and class of a located error
+    pub fn location(&self) -> Option<(u64, &str, Option<&str>)> {
+        match self {
+            Error::Located {
+                offset, path, class, ..
+            } => Some((*offset, path.as_str(), class.as_deref())),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+pub enum ConstantPoolError {
+    #[error("Index {0} is outside of the constant pool")]
+    InvalidIndex(u16),
+
+    #[error("Expected {expected} at index {index}, but found {found}")]
+    UnexpectedConstant {
+        index: u16,
+        expected: &'static str,
+        found: &'static str,
+    },
+
+    #[error("The constant pool exceeds 65535 entries")]
+    Full,
+
+    #[error("The constant pool count is 0, but must be at least 1")]
+    ZeroCount,
+
+    #[error("Constant {0} holds raw data whose references cannot be remapped")]
+    UnparsedConstant(u16),
+
+    #[error("Attribute named by index {0} holds raw data whose references cannot be remapped")]
+    UnparsedAttribute(u16),
+}
+
+#[derive(Debug, Error, Clone, Eq, PartialEq)]
+pub enum DescriptorError {
+    #[error("{descriptor}: unexpected end")]
+    UnexpectedEnd { descriptor: String },
+
+    #[error("{descriptor}: unexpected character {found:?} at {position}")]
+    UnexpectedCharacter { descriptor: String, position: usize, found: char },
+
+    #[error("{descriptor}: invalid class name at {position}")]
+    InvalidClassName { descriptor: String, position: usize },
+
+    #[error("{descriptor}: array type at {position} has more than 255 dimensions")]
+    TooManyDimensions { descriptor: String, position: usize },
+
+    #[error("{descriptor}: parameters take more than 255 slots")]
+    TooManyParameterSlots { descriptor: String },
 }
 
 #[derive(Debug, Error)]
@@ -26,12 +26,37 @@ pub struct WriteContext {
     pub position: Option<u64>,
 }
 
+/// Counts the bytes read through it, for locating errors
+pub struct PositionReader<R> {
+    inner: R,
+    position: u64,
+}
+
+impl<R: Read> PositionReader<R> {
+    pub fn new(inner: R) -> Self {
+        PositionReader { inner, position: 0 }
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+}
+
+impl<R: Read> Read for PositionReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
 #[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub enum AttributeLocation {
     ClassFile,
     Field,
     Method,
     Code,
+    RecordComponent,
 }
 
 #[derive(Default, Clone, PartialEq)]
@@ -191,6 +216,20 @@ impl ClassFilePartSize for u32 {
     }
 }
 
+impl ClassFilePart for i8 {
+    fn read<R: Read>(reader: &mut R, _: &ReadContext) -> Result<Self, Error>
+    where
+        Self: std::marker::Sized,
+    {
+        Ok(reader.read_i8()?)
+    }
+
+    fn write<W: Write>(&self, writer: &mut W, _: &WriteContext) -> Result<(), Error> {
+        writer.write_i8(*self)?;
+        Ok(())
+    }
+}
+
 impl ClassFilePart for i16 {
     fn read<R: Read>(reader: &mut R, _: &ReadContext) -> Result<Self, Error>
     where
@@ -1,4 +1,5 @@
 pub mod constant_pool;
+pub mod descriptor;
 pub mod error;
 pub mod io;
 pub mod java_utf8;
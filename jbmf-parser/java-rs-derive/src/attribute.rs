@@ -131,9 +131,10 @@ impl JavaRsAttribute {
                 "Method" => Some(AttributeLocation::Method),
                 "Field" => Some(AttributeLocation::Field),
                 "ClassFile" => Some(AttributeLocation::ClassFile),
+                "RecordComponent" => Some(AttributeLocation::RecordComponent),
                 v => {
                     proc_macro_error::emit_error!(ident.span(),
-                "Unknown location type {}", v; help = "Only Code, Method, Field or Class are currently available");
+                "Unknown location type {}", v; help = "Only Code, Method, Field, ClassFile or RecordComponent are currently available");
                     None
                 }
             })
@@ -75,6 +75,9 @@ fn generate_cases(data: &DataEnum) -> (Vec<TokenStream>, Vec<TokenStream>) {
                                     AttributeLocation::Field => quote! { java_rs_base::io::AttributeLocation::Field },
                                     AttributeLocation::Method => quote! { java_rs_base::io::AttributeLocation::Method },
                                     AttributeLocation::Code => quote! { java_rs_base::io::AttributeLocation::Code },
+                                    AttributeLocation::RecordComponent => {
+                                        quote! { java_rs_base::io::AttributeLocation::RecordComponent }
+                                    }
                                 })
                                 .collect(),
                         )
@@ -103,15 +106,20 @@ fn generate_cases(data: &DataEnum) -> (Vec<TokenStream>, Vec<TokenStream>) {
             };
 
             if custom_io {
+                // Errors located inside the data, like those of instructions, are moved behind the name and
+                // length of the attribute
                 read_body = quote! {
                     #read_body
-                    if result.is_err() {
-                        return Ok(Self::Raw(RawAttribute {
-                            name,
-                            info: data
-                        }))
-                    }
-                    let result = result.unwrap();
+                    let result = match result {
+                        Ok(result) => result,
+                        Err(error) if error.location().is_some() => return Err(error.offset_by(6)),
+                        Err(_) => {
+                            return Ok(Self::Raw(RawAttribute {
+                                name,
+                                info: data
+                            }))
+                        }
+                    };
                 };
             } else if parameters.len() > 1 {
                 let filtered: Vec<&Ident> = parameters
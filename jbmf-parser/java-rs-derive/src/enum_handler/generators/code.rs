@@ -25,13 +25,19 @@ pub struct CodeOptions {
 
 fn generate_read_body(data: &DataEnum) -> TokenStream {
     let cases = generate_cases(data, |variant| generate_read_case(variant));
+    let opcodes: Vec<u8> = data.variants.iter().map(|variant| derive_options(variant).opcode).collect();
+    let first = opcodes.iter().min();
+    let last = opcodes.iter().max();
 
     quote! {
         let opcode: u8 = java_rs_base::io::ClassFilePart::read(reader, ctx)?;
 
         match opcode {
             #(#cases)*
-            _ => unimplemented!("opcode unknown: {}", opcode)
+            found => Err(java_rs_base::error::Error::UnexpectedOpCodeValue {
+                expected: (#first, #last),
+                found,
+            })
         }
     }
 }
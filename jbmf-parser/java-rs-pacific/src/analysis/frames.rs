@@ -0,0 +1,1152 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use java_rs_base::descriptor::TypeSignature;
+use java_rs_base::error::Error;
+
+use crate::analysis::{constant, field_type, instruction_positions, member_descriptor, method_type, successors, utf8};
+use crate::attribute::{
+    ArrayType, Attribute, Compatibility, ExceptionTable, Instruction, SizedIndex, StackMapFrame, VerificationTypeInfo,
+};
+use crate::{AccessFlags, Constant, ConstantPool, ConstantPoolBuilder, ConstantPoolIndex};
+
+const OBJECT: &str = "java/lang/Object";
+const THROWABLE: &str = "java/lang/Throwable";
+
+/// Answers the questions about classes other than the one being written which the frame computation
+/// needs to merge reference types
+pub trait ClassHierarchy {
+    /// The direct superclass of a class, `None` for `java/lang/Object` and unknown classes
+    fn superclass(&self, class: &str) -> Option<String>;
+
+    fn is_interface(&self, class: &str) -> bool;
+
+    /// The most specific class both classes are assignable to. Interfaces are treated as
+    /// `java/lang/Object`, like the verifier does
+    fn common_superclass(&self, first: &str, second: &str) -> String {
+        if self.is_interface(first) || self.is_interface(second) {
+            return OBJECT.to_string();
+        }
+
+        let mut ancestors = HashSet::new();
+        let mut current = Some(first.to_string());
+        while let Some(class) = current {
+            current = self.superclass(&class);
+            ancestors.insert(class);
+        }
+
+        let mut current = Some(second.to_string());
+        while let Some(class) = current {
+            if ancestors.contains(&class) {
+                return class;
+            }
+            current = self.superclass(&class);
+        }
+
+        OBJECT.to_string()
+    }
+}
+
+/// A hierarchy given by a table of superclasses, classes missing from it are direct subclasses of
+/// `java/lang/Object`. The default value knows no classes at all
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct StaticHierarchy {
+    pub superclasses: HashMap<String, String>,
+    pub interfaces: HashSet<String>,
+}
+
+impl ClassHierarchy for StaticHierarchy {
+    fn superclass(&self, class: &str) -> Option<String> {
+        match self.superclasses.get(class) {
+            Some(superclass) => Some(superclass.clone()),
+            None if class == OBJECT => None,
+            None => Some(OBJECT.to_string()),
+        }
+    }
+
+    fn is_interface(&self, class: &str) -> bool {
+        self.interfaces.contains(class)
+    }
+}
+
+/// Verification type of a single local variable slot or operand stack word. Long and double values
+/// take two words, the second one being `Top`
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Type {
+    Top,
+    Integer,
+    Float,
+    Long,
+    Double,
+    Null,
+    UninitializedThis,
+    /// Created by the `new` instruction at the given position
+    Uninitialized { position: u64, class: String },
+    /// A class name, or a descriptor for array classes
+    Object(String),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct State {
+    locals: Vec<Type>,
+    stack: Vec<Type>,
+}
+
+/// Computes the `StackMapTable` of a code attribute by inferring the types of the local variables and
+/// operand stack words at every branch target, handler and instruction following an unconditional
+/// jump. The table is replaced, or removed if the code needs no frames. `name`, `descriptor` and
+/// `access_flags` are those of the method owning the code.
+///
+/// Unreachable code cannot be described by frames, so it is replaced by `nop`s followed by `athrow` and
+/// excluded from the exception table. Code using subroutines is rejected.
+pub fn compute_frames(
+    attribute: &mut Attribute,
+    class: &str,
+    name: &str,
+    descriptor: &str,
+    access_flags: AccessFlags,
+    constant_pool: &mut ConstantPool,
+    hierarchy: &dyn ClassHierarchy,
+) -> Result<(), Error> {
+    let (max_stack, code, exception_table, attributes) = match attribute {
+        Attribute::Code {
+            max_stack,
+            code: Compatibility::Current(code),
+            exception_table,
+            attributes,
+            ..
+        } => (max_stack, code, exception_table, attributes),
+        _ => return Ok(()),
+    };
+
+    let mut inference = Inference {
+        class,
+        constants: ConstantPoolBuilder::new(constant_pool),
+        hierarchy,
+    };
+    let initial = inference.initial_state(name, descriptor, access_flags.contains(AccessFlags::STATIC))?;
+
+    let (positions, length) = instruction_positions(code)?;
+    let states = inference.infer(code, exception_table, &positions, length, initial.clone())?;
+
+    // Replace every unreachable range with `nop ... athrow` of the same length
+    let mut dead_ranges: Vec<(u64, u64)> = Vec::new();
+    for (index, state) in states.iter().enumerate() {
+        let end = positions.get(index + 1).copied().unwrap_or(length);
+        match (state, dead_ranges.last_mut()) {
+            (Some(_), _) => {}
+            (None, Some(range)) if range.1 == positions[index] => range.1 = end,
+            (None, _) => dead_ranges.push((positions[index], end)),
+        }
+    }
+
+    let mut frames: Vec<(u64, State)> = Vec::new();
+    let mut frame_points = BTreeSet::new();
+
+    for (index, instruction) in code.iter().enumerate() {
+        if states[index].is_none() {
+            continue;
+        }
+
+        let position = positions[index];
+        let next = positions.get(index + 1).copied().unwrap_or(length);
+        let falls_through = falls_through(instruction);
+
+        for (successor, target) in successors(instruction, position, next).into_iter().enumerate() {
+            if !(falls_through && successor == 0) {
+                frame_points.insert(target);
+            }
+        }
+        if !falls_through && next < length {
+            frame_points.insert(next);
+        }
+    }
+
+    if !dead_ranges.is_empty() {
+        let mut rewritten = Vec::with_capacity(code.len());
+        for (index, instruction) in code.iter().enumerate() {
+            if states[index].is_some() {
+                rewritten.push(instruction.clone());
+                continue;
+            }
+
+            let next = positions.get(index + 1).copied().unwrap_or(length);
+            rewritten.extend((positions[index]..next).map(|_| Instruction::Nop));
+            if dead_ranges.iter().any(|(_, end)| *end == next) {
+                *rewritten.last_mut().unwrap() = Instruction::AThrow;
+            }
+        }
+        *code = rewritten.into();
+
+        let mut table = Vec::new();
+        for entry in exception_table.iter() {
+            let mut start = entry.start_pc as u64;
+            let end = entry.end_pc as u64;
+
+            for (dead_start, dead_end) in dead_ranges.iter() {
+                if *dead_end <= start || *dead_start >= end {
+                    continue;
+                }
+                if *dead_start > start {
+                    table.push(ExceptionTable {
+                        start_pc: start as u16,
+                        end_pc: *dead_start as u16,
+                        ..entry.clone()
+                    });
+                }
+                start = start.max(*dead_end);
+            }
+
+            if start < end {
+                table.push(ExceptionTable {
+                    start_pc: start as u16,
+                    ..entry.clone()
+                });
+            }
+        }
+        *exception_table = table.into();
+
+        let throwable = State {
+            locals: Vec::new(),
+            stack: vec![Type::Object(THROWABLE.to_string())],
+        };
+        for (start, _) in dead_ranges.iter() {
+            frame_points.insert(*start);
+            frames.push((*start, throwable.clone()));
+        }
+        if let Compatibility::Current(max_stack) = max_stack {
+            *max_stack = (*max_stack).max(1);
+        }
+    }
+
+    frame_points.extend(exception_table.iter().map(|entry| entry.handler_pc as u64));
+
+    let indices: HashMap<u64, usize> = positions.iter().enumerate().map(|(index, position)| (*position, index)).collect();
+    for point in frame_points.iter() {
+        if let Some(state) = indices.get(point).and_then(|index| states[*index].as_ref()) {
+            frames.push((*point, state.clone()));
+        }
+    }
+    frames.sort_by_key(|(position, _)| *position);
+
+    let mut encoded = Vec::with_capacity(frames.len());
+    let mut previous_locals = inference.verification_types(&initial.locals)?;
+    let mut previous_position: Option<u64> = None;
+
+    for (position, state) in frames.iter() {
+        let offset_delta = match previous_position {
+            Some(previous) => position - previous - 1,
+            None => *position,
+        } as u16;
+        let locals = inference.verification_types(&state.locals)?;
+        let stack = inference.verification_types(&state.stack)?;
+
+        encoded.push(encode_frame(offset_delta, &previous_locals, locals.clone(), stack));
+        previous_locals = locals;
+        previous_position = Some(*position);
+    }
+
+    let existing = attributes
+        .iter()
+        .position(|attribute| matches!(attribute, Attribute::StackMapTable { .. }));
+    match (existing, encoded.is_empty()) {
+        (Some(index), true) => {
+            attributes.remove(index);
+        }
+        (Some(index), false) => {
+            if let Attribute::StackMapTable { entries, .. } = &mut attributes[index] {
+                *entries = encoded.into();
+            }
+        }
+        (None, true) => {}
+        (None, false) => {
+            let name = inference.constants.utf8("StackMapTable")?;
+            attributes.push(Attribute::StackMapTable {
+                name,
+                entries: encoded.into(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Picks the shortest encoding of a frame relative to the locals of the previous frame
+fn encode_frame(
+    offset_delta: u16,
+    previous_locals: &[VerificationTypeInfo],
+    locals: Vec<VerificationTypeInfo>,
+    mut stack: Vec<VerificationTypeInfo>,
+) -> StackMapFrame {
+    let same_locals = locals == previous_locals;
+
+    if same_locals && stack.is_empty() {
+        return match offset_delta {
+            0..=63 => StackMapFrame::Same {
+                frame_type: offset_delta as u8,
+            },
+            _ => StackMapFrame::SameExtended { offset_delta },
+        };
+    }
+
+    if same_locals && stack.len() == 1 {
+        let stack = stack.remove(0);
+        return match offset_delta {
+            0..=63 => StackMapFrame::SameLocals1StackItem {
+                frame_type: 64 + offset_delta as u8,
+                stack,
+            },
+            _ => StackMapFrame::SameLocals1StackItemExtended { offset_delta, stack },
+        };
+    }
+
+    if stack.is_empty() && locals.len() < previous_locals.len() {
+        let chopped = previous_locals.len() - locals.len();
+        if chopped <= 3 && previous_locals.starts_with(&locals) {
+            return StackMapFrame::Chop {
+                frame_type: 251 - chopped as u8,
+                offset_delta,
+            };
+        }
+    }
+
+    if stack.is_empty() && locals.len() > previous_locals.len() {
+        let appended = locals.len() - previous_locals.len();
+        if appended <= 3 && locals.starts_with(previous_locals) {
+            return StackMapFrame::Append {
+                frame_type: 251 + appended as u8,
+                offset_delta,
+                locals: locals[previous_locals.len()..].to_vec().into(),
+            };
+        }
+    }
+
+    StackMapFrame::Full {
+        offset_delta,
+        locals: locals.into(),
+        stack: stack.into(),
+    }
+}
+
+fn falls_through(instruction: &Instruction) -> bool {
+    !matches!(
+        instruction,
+        Instruction::Goto { .. }
+            | Instruction::GotoW { .. }
+            | Instruction::TableSwitch { .. }
+            | Instruction::LookUpSwitch { .. }
+            | Instruction::AReturn
+            | Instruction::DReturn
+            | Instruction::FReturn
+            | Instruction::IReturn
+            | Instruction::LReturn
+            | Instruction::Return
+            | Instruction::AThrow
+            | Instruction::Ret { .. }
+    )
+}
+
+/// The verification types of the words a value takes, empty for `void`
+fn types(ty: &TypeSignature) -> Vec<Type> {
+    match ty {
+        TypeSignature::Boolean
+        | TypeSignature::Byte
+        | TypeSignature::Char
+        | TypeSignature::Short
+        | TypeSignature::Integer => vec![Type::Integer],
+        TypeSignature::Float => vec![Type::Float],
+        TypeSignature::Long => vec![Type::Long, Type::Top],
+        TypeSignature::Double => vec![Type::Double, Type::Top],
+        TypeSignature::Class(name) => vec![Type::Object(name.clone())],
+        TypeSignature::Array(_) => vec![Type::Object(ty.descriptor())],
+        TypeSignature::Arbitrary => vec![Type::Top],
+        TypeSignature::Void => Vec::new(),
+    }
+}
+
+/// Converts a class name as used by class constants into a field descriptor
+fn class_descriptor(name: &str) -> String {
+    if name.starts_with('[') {
+        name.to_string()
+    } else {
+        format!("L{};", name)
+    }
+}
+
+struct Inference<'a> {
+    class: &'a str,
+    constants: ConstantPoolBuilder<'a>,
+    hierarchy: &'a dyn ClassHierarchy,
+}
+
+impl<'a> Inference<'a> {
+    fn initial_state(&self, name: &str, descriptor: &str, is_static: bool) -> Result<State, Error> {
+        let parameters = method_type(descriptor, 0)?.parameters;
+        let mut locals = Vec::new();
+
+        if !is_static {
+            locals.push(if name == "<init>" && self.class != OBJECT {
+                Type::UninitializedThis
+            } else {
+                Type::Object(self.class.to_string())
+            });
+        }
+        for parameter in parameters {
+            locals.extend(types(&parameter));
+        }
+
+        Ok(State {
+            locals,
+            stack: Vec::new(),
+        })
+    }
+
+    /// The state before every instruction, `None` for unreachable instructions
+    fn infer(
+        &mut self,
+        code: &[Instruction],
+        exception_table: &[ExceptionTable],
+        positions: &[u64],
+        length: u64,
+        initial: State,
+    ) -> Result<Vec<Option<State>>, Error> {
+        let indices: HashMap<u64, usize> =
+            positions.iter().enumerate().map(|(index, position)| (*position, index)).collect();
+        let index_of = |position: u64, from: u64| {
+            indices.get(&position).copied().ok_or_else(|| Error::InvalidCode {
+                position: from,
+                reason: format!("jump to {}, which is not the start of an instruction", position),
+            })
+        };
+
+        let mut handlers = Vec::new();
+        for entry in exception_table {
+            let catch_type = match entry.catch_type.0 {
+                0 => THROWABLE.to_string(),
+                _ => match constant(self.constants.pool(), entry.catch_type) {
+                    Some(Constant::Class(name)) => utf8(self.constants.pool(), *name).map(str::to_string),
+                    _ => None,
+                }
+                .ok_or_else(|| Error::InvalidCode {
+                    position: entry.handler_pc as u64,
+                    reason: "catch type is not a class constant".to_string(),
+                })?,
+            };
+            handlers.push((entry, catch_type));
+        }
+
+        let mut states: Vec<Option<State>> = vec![None; code.len()];
+        let mut worklist = Vec::new();
+        if !code.is_empty() {
+            states[0] = Some(initial);
+            worklist.push(0);
+        }
+
+        while let Some(index) = worklist.pop() {
+            let position = positions[index];
+            let before = states[index].clone().unwrap();
+            let instruction = &code[index];
+            let mut after = before.clone();
+            self.execute(instruction, position, &mut after)?;
+
+            let mut targets = Vec::new();
+            for (entry, catch_type) in handlers.iter() {
+                if (entry.start_pc as u64..entry.end_pc as u64).contains(&position) {
+                    let handler = index_of(entry.handler_pc as u64, position)?;
+                    for locals in [&before.locals, &after.locals] {
+                        targets.push((
+                            handler,
+                            State {
+                                locals: locals.clone(),
+                                stack: vec![Type::Object(catch_type.clone())],
+                            },
+                        ));
+                    }
+                }
+            }
+
+            let next = positions.get(index + 1).copied().unwrap_or(length);
+            for target in successors(instruction, position, next) {
+                if target == length {
+                    return Err(Error::InvalidCode {
+                        position,
+                        reason: "control falls off the end of the code".to_string(),
+                    });
+                }
+                targets.push((index_of(target, position)?, after.clone()));
+            }
+
+            for (target, state) in targets {
+                let merged = match &states[target] {
+                    Some(existing) => self.merge(existing, &state, positions[target])?,
+                    None => state,
+                };
+
+                if states[target].as_ref() != Some(&merged) {
+                    states[target] = Some(merged);
+                    worklist.push(target);
+                }
+            }
+        }
+
+        Ok(states)
+    }
+
+    fn merge(&self, existing: &State, incoming: &State, position: u64) -> Result<State, Error> {
+        if existing.stack.len() != incoming.stack.len() {
+            return Err(Error::InvalidCode {
+                position,
+                reason: format!(
+                    "stack holds {} words on one path and {} on another",
+                    existing.stack.len(),
+                    incoming.stack.len()
+                ),
+            });
+        }
+
+        let mut stack = Vec::with_capacity(existing.stack.len());
+        for (first, second) in existing.stack.iter().zip(incoming.stack.iter()) {
+            stack.push(self.merge_types(first, second).ok_or_else(|| Error::InvalidCode {
+                position,
+                reason: format!("stack holds {:?} on one path and {:?} on another", first, second),
+            })?);
+        }
+
+        let length = existing.locals.len().min(incoming.locals.len());
+        let mut locals: Vec<Type> = existing.locals[..length]
+            .iter()
+            .zip(incoming.locals[..length].iter())
+            .map(|(first, second)| self.merge_types(first, second).unwrap_or(Type::Top))
+            .collect();
+
+        while locals.last() == Some(&Type::Top) {
+            locals.pop();
+        }
+        if matches!(locals.last(), Some(Type::Long | Type::Double)) {
+            locals.push(Type::Top);
+        }
+
+        Ok(State { locals, stack })
+    }
+
+    fn merge_types(&self, first: &Type, second: &Type) -> Option<Type> {
+        match (first, second) {
+            (first, second) if first == second => Some(first.clone()),
+            (Type::Null, Type::Object(_)) => Some(second.clone()),
+            (Type::Object(_), Type::Null) => Some(first.clone()),
+            (Type::Object(first), Type::Object(second)) => Some(Type::Object(self.common_reference(first, second))),
+            _ => None,
+        }
+    }
+
+    fn common_reference(&self, first: &str, second: &str) -> String {
+        match (first.strip_prefix('['), second.strip_prefix('[')) {
+            (Some(first), Some(second)) => {
+                let name = |element: &str| match element.chars().next() {
+                    Some('L') => Some(element[1..element.len() - 1].to_string()),
+                    Some('[') => Some(element.to_string()),
+                    _ => None,
+                };
+
+                match (name(first), name(second)) {
+                    (Some(first), Some(second)) => format!("[{}", class_descriptor(&self.common_reference(&first, &second))),
+                    _ => OBJECT.to_string(),
+                }
+            }
+            (None, None) => self.hierarchy.common_superclass(first, second),
+            _ => OBJECT.to_string(),
+        }
+    }
+
+    fn verification_types(&mut self, types: &[Type]) -> Result<Vec<VerificationTypeInfo>, Error> {
+        let mut result = Vec::new();
+        let mut iterator = types.iter();
+
+        while let Some(ty) = iterator.next() {
+            result.push(match ty {
+                Type::Top => VerificationTypeInfo::Top,
+                Type::Integer => VerificationTypeInfo::Integer,
+                Type::Float => VerificationTypeInfo::Float,
+                Type::Long => {
+                    iterator.next();
+                    VerificationTypeInfo::Long
+                }
+                Type::Double => {
+                    iterator.next();
+                    VerificationTypeInfo::Double
+                }
+                Type::Null => VerificationTypeInfo::Null,
+                Type::UninitializedThis => VerificationTypeInfo::UninitializedThis,
+                Type::Uninitialized { position, .. } => VerificationTypeInfo::Uninitialized {
+                    offset: *position as u16,
+                },
+                Type::Object(name) => VerificationTypeInfo::Object {
+                    index: self.constants.class(name)?,
+                },
+            });
+        }
+
+        while result.last() == Some(&VerificationTypeInfo::Top) {
+            result.pop();
+        }
+        Ok(result)
+    }
+
+    fn class_name(&self, index: &ConstantPoolIndex, position: u64) -> Result<String, Error> {
+        match constant(self.constants.pool(), *index) {
+            Some(Constant::Class(name)) => utf8(self.constants.pool(), *name).map(str::to_string),
+            _ => None,
+        }
+        .ok_or_else(|| Error::InvalidCode {
+            position,
+            reason: format!("constant {} is not a class", index.0),
+        })
+    }
+
+    fn member_descriptor(&self, index: &ConstantPoolIndex, position: u64) -> Result<String, Error> {
+        member_descriptor(self.constants.pool(), *index)
+            .map(str::to_string)
+            .ok_or_else(|| Error::InvalidCode {
+                position,
+                reason: format!("constant {} is not a member reference", index.0),
+            })
+    }
+
+    /// Applies the effect of an instruction to the state before it
+    fn execute(&self, instruction: &Instruction, position: u64, state: &mut State) -> Result<(), Error> {
+        let invalid = |reason: String| Error::InvalidCode { position, reason };
+
+        let stack = &mut state.stack;
+        let locals = &mut state.locals;
+        let pop = |stack: &mut Vec<Type>, words: usize| -> Result<Vec<Type>, Error> {
+            if stack.len() < words {
+                return Err(invalid("operand stack underflow".to_string()));
+            }
+            Ok(stack.split_off(stack.len() - words))
+        };
+
+        let slot = |index: &SizedIndex| match index {
+            SizedIndex::Normal(index) => index.0 as usize,
+            SizedIndex::Wide(index) => index.0 as usize,
+        };
+        let load = |locals: &Vec<Type>, stack: &mut Vec<Type>, slot: usize, words: usize| {
+            for offset in 0..words {
+                stack.push(locals.get(slot + offset).cloned().unwrap_or(Type::Top));
+            }
+        };
+        let store = |locals: &mut Vec<Type>, slot: usize, values: Vec<Type>| {
+            if locals.len() < slot + values.len() {
+                locals.resize(slot + values.len(), Type::Top);
+            }
+            if slot > 0 && matches!(locals[slot - 1], Type::Long | Type::Double) {
+                locals[slot - 1] = Type::Top;
+            }
+            for (offset, value) in values.into_iter().enumerate() {
+                locals[slot + offset] = value;
+            }
+        };
+
+        match instruction {
+            Instruction::Nop | Instruction::Wide | Instruction::IInc { .. } | Instruction::Goto { .. } | Instruction::GotoW { .. } => {}
+
+            Instruction::JSR { .. } | Instruction::JSRW { .. } | Instruction::Ret { .. } => {
+                return Err(invalid("subroutines cannot be described by stack map frames".to_string()))
+            }
+
+            Instruction::AConstNull => stack.push(Type::Null),
+            Instruction::IConstM1
+            | Instruction::IConst0
+            | Instruction::IConst1
+            | Instruction::IConst2
+            | Instruction::IConst3
+            | Instruction::IConst4
+            | Instruction::IConst5
+            | Instruction::BIPush { .. }
+            | Instruction::SIPush { .. } => stack.push(Type::Integer),
+            Instruction::FConst0 | Instruction::FConst1 | Instruction::FConst2 => stack.push(Type::Float),
+            Instruction::LConst0 | Instruction::LConst1 => stack.extend([Type::Long, Type::Top]),
+            Instruction::DConst0 | Instruction::DConst1 => stack.extend([Type::Double, Type::Top]),
+
+            Instruction::LDC { index } => stack.extend(self.loadable((*index).into(), position)?),
+            Instruction::LDCW { index } | Instruction::LDC2W { index } => stack.extend(self.loadable(*index, position)?),
+
+            Instruction::ILoad { index } | Instruction::FLoad { index } | Instruction::ALoad { index } => {
+                load(locals, stack, slot(index), 1)
+            }
+            Instruction::LLoad { index } | Instruction::DLoad { index } => load(locals, stack, slot(index), 2),
+            Instruction::ILoad0 | Instruction::FLoad0 | Instruction::ALoad0 => load(locals, stack, 0, 1),
+            Instruction::ILoad1 | Instruction::FLoad1 | Instruction::ALoad1 => load(locals, stack, 1, 1),
+            Instruction::ILoad2 | Instruction::FLoad2 | Instruction::ALoad2 => load(locals, stack, 2, 1),
+            Instruction::ILoad3 | Instruction::FLoad3 | Instruction::ALoad3 => load(locals, stack, 3, 1),
+            Instruction::LLoad0 | Instruction::DLoad0 => load(locals, stack, 0, 2),
+            Instruction::LLoad1 | Instruction::DLoad1 => load(locals, stack, 1, 2),
+            Instruction::LLoad2 | Instruction::DLoad2 => load(locals, stack, 2, 2),
+            Instruction::LLoad3 | Instruction::DLoad3 => load(locals, stack, 3, 2),
+
+            Instruction::IStore { index } | Instruction::FStore { index } | Instruction::AStore { index } => {
+                let value = pop(stack, 1)?;
+                store(locals, slot(index), value)
+            }
+            Instruction::LStore { index } | Instruction::DStore { index } => {
+                let value = pop(stack, 2)?;
+                store(locals, slot(index), value)
+            }
+            Instruction::IStore0 | Instruction::FStore0 | Instruction::AStore0 => store(locals, 0, pop(stack, 1)?),
+            Instruction::IStore1 | Instruction::FStore1 | Instruction::AStore1 => store(locals, 1, pop(stack, 1)?),
+            Instruction::IStore2 | Instruction::FStore2 | Instruction::AStore2 => store(locals, 2, pop(stack, 1)?),
+            Instruction::IStore3 | Instruction::FStore3 | Instruction::AStore3 => store(locals, 3, pop(stack, 1)?),
+            Instruction::LStore0 | Instruction::DStore0 => store(locals, 0, pop(stack, 2)?),
+            Instruction::LStore1 | Instruction::DStore1 => store(locals, 1, pop(stack, 2)?),
+            Instruction::LStore2 | Instruction::DStore2 => store(locals, 2, pop(stack, 2)?),
+            Instruction::LStore3 | Instruction::DStore3 => store(locals, 3, pop(stack, 2)?),
+
+            Instruction::IALoad | Instruction::BALoad | Instruction::CALoad | Instruction::SALoad => {
+                pop(stack, 2)?;
+                stack.push(Type::Integer);
+            }
+            Instruction::FALoad => {
+                pop(stack, 2)?;
+                stack.push(Type::Float);
+            }
+            Instruction::LALoad => {
+                pop(stack, 2)?;
+                stack.extend([Type::Long, Type::Top]);
+            }
+            Instruction::DALoad => {
+                pop(stack, 2)?;
+                stack.extend([Type::Double, Type::Top]);
+            }
+            Instruction::AALoad => {
+                let array = pop(stack, 2)?.remove(0);
+                stack.push(match array {
+                    Type::Object(descriptor) if descriptor.starts_with('[') => {
+                        let element = field_type(&descriptor[1..], position)?;
+                        types(&element).pop().unwrap_or(Type::Top)
+                    }
+                    _ => Type::Null,
+                });
+            }
+
+            Instruction::IAStore
+            | Instruction::FAStore
+            | Instruction::AAStore
+            | Instruction::BAStore
+            | Instruction::CAStore
+            | Instruction::SAStore => {
+                pop(stack, 3)?;
+            }
+            Instruction::LAStore | Instruction::DAStore => {
+                pop(stack, 4)?;
+            }
+
+            Instruction::Pop
+            | Instruction::IfEq { .. }
+            | Instruction::IfNe { .. }
+            | Instruction::IfLt { .. }
+            | Instruction::IfGe { .. }
+            | Instruction::IfGt { .. }
+            | Instruction::IfLe { .. }
+            | Instruction::IfNull { .. }
+            | Instruction::IfNonNull { .. }
+            | Instruction::TableSwitch { .. }
+            | Instruction::LookUpSwitch { .. }
+            | Instruction::IReturn
+            | Instruction::FReturn
+            | Instruction::AReturn
+            | Instruction::AThrow
+            | Instruction::MonitorEnter
+            | Instruction::MonitorExit => {
+                pop(stack, 1)?;
+            }
+            Instruction::Pop2
+            | Instruction::IfICmpEq { .. }
+            | Instruction::IfICmpNe { .. }
+            | Instruction::IfICmpLt { .. }
+            | Instruction::IfICmpGe { .. }
+            | Instruction::IfICmpGt { .. }
+            | Instruction::IfICmpLe { .. }
+            | Instruction::IfACmpEq { .. }
+            | Instruction::IfACmpNe { .. }
+            | Instruction::LReturn
+            | Instruction::DReturn => {
+                pop(stack, 2)?;
+            }
+            Instruction::Return => {}
+
+            Instruction::Dup => {
+                let values = pop(stack, 1)?;
+                stack.extend(values.clone());
+                stack.extend(values);
+            }
+            Instruction::DupX1 => {
+                let values = pop(stack, 2)?;
+                stack.extend([values[1].clone(), values[0].clone(), values[1].clone()]);
+            }
+            Instruction::DupX2 => {
+                let values = pop(stack, 3)?;
+                stack.extend([values[2].clone(), values[0].clone(), values[1].clone(), values[2].clone()]);
+            }
+            Instruction::Dup2 => {
+                let values = pop(stack, 2)?;
+                stack.extend(values.clone());
+                stack.extend(values);
+            }
+            Instruction::Dup2X1 => {
+                let values = pop(stack, 3)?;
+                stack.extend(values[1..].to_vec());
+                stack.extend(values);
+            }
+            Instruction::Dup2X2 => {
+                let values = pop(stack, 4)?;
+                stack.extend(values[2..].to_vec());
+                stack.extend(values);
+            }
+            Instruction::Swap => {
+                let values = pop(stack, 2)?;
+                stack.extend([values[1].clone(), values[0].clone()]);
+            }
+
+            Instruction::IAdd
+            | Instruction::ISub
+            | Instruction::IMul
+            | Instruction::IDiv
+            | Instruction::IRem
+            | Instruction::IAnd
+            | Instruction::IOr
+            | Instruction::IXor
+            | Instruction::IShl
+            | Instruction::IShr
+            | Instruction::IUShr
+            | Instruction::FCmpPL
+            | Instruction::FCmpPG
+            | Instruction::L2I
+            | Instruction::D2I => {
+                pop(stack, 2)?;
+                stack.push(Type::Integer);
+            }
+            Instruction::LCmp | Instruction::DCmpL | Instruction::DCmpG => {
+                pop(stack, 4)?;
+                stack.push(Type::Integer);
+            }
+            Instruction::FAdd | Instruction::FSub | Instruction::FMul | Instruction::FDiv | Instruction::FRem => {
+                pop(stack, 2)?;
+                stack.push(Type::Float);
+            }
+            Instruction::L2F | Instruction::D2F => {
+                pop(stack, 2)?;
+                stack.push(Type::Float);
+            }
+            Instruction::LAdd
+            | Instruction::LSub
+            | Instruction::LMul
+            | Instruction::LDiv
+            | Instruction::LRem
+            | Instruction::LAnd
+            | Instruction::LOr
+            | Instruction::LXor => {
+                pop(stack, 4)?;
+                stack.extend([Type::Long, Type::Top]);
+            }
+            Instruction::LShl | Instruction::LShr | Instruction::LUShr => {
+                pop(stack, 3)?;
+                stack.extend([Type::Long, Type::Top]);
+            }
+            Instruction::DAdd | Instruction::DSub | Instruction::DMul | Instruction::DDiv | Instruction::DRem => {
+                pop(stack, 4)?;
+                stack.extend([Type::Double, Type::Top]);
+            }
+            Instruction::LNeg | Instruction::D2L => {
+                pop(stack, 2)?;
+                stack.extend([Type::Long, Type::Top]);
+            }
+            Instruction::DNeg | Instruction::L2D => {
+                pop(stack, 2)?;
+                stack.extend([Type::Double, Type::Top]);
+            }
+            Instruction::INeg | Instruction::F2I | Instruction::I2B | Instruction::I2C | Instruction::I2S => {
+                pop(stack, 1)?;
+                stack.push(Type::Integer);
+            }
+            Instruction::FNeg | Instruction::I2F => {
+                pop(stack, 1)?;
+                stack.push(Type::Float);
+            }
+            Instruction::I2L | Instruction::F2L => {
+                pop(stack, 1)?;
+                stack.extend([Type::Long, Type::Top]);
+            }
+            Instruction::I2D | Instruction::F2D => {
+                pop(stack, 1)?;
+                stack.extend([Type::Double, Type::Top]);
+            }
+
+            Instruction::GetStatic { index } | Instruction::GetField { index } => {
+                if matches!(instruction, Instruction::GetField { .. }) {
+                    pop(stack, 1)?;
+                }
+                let descriptor = self.member_descriptor(index, position)?;
+                stack.extend(types(&field_type(&descriptor, position)?));
+            }
+            Instruction::PutStatic { index } | Instruction::PutField { index } => {
+                let descriptor = self.member_descriptor(index, position)?;
+                let words = field_type(&descriptor, position)?.slot_size() as usize;
+                pop(stack, words)?;
+                if matches!(instruction, Instruction::PutField { .. }) {
+                    pop(stack, 1)?;
+                }
+            }
+
+            Instruction::InvokeVirtual { index }
+            | Instruction::InvokeSpecial { index }
+            | Instruction::InvokeStatic { index }
+            | Instruction::InvokeInterface { index, .. }
+            | Instruction::InvokeDynamic { index, .. } => {
+                let descriptor = self.member_descriptor(index, position)?;
+                let method = method_type(&descriptor, position)?;
+                pop(stack, method.parameter_slots() as usize)?;
+
+                if !matches!(instruction, Instruction::InvokeStatic { .. } | Instruction::InvokeDynamic { .. }) {
+                    let receiver = pop(stack, 1)?.remove(0);
+
+                    // A constructor call initializes every copy of the receiver
+                    if let Instruction::InvokeSpecial { .. } = instruction {
+                        let initialized = match &receiver {
+                            Type::UninitializedThis => Some(Type::Object(self.class.to_string())),
+                            Type::Uninitialized { class, .. } => Some(Type::Object(class.clone())),
+                            _ => None,
+                        };
+
+                        if let Some(initialized) = initialized {
+                            for ty in stack.iter_mut().chain(locals.iter_mut()) {
+                                if *ty == receiver {
+                                    *ty = initialized.clone();
+                                }
+                            }
+                        }
+                    }
+                }
+
+                stack.extend(types(&method.return_type));
+            }
+
+            Instruction::New { index } => stack.push(Type::Uninitialized {
+                position,
+                class: self.class_name(index, position)?,
+            }),
+            Instruction::NewArray { ty } => {
+                pop(stack, 1)?;
+                let descriptor = match ty {
+                    ArrayType::Boolean => "[Z",
+                    ArrayType::Char => "[C",
+                    ArrayType::Float => "[F",
+                    ArrayType::Double => "[D",
+                    ArrayType::Byte => "[B",
+                    ArrayType::Short => "[S",
+                    ArrayType::Int => "[I",
+                    ArrayType::Long => "[J",
+                };
+                stack.push(Type::Object(descriptor.to_string()));
+            }
+            Instruction::ANewArray { index } => {
+                pop(stack, 1)?;
+                let name = self.class_name(index, position)?;
+                stack.push(Type::Object(format!("[{}", class_descriptor(&name))));
+            }
+            Instruction::MultiANewArray { index, dimensions } => {
+                pop(stack, *dimensions as usize)?;
+                stack.push(Type::Object(self.class_name(index, position)?));
+            }
+            Instruction::ArrayLength | Instruction::InstanceOf { .. } => {
+                pop(stack, 1)?;
+                stack.push(Type::Integer);
+            }
+            Instruction::CheckCast { index } => {
+                pop(stack, 1)?;
+                stack.push(Type::Object(self.class_name(index, position)?));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The words pushed by `ldc`, `ldc_w` and `ldc2_w`
+    fn loadable(&self, index: ConstantPoolIndex, position: u64) -> Result<Vec<Type>, Error> {
+        let object = |name: &str| Some(vec![Type::Object(name.to_string())]);
+        let types = match constant(self.constants.pool(), index) {
+            Some(Constant::Integer(_)) => Some(vec![Type::Integer]),
+            Some(Constant::Float(_)) => Some(vec![Type::Float]),
+            Some(Constant::Long(_)) => Some(vec![Type::Long, Type::Top]),
+            Some(Constant::Double(_)) => Some(vec![Type::Double, Type::Top]),
+            Some(Constant::String(_)) => object("java/lang/String"),
+            Some(Constant::Class(_)) => object("java/lang/Class"),
+            Some(Constant::MethodType(_)) => object("java/lang/invoke/MethodType"),
+            Some(Constant::MethodHandle { .. }) => object("java/lang/invoke/MethodHandle"),
+            Some(Constant::Dynamic { .. }) => {
+                member_descriptor(self.constants.pool(), index)
+                    .and_then(|descriptor| field_type(descriptor, position).ok())
+                    .map(|ty| types(&ty))
+            }
+            _ => None,
+        };
+
+        types.ok_or_else(|| Error::InvalidCode {
+            position,
+            reason: format!("constant {} is not loadable", index.0),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::attribute::SmallIndex;
+
+    use super::*;
+
+    fn code(instructions: Vec<Instruction>, exception_table: Vec<ExceptionTable>) -> Attribute {
+        Attribute::Code {
+            name: ConstantPoolIndex(0),
+            max_stack: Compatibility::Current(2),
+            max_locals: Compatibility::Current(3),
+            code: Compatibility::Current(instructions.into()),
+            exception_table: exception_table.into(),
+            attributes: Vec::new().into(),
+        }
+    }
+
+    fn frames(attribute: &Attribute) -> Vec<StackMapFrame> {
+        match attribute {
+            Attribute::Code { attributes, .. } => attributes
+                .iter()
+                .find_map(|attribute| match attribute {
+                    Attribute::StackMapTable { entries, .. } => Some(entries.to_vec()),
+                    _ => None,
+                })
+                .unwrap_or_default(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn diamond_appends_local() -> Result<(), Error> {
+        let mut attribute = code(
+            vec![
+                Instruction::ILoad0,
+                Instruction::IfEq { offset: 8 },
+                Instruction::IConst1,
+                Instruction::IStore1,
+                Instruction::Goto { offset: 5 },
+                Instruction::IConst2,
+                Instruction::IStore1,
+                Instruction::ILoad1,
+                Instruction::IReturn,
+            ],
+            Vec::new(),
+        );
+        let mut constant_pool = ConstantPool(Vec::new());
+
+        compute_frames(
+            &mut attribute,
+            "Test",
+            "test",
+            "(I)I",
+            AccessFlags::STATIC,
+            &mut constant_pool,
+            &StaticHierarchy::default(),
+        )?;
+
+        assert_eq!(
+            frames(&attribute),
+            vec![
+                StackMapFrame::Same { frame_type: 9 },
+                StackMapFrame::Append {
+                    frame_type: 252,
+                    offset_delta: 1,
+                    locals: vec![VerificationTypeInfo::Integer].into(),
+                },
+            ]
+        );
+        assert_eq!(constant_pool.0, vec![Constant::Utf8("StackMapTable".into())]);
+        Ok(())
+    }
+
+    #[test]
+    fn dead_code_and_handler() -> Result<(), Error> {
+        let mut constant_pool = ConstantPool(vec![
+            Constant::Utf8("java/lang/Object".into()),
+            Constant::Class(ConstantPoolIndex(1)),
+            Constant::Utf8("<init>".into()),
+            Constant::Utf8("()V".into()),
+            Constant::NameAndType {
+                name: ConstantPoolIndex(3),
+                descriptor: ConstantPoolIndex(4),
+            },
+            Constant::MethodRef {
+                class: ConstantPoolIndex(2),
+                name_and_type: ConstantPoolIndex(5),
+            },
+        ]);
+        let mut attribute = code(
+            vec![
+                Instruction::New { index: ConstantPoolIndex(2) },
+                Instruction::Dup,
+                Instruction::InvokeSpecial { index: ConstantPoolIndex(6) },
+                Instruction::AStore1,
+                Instruction::Return,
+                Instruction::IConst0,
+                Instruction::IReturn,
+                Instruction::AStore { index: SizedIndex::Normal(SmallIndex(2)) },
+                Instruction::Return,
+            ],
+            vec![ExceptionTable {
+                start_pc: 0,
+                end_pc: 8,
+                handler_pc: 11,
+                catch_type: ConstantPoolIndex(0),
+            }],
+        );
+
+        compute_frames(
+            &mut attribute,
+            "Test",
+            "test",
+            "()V",
+            AccessFlags::NONE,
+            &mut constant_pool,
+            &StaticHierarchy::default(),
+        )?;
+
+        let throwable = VerificationTypeInfo::Object { index: ConstantPoolIndex(10) };
+        assert_eq!(
+            frames(&attribute),
+            vec![
+                StackMapFrame::Full {
+                    offset_delta: 9,
+                    locals: Vec::new().into(),
+                    stack: vec![throwable.clone()].into(),
+                },
+                StackMapFrame::Full {
+                    offset_delta: 1,
+                    locals: vec![VerificationTypeInfo::Object { index: ConstantPoolIndex(8) }].into(),
+                    stack: vec![throwable].into(),
+                },
+            ]
+        );
+        if let Attribute::Code {
+            code: Compatibility::Current(code),
+            ..
+        } = &attribute
+        {
+            assert_eq!(code[5..7], [Instruction::Nop, Instruction::AThrow]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn common_superclass_walks_hierarchy() {
+        let mut hierarchy = StaticHierarchy::default();
+        hierarchy.superclasses.insert("B".to_string(), "A".to_string());
+        hierarchy.superclasses.insert("C".to_string(), "A".to_string());
+
+        assert_eq!(hierarchy.common_superclass("B", "C"), "A");
+        assert_eq!(hierarchy.common_superclass("B", "D"), OBJECT);
+    }
+}
@@ -0,0 +1,476 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use java_rs_base::error::Error;
+
+use crate::analysis::{field_type, instruction_positions, member_descriptor, method_type, successors};
+use crate::attribute::{Attribute, Compatibility, ExceptionTable, Instruction, SizedIndex};
+use crate::{AccessFlags, ConstantPool, ConstantPoolIndex};
+
+/// The operand stack depth and number of local variable slots a method's code requires
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct MaxValues {
+    pub max_stack: u16,
+    pub max_locals: u16,
+}
+
+/// Recomputes `max_stack` and `max_locals` of a code attribute, other attributes are left untouched.
+/// `descriptor` and `access_flags` are those of the method owning the code
+pub fn compute_max_values(
+    attribute: &mut Attribute,
+    descriptor: &str,
+    access_flags: AccessFlags,
+    constant_pool: &ConstantPool,
+) -> Result<(), Error> {
+    if let Attribute::Code {
+        max_stack,
+        max_locals,
+        code,
+        exception_table,
+        ..
+    } = attribute
+    {
+        let instructions = match code {
+            Compatibility::PreJava1(code) => code.as_slice(),
+            Compatibility::Current(code) => code.as_slice(),
+        };
+        let values = max_values(instructions, exception_table, descriptor, access_flags, constant_pool)?;
+
+        match (max_stack, max_locals) {
+            (Compatibility::PreJava1(max_stack), Compatibility::PreJava1(max_locals)) => {
+                let narrow = |value: u16| u8::try_from(value).map_err(|_| Error::PreJava1MaxValue(value));
+                *max_stack = narrow(values.max_stack)?;
+                *max_locals = narrow(values.max_locals)?;
+            }
+            (max_stack, max_locals) => {
+                *max_stack = Compatibility::Current(values.max_stack);
+                *max_locals = Compatibility::Current(values.max_locals);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Follows every path through the code, including exception handlers and subroutines, to find the
+/// deepest operand stack. Code which is not reachable only counts towards the local variables
+pub fn max_values(
+    code: &[Instruction],
+    exception_table: &[ExceptionTable],
+    descriptor: &str,
+    access_flags: AccessFlags,
+    constant_pool: &ConstantPool,
+) -> Result<MaxValues, Error> {
+    let (positions, length) = instruction_positions(code)?;
+    let indices: HashMap<u64, usize> = positions.iter().enumerate().map(|(index, position)| (*position, index)).collect();
+    let index_of = |position: u64, from: u64| {
+        indices.get(&position).copied().ok_or_else(|| Error::InvalidCode {
+            position: from,
+            reason: format!("jump to {}, which is not the start of an instruction", position),
+        })
+    };
+
+    let mut depths: Vec<Option<u16>> = vec![None; code.len()];
+    let mut worklist: Vec<(usize, u16)> = Vec::new();
+    let mut max_stack = 0;
+
+    if !code.is_empty() {
+        worklist.push((0, 0));
+    }
+
+    while let Some((index, depth)) = worklist.pop() {
+        let position = positions[index];
+
+        match depths[index] {
+            Some(existing) if existing == depth => continue,
+            Some(existing) => {
+                return Err(Error::InvalidCode {
+                    position,
+                    reason: format!("stack depth is {} on one path and {} on another", existing, depth),
+                })
+            }
+            None => depths[index] = Some(depth),
+        }
+
+        for entry in exception_table {
+            if (entry.start_pc as u64..entry.end_pc as u64).contains(&position) {
+                worklist.push((index_of(entry.handler_pc as u64, position)?, 1));
+                max_stack = max_stack.max(1);
+            }
+        }
+
+        let instruction = &code[index];
+        let (pop, push) = stack_effect(instruction, constant_pool, position)?;
+        let after = depth.checked_sub(pop).ok_or_else(|| Error::InvalidCode {
+            position,
+            reason: "operand stack underflow".to_string(),
+        })? + push;
+        max_stack = max_stack.max(depth).max(after);
+
+        let next = positions.get(index + 1).copied().unwrap_or(length);
+        for (successor, target) in successors(instruction, position, next).into_iter().enumerate() {
+            if target == length {
+                return Err(Error::InvalidCode {
+                    position,
+                    reason: "control falls off the end of the code".to_string(),
+                });
+            }
+
+            // A subroutine returns with the stack it was called with, minus the return address
+            let depth = match instruction {
+                Instruction::JSR { .. } | Instruction::JSRW { .. } if successor == 1 => depth,
+                _ => after,
+            };
+            worklist.push((index_of(target, position)?, depth));
+        }
+    }
+
+    let receiver = if access_flags.contains(AccessFlags::STATIC) { 0 } else { 1 };
+    let mut max_locals = method_type(descriptor, 0)?.parameter_slots() as u16 + receiver;
+
+    for instruction in code {
+        if let Some((slot, size)) = local_variable(instruction) {
+            max_locals = max_locals.max(slot + size);
+        }
+    }
+
+    Ok(MaxValues { max_stack, max_locals })
+}
+
+/// The local variable slot accessed by an instruction and the size of the accessed value
+fn local_variable(instruction: &Instruction) -> Option<(u16, u16)> {
+    let slot = |index: &SizedIndex| match index {
+        SizedIndex::Normal(index) => index.0 as u16,
+        SizedIndex::Wide(index) => index.0,
+    };
+
+    match instruction {
+        Instruction::ILoad { index }
+        | Instruction::FLoad { index }
+        | Instruction::ALoad { index }
+        | Instruction::IStore { index }
+        | Instruction::FStore { index }
+        | Instruction::AStore { index }
+        | Instruction::IInc { index, .. }
+        | Instruction::Ret { index } => Some((slot(index), 1)),
+        Instruction::LLoad { index }
+        | Instruction::DLoad { index }
+        | Instruction::LStore { index }
+        | Instruction::DStore { index } => Some((slot(index), 2)),
+        Instruction::ILoad0 | Instruction::FLoad0 | Instruction::ALoad0 => Some((0, 1)),
+        Instruction::IStore0 | Instruction::FStore0 | Instruction::AStore0 => Some((0, 1)),
+        Instruction::ILoad1 | Instruction::FLoad1 | Instruction::ALoad1 => Some((1, 1)),
+        Instruction::IStore1 | Instruction::FStore1 | Instruction::AStore1 => Some((1, 1)),
+        Instruction::ILoad2 | Instruction::FLoad2 | Instruction::ALoad2 => Some((2, 1)),
+        Instruction::IStore2 | Instruction::FStore2 | Instruction::AStore2 => Some((2, 1)),
+        Instruction::ILoad3 | Instruction::FLoad3 | Instruction::ALoad3 => Some((3, 1)),
+        Instruction::IStore3 | Instruction::FStore3 | Instruction::AStore3 => Some((3, 1)),
+        Instruction::LLoad0 | Instruction::DLoad0 | Instruction::LStore0 | Instruction::DStore0 => Some((0, 2)),
+        Instruction::LLoad1 | Instruction::DLoad1 | Instruction::LStore1 | Instruction::DStore1 => Some((1, 2)),
+        Instruction::LLoad2 | Instruction::DLoad2 | Instruction::LStore2 | Instruction::DStore2 => Some((2, 2)),
+        Instruction::LLoad3 | Instruction::DLoad3 | Instruction::LStore3 | Instruction::DStore3 => Some((3, 2)),
+        _ => None,
+    }
+}
+
+/// The number of operand stack slots an instruction pops and pushes
+fn stack_effect(instruction: &Instruction, constant_pool: &ConstantPool, position: u64) -> Result<(u16, u16), Error> {
+    let descriptor = |index: &ConstantPoolIndex| {
+        member_descriptor(constant_pool, *index).ok_or_else(|| Error::InvalidCode {
+            position,
+            reason: format!("constant {} is not a member reference", index.0),
+        })
+    };
+    let field = |index| -> Result<u16, Error> { Ok(field_type(descriptor(index)?, position)?.slot_size()) };
+    let method = |index| -> Result<(u16, u16), Error> {
+        let method = method_type(descriptor(index)?, position)?;
+        Ok((method.parameter_slots() as u16, method.return_type.slot_size()))
+    };
+
+    let effect = match instruction {
+        Instruction::Nop
+        | Instruction::IInc { .. }
+        | Instruction::Goto { .. }
+        | Instruction::GotoW { .. }
+        | Instruction::Ret { .. }
+        | Instruction::Return
+        | Instruction::Wide => (0, 0),
+
+        Instruction::AConstNull
+        | Instruction::IConstM1
+        | Instruction::IConst0
+        | Instruction::IConst1
+        | Instruction::IConst2
+        | Instruction::IConst3
+        | Instruction::IConst4
+        | Instruction::IConst5
+        | Instruction::FConst0
+        | Instruction::FConst1
+        | Instruction::FConst2
+        | Instruction::BIPush { .. }
+        | Instruction::SIPush { .. }
+        | Instruction::LDC { .. }
+        | Instruction::LDCW { .. }
+        | Instruction::ILoad { .. }
+        | Instruction::ILoad0
+        | Instruction::ILoad1
+        | Instruction::ILoad2
+        | Instruction::ILoad3
+        | Instruction::FLoad { .. }
+        | Instruction::FLoad0
+        | Instruction::FLoad1
+        | Instruction::FLoad2
+        | Instruction::FLoad3
+        | Instruction::ALoad { .. }
+        | Instruction::ALoad0
+        | Instruction::ALoad1
+        | Instruction::ALoad2
+        | Instruction::ALoad3
+        | Instruction::New { .. }
+        | Instruction::JSR { .. }
+        | Instruction::JSRW { .. } => (0, 1),
+
+        Instruction::LConst0
+        | Instruction::LConst1
+        | Instruction::DConst0
+        | Instruction::DConst1
+        | Instruction::LDC2W { .. }
+        | Instruction::LLoad { .. }
+        | Instruction::LLoad0
+        | Instruction::LLoad1
+        | Instruction::LLoad2
+        | Instruction::LLoad3
+        | Instruction::DLoad { .. }
+        | Instruction::DLoad0
+        | Instruction::DLoad1
+        | Instruction::DLoad2
+        | Instruction::DLoad3 => (0, 2),
+
+        Instruction::IStore { .. }
+        | Instruction::IStore0
+        | Instruction::IStore1
+        | Instruction::IStore2
+        | Instruction::IStore3
+        | Instruction::FStore { .. }
+        | Instruction::FStore0
+        | Instruction::FStore1
+        | Instruction::FStore2
+        | Instruction::FStore3
+        | Instruction::AStore { .. }
+        | Instruction::AStore0
+        | Instruction::AStore1
+        | Instruction::AStore2
+        | Instruction::AStore3
+        | Instruction::Pop
+        | Instruction::IfEq { .. }
+        | Instruction::IfNe { .. }
+        | Instruction::IfLt { .. }
+        | Instruction::IfGe { .. }
+        | Instruction::IfGt { .. }
+        | Instruction::IfLe { .. }
+        | Instruction::IfNull { .. }
+        | Instruction::IfNonNull { .. }
+        | Instruction::TableSwitch { .. }
+        | Instruction::LookUpSwitch { .. }
+        | Instruction::IReturn
+        | Instruction::FReturn
+        | Instruction::AReturn
+        | Instruction::AThrow
+        | Instruction::MonitorEnter
+        | Instruction::MonitorExit => (1, 0),
+
+        Instruction::LStore { .. }
+        | Instruction::LStore0
+        | Instruction::LStore1
+        | Instruction::LStore2
+        | Instruction::LStore3
+        | Instruction::DStore { .. }
+        | Instruction::DStore0
+        | Instruction::DStore1
+        | Instruction::DStore2
+        | Instruction::DStore3
+        | Instruction::Pop2
+        | Instruction::IfICmpEq { .. }
+        | Instruction::IfICmpNe { .. }
+        | Instruction::IfICmpLt { .. }
+        | Instruction::IfICmpGe { .. }
+        | Instruction::IfICmpGt { .. }
+        | Instruction::IfICmpLe { .. }
+        | Instruction::IfACmpEq { .. }
+        | Instruction::IfACmpNe { .. }
+        | Instruction::LReturn
+        | Instruction::DReturn => (2, 0),
+
+        Instruction::IAStore
+        | Instruction::FAStore
+        | Instruction::AAStore
+        | Instruction::BAStore
+        | Instruction::CAStore
+        | Instruction::SAStore => (3, 0),
+        Instruction::LAStore | Instruction::DAStore => (4, 0),
+
+        Instruction::IALoad
+        | Instruction::FALoad
+        | Instruction::AALoad
+        | Instruction::BALoad
+        | Instruction::CALoad
+        | Instruction::SALoad
+        | Instruction::IAdd
+        | Instruction::ISub
+        | Instruction::IMul
+        | Instruction::IDiv
+        | Instruction::IRem
+        | Instruction::IAnd
+        | Instruction::IOr
+        | Instruction::IXor
+        | Instruction::IShl
+        | Instruction::IShr
+        | Instruction::IUShr
+        | Instruction::FAdd
+        | Instruction::FSub
+        | Instruction::FMul
+        | Instruction::FDiv
+        | Instruction::FRem
+        | Instruction::FCmpPL
+        | Instruction::FCmpPG
+        | Instruction::L2I
+        | Instruction::L2F
+        | Instruction::D2I
+        | Instruction::D2F => (2, 1),
+        Instruction::LALoad | Instruction::DALoad | Instruction::Swap | Instruction::L2D | Instruction::D2L => {
+            (2, 2)
+        }
+
+        Instruction::LAdd
+        | Instruction::LSub
+        | Instruction::LMul
+        | Instruction::LDiv
+        | Instruction::LRem
+        | Instruction::LAnd
+        | Instruction::LOr
+        | Instruction::LXor
+        | Instruction::DAdd
+        | Instruction::DSub
+        | Instruction::DMul
+        | Instruction::DDiv
+        | Instruction::DRem => (4, 2),
+        Instruction::LShl | Instruction::LShr | Instruction::LUShr => (3, 2),
+        Instruction::LCmp | Instruction::DCmpL | Instruction::DCmpG => (4, 1),
+
+        Instruction::INeg
+        | Instruction::FNeg
+        | Instruction::I2F
+        | Instruction::F2I
+        | Instruction::I2B
+        | Instruction::I2C
+        | Instruction::I2S
+        | Instruction::NewArray { .. }
+        | Instruction::ANewArray { .. }
+        | Instruction::ArrayLength
+        | Instruction::CheckCast { .. }
+        | Instruction::InstanceOf { .. } => (1, 1),
+        Instruction::LNeg | Instruction::DNeg => (2, 2),
+        Instruction::I2L | Instruction::I2D | Instruction::F2L | Instruction::F2D => (1, 2),
+
+        Instruction::Dup => (1, 2),
+        Instruction::DupX1 => (2, 3),
+        Instruction::DupX2 => (3, 4),
+        Instruction::Dup2 => (2, 4),
+        Instruction::Dup2X1 => (3, 5),
+        Instruction::Dup2X2 => (4, 6),
+
+        Instruction::GetStatic { index } => (0, field(index)?),
+        Instruction::PutStatic { index } => (field(index)?, 0),
+        Instruction::GetField { index } => (1, field(index)?),
+        Instruction::PutField { index } => (1 + field(index)?, 0),
+
+        Instruction::InvokeVirtual { index }
+        | Instruction::InvokeSpecial { index }
+        | Instruction::InvokeInterface { index, .. } => {
+            let (parameters, result) = method(index)?;
+            (parameters + 1, result)
+        }
+        Instruction::InvokeStatic { index } | Instruction::InvokeDynamic { index, .. } => method(index)?,
+
+        Instruction::MultiANewArray { dimensions, .. } => (*dimensions as u16, 1),
+    };
+
+    Ok(effect)
+}
+
+#[cfg(test)]
+mod tests {
+    use java_rs_base::error::DescriptorError;
+
+    use crate::attribute::SmallIndex;
+    use crate::Constant;
+
+    use super::*;
+
+    #[test]
+    fn long_arithmetic() -> Result<(), Error> {
+        let code = vec![
+            Instruction::LLoad0,
+            Instruction::ILoad2,
+            Instruction::I2L,
+            Instruction::LAdd,
+            Instruction::LReturn,
+        ];
+
+        let values = max_values(&code, &[], "(JI)J", AccessFlags::STATIC, &ConstantPool(Vec::new()))?;
+        assert_eq!(values, MaxValues { max_stack: 4, max_locals: 3 });
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_method_descriptor() {
+        let result = max_values(&[Instruction::Return], &[], "(Q)V", AccessFlags::STATIC, &ConstantPool(Vec::new()));
+        assert!(matches!(
+            result,
+            Err(Error::InvalidDescriptor {
+                position: 0,
+                source: DescriptorError::UnexpectedCharacter { position: 1, found: 'Q', .. },
+            })
+        ));
+    }
+
+    #[test]
+    fn handlers_and_subroutines() -> Result<(), Error> {
+        let constant_pool = ConstantPool(vec![
+            Constant::Utf8("out".into()),
+            Constant::Utf8("Ljava/io/PrintStream;".into()),
+            Constant::NameAndType {
+                name: ConstantPoolIndex(1),
+                descriptor: ConstantPoolIndex(2),
+            },
+            Constant::FieldRef {
+                class: ConstantPoolIndex(0),
+                name_and_type: ConstantPoolIndex(3),
+            },
+        ]);
+        let code = vec![
+            Instruction::GetStatic {
+                index: ConstantPoolIndex(4),
+            },
+            Instruction::AStore1,
+            Instruction::JSR { offset: 4 },
+            Instruction::Return,
+            Instruction::AStore2,
+            Instruction::Ret {
+                index: SizedIndex::Normal(SmallIndex(2)),
+            },
+            Instruction::Dup,
+            Instruction::AThrow,
+        ];
+        let exception_table = vec![ExceptionTable {
+            start_pc: 0,
+            end_pc: 4,
+            handler_pc: 11,
+            catch_type: ConstantPoolIndex(0),
+        }];
+
+        let values = max_values(&code, &exception_table, "()V", AccessFlags::NONE, &constant_pool)?;
+        assert_eq!(values, MaxValues { max_stack: 2, max_locals: 3 });
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use java_rs_base::descriptor::{parse_field_descriptor, MethodDescriptor, TypeSignature};
+use java_rs_base::error::{DescriptorError, Error};
+use java_rs_base::io::{ClassFilePart, WriteContext};
+
+use crate::attribute::Instruction;
+use crate::{Constant, ConstantPool, ConstantPoolIndex};
+
+pub mod frames;
+pub mod max_values;
+
+/// Byte position of every instruction and the total length of the code. Instructions which cannot be
+/// encoded are reported at `code[position]`
+pub fn instruction_positions(code: &[Instruction]) -> Result<(Vec<u64>, u64), Error> {
+    let mut positions = Vec::with_capacity(code.len());
+    let mut position = 0u64;
+    let mut buffer = Vec::new();
+
+    for instruction in code {
+        positions.push(position);
+        buffer.clear();
+        instruction
+            .write(&mut buffer, &WriteContext { position: Some(position) })
+            .map_err(|error| error.located(position, format!("code[{}]", position)))?;
+        position += buffer.len() as u64;
+    }
+
+    Ok((positions, position))
+}
+
+/// How an instruction hands control to one of its branch targets
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum BranchKind {
+    /// Taken side of a conditional jump
+    Conditional,
+    Jump,
+    /// `jsr` into a subroutine
+    Subroutine,
+    /// Switch case with its key, `None` for the default case
+    Switch(Option<i32>),
+}
+
+/// The branch targets of an instruction as offsets relative to its position. Switch cases come before
+/// the default case
+pub fn branch_offsets(instruction: &Instruction) -> Vec<(BranchKind, i64)> {
+    match instruction {
+        Instruction::IfACmpEq { offset }
+        | Instruction::IfACmpNe { offset }
+        | Instruction::IfICmpEq { offset }
+        | Instruction::IfICmpNe { offset }
+        | Instruction::IfICmpLt { offset }
+        | Instruction::IfICmpGe { offset }
+        | Instruction::IfICmpGt { offset }
+        | Instruction::IfICmpLe { offset }
+        | Instruction::IfEq { offset }
+        | Instruction::IfNe { offset }
+        | Instruction::IfLt { offset }
+        | Instruction::IfGe { offset }
+        | Instruction::IfGt { offset }
+        | Instruction::IfLe { offset }
+        | Instruction::IfNonNull { offset }
+        | Instruction::IfNull { offset } => vec![(BranchKind::Conditional, *offset as i64)],
+        Instruction::Goto { offset } => vec![(BranchKind::Jump, *offset as i64)],
+        Instruction::GotoW { offset } => vec![(BranchKind::Jump, *offset as i64)],
+        Instruction::JSR { offset } => vec![(BranchKind::Subroutine, *offset as i64)],
+        Instruction::JSRW { offset } => vec![(BranchKind::Subroutine, *offset as i64)],
+        Instruction::TableSwitch {
+            default, low, offsets, ..
+        } => offsets
+            .iter()
+            .enumerate()
+            .map(|(index, offset)| (BranchKind::Switch(Some(low + index as i32)), *offset as i64))
+            .chain(std::iter::once((BranchKind::Switch(None), *default as i64)))
+            .collect(),
+        Instruction::LookUpSwitch { default, pairs } => pairs
+            .iter()
+            .map(|pair| (BranchKind::Switch(Some(pair.match_value)), pair.offset as i64))
+            .chain(std::iter::once((BranchKind::Switch(None), *default as i64)))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Positions control may continue at after an instruction, not counting exception handlers. The next
+/// position comes first if control can fall through to it, except for `jsr` where it comes last as that is
+/// where the subroutine returns to
+pub(crate) fn successors(instruction: &Instruction, position: u64, next: u64) -> Vec<u64> {
+    let targets = branch_offsets(instruction)
+        .into_iter()
+        .map(|(_, offset)| (position as i64 + offset) as u64);
+
+    match instruction {
+        Instruction::JSR { .. } | Instruction::JSRW { .. } => targets.chain(std::iter::once(next)).collect(),
+        Instruction::Goto { .. }
+        | Instruction::GotoW { .. }
+        | Instruction::TableSwitch { .. }
+        | Instruction::LookUpSwitch { .. } => targets.collect(),
+        Instruction::AReturn
+        | Instruction::DReturn
+        | Instruction::FReturn
+        | Instruction::IReturn
+        | Instruction::LReturn
+        | Instruction::Return
+        | Instruction::AThrow
+        | Instruction::Ret { .. } => Vec::new(),
+        _ => std::iter::once(next).chain(targets).collect(),
+    }
+}
+
+/// Looks up a constant, looking through constants which are unsupported by the class file version
+pub(crate) fn constant(constant_pool: &ConstantPool, index: ConstantPoolIndex) -> Option<&Constant> {
+    constant_pool.resolve(index).ok()
+}
+
+pub(crate) fn utf8(constant_pool: &ConstantPool, index: ConstantPoolIndex) -> Option<&str> {
+    constant_pool.resolve_utf8(index).ok()
+}
+
+/// The descriptor of a field, method or dynamically computed reference
+pub(crate) fn member_descriptor(constant_pool: &ConstantPool, index: ConstantPoolIndex) -> Option<&str> {
+    let name_and_type = match constant(constant_pool, index)? {
+        Constant::FieldRef { name_and_type, .. }
+        | Constant::MethodRef { name_and_type, .. }
+        | Constant::InterfaceMethodRef { name_and_type, .. }
+        | Constant::Dynamic { name_and_type, .. }
+        | Constant::InvokeDynamic { name_and_type, .. } => *name_and_type,
+        _ => return None,
+    };
+
+    match constant(constant_pool, name_and_type)? {
+        Constant::NameAndType { descriptor, .. } => utf8(constant_pool, *descriptor),
+        _ => None,
+    }
+}
+
+/// Parses the descriptor of a field or of a loaded constant used by the instruction at `position`
+pub(crate) fn field_type(descriptor: &str, position: u64) -> Result<TypeSignature, Error> {
+    parse_field_descriptor(descriptor).map_err(|error| invalid_descriptor(error, position))
+}
+
+pub(crate) fn method_type(descriptor: &str, position: u64) -> Result<MethodDescriptor, Error> {
+    MethodDescriptor::parse(descriptor).map_err(|error| invalid_descriptor(error, position))
+}
+
+fn invalid_descriptor(source: DescriptorError, position: u64) -> Error {
+    Error::InvalidDescriptor { position, source }
+}
@@ -2,11 +2,13 @@ use std::io::{Read, Write};
 
 use byteorder::ReadBytesExt;
 
-use java_rs_base::constant_pool::ConstantPoolIndex;
-use java_rs_base::error::Error;
+use java_rs_base::constant_pool::{ConstantPool, ConstantPoolBuilder, ConstantPoolIndex};
+use java_rs_base::error::{ConstantPoolError, Error};
 use java_rs_base::io::{ClassFilePart, ReadContext, SizedVec, WriteContext};
 use java_rs_derive::ClassFilePart;
 
+use crate::attribute::ConstantReferences;
+
 #[derive(Debug, ClassFilePart, Clone, Eq, PartialEq)]
 pub struct Annotation {
     ty: ConstantPoolIndex,
@@ -85,3 +87,336 @@ impl ClassFilePart for ElementValue {
         Ok(())
     }
 }
+
+impl Annotation {
+    pub fn new(ty: ConstantPoolIndex, element_value_pairs: Vec<ElementValuePair>) -> Self {
+        Annotation {
+            ty,
+            element_value_pairs: element_value_pairs.into(),
+        }
+    }
+
+    /// Index of the annotation interface's field descriptor
+    pub fn ty(&self) -> ConstantPoolIndex {
+        self.ty
+    }
+
+    pub fn element_value_pairs(&self) -> &[ElementValuePair] {
+        &self.element_value_pairs
+    }
+
+    /// Resolves the annotation type and its element values through the constant pool
+    pub fn resolve(&self, constant_pool: &ConstantPool) -> Result<AnnotationInfo, Error> {
+        Ok(AnnotationInfo {
+            ty: constant_pool.resolve_utf8(self.ty)?.to_string(),
+            elements: self
+                .element_value_pairs
+                .iter()
+                .map(|pair| {
+                    Ok((
+                        constant_pool.resolve_utf8(pair.element_name)?.to_string(),
+                        pair.element_value.resolve(constant_pool)?,
+                    ))
+                })
+                .collect::<Result<_, Error>>()?,
+        })
+    }
+}
+
+impl ElementValuePair {
+    pub fn new(element_name: ConstantPoolIndex, element_value: ElementValue) -> Self {
+        ElementValuePair {
+            element_name,
+            element_value,
+        }
+    }
+
+    pub fn element_name(&self) -> ConstantPoolIndex {
+        self.element_name
+    }
+
+    pub fn element_value(&self) -> &ElementValue {
+        &self.element_value
+    }
+}
+
+impl ElementValue {
+    /// Fails with [`Error::InvalidElementValueTag`] for a constant with a tag which is not a constant type
+    pub fn resolve(&self, constant_pool: &ConstantPool) -> Result<AnnotationElement, Error> {
+        let integer = |index| constant_pool.resolve_integer(index);
+
+        Ok(match self {
+            ElementValue::ConstValue { tag, index } => match tag {
+                'B' => AnnotationElement::Byte(integer(*index)? as i8),
+                'C' => AnnotationElement::Char(integer(*index)? as u16),
+                'D' => AnnotationElement::Double(constant_pool.resolve_double(*index)?),
+                'F' => AnnotationElement::Float(constant_pool.resolve_float(*index)?),
+                'I' => AnnotationElement::Int(integer(*index)?),
+                'J' => AnnotationElement::Long(constant_pool.resolve_long(*index)?),
+                'S' => AnnotationElement::Short(integer(*index)? as i16),
+                'Z' => AnnotationElement::Boolean(integer(*index)? != 0),
+                's' => AnnotationElement::String(constant_pool.resolve_utf8(*index)?.to_string()),
+                tag => return Err(Error::InvalidElementValueTag(*tag)),
+            },
+            ElementValue::EnumConstValue { type_name, const_name } => AnnotationElement::Enum {
+                type_name: constant_pool.resolve_utf8(*type_name)?.to_string(),
+                const_name: constant_pool.resolve_utf8(*const_name)?.to_string(),
+            },
+            ElementValue::Class(index) => AnnotationElement::Class(constant_pool.resolve_utf8(*index)?.to_string()),
+            ElementValue::AnnotationValue(annotation) => {
+                AnnotationElement::Annotation(annotation.resolve(constant_pool)?)
+            }
+            ElementValue::ArrayValue(values) => AnnotationElement::Array(
+                values
+                    .iter()
+                    .map(|value| value.resolve(constant_pool))
+                    .collect::<Result<_, _>>()?,
+            ),
+        })
+    }
+}
+
+/// An annotation with resolved names and values, see [`Annotation::resolve`]. New annotations are built with
+/// [`AnnotationInfo::new`] and [`AnnotationInfo::element`], then added to a class by [`AnnotationInfo::build`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotationInfo {
+    /// Field descriptor of the annotation interface, e.g. `Ljava/lang/Deprecated;`
+    pub ty: String,
+    pub elements: Vec<(String, AnnotationElement)>,
+}
+
+/// The value of an annotation element
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnnotationElement {
+    Byte(i8),
+    /// A UTF-16 code unit
+    Char(u16),
+    Double(f64),
+    Float(f32),
+    Int(i32),
+    Long(i64),
+    Short(i16),
+    Boolean(bool),
+    String(String),
+    /// `type_name` is the field descriptor of the enum
+    Enum {
+        type_name: String,
+        const_name: String,
+    },
+    /// A class literal by its return descriptor, e.g. `Ljava/lang/String;`, `[I` or `V`
+    Class(String),
+    Annotation(AnnotationInfo),
+    Array(Vec<AnnotationElement>),
+}
+
+impl AnnotationInfo {
+    pub fn new(ty: impl Into<String>) -> Self {
+        AnnotationInfo {
+            ty: ty.into(),
+            elements: Vec::new(),
+        }
+    }
+
+    /// Appends an element value
+    pub fn element(mut self, name: impl Into<String>, value: impl Into<AnnotationElement>) -> Self {
+        self.elements.push((name.into(), value.into()));
+        self
+    }
+
+    /// The value of the first element named `name`
+    pub fn get(&self, name: &str) -> Option<&AnnotationElement> {
+        self.elements
+            .iter()
+            .find(|(element, _)| element == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Adds the names and values to the constant pool, reusing equal constants
+    pub fn build(&self, constants: &mut ConstantPoolBuilder) -> Result<Annotation, ConstantPoolError> {
+        let ty = constants.utf8(&self.ty)?;
+        let element_value_pairs = self
+            .elements
+            .iter()
+            .map(|(name, value)| Ok(ElementValuePair::new(constants.utf8(name)?, value.build(constants)?)))
+            .collect::<Result<_, ConstantPoolError>>()?;
+
+        Ok(Annotation::new(ty, element_value_pairs))
+    }
+}
+
+impl AnnotationElement {
+    pub fn build(&self, constants: &mut ConstantPoolBuilder) -> Result<ElementValue, ConstantPoolError> {
+        let constant = |tag: char, index| Ok(ElementValue::ConstValue { tag, index });
+
+        match self {
+            AnnotationElement::Byte(value) => constant('B', constants.integer(*value as i32)?),
+            AnnotationElement::Char(value) => constant('C', constants.integer(*value as i32)?),
+            AnnotationElement::Double(value) => constant('D', constants.double(*value)?),
+            AnnotationElement::Float(value) => constant('F', constants.float(*value)?),
+            AnnotationElement::Int(value) => constant('I', constants.integer(*value)?),
+            AnnotationElement::Long(value) => constant('J', constants.long(*value)?),
+            AnnotationElement::Short(value) => constant('S', constants.integer(*value as i32)?),
+            AnnotationElement::Boolean(value) => constant('Z', constants.integer(*value as i32)?),
+            AnnotationElement::String(value) => constant('s', constants.utf8(value)?),
+            AnnotationElement::Enum { type_name, const_name } => Ok(ElementValue::EnumConstValue {
+                type_name: constants.utf8(type_name)?,
+                const_name: constants.utf8(const_name)?,
+            }),
+            AnnotationElement::Class(descriptor) => Ok(ElementValue::Class(constants.utf8(descriptor)?)),
+            AnnotationElement::Annotation(annotation) => {
+                Ok(ElementValue::AnnotationValue(annotation.build(constants)?))
+            }
+            AnnotationElement::Array(values) => Ok(ElementValue::ArrayValue(
+                values
+                    .iter()
+                    .map(|value| value.build(constants))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .into(),
+            )),
+        }
+    }
+}
+
+macro_rules! implement_from {
+    ($($ty:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$ty> for AnnotationElement {
+                fn from(value: $ty) -> Self {
+                    AnnotationElement::$variant(value.into())
+                }
+            }
+        )*
+    };
+}
+
+implement_from!(
+    i8 => Byte,
+    f64 => Double,
+    f32 => Float,
+    i32 => Int,
+    i64 => Long,
+    i16 => Short,
+    bool => Boolean,
+    &str => String,
+    String => String,
+    AnnotationInfo => Annotation,
+    Vec<AnnotationElement> => Array,
+);
+
+impl ConstantReferences for Annotation {
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        visit(&mut self.ty);
+        self.element_value_pairs.visit_constants(visit)
+    }
+}
+
+impl ConstantReferences for ElementValuePair {
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        visit(&mut self.element_name);
+        self.element_value.visit_constants(visit)
+    }
+}
+
+impl ConstantReferences for ElementValue {
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        match self {
+            ElementValue::ConstValue { index, .. } | ElementValue::Class(index) => visit(index),
+            ElementValue::EnumConstValue { type_name, const_name } => {
+                visit(type_name);
+                visit(const_name);
+            }
+            ElementValue::AnnotationValue(annotation) => annotation.visit_constants(visit)?,
+            ElementValue::ArrayValue(values) => values.visit_constants(visit)?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::attribute::{Annotation, AnnotationElement, AnnotationInfo, Attribute, ElementValue};
+    use crate::{
+        AccessFlags, Constant, ConstantPool, ConstantPoolBuilder, ConstantPoolIndex, Error, JavaClass, JavaVersion,
+        MagicNumber, SizedVec,
+    };
+
+    fn annotation() -> AnnotationInfo {
+        AnnotationInfo::new("Lcom/example/Mapping;")
+            .element("value", "/users")
+            .element("retries", 3)
+            .element("timeout", 2.5)
+            .element("cached", true)
+            .element("separator", AnnotationElement::Char(u16::from(b',')))
+            .element(
+                "method",
+                AnnotationElement::Enum {
+                    type_name: "Lcom/example/Method;".to_string(),
+                    const_name: "GET".to_string(),
+                },
+            )
+            .element("response", AnnotationElement::Class("Lcom/example/User;".to_string()))
+            .element(
+                "headers",
+                vec![
+                    AnnotationInfo::new("Lcom/example/Header;").element("name", "Accept").into(),
+                    AnnotationInfo::new("Lcom/example/Header;").element("name", "Accept").into(),
+                ],
+            )
+            .element("limits", vec![AnnotationElement::Long(1), AnnotationElement::Short(-2), 3i8.into()])
+    }
+
+    #[test]
+    fn build_and_resolve_annotation() -> Result<(), Error> {
+        let mut constant_pool = ConstantPool(Vec::new());
+        let mut constants = ConstantPoolBuilder::new(&mut constant_pool);
+        let runtime_visible_annotations = constants.utf8("RuntimeVisibleAnnotations")?;
+        let built = annotation().build(&mut constants)?;
+        let this_class = constants.class("Mapped")?;
+
+        // Equal names and values share their constants
+        assert_eq!(built.element_value_pairs().len(), 9);
+        assert_eq!(constants.len(), 28);
+
+        let class = JavaClass {
+            magic: MagicNumber::Cafebabe,
+            version: JavaVersion { major: 52, minor: 0 },
+            constant_pool,
+            access_flags: AccessFlags::PUBLIC,
+            this_class,
+            super_class: ConstantPoolIndex(0),
+            interfaces: SizedVec::new(),
+            fields: SizedVec::new(),
+            methods: SizedVec::new(),
+            attributes: vec![Attribute::RuntimeVisibleAnnotations {
+                name: runtime_visible_annotations,
+                annotations: vec![built].into(),
+            }]
+            .into(),
+        };
+        let mut buffer = Vec::new();
+        class.write(&mut buffer)?;
+        let class = JavaClass::read(&mut buffer.as_slice())?;
+
+        let annotations: &[Annotation] = match &class.attributes[0] {
+            Attribute::RuntimeVisibleAnnotations { annotations, .. } => annotations,
+            attribute => panic!("Expected annotations, got {:?}", attribute),
+        };
+        let resolved = annotations[0].resolve(&class.constant_pool)?;
+        assert_eq!(resolved, annotation());
+        assert_eq!(resolved.get("retries"), Some(&AnnotationElement::Int(3)));
+        assert_eq!(resolved.get("missing"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn reject_unknown_constant_tag() {
+        let constant_pool = ConstantPool(vec![Constant::Integer(7)]);
+        let value = |tag| ElementValue::ConstValue {
+            tag,
+            index: ConstantPoolIndex(1),
+        };
+
+        assert_eq!(value('I').resolve(&constant_pool).unwrap(), AnnotationElement::Int(7));
+        assert!(matches!(value('X').resolve(&constant_pool), Err(Error::InvalidElementValueTag('X'))));
+    }
+}
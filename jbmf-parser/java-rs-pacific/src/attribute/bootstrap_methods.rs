@@ -1,9 +1,19 @@
 use java_rs_base::constant_pool::ConstantPoolIndex;
+use java_rs_base::error::ConstantPoolError;
 use java_rs_base::io::SizedVec;
 use java_rs_derive::ClassFilePart;
 
+use crate::attribute::ConstantReferences;
+
 #[derive(Debug, ClassFilePart, Clone, Eq, PartialEq)]
 pub struct BootstrapMethod {
     method_ref: ConstantPoolIndex,
     arguments: SizedVec<u16, ConstantPoolIndex>,
 }
+
+impl ConstantReferences for BootstrapMethod {
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        visit(&mut self.method_ref);
+        self.arguments.visit_constants(visit)
+    }
+}
@@ -1,11 +1,15 @@
+use std::convert::TryFrom;
 use std::io::{Cursor, Read, Write};
 use std::ops::Deref;
 
-use java_rs_base::error::Error;
-use java_rs_base::io::{AttributeLocation, ClassFilePart, ClassFilePartSize, ReadContext, SizedVec, WriteContext};
+use java_rs_base::constant_pool::ConstantPoolIndex;
+use java_rs_base::error::{ConstantPoolError, Error};
+use java_rs_base::io::{
+    AttributeLocation, ClassFilePart, ClassFilePartSize, PositionReader, ReadContext, SizedVec, WriteContext,
+};
 use java_rs_derive::ClassFilePart;
 
-use super::Attribute;
+use super::{Attribute, ConstantReferences};
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Compatibility<Pre: ClassFilePart + Eq, Current: ClassFilePart + Eq> {
@@ -48,6 +52,7 @@ impl<Pre: ClassFilePartSize, Current: ClassFilePartSize, ItemType: ClassFilePart
 pub struct CodeIO;
 
 impl CodeIO {
+    /// Errors of instructions are located at `code[position]`, relative to the attribute's data
     pub fn read<R: Read>(reader: &mut R, ctx: &ReadContext) -> Result<Attribute, Error>
     where
         Self: Sized,
@@ -58,6 +63,7 @@ impl CodeIO {
             ..*ctx
         };
 
+        let reader = &mut PositionReader::new(reader);
         let max_stack = ClassFilePart::read(reader, &ctx)?;
         let max_locals = ClassFilePart::read(reader, &ctx)?;
 
@@ -70,6 +76,7 @@ impl CodeIO {
             }
         };
         let size = data.len();
+        let code_offset = reader.position() - size as u64;
         let mut code_reader = Cursor::new(data);
 
         while (code_reader.position() as usize) < size {
@@ -78,7 +85,9 @@ impl CodeIO {
                 ..ctx
             };
 
-            let instruction = ClassFilePart::read(&mut code_reader, &ctx)?;
+            let position = code_reader.position();
+            let instruction = ClassFilePart::read(&mut code_reader, &ctx)
+                .map_err(|error| error.located(code_offset + position, format!("code[{}]", position)))?;
 
             ctx = match instruction {
                 Instruction::Wide => {
@@ -223,6 +232,64 @@ impl ClassFilePart for WideIndex {
     }
 }
 
+/// The constant added by `iinc`, a word follows a `wide` instruction
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SizedIncrement {
+    Normal(i8),
+    Wide(i16),
+}
+
+impl SizedIncrement {
+    pub fn value(&self) -> i16 {
+        match self {
+            SizedIncrement::Normal(v) => *v as i16,
+            SizedIncrement::Wide(v) => *v,
+        }
+    }
+}
+
+impl ClassFilePart for SizedIncrement {
+    fn read<R: Read>(reader: &mut R, ctx: &ReadContext) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        match ctx.wide.unwrap() {
+            false => i8::read(reader, ctx).map(SizedIncrement::Normal),
+            true => i16::read(reader, ctx).map(SizedIncrement::Wide),
+        }
+    }
+
+    fn write<W: Write>(&self, writer: &mut W, ctx: &WriteContext) -> Result<(), Error> {
+        match self {
+            SizedIncrement::Normal(v) => v.write(writer, ctx),
+            SizedIncrement::Wide(v) => v.write(writer, ctx),
+        }
+    }
+}
+
+/// The one byte constant pool index of `ldc`
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Copy, Clone)]
+pub struct SmallConstantPoolIndex(pub u8);
+
+impl From<SmallConstantPoolIndex> for ConstantPoolIndex {
+    fn from(index: SmallConstantPoolIndex) -> Self {
+        ConstantPoolIndex(index.0 as u16)
+    }
+}
+
+impl ClassFilePart for SmallConstantPoolIndex {
+    fn read<R: Read>(reader: &mut R, ctx: &ReadContext) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        Ok(Self(u8::read(reader, ctx)?))
+    }
+
+    fn write<W: Write>(&self, writer: &mut W, ctx: &WriteContext) -> Result<(), Error> {
+        u8::write(&self.0, writer, ctx)
+    }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct AlwaysZero;
 
@@ -419,7 +486,7 @@ pub enum Instruction {
     #[java_rs(opcode = 0x2D)]
     ALoad3,
     #[java_rs(opcode = 0xBD)]
-    ANewArray { index: WideIndex },
+    ANewArray { index: ConstantPoolIndex },
     #[java_rs(opcode = 0xB0)]
     AReturn,
     #[java_rs(opcode = 0xBE)]
@@ -441,13 +508,13 @@ pub enum Instruction {
     #[java_rs(opcode = 0x54)]
     BAStore,
     #[java_rs(opcode = 0x10)]
-    BIPush { value: u8 },
+    BIPush { value: i8 },
     #[java_rs(opcode = 0x34)]
     CALoad,
     #[java_rs(opcode = 0x55)]
     CAStore,
     #[java_rs(opcode = 0xC0)]
-    CheckCast { index: WideIndex },
+    CheckCast { index: ConstantPoolIndex },
     #[java_rs(opcode = 0x90)]
     D2F,
     #[java_rs(opcode = 0x8E)]
@@ -567,13 +634,13 @@ pub enum Instruction {
     #[java_rs(opcode = 0x66)]
     FSub,
     #[java_rs(opcode = 0xB4)]
-    GetField { index: WideIndex },
+    GetField { index: ConstantPoolIndex },
     #[java_rs(opcode = 0xB2)]
-    GetStatic { index: WideIndex },
+    GetStatic { index: ConstantPoolIndex },
     #[java_rs(opcode = 0xA7)]
-    Goto { offset: u16 },
+    Goto { offset: i16 },
     #[java_rs(opcode = 0xC8)]
-    GotoW { offset: u32 },
+    GotoW { offset: i32 },
     #[java_rs(opcode = 0x91)]
     I2B,
     #[java_rs(opcode = 0x92)]
@@ -611,39 +678,39 @@ pub enum Instruction {
     #[java_rs(opcode = 0x6C)]
     IDiv,
     #[java_rs(opcode = 0xA5)]
-    IfACmpEq { offset: u16 },
+    IfACmpEq { offset: i16 },
     #[java_rs(opcode = 0xA6)]
-    IfACmpNe { offset: u16 },
+    IfACmpNe { offset: i16 },
     #[java_rs(opcode = 0x9F)]
-    IfICmpEq { offset: u16 },
+    IfICmpEq { offset: i16 },
     #[java_rs(opcode = 0xA0)]
-    IfICmpNe { offset: u16 },
+    IfICmpNe { offset: i16 },
     #[java_rs(opcode = 0xA1)]
-    IfICmpLt { offset: u16 },
+    IfICmpLt { offset: i16 },
     #[java_rs(opcode = 0xA2)]
-    IfICmpGe { offset: u16 },
+    IfICmpGe { offset: i16 },
     #[java_rs(opcode = 0xA3)]
-    IfICmpGt { offset: u16 },
+    IfICmpGt { offset: i16 },
     #[java_rs(opcode = 0xA4)]
-    IfICmpLe { offset: u16 },
+    IfICmpLe { offset: i16 },
     #[java_rs(opcode = 0x99)]
-    IfEq { offset: u16 },
+    IfEq { offset: i16 },
     #[java_rs(opcode = 0x9A)]
-    IfNe { offset: u16 },
+    IfNe { offset: i16 },
     #[java_rs(opcode = 0x9B)]
-    IfLt { offset: u16 },
+    IfLt { offset: i16 },
     #[java_rs(opcode = 0x9C)]
-    IfGe { offset: u16 },
+    IfGe { offset: i16 },
     #[java_rs(opcode = 0x9D)]
-    IfGt { offset: u16 },
+    IfGt { offset: i16 },
     #[java_rs(opcode = 0x9E)]
-    IfLe { offset: u16 },
+    IfLe { offset: i16 },
     #[java_rs(opcode = 0xC7)]
-    IfNonNull { offset: u16 },
+    IfNonNull { offset: i16 },
     #[java_rs(opcode = 0xC6)]
-    IfNull { offset: u16 },
+    IfNull { offset: i16 },
     #[java_rs(opcode = 0x84)]
-    IInc { index: SizedIndex, value: SizedIndex },
+    IInc { index: SizedIndex, value: SizedIncrement },
     #[java_rs(opcode = 0x15)]
     ILoad { index: SizedIndex },
     #[java_rs(opcode = 0x1A)]
@@ -659,25 +726,25 @@ pub enum Instruction {
     #[java_rs(opcode = 0x74)]
     INeg,
     #[java_rs(opcode = 0xC1)]
-    InstanceOf { index: WideIndex },
+    InstanceOf { index: ConstantPoolIndex },
     #[java_rs(opcode = 0xBA)]
     InvokeDynamic {
-        index: WideIndex,
+        index: ConstantPoolIndex,
         _zero0: AlwaysZero,
         _zero1: AlwaysZero,
     },
     #[java_rs(opcode = 0xB9)]
     InvokeInterface {
-        index: WideIndex,
+        index: ConstantPoolIndex,
         count: u8,
         _zero: AlwaysZero,
     },
     #[java_rs(opcode = 0xB7)]
-    InvokeSpecial { index: WideIndex },
+    InvokeSpecial { index: ConstantPoolIndex },
     #[java_rs(opcode = 0xB8)]
-    InvokeStatic { index: WideIndex },
+    InvokeStatic { index: ConstantPoolIndex },
     #[java_rs(opcode = 0xB6)]
-    InvokeVirtual { index: WideIndex },
+    InvokeVirtual { index: ConstantPoolIndex },
     #[java_rs(opcode = 0x80)]
     IOr,
     #[java_rs(opcode = 0x70)]
@@ -705,9 +772,9 @@ pub enum Instruction {
     #[java_rs(opcode = 0x82)]
     IXor,
     #[java_rs(opcode = 0xA8)]
-    JSR { offset: u16 },
+    JSR { offset: i16 },
     #[java_rs(opcode = 0xC9)]
-    JSRW { offset: u32 },
+    JSRW { offset: i32 },
     #[java_rs(opcode = 0x8A)]
     L2D,
     #[java_rs(opcode = 0x89)]
@@ -729,11 +796,11 @@ pub enum Instruction {
     #[java_rs(opcode = 0xA)]
     LConst1,
     #[java_rs(opcode = 0x12)]
-    LDC { index: SmallIndex },
+    LDC { index: SmallConstantPoolIndex },
     #[java_rs(opcode = 0x13)]
-    LDCW { index: WideIndex },
+    LDCW { index: ConstantPoolIndex },
     #[java_rs(opcode = 0x14)]
-    LDC2W { index: WideIndex },
+    LDC2W { index: ConstantPoolIndex },
     #[java_rs(opcode = 0x6D)]
     LDiv,
     #[java_rs(opcode = 0x16)]
@@ -786,9 +853,9 @@ pub enum Instruction {
     #[java_rs(opcode = 0xC3)]
     MonitorExit,
     #[java_rs(opcode = 0xC5)]
-    MultiANewArray { index: WideIndex, dimensions: u8 },
+    MultiANewArray { index: ConstantPoolIndex, dimensions: u8 },
     #[java_rs(opcode = 0xBB)]
-    New { index: WideIndex },
+    New { index: ConstantPoolIndex },
     #[java_rs(opcode = 0xBC)]
     NewArray { ty: ArrayType },
     #[java_rs(opcode = 0x0)]
@@ -798,9 +865,9 @@ pub enum Instruction {
     #[java_rs(opcode = 0x58)]
     Pop2,
     #[java_rs(opcode = 0xB5)]
-    PutField { index: WideIndex },
+    PutField { index: ConstantPoolIndex },
     #[java_rs(opcode = 0xB3)]
-    PutStatic { index: WideIndex },
+    PutStatic { index: ConstantPoolIndex },
     #[java_rs(opcode = 0xA9)]
     Ret { index: SizedIndex },
     #[java_rs(opcode = 0xB1)]
@@ -824,15 +891,109 @@ pub enum Instruction {
     Wide,
 }
 
+impl ConstantReferences for Instruction {
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        match self {
+            Instruction::ANewArray { index }
+            | Instruction::CheckCast { index }
+            | Instruction::GetField { index }
+            | Instruction::GetStatic { index }
+            | Instruction::InstanceOf { index }
+            | Instruction::InvokeDynamic { index, .. }
+            | Instruction::InvokeInterface { index, .. }
+            | Instruction::InvokeSpecial { index }
+            | Instruction::InvokeStatic { index }
+            | Instruction::InvokeVirtual { index }
+            | Instruction::LDCW { index }
+            | Instruction::LDC2W { index }
+            | Instruction::MultiANewArray { index, .. }
+            | Instruction::New { index }
+            | Instruction::PutField { index }
+            | Instruction::PutStatic { index } => visit(index),
+            Instruction::LDC { index } => {
+                let mut wide = ConstantPoolIndex::from(*index);
+                visit(&mut wide);
+                // LDC can only address the first 255 constants
+                index.0 = u8::try_from(wide.0).map_err(|_| ConstantPoolError::InvalidIndex(wide.0))?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs::File;
     use std::io::{BufReader, BufWriter};
 
-    use crate::{AccessFlags, Constant, ConstantPoolIndex, Error, JavaClass, JavaVersion, MagicNumber, Method, SizedVec};
-    use crate::attribute::{Attribute, Compatibility};
+    use java_rs_base::io::{ReadContext, WriteContext};
+
+    use crate::{AccessFlags, Constant, ConstantPool, ConstantPoolIndex, Error, JavaClass, JavaVersion, MagicNumber, Method, SizedVec};
+    use crate::attribute::{Attribute, CodeIO, Compatibility, Instruction, SizedIncrement, SizedIndex, SmallConstantPoolIndex, SmallIndex, WideIndex};
     use crate::helper::*;
 
+    #[test]
+    fn check_signed_operands() -> Result<(), Error> {
+        let reference = Attribute::Code {
+            name: ConstantPoolIndex(1),
+            max_stack: Compatibility::Current(1),
+            max_locals: Compatibility::Current(1),
+            code: Compatibility::Current(vec![
+                Instruction::BIPush { value: -2 },
+                Instruction::IInc { index: SizedIndex::Normal(SmallIndex(0)), value: SizedIncrement::Normal(-1) },
+                Instruction::Wide,
+                Instruction::IInc { index: SizedIndex::Wide(WideIndex(0)), value: SizedIncrement::Wide(-300) },
+                Instruction::LDC { index: SmallConstantPoolIndex(2) },
+                Instruction::Goto { offset: -14 },
+                Instruction::GotoW { offset: -17 },
+            ].into()),
+            exception_table: SizedVec::new(),
+            attributes: SizedVec::new(),
+        };
+
+        let mut buffer = Vec::new();
+        CodeIO::write(&reference, &mut buffer, &WriteContext { position: None })?;
+        assert_eq!(
+            &buffer[8..],
+            &[0x10, 0xFE, 0x84, 0, 0xFF, 0xC4, 0x84, 0, 0, 0xFE, 0xD4, 0x12, 2, 0xA7, 0xFF, 0xF2, 0xC8, 0xFF, 0xFF, 0xFF, 0xEF, 0, 0, 0, 0]
+        );
+
+        let ctx = ReadContext {
+            version: &JavaVersion { major: 52, minor: 0 },
+            constant_pool: &ConstantPool(Vec::new()),
+            location: None,
+            name: Some(ConstantPoolIndex(1)),
+            position: None,
+            length: None,
+            wide: None,
+        };
+        assert_eq!(reference, CodeIO::read(&mut buffer.as_slice(), &ctx)?);
+        Ok(())
+    }
+
+    #[test]
+    fn reject_unknown_opcode() {
+        let buffer = [0, 1, 0, 0, 0, 0, 0, 2, 0x00, 0xCB, 0, 0, 0, 0];
+        let ctx = ReadContext {
+            version: &JavaVersion { major: 52, minor: 0 },
+            constant_pool: &ConstantPool(Vec::new()),
+            location: None,
+            name: Some(ConstantPoolIndex(1)),
+            position: None,
+            length: None,
+            wide: None,
+        };
+
+        let error = CodeIO::read(&mut buffer.as_slice(), &ctx).unwrap_err();
+        assert_eq!(error.location(), Some((9, "code[1]", None)));
+        assert!(matches!(
+            error,
+            Error::Located { source, .. }
+                if matches!(*source, Error::UnexpectedOpCodeValue { expected: (0x00, 0xC9), found: 0xCB })
+        ));
+    }
+
     #[test]
     fn check_empty_current_code() -> Result<(), Error> {
         let (path, _guard) = init_tmp_dir("CheckEmptyCodeTest.class");
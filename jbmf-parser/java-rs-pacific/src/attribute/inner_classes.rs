@@ -1,6 +1,8 @@
 use java_rs_base::constant_pool::ConstantPoolIndex;
+use java_rs_base::error::ConstantPoolError;
 use java_rs_derive::ClassFilePart;
 
+use crate::attribute::ConstantReferences;
 use crate::flags::AccessFlags;
 
 #[derive(Debug, ClassFilePart, Clone, Eq, PartialEq)]
@@ -11,6 +13,15 @@ pub struct InnerClass {
     inner_class_access_flags: AccessFlags,
 }
 
+impl ConstantReferences for InnerClass {
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        visit(&mut self.inner_class);
+        visit(&mut self.outer_class);
+        visit(&mut self.inner_name);
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs::File;
@@ -1,6 +1,9 @@
 use java_rs_base::constant_pool::ConstantPoolIndex;
+use java_rs_base::error::ConstantPoolError;
 use java_rs_derive::ClassFilePart;
 
+use crate::attribute::ConstantReferences;
+
 #[derive(Debug, ClassFilePart, Clone, Eq, PartialEq)]
 pub struct LocalVariableTable {
     pub start_pc: u16,
@@ -9,3 +12,11 @@ pub struct LocalVariableTable {
     pub descriptor: ConstantPoolIndex,
     pub index: u16,
 }
+
+impl ConstantReferences for LocalVariableTable {
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        visit(&mut self.name);
+        visit(&mut self.descriptor);
+        Ok(())
+    }
+}
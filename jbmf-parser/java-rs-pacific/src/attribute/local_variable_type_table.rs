@@ -1,11 +1,22 @@
 use java_rs_base::constant_pool::ConstantPoolIndex;
+use java_rs_base::error::ConstantPoolError;
 use java_rs_derive::ClassFilePart;
 
+use crate::attribute::ConstantReferences;
+
 #[derive(Debug, ClassFilePart, Clone, Eq, PartialEq)]
 pub struct LocalVariableTypeTable {
-    start_pc: u16,
-    length: u16,
-    name: ConstantPoolIndex,
-    signature: ConstantPoolIndex,
-    index: u16,
+    pub start_pc: u16,
+    pub length: u16,
+    pub name: ConstantPoolIndex,
+    pub signature: ConstantPoolIndex,
+    pub index: u16,
+}
+
+impl ConstantReferences for LocalVariableTypeTable {
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        visit(&mut self.name);
+        visit(&mut self.signature);
+        Ok(())
+    }
 }
@@ -1,6 +1,8 @@
 use java_rs_base::constant_pool::ConstantPoolIndex;
+use java_rs_base::error::ConstantPoolError;
 use java_rs_derive::ClassFilePart;
 
+use crate::attribute::ConstantReferences;
 use crate::flags::AccessFlags;
 
 #[derive(Debug, ClassFilePart, Clone, Eq, PartialEq)]
@@ -8,3 +10,10 @@ pub struct MethodParameter {
     name: ConstantPoolIndex,
     access_flags: AccessFlags,
 }
+
+impl ConstantReferences for MethodParameter {
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        visit(&mut self.name);
+        Ok(())
+    }
+}
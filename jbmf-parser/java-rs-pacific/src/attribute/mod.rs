@@ -1,22 +1,22 @@
 use std::io::{Read, Write};
 
-use annotation::Annotation;
+pub use annotation::*;
 pub use bootstrap_methods::*;
 pub use code::*;
 pub use inner_classes::*;
 use java_rs_base::constant_pool::ConstantPoolIndex;
-use java_rs_base::error::Error;
-use java_rs_base::io::{ClassFilePart, ReadContext, SizedVec, WriteContext};
+use java_rs_base::error::{ConstantPoolError, Error};
+use java_rs_base::io::{ClassFilePart, ClassFilePartSize, ReadContext, SizedVec, WriteContext};
 use java_rs_derive::ClassFilePart;
 pub use line_number_table::*;
 pub use local_variable_table::*;
 pub use local_variable_type_table::*;
 pub use method_parameters::*;
 pub use module::*;
+pub use record::*;
 pub use stack_map::*;
-use type_annotation::TypeAnnotation;
+pub use type_annotation::*;
 
-use crate::attribute::annotation::ElementValue;
 use crate::flags::ModuleFlags;
 
 mod annotation;
@@ -28,6 +28,7 @@ mod local_variable_table;
 mod local_variable_type_table;
 mod method_parameters;
 mod module;
+mod record;
 mod source_debug_extension;
 mod stack_map;
 mod type_annotation;
@@ -89,7 +90,7 @@ pub enum Attribute {
     Synthetic {
         name: ConstantPoolIndex,
     },
-    #[java_rs(version = 49.0, location = [ClassFile, Field, Method])]
+    #[java_rs(version = 49.0, location = [ClassFile, Field, Method, RecordComponent])]
     Signature {
         name: ConstantPoolIndex,
         signature: ConstantPoolIndex,
@@ -123,12 +124,12 @@ pub enum Attribute {
     Deprecated {
         name: ConstantPoolIndex,
     },
-    #[java_rs(version = 49.0, location = [ClassFile, Field, Method])]
+    #[java_rs(version = 49.0, location = [ClassFile, Field, Method, RecordComponent])]
     RuntimeVisibleAnnotations {
         name: ConstantPoolIndex,
         annotations: SizedVec<u16, Annotation>,
     },
-    #[java_rs(version = 49.0, location = [ClassFile, Field, Method])]
+    #[java_rs(version = 49.0, location = [ClassFile, Field, Method, RecordComponent])]
     RuntimeInvisibleAnnotations {
         name: ConstantPoolIndex,
         annotations: SizedVec<u16, Annotation>,
@@ -143,12 +144,12 @@ pub enum Attribute {
         name: ConstantPoolIndex,
         annotations: SizedVec<u8, SizedVec<u16, Annotation>>,
     },
-    #[java_rs(version = 52.0, location = [ClassFile, Field, Method, Code])]
+    #[java_rs(version = 52.0, location = [ClassFile, Field, Method, Code, RecordComponent])]
     RuntimeVisibleTypeAnnotations {
         name: ConstantPoolIndex,
         annotations: SizedVec<u16, TypeAnnotation>,
     },
-    #[java_rs(version = 52.0, location = [ClassFile, Field, Method, Code])]
+    #[java_rs(version = 52.0, location = [ClassFile, Field, Method, Code, RecordComponent])]
     RuntimeInvisibleTypeAnnotations {
         name: ConstantPoolIndex,
         annotations: SizedVec<u16, TypeAnnotation>,
@@ -174,11 +175,11 @@ pub enum Attribute {
         module_name: ConstantPoolIndex,
         flags: ModuleFlags,
         module_version: ConstantPoolIndex,
-        requires: ModuleRequires,
-        exports: ModuleExports,
-        opens: ModuleOpens,
+        requires: SizedVec<u16, ModuleRequires>,
+        exports: SizedVec<u16, ModuleExports>,
+        opens: SizedVec<u16, ModuleOpens>,
         uses: SizedVec<u16, ConstantPoolIndex>,
-        provides: ModuleProvides,
+        provides: SizedVec<u16, ModuleProvides>,
     },
     #[java_rs(version = 53.0, location = ClassFile)]
     ModulePackages {
@@ -200,6 +201,16 @@ pub enum Attribute {
         name: ConstantPoolIndex,
         classes: SizedVec<u16, ConstantPoolIndex>,
     },
+    #[java_rs(version = 60.0, location = ClassFile)]
+    Record {
+        name: ConstantPoolIndex,
+        components: SizedVec<u16, RecordComponent>,
+    },
+    #[java_rs(version = 61.0, location = ClassFile)]
+    PermittedSubclasses {
+        name: ConstantPoolIndex,
+        classes: SizedVec<u16, ConstantPoolIndex>,
+    },
     InvalidUtf8(RawAttribute),
     IllegalNameReference(RawAttribute),
     UnsupportedAndInvalidLocation(Box<Attribute>),
@@ -249,8 +260,191 @@ impl ClassFilePart for CustomAttribute {
 
 #[derive(Debug, ClassFilePart, Clone, Eq, PartialEq)]
 pub struct ExceptionTable {
-    start_pc: u16,
-    end_pc: u16,
-    handler_pc: u16,
-    catch_type: u16,
+    pub start_pc: u16,
+    /// Exclusive end of the protected range
+    pub end_pc: u16,
+    pub handler_pc: u16,
+    /// Index of the caught class, zero for handlers which catch every exception
+    pub catch_type: ConstantPoolIndex,
+}
+
+/// Parts of a class file which refer to constant pool entries
+pub(crate) trait ConstantReferences {
+    /// Passes every constant pool index held by the part to `visit`, which may rewrite it in place
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError>;
+}
+
+impl ConstantReferences for ConstantPoolIndex {
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        visit(self);
+        Ok(())
+    }
+}
+
+impl<S: ClassFilePartSize, T: ClassFilePart + ConstantReferences> ConstantReferences for SizedVec<S, T> {
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        self.iter_mut().try_for_each(|part| part.visit_constants(visit))
+    }
+}
+
+impl<Pre, Current> ConstantReferences for Compatibility<Pre, Current>
+where
+    Pre: ClassFilePart + Eq + ConstantReferences,
+    Current: ClassFilePart + Eq + ConstantReferences,
+{
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        match self {
+            Compatibility::PreJava1(part) => part.visit_constants(visit),
+            Compatibility::Current(part) => part.visit_constants(visit),
+        }
+    }
+}
+
+impl ConstantReferences for ExceptionTable {
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        self.catch_type.visit_constants(visit)
+    }
+}
+
+/// Unknown and custom attributes only refer to their name, the contents of attributes which failed to parse are
+/// opaque and reported as [ConstantPoolError::UnparsedAttribute]
+impl ConstantReferences for Attribute {
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        match self {
+            Attribute::ConstantValue { name, value } => {
+                visit(name);
+                visit(value);
+            }
+            Attribute::Code {
+                name,
+                code,
+                exception_table,
+                attributes,
+                ..
+            } => {
+                visit(name);
+                code.visit_constants(visit)?;
+                exception_table.visit_constants(visit)?;
+                attributes.visit_constants(visit)?;
+            }
+            Attribute::StackMapTable { name, entries } => {
+                visit(name);
+                entries.visit_constants(visit)?;
+            }
+            Attribute::Exceptions {
+                name,
+                exception_index_table,
+            } => {
+                visit(name);
+                exception_index_table.visit_constants(visit)?;
+            }
+            Attribute::InnerClasses { name, classes } => {
+                visit(name);
+                classes.visit_constants(visit)?;
+            }
+            Attribute::EnclosingMethod { name, class, method } => {
+                visit(name);
+                visit(class);
+                visit(method);
+            }
+            Attribute::Synthetic { name } | Attribute::Deprecated { name } => visit(name),
+            Attribute::Signature { name, signature } => {
+                visit(name);
+                visit(signature);
+            }
+            Attribute::SourceFile { name, sourcefile } => {
+                visit(name);
+                visit(sourcefile);
+            }
+            Attribute::SourceDebugExtension { name, .. } => visit(name),
+            Attribute::LineNumberTable { name, .. } => visit(name),
+            Attribute::LocalVariableTable { name, local_variables } => {
+                visit(name);
+                local_variables.visit_constants(visit)?;
+            }
+            Attribute::LocalVariableTypeTable {
+                name,
+                local_variable_type_table,
+            } => {
+                visit(name);
+                local_variable_type_table.visit_constants(visit)?;
+            }
+            Attribute::RuntimeVisibleAnnotations { name, annotations }
+            | Attribute::RuntimeInvisibleAnnotations { name, annotations } => {
+                visit(name);
+                annotations.visit_constants(visit)?;
+            }
+            Attribute::RuntimeVisibleParameterAnnotations { name, annotations }
+            | Attribute::RuntimeInvisibleParameterAnnotations { name, annotations } => {
+                visit(name);
+                annotations.visit_constants(visit)?;
+            }
+            Attribute::RuntimeVisibleTypeAnnotations { name, annotations }
+            | Attribute::RuntimeInvisibleTypeAnnotations { name, annotations } => {
+                visit(name);
+                annotations.visit_constants(visit)?;
+            }
+            Attribute::AnnotationDefault { name, default } => {
+                visit(name);
+                default.visit_constants(visit)?;
+            }
+            Attribute::BootstrapMethods { name, methods } => {
+                visit(name);
+                methods.visit_constants(visit)?;
+            }
+            Attribute::MethodParameters { name, parameters } => {
+                visit(name);
+                parameters.visit_constants(visit)?;
+            }
+            Attribute::Module {
+                name,
+                module_name,
+                module_version,
+                requires,
+                exports,
+                opens,
+                uses,
+                provides,
+                ..
+            } => {
+                visit(name);
+                visit(module_name);
+                visit(module_version);
+                requires.visit_constants(visit)?;
+                exports.visit_constants(visit)?;
+                opens.visit_constants(visit)?;
+                uses.visit_constants(visit)?;
+                provides.visit_constants(visit)?;
+            }
+            Attribute::ModulePackages { name, packages } => {
+                visit(name);
+                packages.visit_constants(visit)?;
+            }
+            Attribute::ModuleMainClass { name, main_class } => {
+                visit(name);
+                visit(main_class);
+            }
+            Attribute::NestHost { name, host_class } => {
+                visit(name);
+                visit(host_class);
+            }
+            Attribute::NestMembers { name, classes } | Attribute::PermittedSubclasses { name, classes } => {
+                visit(name);
+                classes.visit_constants(visit)?;
+            }
+            Attribute::Record { name, components } => {
+                visit(name);
+                components.visit_constants(visit)?;
+            }
+            Attribute::InvalidUtf8(attribute) | Attribute::IllegalNameReference(attribute) | Attribute::Unknown(attribute) => {
+                visit(&mut attribute.name)
+            }
+            Attribute::Raw(attribute) => return Err(ConstantPoolError::UnparsedAttribute(attribute.name.0)),
+            Attribute::UnsupportedAndInvalidLocation(attribute)
+            | Attribute::InvalidLocation(attribute)
+            | Attribute::Unsupported(attribute) => attribute.visit_constants(visit)?,
+            Attribute::Custom(attribute) => visit(&mut attribute.name),
+        }
+        Ok(())
+    }
 }
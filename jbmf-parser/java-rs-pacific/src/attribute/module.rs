@@ -1,8 +1,10 @@
-use java_rs_base::constant_pool::ConstantPoolIndex;
+use java_rs_base::constant_pool::{ConstantPool, ConstantPoolIndex};
+use java_rs_base::error::ConstantPoolError;
 use java_rs_base::io::SizedVec;
 use java_rs_derive::ClassFilePart;
 
-use crate::flags::ModuleDependencyFlags;
+use crate::attribute::{Attribute, ConstantReferences};
+use crate::flags::{ModuleDependencyFlags, ModuleFlags};
 
 #[derive(Debug, ClassFilePart, Clone, Eq, PartialEq)]
 pub struct ModuleRequires {
@@ -22,11 +24,346 @@ pub struct ModuleExports {
 pub struct ModuleOpens {
     open: ConstantPoolIndex,
     flags: ModuleDependencyFlags,
-    to_index: SizedVec<u16, ConstantPoolIndex>,
+    to: SizedVec<u16, ConstantPoolIndex>,
 }
 
 #[derive(Debug, ClassFilePart, Clone, Eq, PartialEq)]
 pub struct ModuleProvides {
     provide: ConstantPoolIndex,
-    with_index: SizedVec<u16, ConstantPoolIndex>,
+    with: SizedVec<u16, ConstantPoolIndex>,
+}
+
+impl ModuleRequires {
+    pub fn new(requires: ConstantPoolIndex, flags: ModuleDependencyFlags, version: ConstantPoolIndex) -> Self {
+        ModuleRequires {
+            requires,
+            flags,
+            version,
+        }
+    }
+
+    /// Index of the required `CONSTANT_Module`
+    pub fn requires(&self) -> ConstantPoolIndex {
+        self.requires
+    }
+
+    pub fn flags(&self) -> ModuleDependencyFlags {
+        self.flags
+    }
+
+    /// Index of the version string, zero if no version was recorded
+    pub fn version(&self) -> ConstantPoolIndex {
+        self.version
+    }
+}
+
+impl ModuleExports {
+    pub fn new(export: ConstantPoolIndex, flags: ModuleDependencyFlags, to: Vec<ConstantPoolIndex>) -> Self {
+        ModuleExports {
+            export,
+            flags,
+            to: to.into(),
+        }
+    }
+
+    /// Index of the exported `CONSTANT_Package`
+    pub fn export(&self) -> ConstantPoolIndex {
+        self.export
+    }
+
+    pub fn flags(&self) -> ModuleDependencyFlags {
+        self.flags
+    }
+
+    /// Indices of the modules the package is exported to, empty if it is exported to every module
+    pub fn to(&self) -> &[ConstantPoolIndex] {
+        &self.to
+    }
+}
+
+impl ModuleOpens {
+    pub fn new(open: ConstantPoolIndex, flags: ModuleDependencyFlags, to: Vec<ConstantPoolIndex>) -> Self {
+        ModuleOpens {
+            open,
+            flags,
+            to: to.into(),
+        }
+    }
+
+    /// Index of the opened `CONSTANT_Package`
+    pub fn open(&self) -> ConstantPoolIndex {
+        self.open
+    }
+
+    pub fn flags(&self) -> ModuleDependencyFlags {
+        self.flags
+    }
+
+    /// Indices of the modules the package is opened to, empty if it is opened to every module
+    pub fn to(&self) -> &[ConstantPoolIndex] {
+        &self.to
+    }
+}
+
+impl ModuleProvides {
+    pub fn new(provide: ConstantPoolIndex, with: Vec<ConstantPoolIndex>) -> Self {
+        ModuleProvides {
+            provide,
+            with: with.into(),
+        }
+    }
+
+    /// Index of the service interface's `CONSTANT_Class`
+    pub fn provide(&self) -> ConstantPoolIndex {
+        self.provide
+    }
+
+    /// Indices of the implementing classes
+    pub fn with(&self) -> &[ConstantPoolIndex] {
+        &self.with
+    }
+}
+
+impl ConstantReferences for ModuleRequires {
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        visit(&mut self.requires);
+        visit(&mut self.version);
+        Ok(())
+    }
+}
+
+impl ConstantReferences for ModuleExports {
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        visit(&mut self.export);
+        self.to.visit_constants(visit)
+    }
+}
+
+impl ConstantReferences for ModuleOpens {
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        visit(&mut self.open);
+        self.to.visit_constants(visit)
+    }
+}
+
+impl ConstantReferences for ModuleProvides {
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        visit(&mut self.provide);
+        self.with.visit_constants(visit)
+    }
+}
+
+/// A `Module` attribute with its names resolved, packages and classes keep their internal names such as
+/// `java/lang`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ModuleInfo<'a> {
+    pub name: &'a str,
+    pub flags: ModuleFlags,
+    pub version: Option<&'a str>,
+    pub requires: Vec<Requires<'a>>,
+    pub exports: Vec<PackageAccess<'a>>,
+    pub opens: Vec<PackageAccess<'a>>,
+    /// Services the module looks up
+    pub uses: Vec<&'a str>,
+    pub provides: Vec<Provides<'a>>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Requires<'a> {
+    pub module: &'a str,
+    pub flags: ModuleDependencyFlags,
+    pub version: Option<&'a str>,
+}
+
+/// An exported or opened package, `to` is empty if every module has access
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PackageAccess<'a> {
+    pub package: &'a str,
+    pub flags: ModuleDependencyFlags,
+    pub to: Vec<&'a str>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Provides<'a> {
+    pub service: &'a str,
+    pub implementations: Vec<&'a str>,
+}
+
+impl<'a> ModuleInfo<'a> {
+    /// Resolves the names of a `Module` attribute, `None` for other attributes
+    pub fn resolve(attribute: &'a Attribute, constant_pool: &'a ConstantPool) -> Result<Option<Self>, ConstantPoolError> {
+        let (module_name, flags, module_version, requires, exports, opens, uses, provides) = match attribute {
+            Attribute::Module {
+                module_name,
+                flags,
+                module_version,
+                requires,
+                exports,
+                opens,
+                uses,
+                provides,
+                ..
+            } => (module_name, flags, module_version, requires, exports, opens, uses, provides),
+            _ => return Ok(None),
+        };
+
+        let version = |index: ConstantPoolIndex| match index.0 {
+            0 => Ok(None),
+            _ => constant_pool.resolve_utf8(index).map(Some),
+        };
+        let modules = |indices: &[ConstantPoolIndex]| {
+            indices
+                .iter()
+                .map(|index| constant_pool.resolve_module_name(*index))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let classes = |indices: &[ConstantPoolIndex]| {
+            indices
+                .iter()
+                .map(|index| constant_pool.resolve_class_name(*index))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        Ok(Some(ModuleInfo {
+            name: constant_pool.resolve_module_name(*module_name)?,
+            flags: *flags,
+            version: version(*module_version)?,
+            requires: requires
+                .iter()
+                .map(|entry| {
+                    Ok(Requires {
+                        module: constant_pool.resolve_module_name(entry.requires)?,
+                        flags: entry.flags,
+                        version: version(entry.version)?,
+                    })
+                })
+                .collect::<Result<_, ConstantPoolError>>()?,
+            exports: exports
+                .iter()
+                .map(|entry| {
+                    Ok(PackageAccess {
+                        package: constant_pool.resolve_package_name(entry.export)?,
+                        flags: entry.flags,
+                        to: modules(&entry.to)?,
+                    })
+                })
+                .collect::<Result<_, ConstantPoolError>>()?,
+            opens: opens
+                .iter()
+                .map(|entry| {
+                    Ok(PackageAccess {
+                        package: constant_pool.resolve_package_name(entry.open)?,
+                        flags: entry.flags,
+                        to: modules(&entry.to)?,
+                    })
+                })
+                .collect::<Result<_, ConstantPoolError>>()?,
+            uses: classes(uses)?,
+            provides: provides
+                .iter()
+                .map(|entry| {
+                    Ok(Provides {
+                        service: constant_pool.resolve_class_name(entry.provide)?,
+                        implementations: classes(&entry.with)?,
+                    })
+                })
+                .collect::<Result<_, ConstantPoolError>>()?,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::attribute::{Attribute, ModuleExports, ModuleOpens, ModuleProvides, ModuleRequires, PackageAccess};
+    use crate::{ConstantPoolIndex, Error, JavaClass, ModuleDependencyFlags, ModuleFlags};
+
+    /// Compiled by javac 17 from `module-info.java` next to it
+    const MODULE_INFO: &[u8] = include_bytes!("../../tests/resources/module-info.class");
+
+    #[test]
+    fn check_module_tables() -> Result<(), Error> {
+        let class = JavaClass::read(&mut &MODULE_INFO[..])?;
+
+        match &class.attributes[1] {
+            Attribute::Module {
+                requires,
+                exports,
+                opens,
+                uses,
+                provides,
+                ..
+            } => {
+                assert_eq!(requires.len(), 4);
+                assert_eq!(requires[1].flags(), ModuleDependencyFlags::TRANSITIVE);
+                assert_eq!(exports.len(), 2);
+                assert_eq!(exports[1].to().len(), 2);
+                assert_eq!(opens.len(), 1);
+                assert_eq!(uses.len(), 2);
+                assert_eq!(provides[0].with().len(), 2);
+            }
+            attribute => panic!("Expected a module, got {:?}", attribute),
+        }
+
+        let mut buffer = Vec::new();
+        class.write(&mut buffer)?;
+        assert_eq!(buffer, MODULE_INFO);
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_module_info() -> Result<(), Error> {
+        let class = JavaClass::read(&mut &MODULE_INFO[..])?;
+        let module = class.module_info()?.unwrap();
+
+        assert_eq!(module.name, "com.example.app");
+        assert_eq!(module.flags, ModuleFlags::empty());
+        assert_eq!(module.version, None);
+        assert_eq!(
+            module
+                .requires
+                .iter()
+                .map(|requires| (requires.module, requires.flags))
+                .collect::<Vec<_>>(),
+            vec![
+                ("java.base", ModuleDependencyFlags::MANDATED),
+                ("java.logging", ModuleDependencyFlags::TRANSITIVE),
+                ("java.sql", ModuleDependencyFlags::STATIC_PHASE),
+                ("java.desktop", ModuleDependencyFlags::empty()),
+            ]
+        );
+        assert!(module.requires.iter().all(|requires| requires.version == Some("17.0.15")));
+        assert_eq!(
+            module.exports,
+            vec![
+                PackageAccess {
+                    package: "com/example/app/api",
+                    flags: ModuleDependencyFlags::empty(),
+                    to: vec![],
+                },
+                PackageAccess {
+                    package: "com/example/app/spi",
+                    flags: ModuleDependencyFlags::empty(),
+                    to: vec!["java.logging", "java.desktop"],
+                },
+            ]
+        );
+        assert_eq!(module.opens[0].package, "com/example/app/internal");
+        assert_eq!(module.opens[0].to, vec!["java.desktop"]);
+        assert_eq!(module.uses, vec!["com/example/app/spi/Plugin", "java/sql/Driver"]);
+        assert_eq!(module.provides[0].service, "com/example/app/spi/Plugin");
+        assert_eq!(
+            module.provides[0].implementations,
+            vec!["com/example/app/internal/DefaultPlugin", "com/example/app/internal/OtherPlugin"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn check_module_accessors() {
+        let requires = ModuleRequires::new(ConstantPoolIndex(1), ModuleDependencyFlags::TRANSITIVE, ConstantPoolIndex(0));
+        assert_eq!((requires.requires(), requires.version()), (ConstantPoolIndex(1), ConstantPoolIndex(0)));
+        assert_eq!(ModuleExports::new(ConstantPoolIndex(2), ModuleDependencyFlags::empty(), vec![ConstantPoolIndex(1)]).to(), &[ConstantPoolIndex(1)]);
+        assert_eq!(ModuleOpens::new(ConstantPoolIndex(3), ModuleDependencyFlags::SYNTHETIC, vec![]).open(), ConstantPoolIndex(3));
+        assert_eq!(ModuleProvides::new(ConstantPoolIndex(4), vec![ConstantPoolIndex(5), ConstantPoolIndex(6)]).with().len(), 2);
+    }
 }
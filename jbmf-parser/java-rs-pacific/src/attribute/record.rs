@@ -0,0 +1,139 @@
+use std::io::{Read, Write};
+
+use java_rs_base::constant_pool::ConstantPoolIndex;
+use java_rs_base::error::{ConstantPoolError, Error};
+use java_rs_base::io::{AttributeLocation, ClassFilePart, ReadContext, SizedVec, WriteContext};
+
+use crate::attribute::{Attribute, ConstantReferences};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RecordComponent {
+    pub name: ConstantPoolIndex,
+    pub descriptor: ConstantPoolIndex,
+    pub attributes: SizedVec<u16, Attribute>,
+}
+
+impl ClassFilePart for RecordComponent {
+    /// Nested attributes are read in the record component location
+    fn read<R: Read>(reader: &mut R, ctx: &ReadContext) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        let name = ClassFilePart::read(reader, ctx)?;
+        let descriptor = ClassFilePart::read(reader, ctx)?;
+        let ctx = ReadContext {
+            location: Some(&AttributeLocation::RecordComponent),
+            ..*ctx
+        };
+        let attributes = ClassFilePart::read(reader, &ctx)?;
+
+        Ok(RecordComponent {
+            name,
+            descriptor,
+            attributes,
+        })
+    }
+
+    fn write<W: Write>(&self, writer: &mut W, ctx: &WriteContext) -> Result<(), Error> {
+        self.name.write(writer, ctx)?;
+        self.descriptor.write(writer, ctx)?;
+        self.attributes.write(writer, ctx)
+    }
+}
+
+impl ConstantReferences for RecordComponent {
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        visit(&mut self.name);
+        visit(&mut self.descriptor);
+        self.attributes.visit_constants(visit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::{BufReader, BufWriter};
+
+    use crate::attribute::{Attribute, RecordComponent};
+    use crate::helper::*;
+    use crate::{AccessFlags, Constant, ConstantPoolIndex, Error, JavaClass, JavaVersion, MagicNumber, SizedVec};
+
+    fn record(version: JavaVersion) -> JavaClass {
+        JavaClass {
+            magic: MagicNumber::Cafebabe,
+            version,
+            constant_pool: vec![
+                Constant::Class(ConstantPoolIndex(3)),
+                Constant::Class(ConstantPoolIndex(4)),
+                Constant::Utf8("Shape".into()),
+                Constant::Utf8("java/lang/Record".into()),
+                Constant::Utf8("Record".into()),
+                Constant::Utf8("points".into()),
+                Constant::Utf8("Ljava/util/List;".into()),
+                Constant::Utf8("Signature".into()),
+                Constant::Utf8("Ljava/util/List<LPoint;>;".into()),
+                Constant::Utf8("RuntimeVisibleAnnotations".into()),
+                Constant::Utf8("PermittedSubclasses".into()),
+                Constant::Class(ConstantPoolIndex(13)),
+                Constant::Utf8("Square".into()),
+            ]
+            .into(),
+            access_flags: AccessFlags::PUBLIC | AccessFlags::FINAL | AccessFlags::SUPER,
+            this_class: ConstantPoolIndex(1),
+            super_class: ConstantPoolIndex(2),
+            interfaces: SizedVec::new(),
+            fields: SizedVec::new(),
+            methods: SizedVec::new(),
+            attributes: vec![
+                Attribute::Record {
+                    name: ConstantPoolIndex(5),
+                    components: vec![RecordComponent {
+                        name: ConstantPoolIndex(6),
+                        descriptor: ConstantPoolIndex(7),
+                        attributes: vec![
+                            Attribute::Signature {
+                                name: ConstantPoolIndex(8),
+                                signature: ConstantPoolIndex(9),
+                            },
+                            Attribute::RuntimeVisibleAnnotations {
+                                name: ConstantPoolIndex(10),
+                                annotations: SizedVec::new(),
+                            },
+                        ]
+                        .into(),
+                    }]
+                    .into(),
+                },
+                Attribute::PermittedSubclasses {
+                    name: ConstantPoolIndex(11),
+                    classes: vec![ConstantPoolIndex(12)].into(),
+                },
+            ]
+            .into(),
+        }
+    }
+
+    #[test]
+    fn check_record() -> Result<(), Error> {
+        let (path, _guard) = init_tmp_dir("RecordTest.class");
+
+        let reference = record(JavaVersion { major: 61, minor: 0 });
+        reference.write(&mut BufWriter::new(File::create(&path)?))?;
+        assert_eq!(reference, JavaClass::read(&mut BufReader::new(File::open(path)?))?);
+        Ok(())
+    }
+
+    #[test]
+    fn check_unsupported_record() -> Result<(), Error> {
+        let mut buffer = Vec::new();
+        record(JavaVersion { major: 59, minor: 0 }).write(&mut buffer)?;
+
+        let class = JavaClass::read(&mut buffer.as_slice())?;
+        match &class.attributes[0] {
+            Attribute::Unsupported(record) => assert!(matches!(**record, Attribute::Record { .. })),
+            attribute => panic!("Expected an unsupported record, got {:?}", attribute),
+        }
+        assert!(matches!(class.attributes[1], Attribute::Unsupported(_)));
+        Ok(())
+    }
+}
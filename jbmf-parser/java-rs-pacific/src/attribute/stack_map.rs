@@ -1,9 +1,11 @@
 use std::io::{Read, Write};
 
 use java_rs_base::constant_pool::ConstantPoolIndex;
-use java_rs_base::error::Error;
+use java_rs_base::error::{ConstantPoolError, Error};
 use java_rs_base::io::{ClassFilePart, ReadContext, SizedVec, WriteContext};
 
+use crate::attribute::ConstantReferences;
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum VerificationTypeInfo {
     Top,
@@ -217,3 +219,28 @@ impl StackMapFrame {
         }
     }
 }
+
+impl ConstantReferences for VerificationTypeInfo {
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        if let VerificationTypeInfo::Object { index } = self {
+            visit(index);
+        }
+        Ok(())
+    }
+}
+
+impl ConstantReferences for StackMapFrame {
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        match self {
+            StackMapFrame::SameLocals1StackItem { stack, .. } | StackMapFrame::SameLocals1StackItemExtended { stack, .. } => {
+                stack.visit_constants(visit)
+            }
+            StackMapFrame::Append { locals, .. } => locals.visit_constants(visit),
+            StackMapFrame::Full { locals, stack, .. } => {
+                locals.visit_constants(visit)?;
+                stack.visit_constants(visit)
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -1,11 +1,12 @@
 use std::io::{Read, Write};
 
-use java_rs_base::error::Error;
+use java_rs_base::error::{ConstantPoolError, Error};
 use java_rs_base::io::{ClassFilePart, ReadContext, SizedVec, WriteContext};
 use java_rs_derive::ClassFilePart;
 
-use crate::attribute::annotation::ElementValuePair;
-use crate::ConstantPoolIndex;
+use crate::attribute::annotation::{Annotation, AnnotationInfo, ElementValuePair};
+use crate::attribute::ConstantReferences;
+use crate::{ConstantPool, ConstantPoolIndex};
 
 #[derive(Debug, ClassFilePart, Clone, Eq, PartialEq)]
 pub struct TypeAnnotation {
@@ -15,16 +16,63 @@ pub struct TypeAnnotation {
     element_value_pairs: SizedVec<u16, ElementValuePair>,
 }
 
+impl ConstantReferences for TypeAnnotation {
+    fn visit_constants(&mut self, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+        visit(&mut self.ty);
+        self.element_value_pairs.visit_constants(visit)
+    }
+}
+
+impl TypeAnnotation {
+    pub fn new(
+        target: Target,
+        target_path: TypePath,
+        ty: ConstantPoolIndex,
+        element_value_pairs: Vec<ElementValuePair>,
+    ) -> Self {
+        TypeAnnotation {
+            target,
+            target_path,
+            ty,
+            element_value_pairs: element_value_pairs.into(),
+        }
+    }
+
+    pub fn target(&self) -> &Target {
+        &self.target
+    }
+
+    pub fn target_path(&self) -> &TypePath {
+        &self.target_path
+    }
+
+    /// Index of the annotation interface's field descriptor
+    pub fn ty(&self) -> ConstantPoolIndex {
+        self.ty
+    }
+
+    pub fn element_value_pairs(&self) -> &[ElementValuePair] {
+        &self.element_value_pairs
+    }
+
+    /// Resolves the annotation type and its element values, the target is available through [`Target::info`]
+    pub fn resolve(&self, constant_pool: &ConstantPool) -> Result<AnnotationInfo, Error> {
+        Annotation::new(self.ty, self.element_value_pairs.to_vec()).resolve(constant_pool)
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Target {
     TypeParameter {
         target_type: u8,
+        index: u8,
     },
     Supertype {
         supertype: u16,
     },
     TypeParameterBound {
         target_type: u8,
+        type_parameter: u8,
         bound: u8,
     },
     Empty {
@@ -49,6 +97,7 @@ pub enum Target {
     },
     TypeArgument {
         target_type: u8,
+        offset: u16,
         type_argument: u8,
     },
     Raw {
@@ -72,12 +121,16 @@ impl ClassFilePart for Target {
         let target_type = u8::read(reader, ctx)?;
 
         match target_type {
-            0x00 | 0x01 => Ok(Self::TypeParameter { target_type }),
+            0x00 | 0x01 => Ok(Self::TypeParameter {
+                target_type,
+                index: ClassFilePart::read(reader, ctx)?,
+            }),
             0x10 => Ok(Self::Supertype {
                 supertype: ClassFilePart::read(reader, ctx)?,
             }),
             0x11 | 0x12 => Ok(Self::TypeParameterBound {
                 target_type,
+                type_parameter: ClassFilePart::read(reader, ctx)?,
                 bound: ClassFilePart::read(reader, ctx)?,
             }),
             0x13 | 0x14 | 0x15 => Ok(Self::Empty { target_type }),
@@ -100,6 +153,7 @@ impl ClassFilePart for Target {
             }),
             0x47 | 0x48 | 0x49 | 0x4A | 0x4B => Ok(Self::TypeArgument {
                 target_type,
+                offset: ClassFilePart::read(reader, ctx)?,
                 type_argument: ClassFilePart::read(reader, ctx)?,
             }),
             _ => Err(Error::UnknownTargetType(target_type)),
@@ -108,22 +162,30 @@ impl ClassFilePart for Target {
 
     fn write<W: Write>(&self, writer: &mut W, ctx: &WriteContext) -> Result<(), Error> {
         match self {
-            Self::TypeParameter { target_type } => target_type.write(writer, ctx)?,
+            Self::TypeParameter { target_type, index } => {
+                target_type.write(writer, ctx)?;
+                index.write(writer, ctx)?;
+            }
             Self::Supertype { supertype } => {
-                0x10.write(writer, ctx)?;
+                0x10u8.write(writer, ctx)?;
                 supertype.write(writer, ctx)?;
             }
-            Self::TypeParameterBound { target_type, bound } => {
+            Self::TypeParameterBound {
+                target_type,
+                type_parameter,
+                bound,
+            } => {
                 target_type.write(writer, ctx)?;
+                type_parameter.write(writer, ctx)?;
                 bound.write(writer, ctx)?;
             }
             Self::Empty { target_type } => target_type.write(writer, ctx)?,
             Self::FormalParameter { formal_parameter } => {
-                0x16.write(writer, ctx)?;
+                0x16u8.write(writer, ctx)?;
                 formal_parameter.write(writer, ctx)?;
             }
             Self::Throws { throws_type } => {
-                0x17.write(writer, ctx)?;
+                0x17u8.write(writer, ctx)?;
                 throws_type.write(writer, ctx)?;
             }
             Self::Localvar { target_type, table } => {
@@ -131,7 +193,7 @@ impl ClassFilePart for Target {
                 table.write(writer, ctx)?;
             }
             Self::Catch { exception_table } => {
-                0x42.write(writer, ctx)?;
+                0x42u8.write(writer, ctx)?;
                 exception_table.write(writer, ctx)?;
             }
             Self::Offset { target_type, offset } => {
@@ -140,9 +202,11 @@ impl ClassFilePart for Target {
             }
             Self::TypeArgument {
                 target_type,
+                offset,
                 type_argument,
             } => {
                 target_type.write(writer, ctx)?;
+                offset.write(writer, ctx)?;
                 type_argument.write(writer, ctx)?;
             }
             Self::Raw { target_type, data } => {
@@ -156,6 +220,284 @@ impl ClassFilePart for Target {
     }
 }
 
+/// What a type annotation annotates, see JVMS 4.7.20.1. Positions are byte offsets into the code of the
+/// enclosing method, or labels when the annotation is part of an [`InstructionList`](crate::instruction_list)
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum TargetInfo<P = u16> {
+    /// A type parameter of the class, or of the method if `method` is set
+    TypeParameter { method: bool, index: u8 },
+    /// An interface by its index in `interfaces`, `None` for the superclass
+    Supertype(Option<u16>),
+    TypeParameterBound { method: bool, type_parameter: u8, bound: u8 },
+    Field,
+    /// The return type of a method or the type of a newly constructed object
+    Return,
+    Receiver,
+    /// A parameter by its index in the descriptor, or in the `Signature` if the method has one
+    FormalParameter(u8),
+    /// An exception by its index in the `Exceptions` attribute
+    Throws(u16),
+    /// The live ranges of a local variable, or of a resource variable if `resource` is set
+    LocalVariable { resource: bool, ranges: Vec<LocalVariableRange<P>> },
+    /// An exception parameter by its index in the exception table
+    Catch(u16),
+    InstanceOf(P),
+    New(P),
+    ConstructorReference(P),
+    MethodReference(P),
+    /// `type_argument` is the index of the type in a cast to an intersection type
+    Cast { position: P, type_argument: u8 },
+    ConstructorInvocationTypeArgument { position: P, type_argument: u8 },
+    MethodInvocationTypeArgument { position: P, type_argument: u8 },
+    ConstructorReferenceTypeArgument { position: P, type_argument: u8 },
+    MethodReferenceTypeArgument { position: P, type_argument: u8 },
+}
+
+/// The variable in local slot `index` lives from `start` up to `end`
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct LocalVariableRange<P = u16> {
+    pub start: P,
+    pub end: P,
+    pub index: u16,
+}
+
+impl Target {
+    pub fn info(&self) -> Result<TargetInfo, Error> {
+        Ok(match self {
+            Target::TypeParameter { target_type, index } => TargetInfo::TypeParameter {
+                method: *target_type == 0x01,
+                index: *index,
+            },
+            Target::Supertype { supertype: 0xFFFF } => TargetInfo::Supertype(None),
+            Target::Supertype { supertype } => TargetInfo::Supertype(Some(*supertype)),
+            Target::TypeParameterBound {
+                target_type,
+                type_parameter,
+                bound,
+            } => TargetInfo::TypeParameterBound {
+                method: *target_type == 0x12,
+                type_parameter: *type_parameter,
+                bound: *bound,
+            },
+            Target::Empty { target_type: 0x13 } => TargetInfo::Field,
+            Target::Empty { target_type: 0x14 } => TargetInfo::Return,
+            Target::Empty { .. } => TargetInfo::Receiver,
+            Target::FormalParameter { formal_parameter } => TargetInfo::FormalParameter(*formal_parameter),
+            Target::Throws { throws_type } => TargetInfo::Throws(*throws_type),
+            Target::Localvar { target_type, table } => TargetInfo::LocalVariable {
+                resource: *target_type == 0x41,
+                ranges: table
+                    .iter()
+                    .map(|entry| LocalVariableRange {
+                        start: entry.start_pc,
+                        end: entry.start_pc.saturating_add(entry.length),
+                        index: entry.index,
+                    })
+                    .collect(),
+            },
+            Target::Catch { exception_table } => TargetInfo::Catch(*exception_table),
+            Target::Offset { target_type, offset } => match target_type {
+                0x43 => TargetInfo::InstanceOf(*offset),
+                0x44 => TargetInfo::New(*offset),
+                0x45 => TargetInfo::ConstructorReference(*offset),
+                _ => TargetInfo::MethodReference(*offset),
+            },
+            Target::TypeArgument {
+                target_type,
+                offset,
+                type_argument,
+            } => {
+                let (position, type_argument) = (*offset, *type_argument);
+                match target_type {
+                    0x47 => TargetInfo::Cast { position, type_argument },
+                    0x48 => TargetInfo::ConstructorInvocationTypeArgument { position, type_argument },
+                    0x49 => TargetInfo::MethodInvocationTypeArgument { position, type_argument },
+                    0x4A => TargetInfo::ConstructorReferenceTypeArgument { position, type_argument },
+                    _ => TargetInfo::MethodReferenceTypeArgument { position, type_argument },
+                }
+            }
+            Target::Raw { target_type, .. } => return Err(Error::UnknownTargetType(*target_type)),
+        })
+    }
+}
+
+impl<P: Copy> TargetInfo<P> {
+    /// Converts every position, e.g. from byte offsets to labels
+    pub fn map_positions<Q, E>(&self, mut map: impl FnMut(P) -> Result<Q, E>) -> Result<TargetInfo<Q>, E> {
+        Ok(match self {
+            TargetInfo::TypeParameter { method, index } => TargetInfo::TypeParameter {
+                method: *method,
+                index: *index,
+            },
+            TargetInfo::Supertype(index) => TargetInfo::Supertype(*index),
+            TargetInfo::TypeParameterBound {
+                method,
+                type_parameter,
+                bound,
+            } => TargetInfo::TypeParameterBound {
+                method: *method,
+                type_parameter: *type_parameter,
+                bound: *bound,
+            },
+            TargetInfo::Field => TargetInfo::Field,
+            TargetInfo::Return => TargetInfo::Return,
+            TargetInfo::Receiver => TargetInfo::Receiver,
+            TargetInfo::FormalParameter(index) => TargetInfo::FormalParameter(*index),
+            TargetInfo::Throws(index) => TargetInfo::Throws(*index),
+            TargetInfo::LocalVariable { resource, ranges } => TargetInfo::LocalVariable {
+                resource: *resource,
+                ranges: ranges
+                    .iter()
+                    .map(|range| {
+                        Ok(LocalVariableRange {
+                            start: map(range.start)?,
+                            end: map(range.end)?,
+                            index: range.index,
+                        })
+                    })
+                    .collect::<Result<_, E>>()?,
+            },
+            TargetInfo::Catch(index) => TargetInfo::Catch(*index),
+            TargetInfo::InstanceOf(position) => TargetInfo::InstanceOf(map(*position)?),
+            TargetInfo::New(position) => TargetInfo::New(map(*position)?),
+            TargetInfo::ConstructorReference(position) => TargetInfo::ConstructorReference(map(*position)?),
+            TargetInfo::MethodReference(position) => TargetInfo::MethodReference(map(*position)?),
+            TargetInfo::Cast {
+                position,
+                type_argument,
+            } => TargetInfo::Cast {
+                position: map(*position)?,
+                type_argument: *type_argument,
+            },
+            TargetInfo::ConstructorInvocationTypeArgument {
+                position,
+                type_argument,
+            } => TargetInfo::ConstructorInvocationTypeArgument {
+                position: map(*position)?,
+                type_argument: *type_argument,
+            },
+            TargetInfo::MethodInvocationTypeArgument {
+                position,
+                type_argument,
+            } => TargetInfo::MethodInvocationTypeArgument {
+                position: map(*position)?,
+                type_argument: *type_argument,
+            },
+            TargetInfo::ConstructorReferenceTypeArgument {
+                position,
+                type_argument,
+            } => TargetInfo::ConstructorReferenceTypeArgument {
+                position: map(*position)?,
+                type_argument: *type_argument,
+            },
+            TargetInfo::MethodReferenceTypeArgument {
+                position,
+                type_argument,
+            } => TargetInfo::MethodReferenceTypeArgument {
+                position: map(*position)?,
+                type_argument: *type_argument,
+            },
+        })
+    }
+
+    /// Every position the target refers to
+    pub fn positions(&self) -> Vec<P> {
+        let mut positions = Vec::new();
+        let _ = self.map_positions(|position| {
+            positions.push(position);
+            Ok::<_, ()>(())
+        });
+        positions
+    }
+}
+
+impl TargetInfo {
+    pub fn to_target(&self) -> Target {
+        let type_argument = |target_type: u8, offset: &u16, type_argument: &u8| Target::TypeArgument {
+            target_type,
+            offset: *offset,
+            type_argument: *type_argument,
+        };
+
+        match self {
+            TargetInfo::TypeParameter { method, index } => Target::TypeParameter {
+                target_type: if *method { 0x01 } else { 0x00 },
+                index: *index,
+            },
+            TargetInfo::Supertype(index) => Target::Supertype {
+                supertype: index.unwrap_or(0xFFFF),
+            },
+            TargetInfo::TypeParameterBound {
+                method,
+                type_parameter,
+                bound,
+            } => Target::TypeParameterBound {
+                target_type: if *method { 0x12 } else { 0x11 },
+                type_parameter: *type_parameter,
+                bound: *bound,
+            },
+            TargetInfo::Field => Target::Empty { target_type: 0x13 },
+            TargetInfo::Return => Target::Empty { target_type: 0x14 },
+            TargetInfo::Receiver => Target::Empty { target_type: 0x15 },
+            TargetInfo::FormalParameter(index) => Target::FormalParameter {
+                formal_parameter: *index,
+            },
+            TargetInfo::Throws(index) => Target::Throws { throws_type: *index },
+            TargetInfo::LocalVariable { resource, ranges } => Target::Localvar {
+                target_type: if *resource { 0x41 } else { 0x40 },
+                table: ranges
+                    .iter()
+                    .map(|range| LocalvarTable {
+                        start_pc: range.start,
+                        length: range.end.saturating_sub(range.start),
+                        index: range.index,
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+            },
+            TargetInfo::Catch(index) => Target::Catch {
+                exception_table: *index,
+            },
+            TargetInfo::InstanceOf(offset) => Target::Offset {
+                target_type: 0x43,
+                offset: *offset,
+            },
+            TargetInfo::New(offset) => Target::Offset {
+                target_type: 0x44,
+                offset: *offset,
+            },
+            TargetInfo::ConstructorReference(offset) => Target::Offset {
+                target_type: 0x45,
+                offset: *offset,
+            },
+            TargetInfo::MethodReference(offset) => Target::Offset {
+                target_type: 0x46,
+                offset: *offset,
+            },
+            TargetInfo::Cast {
+                position,
+                type_argument: index,
+            } => type_argument(0x47, position, index),
+            TargetInfo::ConstructorInvocationTypeArgument {
+                position,
+                type_argument: index,
+            } => type_argument(0x48, position, index),
+            TargetInfo::MethodInvocationTypeArgument {
+                position,
+                type_argument: index,
+            } => type_argument(0x49, position, index),
+            TargetInfo::ConstructorReferenceTypeArgument {
+                position,
+                type_argument: index,
+            } => type_argument(0x4A, position, index),
+            TargetInfo::MethodReferenceTypeArgument {
+                position,
+                type_argument: index,
+            } => type_argument(0x4B, position, index),
+        }
+    }
+}
+
 #[derive(Debug, ClassFilePart, Clone, Eq, PartialEq)]
 pub struct TypePath(SizedVec<u8, Path>);
 
@@ -164,3 +506,252 @@ pub struct Path {
     type_path_kind: u8,
     type_argument_index: u8,
 }
+
+/// A step from a type into one of its parts
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TypePathStep {
+    /// Into the component type of an array
+    Array,
+    /// Into the next more deeply nested class of a class type
+    Nested,
+    /// Into the bound of a wildcard
+    WildcardBound,
+    /// Into a type argument of a parameterized type
+    TypeArgument(u8),
+}
+
+impl TypePath {
+    pub fn new(steps: &[TypePathStep]) -> Self {
+        let path = steps.iter().map(|step| {
+            let (type_path_kind, type_argument_index) = match step {
+                TypePathStep::Array => (0, 0),
+                TypePathStep::Nested => (1, 0),
+                TypePathStep::WildcardBound => (2, 0),
+                TypePathStep::TypeArgument(index) => (3, *index),
+            };
+            Path {
+                type_path_kind,
+                type_argument_index,
+            }
+        });
+        TypePath(path.collect::<Vec<_>>().into())
+    }
+
+    pub fn steps(&self) -> Result<Vec<TypePathStep>, Error> {
+        self.0
+            .iter()
+            .map(|path| match path.type_path_kind {
+                0 => Ok(TypePathStep::Array),
+                1 => Ok(TypePathStep::Nested),
+                2 => Ok(TypePathStep::WildcardBound),
+                3 => Ok(TypePathStep::TypeArgument(path.type_argument_index)),
+                kind => Err(Error::UnknownTypePathKind(kind)),
+            })
+            .collect()
+    }
+
+    /// The part of a field signature or descriptor the path points at, e.g. `Ljava/lang/String;` for
+    /// `[TypeArgument(0), Array]` in `Ljava/util/List<[Ljava/lang/String;>;`. Type arguments keep their
+    /// wildcard indicator. Nested steps follow the `.` separated inner classes of a signature and end on the
+    /// inner class, e.g. `Inner<TT;>`. `None` if the path does not fit the type
+    pub fn locate<'a>(&self, signature: &'a str) -> Option<&'a str> {
+        let mut current = signature;
+        let mut nested = 0;
+
+        for step in self.steps().ok()? {
+            match step {
+                TypePathStep::Array if nested == 0 => current = current.strip_prefix('[')?,
+                TypePathStep::WildcardBound if nested == 0 => current = current.strip_prefix(['+', '-'])?,
+                TypePathStep::Nested => nested += 1,
+                TypePathStep::TypeArgument(index) => {
+                    current = type_arguments(class_segment(current, nested)?)?.nth(index as usize)?;
+                    nested = 0;
+                }
+                _ => return None,
+            }
+        }
+
+        match nested {
+            0 => Some(current),
+            _ => class_segment(current, nested),
+        }
+    }
+}
+
+/// The `nested`th of the `.` separated classes of a class type signature
+fn class_segment(signature: &str, nested: usize) -> Option<&str> {
+    let body = signature.strip_prefix('L')?.strip_suffix(';')?;
+    let mut depth = 0;
+    let mut start = 0;
+    let mut segments = Vec::new();
+    for (position, c) in body.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            '.' if depth == 0 => {
+                segments.push(&body[start..position]);
+                start = position + 1;
+            }
+            _ => {}
+        }
+    }
+    segments.push(&body[start..]);
+    segments.get(nested).copied()
+}
+
+fn type_arguments(segment: &str) -> Option<impl Iterator<Item = &str>> {
+    let mut arguments = segment[segment.find('<')? + 1..].strip_suffix('>')?;
+    Some(std::iter::from_fn(move || {
+        let length = signature_length(arguments)?;
+        let (argument, rest) = arguments.split_at(length);
+        arguments = rest;
+        Some(argument)
+    }))
+}
+
+/// Length of the type signature or type argument at the start of `signature`
+fn signature_length(signature: &str) -> Option<usize> {
+    match signature.chars().next()? {
+        'B' | 'C' | 'D' | 'F' | 'I' | 'J' | 'S' | 'Z' | 'V' | '*' => Some(1),
+        '+' | '-' | '[' => Some(1 + signature_length(&signature[1..])?),
+        'T' => Some(signature.find(';')? + 1),
+        'L' => {
+            let mut depth = 0;
+            for (position, c) in signature.char_indices() {
+                match c {
+                    '<' => depth += 1,
+                    '>' => depth -= 1,
+                    ';' if depth == 0 => return Some(position + 1),
+                    _ => {}
+                }
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::attribute::{AnnotationElement, Attribute, LocalVariableRange, TargetInfo, TypePath, TypePathStep};
+    use crate::{Error, JavaClass};
+
+    /// Compiled by javac 17 from `TypeAnnotated.java` next to it
+    const TYPE_ANNOTATED: &[u8] = include_bytes!("../../tests/resources/TypeAnnotated.class");
+
+    /// The target, path and value of every visible type annotation
+    fn type_annotations(class: &JavaClass, attributes: &[Attribute]) -> Vec<(TargetInfo, Vec<TypePathStep>, i32)> {
+        attributes
+            .iter()
+            .filter_map(|attribute| match attribute {
+                Attribute::RuntimeVisibleTypeAnnotations { annotations, .. } => Some(annotations.iter()),
+                _ => None,
+            })
+            .flatten()
+            .map(|annotation| {
+                let value = match annotation.resolve(&class.constant_pool).unwrap().get("value") {
+                    Some(AnnotationElement::Int(value)) => *value,
+                    value => panic!("Expected an int, got {:?}", value),
+                };
+                (annotation.target().info().unwrap(), annotation.target_path().steps().unwrap(), value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn resolve_type_annotation_targets() -> Result<(), Error> {
+        let class = JavaClass::read(&mut &TYPE_ANNOTATED[..])?;
+
+        assert_eq!(
+            type_annotations(&class, &class.fields[0].attributes),
+            vec![
+                (TargetInfo::Field, vec![TypePathStep::TypeArgument(0)], 2),
+                (TargetInfo::Field, vec![TypePathStep::TypeArgument(0), TypePathStep::Array], 1),
+            ]
+        );
+        assert_eq!(
+            type_annotations(&class, &class.methods[1].attributes),
+            vec![(TargetInfo::Throws(0), vec![], 4), (TargetInfo::FormalParameter(0), vec![], 3)]
+        );
+        let code = match &class.methods[1].attributes[0] {
+            Attribute::Code { attributes, .. } => attributes,
+            attribute => panic!("Expected code, got {:?}", attribute),
+        };
+        assert_eq!(
+            type_annotations(&class, code),
+            vec![
+                (TargetInfo::New(0), vec![], 6),
+                (TargetInfo::InstanceOf(9), vec![], 7),
+                (
+                    TargetInfo::Cast {
+                        position: 17,
+                        type_argument: 0
+                    },
+                    vec![],
+                    8
+                ),
+                (
+                    TargetInfo::LocalVariable {
+                        resource: false,
+                        ranges: vec![LocalVariableRange {
+                            start: 8,
+                            end: 28,
+                            index: 3
+                        }]
+                    },
+                    vec![],
+                    5
+                ),
+            ]
+        );
+
+        let mut buffer = Vec::new();
+        class.write(&mut buffer)?;
+        assert_eq!(buffer, TYPE_ANNOTATED);
+        Ok(())
+    }
+
+    #[test]
+    fn locate_type_path() {
+        let path = |steps: &[TypePathStep]| TypePath::new(steps);
+        let signature = "Ljava/util/Map<+[Ljava/lang/String;TV;>;";
+
+        assert_eq!(path(&[]).locate(signature), Some(signature));
+        assert_eq!(
+            path(&[TypePathStep::TypeArgument(0), TypePathStep::WildcardBound, TypePathStep::Array]).locate(signature),
+            Some("Ljava/lang/String;")
+        );
+        assert_eq!(path(&[TypePathStep::TypeArgument(1)]).locate(signature), Some("TV;"));
+        assert_eq!(path(&[TypePathStep::TypeArgument(2)]).locate(signature), None);
+        assert_eq!(path(&[TypePathStep::Array]).locate(signature), None);
+
+        let inner = "La/Outer<TT;>.Inner<*>;";
+        assert_eq!(path(&[TypePathStep::Nested]).locate(inner), Some("Inner<*>"));
+        assert_eq!(path(&[TypePathStep::Nested, TypePathStep::TypeArgument(0)]).locate(inner), Some("*"));
+        assert_eq!(path(&[TypePathStep::TypeArgument(0)]).locate(inner), Some("TT;"));
+
+        let steps = [TypePathStep::Nested, TypePathStep::TypeArgument(3), TypePathStep::WildcardBound];
+        assert_eq!(path(&steps).steps().unwrap(), steps);
+    }
+
+    #[test]
+    fn convert_targets() {
+        let targets = vec![
+            TargetInfo::TypeParameter { method: true, index: 1 },
+            TargetInfo::Supertype(None),
+            TargetInfo::Receiver,
+            TargetInfo::MethodReferenceTypeArgument {
+                position: 7,
+                type_argument: 2,
+            },
+            TargetInfo::LocalVariable {
+                resource: true,
+                ranges: vec![LocalVariableRange { start: 2, end: 9, index: 1 }],
+            },
+        ];
+
+        for target in targets {
+            assert_eq!(target.to_target().info().unwrap(), target);
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use java_rs_base::error::ConstantPoolError;
+
+use crate::attribute::ConstantReferences;
+use crate::{Constant, ConstantPool, ConstantPoolIndex, JavaClass};
+
+/// Drops every constant which is not reachable from the class and rewrites all indices to the compacted pool.
+///
+/// Constants keep their relative order, so `ldc` operands never grow past a byte. Unknown and custom attributes
+/// are assumed to only refer to their name, attributes kept as raw data because they failed to parse can not be
+/// remapped and fail the compaction. The class is left unchanged on errors.
+///
+/// Returns the number of removed constant pool slots
+pub fn compact_constant_pool(class: &mut JavaClass) -> Result<usize, ConstantPoolError> {
+    let reachable = reachable_constants(class)?;
+
+    let mut remapped = vec![0; reachable.len() + 1];
+    let mut next = 1;
+    for (slot, keep) in reachable.iter().enumerate() {
+        if *keep {
+            remapped[slot + 1] = next;
+            next += 1;
+        }
+    }
+    let remap = &mut |index: &mut ConstantPoolIndex| index.0 = remapped[index.0 as usize];
+
+    let constants = std::mem::take(&mut class.constant_pool.0);
+    let removed = constants.len() - (next as usize - 1);
+    class.constant_pool = ConstantPool(
+        constants
+            .into_iter()
+            .zip(reachable)
+            .filter(|(_, keep)| *keep)
+            .map(|(mut constant, _)| {
+                visit_constant(&mut constant, remap);
+                constant
+            })
+            .collect(),
+    );
+    visit_class(class, remap)?;
+
+    Ok(removed)
+}
+
+/// Marks the slots of every constant referenced by the class, directly or through other constants
+fn reachable_constants(class: &mut JavaClass) -> Result<Vec<bool>, ConstantPoolError> {
+    let mut pending = Vec::new();
+    visit_class(class, &mut |index| pending.push(index.0))?;
+
+    let constants = &mut class.constant_pool.0;
+    let mut reachable = vec![false; constants.len()];
+    while let Some(index) = pending.pop() {
+        if index == 0 {
+            continue;
+        }
+        let slot = index as usize - 1;
+        match constants.get_mut(slot) {
+            None | Some(Constant::Unusable) => return Err(ConstantPoolError::InvalidIndex(index)),
+            Some(Constant::Raw { .. }) => return Err(ConstantPoolError::UnparsedConstant(index)),
+            Some(_) if reachable[slot] => {}
+            Some(constant) => {
+                reachable[slot] = true;
+                // The slot after a long or double is part of the constant
+                if let Constant::Long(_) | Constant::Double(_) = constant {
+                    if let Some(next) = reachable.get_mut(slot + 1) {
+                        *next = true;
+                    }
+                }
+                visit_constant(constant, &mut |index| pending.push(index.0));
+            }
+        }
+    }
+
+    Ok(reachable)
+}
+
+fn visit_class(class: &mut JavaClass, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) -> Result<(), ConstantPoolError> {
+    visit(&mut class.this_class);
+    visit(&mut class.super_class);
+    class.interfaces.visit_constants(visit)?;
+    for field in class.fields.iter_mut() {
+        visit(&mut field.name);
+        visit(&mut field.descriptor);
+        field.attributes.visit_constants(visit)?;
+    }
+    for method in class.methods.iter_mut() {
+        visit(&mut method.name);
+        visit(&mut method.descriptor);
+        method.attributes.visit_constants(visit)?;
+    }
+    class.attributes.visit_constants(visit)
+}
+
+fn visit_constant(constant: &mut Constant, visit: &mut dyn FnMut(&mut ConstantPoolIndex)) {
+    match constant {
+        Constant::Class(index)
+        | Constant::String(index)
+        | Constant::MethodType(index)
+        | Constant::Module(index)
+        | Constant::Package(index)
+        | Constant::MethodHandle { reference: index, .. }
+        | Constant::Dynamic {
+            name_and_type: index, ..
+        }
+        | Constant::InvokeDynamic {
+            name_and_type: index, ..
+        } => visit(index),
+        Constant::FieldRef { class, name_and_type }
+        | Constant::MethodRef { class, name_and_type }
+        | Constant::InterfaceMethodRef { class, name_and_type } => {
+            visit(class);
+            visit(name_and_type);
+        }
+        Constant::NameAndType { name, descriptor } => {
+            visit(name);
+            visit(descriptor);
+        }
+        Constant::Unsupported(constant) => visit_constant(constant, visit),
+        Constant::Utf8(_)
+        | Constant::Integer(_)
+        | Constant::Float(_)
+        | Constant::Long(_)
+        | Constant::Double(_)
+        | Constant::Raw { .. }
+        | Constant::InvalidUtf8(_)
+        | Constant::Unusable => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use java_rs_base::error::ConstantPoolError;
+
+    use crate::attribute::{Attribute, Compatibility, Instruction, RawAttribute, SmallConstantPoolIndex};
+    use crate::{AccessFlags, Constant, ConstantPoolIndex, Error, JavaClass, JavaVersion, MagicNumber, Method, SizedVec};
+
+    fn class() -> JavaClass {
+        JavaClass {
+            magic: MagicNumber::Cafebabe,
+            version: JavaVersion { major: 52, minor: 0 },
+            constant_pool: vec![
+                Constant::Class(ConstantPoolIndex(3)),
+                Constant::Utf8("Decoy".into()),
+                Constant::Utf8("Compacted".into()),
+                Constant::Long(7),
+                Constant::Unusable,
+                Constant::Long(42),
+                Constant::Unusable,
+                Constant::Utf8("m".into()),
+                Constant::Utf8("()J".into()),
+                Constant::Utf8("Code".into()),
+                Constant::Utf8("unused".into()),
+                Constant::String(ConstantPoolIndex(14)),
+                Constant::NameAndType {
+                    name: ConstantPoolIndex(2),
+                    descriptor: ConstantPoolIndex(11),
+                },
+                Constant::Utf8("text".into()),
+            ]
+            .into(),
+            access_flags: AccessFlags::PUBLIC,
+            this_class: ConstantPoolIndex(1),
+            super_class: ConstantPoolIndex(0),
+            interfaces: SizedVec::new(),
+            fields: SizedVec::new(),
+            methods: vec![Method {
+                access_flags: AccessFlags::STATIC,
+                name: ConstantPoolIndex(8),
+                descriptor: ConstantPoolIndex(9),
+                attributes: vec![Attribute::Code {
+                    name: ConstantPoolIndex(10),
+                    max_stack: Compatibility::Current(3),
+                    max_locals: Compatibility::Current(0),
+                    code: Compatibility::Current(
+                        vec![
+                            Instruction::LDC { index: SmallConstantPoolIndex(12) },
+                            Instruction::Pop,
+                            Instruction::LDC2W { index: ConstantPoolIndex(6) },
+                            Instruction::LReturn,
+                        ]
+                        .into(),
+                    ),
+                    exception_table: SizedVec::new(),
+                    attributes: SizedVec::new(),
+                }]
+                .into(),
+            }]
+            .into(),
+            attributes: SizedVec::new(),
+        }
+    }
+
+    #[test]
+    fn drops_unreferenced_constants() -> Result<(), Error> {
+        let mut class = class();
+        assert_eq!(class.compact_constant_pool()?, 5);
+
+        assert_eq!(
+            class.constant_pool.0,
+            vec![
+                Constant::Class(ConstantPoolIndex(2)),
+                Constant::Utf8("Compacted".into()),
+                Constant::Long(42),
+                Constant::Unusable,
+                Constant::Utf8("m".into()),
+                Constant::Utf8("()J".into()),
+                Constant::Utf8("Code".into()),
+                Constant::String(ConstantPoolIndex(9)),
+                Constant::Utf8("text".into()),
+            ]
+        );
+        assert_eq!((class.this_class, class.super_class), (ConstantPoolIndex(1), ConstantPoolIndex(0)));
+        let method = &class.methods[0];
+        assert_eq!((method.name, method.descriptor), (ConstantPoolIndex(5), ConstantPoolIndex(6)));
+        match &method.attributes[0] {
+            Attribute::Code {
+                name,
+                code: Compatibility::Current(code),
+                ..
+            } => {
+                assert_eq!(*name, ConstantPoolIndex(7));
+                assert_eq!(code[0], Instruction::LDC { index: SmallConstantPoolIndex(8) });
+                assert_eq!(code[2], Instruction::LDC2W { index: ConstantPoolIndex(3) });
+            }
+            attribute => panic!("Expected code, got {:?}", attribute),
+        }
+
+        let mut buffer = Vec::new();
+        class.write(&mut buffer)?;
+        assert_eq!(JavaClass::read(&mut buffer.as_slice())?, class);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unparsed_attributes() {
+        let mut class = self::class();
+        class.attributes.push(Attribute::Raw(RawAttribute {
+            name: ConstantPoolIndex(11),
+            info: vec![0, 2].into(),
+        }));
+
+        assert_eq!(class.compact_constant_pool(), Err(ConstantPoolError::UnparsedAttribute(11)));
+        assert_eq!(class.constant_pool, self::class().constant_pool);
+    }
+}
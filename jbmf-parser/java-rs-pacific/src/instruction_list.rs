@@ -0,0 +1,979 @@
+use std::convert::TryFrom;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use java_rs_base::error::Error;
+use java_rs_base::io::{ClassFilePart, SizedVec, WriteContext};
+
+use crate::analysis::instruction_positions;
+use crate::attribute::{
+    Attribute, Compatibility, ElementValuePair, ExceptionTable, Instruction, LineNumberTable, LocalVariableTable,
+    LocalVariableTypeTable, MatchOffsetPair, TargetInfo, TypeAnnotation, TypePath,
+};
+use crate::{ConstantPool, ConstantPoolBuilder, ConstantPoolIndex};
+
+/// The code of a method has to be shorter than 65536 bytes
+const MAX_CODE_LENGTH: u64 = u16::MAX as u64;
+
+/// A position in an instruction list which stays valid while instructions are inserted or removed
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct Label(pub u32);
+
+/// The instructions jumping to a single label. `goto` and `jsr` are lowered to their wide forms and
+/// conditional jumps are inverted around a `goto_w` when their target is out of reach
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum JumpKind {
+    Goto,
+    Jsr,
+    IfEq,
+    IfNe,
+    IfLt,
+    IfGe,
+    IfGt,
+    IfLe,
+    IfICmpEq,
+    IfICmpNe,
+    IfICmpLt,
+    IfICmpGe,
+    IfICmpGt,
+    IfICmpLe,
+    IfACmpEq,
+    IfACmpNe,
+    IfNull,
+    IfNonNull,
+}
+
+impl JumpKind {
+    /// The condition jumping exactly when this one does not, `None` for unconditional jumps
+    pub fn negate(self) -> Option<Self> {
+        use JumpKind::*;
+
+        let negated = match self {
+            Goto | Jsr => return None,
+            IfEq => IfNe,
+            IfNe => IfEq,
+            IfLt => IfGe,
+            IfGe => IfLt,
+            IfGt => IfLe,
+            IfLe => IfGt,
+            IfICmpEq => IfICmpNe,
+            IfICmpNe => IfICmpEq,
+            IfICmpLt => IfICmpGe,
+            IfICmpGe => IfICmpLt,
+            IfICmpGt => IfICmpLe,
+            IfICmpLe => IfICmpGt,
+            IfACmpEq => IfACmpNe,
+            IfACmpNe => IfACmpEq,
+            IfNull => IfNonNull,
+            IfNonNull => IfNull,
+        };
+
+        Some(negated)
+    }
+
+    fn instruction(self, offset: i16) -> Instruction {
+        match self {
+            JumpKind::Goto => Instruction::Goto { offset },
+            JumpKind::Jsr => Instruction::JSR { offset },
+            JumpKind::IfEq => Instruction::IfEq { offset },
+            JumpKind::IfNe => Instruction::IfNe { offset },
+            JumpKind::IfLt => Instruction::IfLt { offset },
+            JumpKind::IfGe => Instruction::IfGe { offset },
+            JumpKind::IfGt => Instruction::IfGt { offset },
+            JumpKind::IfLe => Instruction::IfLe { offset },
+            JumpKind::IfICmpEq => Instruction::IfICmpEq { offset },
+            JumpKind::IfICmpNe => Instruction::IfICmpNe { offset },
+            JumpKind::IfICmpLt => Instruction::IfICmpLt { offset },
+            JumpKind::IfICmpGe => Instruction::IfICmpGe { offset },
+            JumpKind::IfICmpGt => Instruction::IfICmpGt { offset },
+            JumpKind::IfICmpLe => Instruction::IfICmpLe { offset },
+            JumpKind::IfACmpEq => Instruction::IfACmpEq { offset },
+            JumpKind::IfACmpNe => Instruction::IfACmpNe { offset },
+            JumpKind::IfNull => Instruction::IfNull { offset },
+            JumpKind::IfNonNull => Instruction::IfNonNull { offset },
+        }
+    }
+
+    /// Splits a jump instruction into its kind and relative offset
+    fn of(instruction: &Instruction) -> Option<(Self, i64)> {
+        let jump = match instruction {
+            Instruction::GotoW { offset } => return Some((JumpKind::Goto, *offset as i64)),
+            Instruction::JSRW { offset } => return Some((JumpKind::Jsr, *offset as i64)),
+            Instruction::Goto { offset } => (JumpKind::Goto, offset),
+            Instruction::JSR { offset } => (JumpKind::Jsr, offset),
+            Instruction::IfEq { offset } => (JumpKind::IfEq, offset),
+            Instruction::IfNe { offset } => (JumpKind::IfNe, offset),
+            Instruction::IfLt { offset } => (JumpKind::IfLt, offset),
+            Instruction::IfGe { offset } => (JumpKind::IfGe, offset),
+            Instruction::IfGt { offset } => (JumpKind::IfGt, offset),
+            Instruction::IfLe { offset } => (JumpKind::IfLe, offset),
+            Instruction::IfICmpEq { offset } => (JumpKind::IfICmpEq, offset),
+            Instruction::IfICmpNe { offset } => (JumpKind::IfICmpNe, offset),
+            Instruction::IfICmpLt { offset } => (JumpKind::IfICmpLt, offset),
+            Instruction::IfICmpGe { offset } => (JumpKind::IfICmpGe, offset),
+            Instruction::IfICmpGt { offset } => (JumpKind::IfICmpGt, offset),
+            Instruction::IfICmpLe { offset } => (JumpKind::IfICmpLe, offset),
+            Instruction::IfACmpEq { offset } => (JumpKind::IfACmpEq, offset),
+            Instruction::IfACmpNe { offset } => (JumpKind::IfACmpNe, offset),
+            Instruction::IfNull { offset } => (JumpKind::IfNull, offset),
+            Instruction::IfNonNull { offset } => (JumpKind::IfNonNull, offset),
+            _ => return None,
+        };
+
+        Some((jump.0, *jump.1 as i64))
+    }
+}
+
+/// An element of an instruction list
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Node {
+    /// Marks the position of the following instruction
+    Label(Label),
+    /// An instruction without branch targets. Wide local variable instructions are preceded by their
+    /// own `Wide` instruction
+    Instruction(Instruction),
+    Jump {
+        kind: JumpKind,
+        target: Label,
+    },
+    TableSwitch {
+        default: Label,
+        low: i32,
+        /// Targets of the keys `low`, `low + 1`, ...
+        targets: Vec<Label>,
+    },
+    LookUpSwitch {
+        default: Label,
+        pairs: Vec<(i32, Label)>,
+    },
+}
+
+/// An exception table entry, the range from `start` up to `end` is protected
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TryCatchBlock {
+    pub start: Label,
+    pub end: Label,
+    pub handler: Label,
+    /// Index of the caught class, zero for handlers which catch every exception
+    pub catch_type: ConstantPoolIndex,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct LineNumber {
+    pub start: Label,
+    pub line_number: u16,
+}
+
+/// An entry of the `LocalVariableTable`, or of the `LocalVariableTypeTable` if `descriptor` is a signature
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct LocalVariable {
+    pub start: Label,
+    pub end: Label,
+    pub name: ConstantPoolIndex,
+    pub descriptor: ConstantPoolIndex,
+    pub index: u16,
+}
+
+/// A type annotation on an instruction, exception parameter or local variable of the code. Exception
+/// parameters are referred to by their index in [`InstructionList::try_catch_blocks`]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CodeTypeAnnotation {
+    /// Kept in `RuntimeVisibleTypeAnnotations` rather than `RuntimeInvisibleTypeAnnotations`
+    pub visible: bool,
+    pub target: TargetInfo<Label>,
+    pub target_path: TypePath,
+    pub ty: ConstantPoolIndex,
+    pub element_value_pairs: Vec<ElementValuePair>,
+}
+
+/// Code whose branch targets and debug information refer to labels instead of byte offsets, so it can
+/// be edited freely. Offsets are computed again when the list is written back into a code attribute
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct InstructionList {
+    pub nodes: Vec<Node>,
+    pub try_catch_blocks: Vec<TryCatchBlock>,
+    pub line_numbers: Vec<LineNumber>,
+    pub local_variables: Vec<LocalVariable>,
+    pub local_variable_types: Vec<LocalVariable>,
+    pub type_annotations: Vec<CodeTypeAnnotation>,
+    next_label: u32,
+}
+
+/// The contents of a code attribute computed from an instruction list
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LoweredCode {
+    pub instructions: Vec<Instruction>,
+    pub exception_table: Vec<ExceptionTable>,
+    pub line_numbers: Vec<LineNumberTable>,
+    pub local_variables: Vec<LocalVariableTable>,
+    pub local_variable_types: Vec<LocalVariableTypeTable>,
+    pub visible_type_annotations: Vec<TypeAnnotation>,
+    pub invisible_type_annotations: Vec<TypeAnnotation>,
+    /// Byte position of every placed label
+    pub labels: HashMap<Label, u64>,
+    /// Byte length of the code
+    pub length: u64,
+}
+
+impl InstructionList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a label which is not placed yet
+    pub fn new_label(&mut self) -> Label {
+        let label = Label(self.next_label);
+        self.next_label += 1;
+        label
+    }
+
+    /// The first instruction placed after `label`, e.g. the `new` or `checkcast` a type annotation refers to
+    pub fn instruction_at(&self, label: Label) -> Option<&Node> {
+        self.nodes
+            .iter()
+            .skip_while(|node| **node != Node::Label(label))
+            .find(|node| !matches!(node, Node::Label(_)))
+    }
+
+    /// Reads the instructions, exception table, debug tables and type annotations of a code attribute
+    pub fn from_code(attribute: &Attribute) -> Result<Self, Error> {
+        match attribute {
+            Attribute::Code {
+                code,
+                exception_table,
+                attributes,
+                ..
+            } => {
+                let code = match code {
+                    Compatibility::PreJava1(code) => code.as_slice(),
+                    Compatibility::Current(code) => code.as_slice(),
+                };
+                Self::read(code, exception_table, attributes)
+            }
+            _ => Err(Error::InvalidCode {
+                position: 0,
+                reason: "attribute is not a code attribute".to_string(),
+            }),
+        }
+    }
+
+    pub fn read(
+        code: &[Instruction],
+        exception_table: &[ExceptionTable],
+        attributes: &[Attribute],
+    ) -> Result<Self, Error> {
+        let (positions, length) = instruction_positions(code)?;
+        let mut targets = BTreeSet::new();
+
+        for (instruction, position) in code.iter().zip(positions.iter()) {
+            let target = |offset: i64| (*position as i64 + offset) as u64;
+
+            match instruction {
+                Instruction::TableSwitch { default, offsets, .. } => {
+                    targets.insert(target(*default as i64));
+                    targets.extend(offsets.iter().map(|offset| target(*offset as i64)));
+                }
+                Instruction::LookUpSwitch { default, pairs } => {
+                    targets.insert(target(*default as i64));
+                    targets.extend(pairs.iter().map(|pair| target(pair.offset as i64)));
+                }
+                instruction => {
+                    if let Some((_, offset)) = JumpKind::of(instruction) {
+                        targets.insert(target(offset));
+                    }
+                }
+            }
+        }
+
+        for entry in exception_table {
+            targets.extend([entry.start_pc as u64, entry.end_pc as u64, entry.handler_pc as u64]);
+        }
+
+        let mut line_numbers = Vec::new();
+        let mut local_variables = Vec::new();
+        let mut local_variable_types = Vec::new();
+        let mut type_annotations = Vec::new();
+        for attribute in attributes {
+            match attribute {
+                Attribute::LineNumberTable { line_numbers: entries, .. } => {
+                    line_numbers.extend(entries.iter().map(|entry| (entry.start_pc as u64, entry.line_number)));
+                }
+                Attribute::LocalVariableTable {
+                    local_variables: entries,
+                    ..
+                } => local_variables.extend(entries.iter().map(|entry| {
+                    let start = entry.start_pc as u64;
+                    (start, start + entry.length as u64, entry.name, entry.descriptor, entry.index)
+                })),
+                Attribute::LocalVariableTypeTable {
+                    local_variable_type_table: entries,
+                    ..
+                } => local_variable_types.extend(entries.iter().map(|entry| {
+                    let start = entry.start_pc as u64;
+                    (start, start + entry.length as u64, entry.name, entry.signature, entry.index)
+                })),
+                Attribute::RuntimeVisibleTypeAnnotations { annotations, .. } => {
+                    for annotation in annotations.iter() {
+                        type_annotations.push((true, annotation.target().info()?, annotation));
+                    }
+                }
+                Attribute::RuntimeInvisibleTypeAnnotations { annotations, .. } => {
+                    for annotation in annotations.iter() {
+                        type_annotations.push((false, annotation.target().info()?, annotation));
+                    }
+                }
+                _ => {}
+            }
+        }
+        targets.extend(line_numbers.iter().map(|(start, _)| *start));
+        for (start, end, ..) in local_variables.iter().chain(local_variable_types.iter()) {
+            targets.extend([*start, *end]);
+        }
+        for (_, target, _) in type_annotations.iter() {
+            targets.extend(target.positions().into_iter().map(u64::from));
+        }
+
+        let boundaries: HashSet<u64> = positions.iter().copied().chain([length]).collect();
+        if let Some(target) = targets.iter().find(|target| !boundaries.contains(target)) {
+            return Err(Error::InvalidCode {
+                position: *target,
+                reason: "referenced position is not the start of an instruction".to_string(),
+            });
+        }
+
+        let mut list = InstructionList::new();
+        let labels: HashMap<u64, Label> = targets.iter().map(|position| (*position, list.new_label())).collect();
+        let label = |position: u64| labels[&position];
+
+        for (instruction, position) in code.iter().zip(positions.iter()) {
+            let target = |offset: i64| label((*position as i64 + offset) as u64);
+
+            if let Some(label) = labels.get(position) {
+                list.nodes.push(Node::Label(*label));
+            }
+
+            list.nodes.push(match instruction {
+                Instruction::TableSwitch {
+                    default, low, offsets, ..
+                } => Node::TableSwitch {
+                    default: target(*default as i64),
+                    low: *low,
+                    targets: offsets.iter().map(|offset| target(*offset as i64)).collect(),
+                },
+                Instruction::LookUpSwitch { default, pairs } => Node::LookUpSwitch {
+                    default: target(*default as i64),
+                    pairs: pairs
+                        .iter()
+                        .map(|pair| (pair.match_value, target(pair.offset as i64)))
+                        .collect(),
+                },
+                instruction => match JumpKind::of(instruction) {
+                    Some((kind, offset)) => Node::Jump {
+                        kind,
+                        target: target(offset),
+                    },
+                    None => Node::Instruction(instruction.clone()),
+                },
+            });
+        }
+        if let Some(label) = labels.get(&length) {
+            list.nodes.push(Node::Label(*label));
+        }
+
+        list.try_catch_blocks = exception_table
+            .iter()
+            .map(|entry| TryCatchBlock {
+                start: label(entry.start_pc as u64),
+                end: label(entry.end_pc as u64),
+                handler: label(entry.handler_pc as u64),
+                catch_type: entry.catch_type,
+            })
+            .collect();
+        list.line_numbers = line_numbers
+            .into_iter()
+            .map(|(start, line_number)| LineNumber {
+                start: label(start),
+                line_number,
+            })
+            .collect();
+        let local_variable = |(start, end, name, descriptor, index)| LocalVariable {
+            start: label(start),
+            end: label(end),
+            name,
+            descriptor,
+            index,
+        };
+        list.local_variables = local_variables.into_iter().map(local_variable).collect();
+        list.local_variable_types = local_variable_types.into_iter().map(local_variable).collect();
+        list.type_annotations = type_annotations
+            .into_iter()
+            .map(|(visible, target, annotation)| {
+                Ok(CodeTypeAnnotation {
+                    visible,
+                    target: target.map_positions(|position| Ok::<_, Error>(label(position as u64)))?,
+                    target_path: annotation.target_path().clone(),
+                    ty: annotation.ty(),
+                    element_value_pairs: annotation.element_value_pairs().to_vec(),
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(list)
+    }
+
+    /// Computes the byte offsets of every label and encodes the instructions. Jumps start out in their
+    /// short form and are widened until every offset fits. Code longer than 65535 bytes is rejected
+    pub fn lower(&self) -> Result<LoweredCode, Error> {
+        let mut wide = HashSet::new();
+
+        let Layout {
+            positions,
+            labels,
+            length,
+        } = loop {
+            let layout = self.layout(&wide)?;
+            let Layout { positions, labels, .. } = &layout;
+            let mut changed = false;
+
+            for (index, node) in self.nodes.iter().enumerate() {
+                if let Node::Jump { target, .. } = node {
+                    let offset = labels[target] as i64 - positions[index] as i64;
+                    if !wide.contains(&index) && i16::try_from(offset).is_err() {
+                        wide.insert(index);
+                        changed = true;
+                    }
+                }
+            }
+
+            if !changed {
+                break layout;
+            }
+        };
+        if length > MAX_CODE_LENGTH {
+            return Err(Error::InvalidCode {
+                position: length,
+                reason: format!("code is {} bytes long, at most {} are allowed", length, MAX_CODE_LENGTH),
+            });
+        }
+
+        let mut instructions = Vec::with_capacity(self.nodes.len());
+        for (index, node) in self.nodes.iter().enumerate() {
+            let position = positions[index];
+            let offset = |label: &Label| -> Result<i32, Error> {
+                i32::try_from(labels[label] as i64 - position as i64).map_err(|_| Error::InvalidCode {
+                    position,
+                    reason: "jump offset exceeds 32 bits".to_string(),
+                })
+            };
+
+            match node {
+                Node::Label(_) => {}
+                Node::Instruction(instruction) => instructions.push(instruction.clone()),
+                Node::Jump { kind, target } if wide.contains(&index) => match kind.negate() {
+                    Some(negated) => {
+                        instructions.push(negated.instruction(8));
+                        instructions.push(Instruction::GotoW {
+                            offset: offset(target)? - 3,
+                        });
+                    }
+                    None if *kind == JumpKind::Jsr => instructions.push(Instruction::JSRW {
+                        offset: offset(target)?,
+                    }),
+                    None => instructions.push(Instruction::GotoW {
+                        offset: offset(target)?,
+                    }),
+                },
+                Node::Jump { kind, target } => instructions.push(kind.instruction(offset(target)? as i16)),
+                Node::TableSwitch { default, low, targets } => instructions.push(Instruction::TableSwitch {
+                    default: offset(default)?,
+                    low: *low,
+                    high: low + targets.len() as i32 - 1,
+                    offsets: targets.iter().map(offset).collect::<Result<Vec<_>, Error>>()?.into(),
+                }),
+                Node::LookUpSwitch { default, pairs } => {
+                    let mut pairs = pairs.clone();
+                    pairs.sort_by_key(|(key, _)| *key);
+
+                    instructions.push(Instruction::LookUpSwitch {
+                        default: offset(default)?,
+                        pairs: pairs
+                            .iter()
+                            .map(|(key, label)| {
+                                Ok(MatchOffsetPair {
+                                    match_value: *key,
+                                    offset: offset(label)?,
+                                })
+                            })
+                            .collect::<Result<Vec<_>, Error>>()?
+                            .into(),
+                    })
+                }
+            }
+        }
+
+        let position = |label: &Label| -> Result<u16, Error> {
+            u16::try_from(labels[label]).map_err(|_| Error::InvalidCode {
+                position: labels[label],
+                reason: "position in a table exceeds 16 bits".to_string(),
+            })
+        };
+
+        let exception_table = self
+            .try_catch_blocks
+            .iter()
+            .map(|block| {
+                Ok(ExceptionTable {
+                    start_pc: position(&block.start)?,
+                    end_pc: position(&block.end)?,
+                    handler_pc: position(&block.handler)?,
+                    catch_type: block.catch_type,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let line_numbers = self
+            .line_numbers
+            .iter()
+            .map(|line| {
+                Ok(LineNumberTable {
+                    start_pc: position(&line.start)?,
+                    line_number: line.line_number,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let local_variables = self
+            .local_variables
+            .iter()
+            .map(|variable| {
+                let start = position(&variable.start)?;
+                Ok(LocalVariableTable {
+                    start_pc: start,
+                    length: position(&variable.end)?.saturating_sub(start),
+                    name: variable.name,
+                    descriptor: variable.descriptor,
+                    index: variable.index,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let local_variable_types = self
+            .local_variable_types
+            .iter()
+            .map(|variable| {
+                let start = position(&variable.start)?;
+                Ok(LocalVariableTypeTable {
+                    start_pc: start,
+                    length: position(&variable.end)?.saturating_sub(start),
+                    name: variable.name,
+                    signature: variable.descriptor,
+                    index: variable.index,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let mut visible_type_annotations = Vec::new();
+        let mut invisible_type_annotations = Vec::new();
+        for annotation in self.type_annotations.iter() {
+            let lowered = TypeAnnotation::new(
+                annotation.target.map_positions(|label| position(&label))?.to_target(),
+                annotation.target_path.clone(),
+                annotation.ty,
+                annotation.element_value_pairs.clone(),
+            );
+            match annotation.visible {
+                true => visible_type_annotations.push(lowered),
+                false => invisible_type_annotations.push(lowered),
+            }
+        }
+
+        Ok(LoweredCode {
+            instructions,
+            exception_table,
+            line_numbers,
+            local_variables,
+            local_variable_types,
+            visible_type_annotations,
+            invisible_type_annotations,
+            labels,
+            length,
+        })
+    }
+
+    /// Replaces the instructions, exception table, debug tables and type annotations of a code attribute.
+    /// Missing attributes are created, their names are added to the constant pool. `max_stack`,
+    /// `max_locals` and the stack map frames are left as they are
+    pub fn write_code(&self, attribute: &mut Attribute, constant_pool: &mut ConstantPool) -> Result<(), Error> {
+        let lowered = self.lower()?;
+
+        let (code, exception_table, attributes) = match attribute {
+            Attribute::Code {
+                code,
+                exception_table,
+                attributes,
+                ..
+            } => (code, exception_table, attributes),
+            _ => {
+                return Err(Error::InvalidCode {
+                    position: 0,
+                    reason: "attribute is not a code attribute".to_string(),
+                })
+            }
+        };
+
+        *code = match code {
+            Compatibility::PreJava1(_) => Compatibility::PreJava1(lowered.instructions.into()),
+            Compatibility::Current(_) => Compatibility::Current(lowered.instructions.into()),
+        };
+        *exception_table = lowered.exception_table.into();
+
+        let mut constants = ConstantPoolBuilder::new(constant_pool);
+        let mut name = |attributes: &SizedVec<u16, Attribute>, kind: &str| {
+            let existing = attributes.iter().find_map(|attribute| match (attribute, kind) {
+                (Attribute::LineNumberTable { name, .. }, "LineNumberTable")
+                | (Attribute::LocalVariableTable { name, .. }, "LocalVariableTable")
+                | (Attribute::LocalVariableTypeTable { name, .. }, "LocalVariableTypeTable")
+                | (Attribute::RuntimeVisibleTypeAnnotations { name, .. }, "RuntimeVisibleTypeAnnotations")
+                | (Attribute::RuntimeInvisibleTypeAnnotations { name, .. }, "RuntimeInvisibleTypeAnnotations") => {
+                    Some(*name)
+                }
+                _ => None,
+            });
+            match existing {
+                Some(name) => Ok(name),
+                None => constants.utf8(kind),
+            }
+        };
+        let line_number_name = match lowered.line_numbers.is_empty() {
+            true => None,
+            false => Some(name(attributes, "LineNumberTable")?),
+        };
+        let local_variable_name = match lowered.local_variables.is_empty() {
+            true => None,
+            false => Some(name(attributes, "LocalVariableTable")?),
+        };
+        let local_variable_type_name = match lowered.local_variable_types.is_empty() {
+            true => None,
+            false => Some(name(attributes, "LocalVariableTypeTable")?),
+        };
+        let visible_type_annotations_name = match lowered.visible_type_annotations.is_empty() {
+            true => None,
+            false => Some(name(attributes, "RuntimeVisibleTypeAnnotations")?),
+        };
+        let invisible_type_annotations_name = match lowered.invisible_type_annotations.is_empty() {
+            true => None,
+            false => Some(name(attributes, "RuntimeInvisibleTypeAnnotations")?),
+        };
+
+        let mut rewritten: Vec<Attribute> = attributes
+            .iter()
+            .filter(|attribute| {
+                !matches!(
+                    attribute,
+                    Attribute::LineNumberTable { .. }
+                        | Attribute::LocalVariableTable { .. }
+                        | Attribute::LocalVariableTypeTable { .. }
+                        | Attribute::RuntimeVisibleTypeAnnotations { .. }
+                        | Attribute::RuntimeInvisibleTypeAnnotations { .. }
+                )
+            })
+            .cloned()
+            .collect();
+
+        if let Some(name) = line_number_name {
+            rewritten.push(Attribute::LineNumberTable {
+                name,
+                line_numbers: lowered.line_numbers.into(),
+            });
+        }
+        if let Some(name) = local_variable_name {
+            rewritten.push(Attribute::LocalVariableTable {
+                name,
+                local_variables: lowered.local_variables.into(),
+            });
+        }
+        if let Some(name) = local_variable_type_name {
+            rewritten.push(Attribute::LocalVariableTypeTable {
+                name,
+                local_variable_type_table: lowered.local_variable_types.into(),
+            });
+        }
+        if let Some(name) = visible_type_annotations_name {
+            rewritten.push(Attribute::RuntimeVisibleTypeAnnotations {
+                name,
+                annotations: lowered.visible_type_annotations.into(),
+            });
+        }
+        if let Some(name) = invisible_type_annotations_name {
+            rewritten.push(Attribute::RuntimeInvisibleTypeAnnotations {
+                name,
+                annotations: lowered.invisible_type_annotations.into(),
+            });
+        }
+        *attributes = rewritten.into();
+
+        Ok(())
+    }
+
+    fn layout(&self, wide: &HashSet<usize>) -> Result<Layout, Error> {
+        let mut positions = Vec::with_capacity(self.nodes.len());
+        let mut labels = HashMap::new();
+        let mut position = 0u64;
+        let mut buffer = Vec::new();
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            positions.push(position);
+            let padding = (4 - (position + 1) % 4) % 4;
+
+            position += match node {
+                Node::Label(label) => {
+                    if labels.insert(*label, position).is_some() {
+                        return Err(Error::InvalidCode {
+                            position,
+                            reason: format!("label {} is placed twice", label.0),
+                        });
+                    }
+                    0
+                }
+                Node::Instruction(instruction) => {
+                    buffer.clear();
+                    instruction.write(&mut buffer, &WriteContext { position: Some(position) })?;
+                    buffer.len() as u64
+                }
+                Node::Jump { kind, .. } if wide.contains(&index) => match kind.negate() {
+                    Some(_) => 8,
+                    None => 5,
+                },
+                Node::Jump { .. } => 3,
+                Node::TableSwitch { targets, .. } => 1 + padding + 12 + 4 * targets.len() as u64,
+                Node::LookUpSwitch { pairs, .. } => 1 + padding + 8 + 8 * pairs.len() as u64,
+            };
+        }
+
+        let mut referenced = Vec::new();
+        for node in self.nodes.iter() {
+            match node {
+                Node::Jump { target, .. } => referenced.push(*target),
+                Node::TableSwitch { default, targets, .. } => {
+                    referenced.push(*default);
+                    referenced.extend(targets.iter().copied());
+                }
+                Node::LookUpSwitch { default, pairs } => {
+                    referenced.push(*default);
+                    referenced.extend(pairs.iter().map(|(_, label)| *label));
+                }
+                _ => {}
+            }
+        }
+        for block in self.try_catch_blocks.iter() {
+            referenced.extend([block.start, block.end, block.handler]);
+        }
+        referenced.extend(self.line_numbers.iter().map(|line| line.start));
+        for variable in self.local_variables.iter().chain(self.local_variable_types.iter()) {
+            referenced.extend([variable.start, variable.end]);
+        }
+        for annotation in self.type_annotations.iter() {
+            referenced.extend(annotation.target.positions());
+        }
+
+        if let Some(label) = referenced.iter().find(|label| !labels.contains_key(label)) {
+            return Err(Error::InvalidCode {
+                position: 0,
+                reason: format!("label {} is referenced but not placed", label.0),
+            });
+        }
+
+        Ok(Layout {
+            positions,
+            labels,
+            length: position,
+        })
+    }
+}
+
+struct Layout {
+    /// Byte position of every node
+    positions: Vec<u64>,
+    labels: HashMap<Label, u64>,
+    length: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attribute::{LocalVariableRange, SizedIncrement, SizedIndex, SmallIndex};
+    use crate::{Constant, JavaClass};
+
+    #[test]
+    fn round_trip() -> Result<(), Error> {
+        let code = vec![
+            Instruction::ILoad0,
+            Instruction::IfEq { offset: 9 },
+            Instruction::IInc {
+                index: SizedIndex::Normal(SmallIndex(0)),
+                value: SizedIncrement::Normal(-1),
+            },
+            Instruction::Goto { offset: -7 },
+            Instruction::Return,
+        ];
+        let exception_table = vec![ExceptionTable {
+            start_pc: 0,
+            end_pc: 7,
+            handler_pc: 10,
+            catch_type: ConstantPoolIndex(0),
+        }];
+
+        let list = InstructionList::read(&code, &exception_table, &[])?;
+        let lowered = list.lower()?;
+
+        assert_eq!(lowered.instructions, code);
+        assert_eq!(lowered.exception_table, exception_table);
+        Ok(())
+    }
+
+    #[test]
+    fn insertion_moves_targets() -> Result<(), Error> {
+        let code = vec![
+            Instruction::ILoad0,
+            Instruction::IfEq { offset: 4 },
+            Instruction::Nop,
+            Instruction::Goto { offset: -4 },
+            Instruction::Return,
+        ];
+        let attributes = vec![Attribute::LineNumberTable {
+            name: ConstantPoolIndex(1),
+            line_numbers: vec![LineNumberTable {
+                start_pc: 8,
+                line_number: 3,
+            }]
+            .into(),
+        }];
+
+        let mut list = InstructionList::read(&code, &[], &attributes)?;
+        let nop = list
+            .nodes
+            .iter()
+            .position(|node| *node == Node::Instruction(Instruction::Nop))
+            .unwrap();
+        list.nodes.insert(nop, Node::Instruction(Instruction::Nop));
+        let lowered = list.lower()?;
+
+        assert_eq!(
+            lowered.instructions,
+            vec![
+                Instruction::ILoad0,
+                Instruction::IfEq { offset: 5 },
+                Instruction::Nop,
+                Instruction::Nop,
+                Instruction::Goto { offset: -5 },
+                Instruction::Return,
+            ]
+        );
+        assert_eq!(
+            lowered.line_numbers,
+            vec![LineNumberTable {
+                start_pc: 9,
+                line_number: 3
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn far_jumps_are_widened() -> Result<(), Error> {
+        let mut list = InstructionList::new();
+        let target = list.new_label();
+        list.nodes.push(Node::Jump {
+            kind: JumpKind::IfNull,
+            target,
+        });
+        list.nodes
+            .extend((0..40000).map(|_| Node::Instruction(Instruction::Nop)));
+        list.nodes.push(Node::Label(target));
+        list.nodes.push(Node::Instruction(Instruction::Return));
+
+        let lowered = list.lower()?;
+
+        assert_eq!(lowered.instructions[0], Instruction::IfNonNull { offset: 8 });
+        assert_eq!(lowered.instructions[1], Instruction::GotoW { offset: 40005 });
+        assert_eq!(lowered.labels[&target], 40008);
+        Ok(())
+    }
+
+    #[test]
+    fn reject_code_longer_than_65535_bytes() {
+        let mut list = InstructionList::new();
+        list.nodes
+            .extend((0..65536).map(|_| Node::Instruction(Instruction::Nop)));
+
+        assert!(matches!(list.lower(), Err(Error::InvalidCode { position: 65536, .. })));
+        list.nodes.pop();
+        assert_eq!(list.lower().map(|lowered| lowered.length).ok(), Some(65535));
+    }
+
+    #[test]
+    fn empty_tables_add_no_names() -> Result<(), Error> {
+        let mut code = Attribute::Code {
+            name: ConstantPoolIndex(1),
+            max_stack: Compatibility::Current(0),
+            max_locals: Compatibility::Current(0),
+            code: Compatibility::Current(vec![Instruction::Return].into()),
+            exception_table: Vec::new().into(),
+            attributes: Vec::new().into(),
+        };
+        let mut constant_pool = ConstantPool(vec![Constant::Utf8("Code".into())]);
+
+        InstructionList::from_code(&code)?.write_code(&mut code, &mut constant_pool)?;
+
+        assert_eq!(constant_pool.0.len(), 1);
+        assert!(matches!(code, Attribute::Code { attributes, .. } if attributes.is_empty()));
+        Ok(())
+    }
+
+    #[test]
+    fn type_annotations_follow_instructions() -> Result<(), Error> {
+        let mut class = JavaClass::read(&mut &include_bytes!("../tests/resources/TypeAnnotated.class")[..])?;
+        let code = &mut class.methods[1].attributes[0];
+
+        let mut list = InstructionList::from_code(code)?;
+        let targets: Vec<&TargetInfo<Label>> =
+            list.type_annotations.iter().map(|annotation| &annotation.target).collect();
+        let (new, cast) = match (targets[0], targets[2]) {
+            (TargetInfo::New(new), TargetInfo::Cast { position, .. }) => (*new, *position),
+            targets => panic!("Expected new and cast, got {:?}", targets),
+        };
+        assert!(matches!(list.instruction_at(new), Some(Node::Instruction(Instruction::New { .. }))));
+        assert!(matches!(list.instruction_at(cast), Some(Node::Instruction(Instruction::CheckCast { .. }))));
+
+        list.nodes.insert(0, Node::Instruction(Instruction::Nop));
+        list.write_code(code, &mut class.constant_pool)?;
+
+        let annotations = match code {
+            Attribute::Code { attributes, .. } => attributes.iter().find_map(|attribute| match attribute {
+                Attribute::RuntimeVisibleTypeAnnotations { annotations, .. } => Some(annotations),
+                _ => None,
+            }),
+            _ => None,
+        };
+        let targets: Vec<TargetInfo> = annotations
+            .unwrap()
+            .iter()
+            .map(|annotation| annotation.target().info())
+            .collect::<Result<_, _>>()?;
+        assert_eq!(
+            targets,
+            vec![
+                TargetInfo::New(1),
+                TargetInfo::InstanceOf(10),
+                TargetInfo::Cast {
+                    position: 18,
+                    type_argument: 0
+                },
+                TargetInfo::LocalVariable {
+                    resource: false,
+                    ranges: vec![LocalVariableRange {
+                        start: 9,
+                        end: 29,
+                        index: 3
+                    }]
+                },
+            ]
+        );
+        Ok(())
+    }
+}
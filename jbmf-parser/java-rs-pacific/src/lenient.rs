@@ -0,0 +1,555 @@
+use std::fmt::{Display, Formatter};
+use std::io::Cursor;
+
+use byteorder::{BigEndian, ByteOrder};
+use java_rs_base::error::Error;
+use java_rs_base::io::{AttributeLocation, ClassFilePart, ReadContext, SizedVec};
+
+use crate::attribute::{Attribute, Compatibility, RawAttribute};
+use crate::{
+    AccessFlags, Constant, ConstantPool, ConstantPoolIndex, Field, JavaClass, JavaVersion, MagicNumber, Method,
+};
+
+/// A problem found while reading a class leniently
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Diagnostic {
+    /// Byte offset of the broken part from the start of the class file
+    pub offset: u64,
+    pub kind: DiagnosticKind,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum DiagnosticKind {
+    InvalidMagic(u32),
+    /// The rest of the class can not be located, the constant is kept as `Constant::Raw` without info
+    UnknownConstantTag { index: u16, tag: u8 },
+    InvalidUtf8Constant(u16),
+    /// The constant could not be parsed, the rest of the class can not be located
+    MalformedConstant(u16),
+    /// The attribute could not be parsed and is kept as `Attribute::Raw`
+    MalformedAttribute(String),
+    /// The attribute name is not a valid UTF-8 constant
+    InvalidAttributeName(ConstantPoolIndex),
+    /// The attribute is not allowed on the class, field, method or code it is attached to
+    InvalidAttributeLocation(String),
+    /// The attribute requires a newer class file version
+    UnsupportedAttribute(String),
+    /// The class ends inside the named part, everything after it is missing
+    UnexpectedEnd(&'static str),
+    /// Bytes after the end of the class
+    TrailingData(u64),
+}
+
+impl Display for DiagnosticKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiagnosticKind::InvalidMagic(magic) => write!(f, "invalid magic number {:08X}", magic),
+            DiagnosticKind::UnknownConstantTag { index, tag } => {
+                write!(f, "constant {} has unknown tag {}", index, tag)
+            }
+            DiagnosticKind::InvalidUtf8Constant(index) => write!(f, "constant {} is not valid UTF-8", index),
+            DiagnosticKind::MalformedConstant(index) => write!(f, "malformed constant {}", index),
+            DiagnosticKind::MalformedAttribute(name) => write!(f, "malformed {} attribute", name),
+            DiagnosticKind::InvalidAttributeName(index) => {
+                write!(f, "attribute name {} is not a UTF-8 constant", index.0)
+            }
+            DiagnosticKind::InvalidAttributeLocation(name) => write!(f, "{} attribute in invalid location", name),
+            DiagnosticKind::UnsupportedAttribute(name) => {
+                write!(f, "{} attribute is not supported by the class version", name)
+            }
+            DiagnosticKind::UnexpectedEnd(part) => write!(f, "class ends inside the {}", part),
+            DiagnosticKind::TrailingData(length) => write!(f, "{} bytes after the end of the class", length),
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at byte {}: {}", self.offset, self.kind)
+    }
+}
+
+/// The end of the part which could be read, nothing after it is read
+struct Stop;
+
+struct LenientReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Reads a class like `JavaClass::read`, but reports broken parts instead of failing. Attributes which can
+/// not be parsed are kept raw, and a class which ends early or can not be located any further is returned
+/// with the parts read so far
+pub fn read_lenient(data: &[u8]) -> (JavaClass, Vec<Diagnostic>) {
+    let mut class = JavaClass {
+        magic: MagicNumber::Unknown(0),
+        version: JavaVersion { major: 0, minor: 0 },
+        constant_pool: ConstantPool(Vec::new()),
+        access_flags: AccessFlags::NONE,
+        this_class: ConstantPoolIndex(0),
+        super_class: ConstantPoolIndex(0),
+        interfaces: Vec::new().into(),
+        fields: Vec::new().into(),
+        methods: Vec::new().into(),
+        attributes: Vec::new().into(),
+    };
+    let mut reader = LenientReader {
+        data,
+        position: 0,
+        diagnostics: Vec::new(),
+    };
+
+    if reader.read_class(&mut class).is_ok() && reader.position < data.len() {
+        let trailing = (data.len() - reader.position) as u64;
+        reader.report(reader.position, DiagnosticKind::TrailingData(trailing));
+    }
+
+    (class, reader.diagnostics)
+}
+
+impl<'a> LenientReader<'a> {
+    fn report(&mut self, offset: usize, kind: DiagnosticKind) {
+        self.diagnostics.push(Diagnostic {
+            offset: offset as u64,
+            kind,
+        });
+    }
+
+    fn bytes(&mut self, length: usize, part: &'static str) -> Result<&'a [u8], Stop> {
+        match self.data.get(self.position..self.position + length) {
+            Some(bytes) => {
+                self.position += length;
+                Ok(bytes)
+            }
+            None => {
+                self.report(self.position, DiagnosticKind::UnexpectedEnd(part));
+                Err(Stop)
+            }
+        }
+    }
+
+    fn u16(&mut self, part: &'static str) -> Result<u16, Stop> {
+        Ok(BigEndian::read_u16(self.bytes(2, part)?))
+    }
+
+    fn read_class(&mut self, class: &mut JavaClass) -> Result<(), Stop> {
+        let magic = BigEndian::read_u32(self.bytes(4, "magic number")?);
+        class.magic = match magic {
+            0xCAFEBABE => MagicNumber::Cafebabe,
+            magic => {
+                self.report(0, DiagnosticKind::InvalidMagic(magic));
+                MagicNumber::Unknown(magic)
+            }
+        };
+        class.version.minor = self.u16("version")?;
+        class.version.major = self.u16("version")?;
+
+        self.read_constant_pool(&class.version, &mut class.constant_pool)?;
+
+        class.access_flags = unsafe { AccessFlags::from_bits_unchecked(self.u16("access flags")?) };
+        class.this_class = ConstantPoolIndex(self.u16("this class")?);
+        class.super_class = ConstantPoolIndex(self.u16("super class")?);
+        let interfaces = self.u16("interfaces")?;
+        for _ in 0..interfaces {
+            let interface = ConstantPoolIndex(self.u16("interfaces")?);
+            class.interfaces.push(interface);
+        }
+
+        let constant_pool = &class.constant_pool;
+        let ctx = ReadContext {
+            version: &class.version,
+            constant_pool,
+            location: None,
+            name: None,
+            position: None,
+            length: None,
+            wide: None,
+        };
+
+        let fields = self.u16("fields")?;
+        for _ in 0..fields {
+            let (access_flags, name, descriptor) = self.member_header("field")?;
+            let mut field = Field {
+                access_flags,
+                name,
+                descriptor,
+                attributes: Vec::new().into(),
+            };
+            let result = self.read_attributes(&ctx, &AttributeLocation::Field, &mut field.attributes);
+            class.fields.push(field);
+            result?;
+        }
+
+        let methods = self.u16("methods")?;
+        for _ in 0..methods {
+            let (access_flags, name, descriptor) = self.member_header("method")?;
+            let mut method = Method {
+                access_flags,
+                name,
+                descriptor,
+                attributes: Vec::new().into(),
+            };
+            let result = self.read_attributes(&ctx, &AttributeLocation::Method, &mut method.attributes);
+            class.methods.push(method);
+            result?;
+        }
+
+        self.read_attributes(&ctx, &AttributeLocation::ClassFile, &mut class.attributes)
+    }
+
+    fn read_constant_pool(&mut self, version: &JavaVersion, constant_pool: &mut ConstantPool) -> Result<(), Stop> {
+        let count = self.u16("constant pool")?;
+
+        while constant_pool.0.len() + 1 < count as usize {
+            let offset = self.position;
+            let index = constant_pool.0.len() as u16 + 1;
+            let mut reader = Cursor::new(&self.data[offset..]);
+
+            match Constant::read(&mut reader, version) {
+                Ok(constants) => {
+                    self.position += reader.position() as usize;
+                    let invalid = constants.iter().any(|constant| match constant {
+                        Constant::InvalidUtf8(_) => true,
+                        Constant::Unsupported(constant) => matches!(constant.as_ref(), Constant::InvalidUtf8(_)),
+                        _ => false,
+                    });
+                    if invalid {
+                        self.report(offset, DiagnosticKind::InvalidUtf8Constant(index));
+                    }
+                    constant_pool.0.extend(constants);
+                }
+                Err(Error::UnknownTag(tag)) => {
+                    self.report(offset, DiagnosticKind::UnknownConstantTag { index, tag });
+                    constant_pool.0.push(Constant::Raw { tag, info: Vec::new() });
+                    return Err(Stop);
+                }
+                Err(Error::IO(_)) => {
+                    self.report(offset, DiagnosticKind::UnexpectedEnd("constant pool"));
+                    return Err(Stop);
+                }
+                Err(_) => {
+                    self.report(offset, DiagnosticKind::MalformedConstant(index));
+                    return Err(Stop);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn member_header(
+        &mut self,
+        part: &'static str,
+    ) -> Result<(AccessFlags, ConstantPoolIndex, ConstantPoolIndex), Stop> {
+        let access_flags = unsafe { AccessFlags::from_bits_unchecked(self.u16(part)?) };
+        let name = ConstantPoolIndex(self.u16(part)?);
+        let descriptor = ConstantPoolIndex(self.u16(part)?);
+        Ok((access_flags, name, descriptor))
+    }
+
+    fn read_attributes(
+        &mut self,
+        ctx: &ReadContext,
+        location: &AttributeLocation,
+        attributes: &mut SizedVec<u16, Attribute>,
+    ) -> Result<(), Stop> {
+        let count = self.u16("attributes")?;
+
+        for _ in 0..count {
+            let offset = self.position;
+            let header = self.bytes(6, "attribute")?;
+            let length = BigEndian::read_u32(&header[2..]) as usize;
+            self.position = offset;
+            let bytes = self.bytes(6 + length, "attribute")?;
+
+            let ctx = ReadContext {
+                location: Some(location),
+                ..*ctx
+            };
+            match Attribute::read(&mut Cursor::new(bytes), &ctx) {
+                Ok(attribute) => {
+                    self.inspect(&attribute, ctx.constant_pool, bytes, offset);
+                    attributes.push(attribute);
+                }
+                // All bytes of the attribute are known, so the rest of the class can still be read
+                Err(_) => {
+                    let name = attribute_name(ctx.constant_pool, bytes);
+                    self.report(offset, DiagnosticKind::MalformedAttribute(name));
+                    attributes.push(Attribute::Raw(RawAttribute {
+                        name: ConstantPoolIndex(BigEndian::read_u16(bytes)),
+                        info: bytes[6..].to_vec().into(),
+                    }));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reports the fallbacks the attribute parser took for an attribute starting at `offset`
+    fn inspect(&mut self, attribute: &Attribute, constant_pool: &ConstantPool, bytes: &[u8], offset: usize) {
+        let name = || attribute_name(constant_pool, bytes);
+
+        match attribute {
+            Attribute::Raw(_) => self.report(offset, DiagnosticKind::MalformedAttribute(name())),
+            Attribute::InvalidUtf8(raw) | Attribute::IllegalNameReference(raw) => {
+                self.report(offset, DiagnosticKind::InvalidAttributeName(raw.name))
+            }
+            Attribute::InvalidLocation(inner) => {
+                self.report(offset, DiagnosticKind::InvalidAttributeLocation(name()));
+                self.inspect(inner, constant_pool, bytes, offset);
+            }
+            Attribute::Unsupported(inner) => {
+                self.report(offset, DiagnosticKind::UnsupportedAttribute(name()));
+                self.inspect(inner, constant_pool, bytes, offset);
+            }
+            Attribute::UnsupportedAndInvalidLocation(inner) => {
+                self.report(offset, DiagnosticKind::UnsupportedAttribute(name()));
+                self.report(offset, DiagnosticKind::InvalidAttributeLocation(name()));
+                self.inspect(inner, constant_pool, bytes, offset);
+            }
+            Attribute::Code {
+                code,
+                exception_table,
+                attributes,
+                ..
+            } => {
+                // Locate the nested attributes behind the code and the exception table. Pre 45.3 code has
+                // one byte max values and a two byte code length
+                let u16_at = |position: usize| bytes.get(position..position + 2).map(BigEndian::read_u16);
+                let u32_at = |position: usize| bytes.get(position..position + 4).map(BigEndian::read_u32);
+                let code_end = match code {
+                    Compatibility::PreJava1(_) => u16_at(8).map(|length| 6 + 1 + 1 + 2 + length as usize),
+                    Compatibility::Current(_) => u32_at(10).map(|length| 6 + 2 + 2 + 4 + length as usize),
+                };
+                let mut position = match code_end {
+                    Some(end) => end + 2 + 8 * exception_table.len() + 2,
+                    None => return,
+                };
+
+                for nested in attributes.iter() {
+                    let nested_bytes = u32_at(position + 2)
+                        .and_then(|length| bytes.get(position..position + 6 + length as usize));
+                    let nested_bytes = match nested_bytes {
+                        Some(nested_bytes) => nested_bytes,
+                        None => return,
+                    };
+                    self.inspect(nested, constant_pool, nested_bytes, offset + position);
+                    position += nested_bytes.len();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The name of the attribute in `bytes`, empty if it is not a UTF-8 constant
+fn attribute_name(constant_pool: &ConstantPool, bytes: &[u8]) -> String {
+    let index = ConstantPoolIndex(BigEndian::read_u16(bytes));
+    constant_pool.resolve_utf8(index).map(str::to_string).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attribute::{ArrayType, Instruction};
+
+    #[test]
+    fn reports_broken_parts() {
+        let mut data = vec![0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 52, 0, 6];
+        data.extend_from_slice(&[7, 0, 3, 7, 0, 4]);
+        data.extend_from_slice(&[1, 0, 1, b'A', 1, 0, 16]);
+        data.extend_from_slice(b"java/lang/Object");
+        data.extend_from_slice(&[1, 0, 13]);
+        data.extend_from_slice(b"StackMapTable");
+        data.extend_from_slice(&[0, 0x21, 0, 1, 0, 2, 0, 0, 0, 0, 0, 0]);
+        // A stack map table with an unknown verification type in the class attributes
+        data.extend_from_slice(&[0, 1, 0, 5, 0, 0, 0, 4, 0, 1, 252, 42]);
+        data.extend_from_slice(&[0xFF, 0xFF]);
+
+        let (class, diagnostics) = read_lenient(&data);
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic {
+                    offset: 69,
+                    kind: DiagnosticKind::MalformedAttribute("StackMapTable".to_string()),
+                },
+                Diagnostic {
+                    offset: 79,
+                    kind: DiagnosticKind::TrailingData(2),
+                },
+            ]
+        );
+        assert_eq!(class.super_class, ConstantPoolIndex(2));
+        assert!(matches!(class.attributes[0], Attribute::Raw(_)));
+    }
+
+    #[test]
+    fn keeps_malformed_code_raw() -> Result<(), Error> {
+        let class = JavaClass {
+            magic: MagicNumber::Cafebabe,
+            version: JavaVersion { major: 52, minor: 0 },
+            constant_pool: vec![
+                Constant::Class(ConstantPoolIndex(3)),
+                Constant::Class(ConstantPoolIndex(4)),
+                Constant::Utf8("A".into()),
+                Constant::Utf8("java/lang/Object".into()),
+                Constant::Utf8("Code".into()),
+                Constant::Utf8("SourceFile".into()),
+            ]
+            .into(),
+            access_flags: AccessFlags::NONE,
+            this_class: ConstantPoolIndex(1),
+            super_class: ConstantPoolIndex(2),
+            interfaces: Vec::new().into(),
+            fields: Vec::new().into(),
+            methods: vec![Method {
+                access_flags: AccessFlags::NONE,
+                name: ConstantPoolIndex(3),
+                descriptor: ConstantPoolIndex(4),
+                attributes: vec![Attribute::Code {
+                    name: ConstantPoolIndex(5),
+                    max_stack: Compatibility::Current(1),
+                    max_locals: Compatibility::Current(0),
+                    code: Compatibility::Current(
+                        vec![Instruction::NewArray { ty: ArrayType::Int }, Instruction::AReturn].into(),
+                    ),
+                    exception_table: Vec::new().into(),
+                    attributes: Vec::new().into(),
+                }]
+                .into(),
+            }]
+            .into(),
+            attributes: vec![Attribute::SourceFile {
+                name: ConstantPoolIndex(6),
+                sourcefile: ConstantPoolIndex(3),
+            }]
+            .into(),
+        };
+        let mut data = Vec::new();
+        class.write(&mut data)?;
+
+        // An array type which does not exist, the code follows the name, length, maxs and code length
+        let position = data.windows(2).position(|bytes| bytes == [0xBC, 10]).unwrap();
+        data[position + 1] = 3;
+
+        let (read, diagnostics) = read_lenient(&data);
+
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                offset: (position - 14) as u64,
+                kind: DiagnosticKind::MalformedAttribute("Code".to_string()),
+            }]
+        );
+        assert!(matches!(read.methods[0].attributes[0], Attribute::Raw(_)));
+        assert_eq!(read.attributes, class.attributes);
+        Ok(())
+    }
+
+    #[test]
+    fn locates_attributes_nested_in_pre_java_1_code() -> Result<(), Error> {
+        let class = JavaClass {
+            magic: MagicNumber::Cafebabe,
+            version: JavaVersion { major: 45, minor: 0 },
+            constant_pool: vec![
+                Constant::Class(ConstantPoolIndex(3)),
+                Constant::Class(ConstantPoolIndex(4)),
+                Constant::Utf8("A".into()),
+                Constant::Utf8("java/lang/Object".into()),
+                Constant::Utf8("Code".into()),
+                Constant::Utf8("StackMapTable".into()),
+            ]
+            .into(),
+            access_flags: AccessFlags::NONE,
+            this_class: ConstantPoolIndex(1),
+            super_class: ConstantPoolIndex(2),
+            interfaces: Vec::new().into(),
+            fields: Vec::new().into(),
+            methods: vec![Method {
+                access_flags: AccessFlags::NONE,
+                name: ConstantPoolIndex(3),
+                descriptor: ConstantPoolIndex(4),
+                attributes: vec![Attribute::Code {
+                    name: ConstantPoolIndex(5),
+                    max_stack: Compatibility::PreJava1(0),
+                    max_locals: Compatibility::PreJava1(0),
+                    code: Compatibility::PreJava1(vec![Instruction::Return].into()),
+                    exception_table: Vec::new().into(),
+                    attributes: vec![Attribute::StackMapTable {
+                        name: ConstantPoolIndex(6),
+                        entries: Vec::new().into(),
+                    }]
+                    .into(),
+                }]
+                .into(),
+            }]
+            .into(),
+            attributes: Vec::new().into(),
+        };
+        let mut data = Vec::new();
+        class.write(&mut data)?;
+
+        let (read, diagnostics) = read_lenient(&data);
+
+        // Code is declared for 45.3, the stack map table is followed by the count of the class attributes
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic {
+                    offset: (data.len() - 23 - 2) as u64,
+                    kind: DiagnosticKind::UnsupportedAttribute("Code".to_string()),
+                },
+                Diagnostic {
+                    offset: (data.len() - 8 - 2) as u64,
+                    kind: DiagnosticKind::UnsupportedAttribute("StackMapTable".to_string()),
+                },
+            ]
+        );
+        assert_eq!(read, JavaClass::read(&mut data.as_slice())?);
+        Ok(())
+    }
+
+    #[test]
+    fn reports_attribute_name_index_0() {
+        let mut data = vec![0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 52, 0, 5];
+        data.extend_from_slice(&[7, 0, 3, 7, 0, 4]);
+        data.extend_from_slice(&[1, 0, 1, b'A', 1, 0, 16]);
+        data.extend_from_slice(b"java/lang/Object");
+        data.extend_from_slice(&[0, 0x21, 0, 1, 0, 2, 0, 0, 0, 0, 0, 0]);
+        data.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 1, 42]);
+
+        let (class, diagnostics) = read_lenient(&data);
+
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                offset: 53,
+                kind: DiagnosticKind::InvalidAttributeName(ConstantPoolIndex(0)),
+            }]
+        );
+        assert!(matches!(class.attributes[0], Attribute::IllegalNameReference(_)));
+    }
+
+    #[test]
+    fn stops_at_unknown_constant() {
+        let data = [0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 52, 0, 3, 3, 0, 0, 0, 1, 99, 0, 0];
+
+        let (class, diagnostics) = read_lenient(&data);
+
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                offset: 15,
+                kind: DiagnosticKind::UnknownConstantTag { index: 2, tag: 99 },
+            }]
+        );
+        assert_eq!(
+            class.constant_pool.0,
+            vec![Constant::Integer(1), Constant::Raw { tag: 99, info: Vec::new() }]
+        );
+    }
+}
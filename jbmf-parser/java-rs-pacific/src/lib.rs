@@ -4,24 +4,31 @@ use std::io::{Read, Write};
 use bitflags::_core::fmt::Formatter;
 use byteorder::{BigEndian, ReadBytesExt};
 
-use attribute::Attribute;
+use analysis::frames::{compute_frames, ClassHierarchy};
+use analysis::max_values::compute_max_values;
+use attribute::{Attribute, ModuleInfo};
 pub use field::Field;
 pub use flags::*;
 pub use java_rs_base::constant_pool::*;
-pub use java_rs_base::error::Error;
+pub use java_rs_base::error::{ConstantPoolError, Error};
 pub use java_rs_base::io::SizedVec;
-use java_rs_base::io::{AttributeLocation, ClassFilePart, ReadContext, WriteContext};
+use java_rs_base::io::{AttributeLocation, ClassFilePart, PositionReader, ReadContext, WriteContext};
 pub use java_rs_base::java_utf8::{FromJavaUtf8Ext, ToJavaUtf8Ext};
 pub use java_rs_base::version::JavaVersion;
 pub use method::Method;
 
+pub mod analysis;
 #[allow(dead_code, unused_variables)]
 pub mod attribute;
+pub mod compaction;
 mod field;
 mod flags;
 #[cfg(test)]
 mod helper;
+pub mod instruction_list;
+pub mod lenient;
 mod method;
+pub mod visitor;
 
 #[derive(Eq, PartialEq)]
 pub enum MagicNumber {
@@ -38,6 +45,16 @@ impl Debug for MagicNumber {
     }
 }
 
+/// Work done on a class before it is written
+#[derive(Default, Copy, Clone)]
+pub struct WriteOptions<'a> {
+    /// Recompute `max_stack` and `max_locals` of every code attribute
+    pub compute_max_values: bool,
+    /// Recompute the stack map frames of every code attribute using the given hierarchy, for classes
+    /// of version 50 and later
+    pub compute_frames: Option<&'a dyn ClassHierarchy>,
+}
+
 // https://docs.oracle.com/javase/specs/jvms/se15/html/jvms-4.html#jvms-4.1
 #[derive(Debug, Eq, PartialEq)]
 pub struct JavaClass {
@@ -53,23 +70,55 @@ pub struct JavaClass {
     pub attributes: SizedVec<u16, Attribute>,
 }
 
+/// Reads a part of a class, locating its errors at the part's start. Errors the part located itself are
+/// relative to its start
+fn read_part<R: Read, T>(
+    reader: &mut PositionReader<R>,
+    path: impl Into<String>,
+    read: impl FnOnce(&mut PositionReader<R>) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let offset = reader.position();
+    read(reader).map_err(|error| error.offset_by(offset).located(offset, path))
+}
+
+/// Reads a table with a `u16` count, locating errors by their index in the table
+fn read_table<R: Read, T: ClassFilePart>(
+    reader: &mut PositionReader<R>,
+    name: &str,
+    mut read: impl FnMut(&mut PositionReader<R>) -> Result<T, Error>,
+) -> Result<SizedVec<u16, T>, Error> {
+    let count = read_part(reader, name, |reader| Ok(reader.read_u16::<BigEndian>()?))?;
+    let mut items = Vec::with_capacity(count as usize);
+
+    for index in 0..count {
+        items.push(read_part(reader, format!("{}[{}]", name, index), &mut read)?);
+    }
+
+    Ok(items.into())
+}
+
 impl JavaClass {
+    /// Errors are located by their offset in the class file and their path, e.g.
+    /// `methods[3].attributes[0]`, and name the class once it is known
     pub fn read<R: Read>(reader: &mut R) -> Result<Self, Error>
     where
         Self: Sized,
     {
-        let magic = match reader.read_u32::<BigEndian>()? {
+        let reader = &mut PositionReader::new(reader);
+
+        let magic = match read_part(reader, "magic", |reader| Ok(reader.read_u32::<BigEndian>()?))? {
             v if v == 0xCAFEBABE => MagicNumber::Cafebabe,
             v => MagicNumber::Unknown(v),
         };
 
-        let version = {
+        let version = read_part(reader, "version", |reader| {
             let minor = reader.read_u16::<BigEndian>()?;
             let major = reader.read_u16::<BigEndian>()?;
-            JavaVersion { minor, major }
-        };
+            Ok(JavaVersion { minor, major })
+        })?;
 
-        let constant_pool = ConstantPool::read(reader, &version)?;
+        let offset = reader.position();
+        let constant_pool = ConstantPool::read(reader, &version).map_err(|error| error.offset_by(offset))?;
 
         let ctx = ReadContext {
             version: &version,
@@ -81,31 +130,41 @@ impl JavaClass {
             wide: None,
         };
 
-        let access_flags = AccessFlags::read(reader, &ctx)?;
-        let this_class = ConstantPoolIndex::read(reader, &ctx)?;
-        let super_class = ConstantPoolIndex::read(reader, &ctx)?;
-        let interfaces = SizedVec::<u16, ConstantPoolIndex>::read(reader, &ctx)?;
-        let fields = SizedVec::<u16, Field>::read(
-            reader,
-            &ReadContext {
-                location: Some(&AttributeLocation::Field),
-                ..ctx
-            },
-        )?;
-        let methods = SizedVec::<u16, Method>::read(
-            reader,
-            &ReadContext {
-                location: Some(&AttributeLocation::Method),
-                ..ctx
-            },
-        )?;
-        let attributes = SizedVec::<u16, Attribute>::read(
-            reader,
-            &ReadContext {
-                location: Some(&AttributeLocation::ClassFile),
-                ..ctx
-            },
-        )?;
+        let access_flags = read_part(reader, "access_flags", |reader| AccessFlags::read(reader, &ctx))?;
+        let this_class = read_part(reader, "this_class", |reader| ConstantPoolIndex::read(reader, &ctx))?;
+
+        let class = match analysis::constant(&constant_pool, this_class) {
+            Some(Constant::Class(name)) => analysis::utf8(&constant_pool, *name),
+            _ => None,
+        };
+        let in_class = |error: Error| match class {
+            Some(class) => error.in_class(class),
+            None => error,
+        };
+
+        let super_class =
+            read_part(reader, "super_class", |reader| ConstantPoolIndex::read(reader, &ctx)).map_err(in_class)?;
+        let interfaces =
+            read_table(reader, "interfaces", |reader| ConstantPoolIndex::read(reader, &ctx)).map_err(in_class)?;
+
+        let field_ctx = ReadContext {
+            location: Some(&AttributeLocation::Field),
+            ..ctx
+        };
+        let fields = read_table(reader, "fields", |reader| Field::read(reader, &field_ctx)).map_err(in_class)?;
+
+        let method_ctx = ReadContext {
+            location: Some(&AttributeLocation::Method),
+            ..ctx
+        };
+        let methods = read_table(reader, "methods", |reader| Method::read(reader, &method_ctx)).map_err(in_class)?;
+
+        let class_ctx = ReadContext {
+            location: Some(&AttributeLocation::ClassFile),
+            ..ctx
+        };
+        let attributes =
+            read_table(reader, "attributes", |reader| Attribute::read(reader, &class_ctx)).map_err(in_class)?;
 
         Ok(JavaClass {
             magic,
@@ -121,6 +180,62 @@ impl JavaClass {
         })
     }
 
+    /// Reads a class without failing on broken parts, see [`lenient::read_lenient`]
+    pub fn read_lenient(data: &[u8]) -> (Self, Vec<lenient::Diagnostic>) {
+        lenient::read_lenient(data)
+    }
+
+    /// Removes unreferenced constants, see [`compaction::compact_constant_pool`]
+    pub fn compact_constant_pool(&mut self) -> Result<usize, ConstantPoolError> {
+        compaction::compact_constant_pool(self)
+    }
+
+    /// The `Module` attribute of a `module-info` class with its names resolved
+    pub fn module_info(&self) -> Result<Option<ModuleInfo<'_>>, ConstantPoolError> {
+        match self.attributes.iter().find(|attribute| matches!(attribute, Attribute::Module { .. })) {
+            Some(attribute) => ModuleInfo::resolve(attribute, &self.constant_pool),
+            None => Ok(None),
+        }
+    }
+
+    /// Updates the class according to the options, then writes it
+    pub fn write_with_options<W: Write>(&mut self, writer: &mut W, options: WriteOptions) -> Result<(), Error> {
+        let utf8 = |constant_pool: &ConstantPool, index| constant_pool.resolve_utf8(index).map(str::to_string);
+
+        if let Some(hierarchy) = options.compute_frames.filter(|_| self.version.major >= 50) {
+            let class = self.constant_pool.resolve_class_name(self.this_class)?.to_string();
+
+            for method in self.methods.iter_mut() {
+                let name = utf8(&self.constant_pool, method.name)?;
+                let descriptor = utf8(&self.constant_pool, method.descriptor)?;
+
+                for attribute in method.attributes.iter_mut() {
+                    compute_frames(
+                        attribute,
+                        &class,
+                        &name,
+                        &descriptor,
+                        method.access_flags,
+                        &mut self.constant_pool,
+                        hierarchy,
+                    )?;
+                }
+            }
+        }
+
+        if options.compute_max_values {
+            for method in self.methods.iter_mut() {
+                let descriptor = utf8(&self.constant_pool, method.descriptor)?;
+
+                for attribute in method.attributes.iter_mut() {
+                    compute_max_values(attribute, &descriptor, method.access_flags, &self.constant_pool)?;
+                }
+            }
+        }
+
+        self.write(writer)
+    }
+
     pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), Error> {
         let ctx = WriteContext { position: None };
         let ctx = &ctx;
@@ -149,8 +264,9 @@ mod tests {
     use std::fs::{File, OpenOptions};
     use std::io::{BufReader, BufWriter};
 
+    use crate::attribute::{ArrayType, Attribute, Compatibility, Instruction};
     use crate::{
-        AccessFlags, Constant, ConstantPoolIndex, Error, JavaClass, JavaVersion, MagicNumber,
+        AccessFlags, Constant, ConstantPoolIndex, Error, JavaClass, JavaVersion, MagicNumber, Method,
         SizedVec,
     };
 
@@ -190,6 +306,111 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn locates_errors() -> Result<(), Error> {
+        let class = JavaClass {
+            magic: MagicNumber::Cafebabe,
+            version: JavaVersion { major: 52, minor: 0 },
+            constant_pool: vec![
+                Constant::Class(ConstantPoolIndex(3)),
+                Constant::Class(ConstantPoolIndex(4)),
+                Constant::Utf8("a/A".into()),
+                Constant::Utf8("java/lang/Object".into()),
+            ]
+            .into(),
+            access_flags: AccessFlags::NONE,
+            this_class: ConstantPoolIndex(1),
+            super_class: ConstantPoolIndex(2),
+            interfaces: SizedVec::new(),
+            fields: SizedVec::new(),
+            methods: vec![Method {
+                access_flags: AccessFlags::NONE,
+                name: ConstantPoolIndex(3),
+                descriptor: ConstantPoolIndex(4),
+                attributes: SizedVec::new(),
+            }]
+            .into(),
+            attributes: SizedVec::new(),
+        };
+        let mut data = Vec::new();
+        class.write(&mut data)?;
+
+        let mut truncated = data.clone();
+        truncated.truncate(data.len() - 3);
+        let error = JavaClass::read(&mut truncated.as_slice()).unwrap_err();
+        assert_eq!(
+            error.location(),
+            Some(((data.len() - 4) as u64, "methods[0].attributes", Some("a/A")))
+        );
+        assert_eq!(
+            error.to_string(),
+            format!("a/A: methods[0].attributes at byte {}: An IO error occurred", data.len() - 4)
+        );
+
+        let mut unknown = data;
+        unknown[13] = 99;
+        let error = JavaClass::read(&mut unknown.as_slice()).unwrap_err();
+        assert_eq!(error.location(), Some((13, "constant_pool[2]", None)));
+        assert!(matches!(error, Error::Located { source, .. } if matches!(*source, Error::UnknownTag(99))));
+        Ok(())
+    }
+
+    #[test]
+    fn locates_errors_in_code() -> Result<(), Error> {
+        let class = JavaClass {
+            magic: MagicNumber::Cafebabe,
+            version: JavaVersion { major: 52, minor: 0 },
+            constant_pool: vec![
+                Constant::Class(ConstantPoolIndex(3)),
+                Constant::Class(ConstantPoolIndex(4)),
+                Constant::Utf8("a/A".into()),
+                Constant::Utf8("java/lang/Object".into()),
+                Constant::Utf8("Code".into()),
+            ]
+            .into(),
+            access_flags: AccessFlags::NONE,
+            this_class: ConstantPoolIndex(1),
+            super_class: ConstantPoolIndex(2),
+            interfaces: SizedVec::new(),
+            fields: SizedVec::new(),
+            methods: vec![Method {
+                access_flags: AccessFlags::NONE,
+                name: ConstantPoolIndex(3),
+                descriptor: ConstantPoolIndex(4),
+                attributes: vec![Attribute::Code {
+                    name: ConstantPoolIndex(5),
+                    max_stack: Compatibility::Current(1),
+                    max_locals: Compatibility::Current(0),
+                    code: Compatibility::Current(
+                        vec![
+                            Instruction::Nop,
+                            Instruction::NewArray { ty: ArrayType::Int },
+                            Instruction::AReturn,
+                        ]
+                        .into(),
+                    ),
+                    exception_table: SizedVec::new(),
+                    attributes: SizedVec::new(),
+                }]
+                .into(),
+            }]
+            .into(),
+            attributes: SizedVec::new(),
+        };
+        let mut data = Vec::new();
+        class.write(&mut data)?;
+
+        // An array type which does not exist
+        let offset = data.windows(2).position(|bytes| bytes == [0xBC, 10]).unwrap();
+        data[offset + 1] = 3;
+        let error = JavaClass::read(&mut data.as_slice()).unwrap_err();
+        assert_eq!(
+            error.location(),
+            Some((offset as u64, "methods[0].attributes[0].code[1]", Some("a/A")))
+        );
+        Ok(())
+    }
+
     #[test]
     pub fn run_cfr_tests() {
         let c = JavaClass::read(&mut BufReader::new(
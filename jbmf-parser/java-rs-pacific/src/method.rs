@@ -1,13 +1,39 @@
-use java_rs_base::io::SizedVec;
-use java_rs_derive::ClassFilePart;
+use std::io::{Read, Write};
+
+use java_rs_base::error::Error;
+use java_rs_base::io::{ClassFilePart, PositionReader, ReadContext, SizedVec, WriteContext};
 
 use crate::flags::AccessFlags;
-use crate::{Attribute, ConstantPoolIndex};
+use crate::{read_table, Attribute, ConstantPoolIndex};
 
-#[derive(Debug, ClassFilePart, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Method {
     pub access_flags: AccessFlags,
     pub name: ConstantPoolIndex,
     pub descriptor: ConstantPoolIndex,
     pub attributes: SizedVec<u16, Attribute>,
 }
+
+impl ClassFilePart for Method {
+    /// Errors of attributes are located at `attributes[index]`, relative to the start of the method
+    fn read<R: Read>(reader: &mut R, ctx: &ReadContext) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        let reader = &mut PositionReader::new(reader);
+
+        Ok(Method {
+            access_flags: AccessFlags::read(reader, ctx)?,
+            name: ConstantPoolIndex::read(reader, ctx)?,
+            descriptor: ConstantPoolIndex::read(reader, ctx)?,
+            attributes: read_table(reader, "attributes", |reader| Attribute::read(reader, ctx))?,
+        })
+    }
+
+    fn write<W: Write>(&self, writer: &mut W, ctx: &WriteContext) -> Result<(), Error> {
+        self.access_flags.write(writer, ctx)?;
+        self.name.write(writer, ctx)?;
+        self.descriptor.write(writer, ctx)?;
+        self.attributes.write(writer, ctx)
+    }
+}
@@ -0,0 +1,514 @@
+use crate::attribute::{AnnotationElement, Attribute};
+use crate::instruction_list::{CodeTypeAnnotation, LineNumber, LocalVariable, Node, TryCatchBlock};
+use crate::{AccessFlags, JavaVersion};
+
+pub use reader::ClassReader;
+pub use writer::ClassWriter;
+
+mod reader;
+mod writer;
+
+/// Receives a class in the order `visit`, `visit_source`, annotations and attributes, fields, methods and
+/// `visit_end`. Every call is forwarded to [`ClassVisitor::delegate`] unless it is overridden, so adapters
+/// only implement what they change. Attributes and instructions refer to the constant pool of the reader
+pub trait ClassVisitor {
+    /// The visitor calls are forwarded to, `None` drops them
+    fn delegate(&mut self) -> Option<&mut dyn ClassVisitor> {
+        None
+    }
+
+    /// Names are internal names, e.g. `java/lang/Object`
+    fn visit(
+        &mut self,
+        version: &JavaVersion,
+        access_flags: AccessFlags,
+        name: &str,
+        signature: Option<&str>,
+        super_name: Option<&str>,
+        interfaces: &[&str],
+    ) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit(version, access_flags, name, signature, super_name, interfaces);
+        }
+    }
+
+    fn visit_source(&mut self, source: &str) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_source(source);
+        }
+    }
+
+    /// `descriptor` is the field descriptor of the annotation interface, `visible` whether the annotation is
+    /// retained at runtime
+    fn visit_annotation(&mut self, descriptor: &str, visible: bool) -> Option<Box<dyn AnnotationVisitor + '_>> {
+        self.delegate()?.visit_annotation(descriptor, visible)
+    }
+
+    /// An attribute without its own visitor method
+    fn visit_attribute(&mut self, attribute: &Attribute) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_attribute(attribute);
+        }
+    }
+
+    fn visit_field(
+        &mut self,
+        access_flags: AccessFlags,
+        name: &str,
+        descriptor: &str,
+        signature: Option<&str>,
+    ) -> Option<Box<dyn FieldVisitor + '_>> {
+        self.delegate()?.visit_field(access_flags, name, descriptor, signature)
+    }
+
+    /// `exceptions` are the internal names of the declared exceptions
+    fn visit_method(
+        &mut self,
+        access_flags: AccessFlags,
+        name: &str,
+        descriptor: &str,
+        signature: Option<&str>,
+        exceptions: &[&str],
+    ) -> Option<Box<dyn MethodVisitor + '_>> {
+        self.delegate()?.visit_method(access_flags, name, descriptor, signature, exceptions)
+    }
+
+    fn visit_end(&mut self) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_end();
+        }
+    }
+}
+
+/// Receives the annotations and attributes of a field, then `visit_end`
+pub trait FieldVisitor {
+    fn delegate(&mut self) -> Option<&mut dyn FieldVisitor> {
+        None
+    }
+
+    fn visit_annotation(&mut self, descriptor: &str, visible: bool) -> Option<Box<dyn AnnotationVisitor + '_>> {
+        self.delegate()?.visit_annotation(descriptor, visible)
+    }
+
+    fn visit_attribute(&mut self, attribute: &Attribute) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_attribute(attribute);
+        }
+    }
+
+    fn visit_end(&mut self) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_end();
+        }
+    }
+}
+
+/// Receives the annotations and attributes of a method, then its code if it has any and `visit_end`. Code
+/// starts with `visit_code` followed by its nodes, exception table, debug information, type annotations and
+/// remaining attributes and ends with `visit_maxs`
+pub trait MethodVisitor {
+    fn delegate(&mut self) -> Option<&mut dyn MethodVisitor> {
+        None
+    }
+
+    fn visit_annotation(&mut self, descriptor: &str, visible: bool) -> Option<Box<dyn AnnotationVisitor + '_>> {
+        self.delegate()?.visit_annotation(descriptor, visible)
+    }
+
+    fn visit_attribute(&mut self, attribute: &Attribute) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_attribute(attribute);
+        }
+    }
+
+    fn visit_code(&mut self) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_code();
+        }
+    }
+
+    fn visit_node(&mut self, node: &Node) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_node(node);
+        }
+    }
+
+    fn visit_try_catch_block(&mut self, block: &TryCatchBlock) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_try_catch_block(block);
+        }
+    }
+
+    fn visit_line_number(&mut self, line_number: &LineNumber) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_line_number(line_number);
+        }
+    }
+
+    fn visit_local_variable(&mut self, variable: &LocalVariable) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_local_variable(variable);
+        }
+    }
+
+    /// An entry of the `LocalVariableTypeTable`, its descriptor is a signature
+    fn visit_local_variable_type(&mut self, variable: &LocalVariable) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_local_variable_type(variable);
+        }
+    }
+
+    fn visit_code_type_annotation(&mut self, annotation: &CodeTypeAnnotation) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_code_type_annotation(annotation);
+        }
+    }
+
+    /// An attribute of the code without its own visitor method, e.g. the `StackMapTable`
+    fn visit_code_attribute(&mut self, attribute: &Attribute) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_code_attribute(attribute);
+        }
+    }
+
+    fn visit_maxs(&mut self, max_stack: u16, max_locals: u16) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_maxs(max_stack, max_locals);
+        }
+    }
+
+    fn visit_end(&mut self) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_end();
+        }
+    }
+}
+
+/// Receives the element values of an annotation, then `visit_end`
+pub trait AnnotationVisitor {
+    fn delegate(&mut self) -> Option<&mut dyn AnnotationVisitor> {
+        None
+    }
+
+    fn visit(&mut self, name: &str, value: &AnnotationElement) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit(name, value);
+        }
+    }
+
+    fn visit_end(&mut self) {
+        if let Some(delegate) = self.delegate() {
+            delegate.visit_end();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::frames::StaticHierarchy;
+    use crate::attribute::{AnnotationElement, AnnotationInfo, Attribute, Compatibility, Instruction, StackMapFrame};
+    use crate::instruction_list::{InstructionList, LineNumber, LocalVariable, Node};
+    use crate::visitor::{ClassReader, ClassVisitor, ClassWriter, MethodVisitor};
+    use crate::{
+        AccessFlags, Constant, ConstantPoolIndex, Error, JavaClass, JavaVersion, MagicNumber, Method, WriteOptions,
+    };
+
+    /// Compiled by javac 17 from `TypeAnnotated.java` next to it
+    const TYPE_ANNOTATED: &[u8] = include_bytes!("../../tests/resources/TypeAnnotated.class");
+
+    fn code(class: &JavaClass, method: usize) -> Result<InstructionList, Error> {
+        let code = class.methods[method]
+            .attributes
+            .iter()
+            .find(|attribute| matches!(attribute, Attribute::Code { .. }))
+            .expect("Method without code");
+        InstructionList::from_code(code)
+    }
+
+    fn member_names(class: &JavaClass) -> Result<Vec<(String, String)>, Error> {
+        let pool = &class.constant_pool;
+        let fields = class.fields.iter().map(|field| (field.name, field.descriptor));
+        let methods = class.methods.iter().map(|method| (method.name, method.descriptor));
+        fields
+            .chain(methods)
+            .map(|(name, descriptor)| Ok((pool.resolve_utf8(name)?.into(), pool.resolve_utf8(descriptor)?.into())))
+            .collect()
+    }
+
+    #[test]
+    fn copy_class() -> Result<(), Error> {
+        let reader = ClassReader::new(TYPE_ANNOTATED)?;
+        let mut writer = ClassWriter::with_constant_pool(reader.constant_pool().clone());
+        reader.accept(&mut writer)?;
+
+        let mut buffer = Vec::new();
+        writer.into_class()?.write(&mut buffer)?;
+        let copy = JavaClass::read(&mut buffer.as_slice())?;
+        let class = JavaClass::read(&mut &*TYPE_ANNOTATED)?;
+
+        assert_eq!(class.version, copy.version);
+        assert_eq!(class.constant_pool, copy.constant_pool);
+        assert_eq!(member_names(&class)?, member_names(&copy)?);
+        for (method, copied) in class.methods.iter().zip(copy.methods.iter()) {
+            assert_eq!(method.attributes.len(), copied.attributes.len());
+        }
+        for method in 0..class.methods.len() {
+            assert_eq!(code(&class, method)?, code(&copy, method)?);
+        }
+        Ok(())
+    }
+
+    /// Drops the source file and debug tables and renames `method`
+    struct StripDebug<'a>(&'a mut dyn ClassVisitor);
+
+    impl ClassVisitor for StripDebug<'_> {
+        fn delegate(&mut self) -> Option<&mut dyn ClassVisitor> {
+            Some(self.0)
+        }
+
+        fn visit_source(&mut self, _source: &str) {}
+
+        fn visit_method(
+            &mut self,
+            access_flags: AccessFlags,
+            name: &str,
+            descriptor: &str,
+            signature: Option<&str>,
+            exceptions: &[&str],
+        ) -> Option<Box<dyn MethodVisitor + '_>> {
+            let name = match name {
+                "method" => "renamed",
+                name => name,
+            };
+            let method = self.0.visit_method(access_flags, name, descriptor, signature, exceptions)?;
+            Some(Box::new(StripMethodDebug(method)))
+        }
+    }
+
+    struct StripMethodDebug<'a>(Box<dyn MethodVisitor + 'a>);
+
+    impl MethodVisitor for StripMethodDebug<'_> {
+        fn delegate(&mut self) -> Option<&mut dyn MethodVisitor> {
+            Some(self.0.as_mut())
+        }
+
+        fn visit_line_number(&mut self, _line_number: &LineNumber) {}
+
+        fn visit_local_variable(&mut self, _variable: &LocalVariable) {}
+
+        fn visit_local_variable_type(&mut self, _variable: &LocalVariable) {}
+    }
+
+    #[test]
+    fn strip_debug_information() -> Result<(), Error> {
+        let reader = ClassReader::new(TYPE_ANNOTATED)?;
+        let mut writer = ClassWriter::with_constant_pool(reader.constant_pool().clone());
+        reader.accept(&mut StripDebug(&mut writer))?;
+        let class = writer.into_class()?;
+
+        assert!(!class.attributes.iter().any(|attribute| matches!(attribute, Attribute::SourceFile { .. })));
+        assert!(member_names(&class)?.iter().any(|(name, _)| name == "renamed"));
+        for method in 0..class.methods.len() {
+            let code = code(&class, method)?;
+            assert!(code.line_numbers.is_empty());
+            assert!(code.local_variables.is_empty());
+            assert!(code.local_variable_types.is_empty());
+        }
+        assert!(!code(&class, 1)?.type_annotations.is_empty());
+        Ok(())
+    }
+
+    #[derive(Default)]
+    struct CountInvokes(usize);
+
+    impl ClassVisitor for CountInvokes {
+        fn visit_method(
+            &mut self,
+            _access_flags: AccessFlags,
+            _name: &str,
+            _descriptor: &str,
+            _signature: Option<&str>,
+            _exceptions: &[&str],
+        ) -> Option<Box<dyn MethodVisitor + '_>> {
+            Some(Box::new(CountMethodInvokes(&mut self.0)))
+        }
+    }
+
+    struct CountMethodInvokes<'a>(&'a mut usize);
+
+    impl MethodVisitor for CountMethodInvokes<'_> {
+        fn visit_node(&mut self, node: &Node) {
+            if let Node::Instruction(
+                Instruction::InvokeSpecial { .. }
+                | Instruction::InvokeVirtual { .. }
+                | Instruction::InvokeStatic { .. }
+                | Instruction::InvokeInterface { .. }
+                | Instruction::InvokeDynamic { .. },
+            ) = node
+            {
+                *self.0 += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn count_invokes() -> Result<(), Error> {
+        let mut visitor = CountInvokes::default();
+        ClassReader::new(TYPE_ANNOTATED)?.accept(&mut visitor)?;
+        assert_eq!(3, visitor.0);
+        Ok(())
+    }
+
+    #[test]
+    fn write_annotated_class() -> Result<(), Error> {
+        let mut writer = ClassWriter::new();
+        let version = JavaVersion { major: 52, minor: 0 };
+        writer.visit(&version, AccessFlags::PUBLIC, "Example", None, Some("java/lang/Object"), &[]);
+        let mut annotation = writer.visit_annotation("LMarker;", true).expect("Writer drops annotations");
+        annotation.visit("value", &AnnotationElement::Int(1));
+        annotation.visit_end();
+        drop(annotation);
+        writer.visit_field(AccessFlags::PRIVATE, "field", "I", None).expect("Writer drops fields").visit_end();
+        writer.visit_end();
+
+        let class = writer.into_class()?;
+        let expected = AnnotationInfo::new("LMarker;").element("value", 1);
+        match &class.attributes[..] {
+            [Attribute::RuntimeVisibleAnnotations { annotations, .. }] => {
+                assert_eq!(expected, annotations[0].resolve(&class.constant_pool)?)
+            }
+            attributes => panic!("Expected the annotation, got {:?}", attributes),
+        }
+        assert_eq!(vec![("field".to_string(), "I".to_string())], member_names(&class)?);
+        Ok(())
+    }
+
+    /// Pushes and pops two ints at the start of every method
+    struct InsertPushes<'a>(&'a mut dyn ClassVisitor);
+
+    impl ClassVisitor for InsertPushes<'_> {
+        fn delegate(&mut self) -> Option<&mut dyn ClassVisitor> {
+            Some(self.0)
+        }
+
+        fn visit_method(
+            &mut self,
+            access_flags: AccessFlags,
+            name: &str,
+            descriptor: &str,
+            signature: Option<&str>,
+            exceptions: &[&str],
+        ) -> Option<Box<dyn MethodVisitor + '_>> {
+            let method = self.0.visit_method(access_flags, name, descriptor, signature, exceptions)?;
+            Some(Box::new(InsertMethodPushes(method)))
+        }
+    }
+
+    struct InsertMethodPushes<'a>(Box<dyn MethodVisitor + 'a>);
+
+    impl MethodVisitor for InsertMethodPushes<'_> {
+        fn delegate(&mut self) -> Option<&mut dyn MethodVisitor> {
+            Some(self.0.as_mut())
+        }
+
+        fn visit_code(&mut self) {
+            self.0.visit_code();
+            for instruction in [Instruction::IConst0, Instruction::IConst0, Instruction::Pop2] {
+                self.0.visit_node(&Node::Instruction(instruction));
+            }
+        }
+    }
+
+    /// The code of the first method, its `max_stack` and its stack map frames
+    fn code_values(class: &JavaClass) -> (Vec<Instruction>, u16, Option<Vec<StackMapFrame>>) {
+        match &class.methods[0].attributes[0] {
+            Attribute::Code {
+                max_stack: Compatibility::Current(max_stack),
+                code: Compatibility::Current(code),
+                attributes,
+                ..
+            } => {
+                let frames = attributes.iter().find_map(|attribute| match attribute {
+                    Attribute::StackMapTable { entries, .. } => Some(entries.to_vec()),
+                    _ => None,
+                });
+                (code.to_vec(), *max_stack, frames)
+            }
+            attribute => panic!("Expected code, got {:?}", attribute),
+        }
+    }
+
+    #[test]
+    fn recompute_frames_and_maxs_of_changed_code() -> Result<(), Error> {
+        // static int m(int) { return value == 0 ? 0 : 1; }
+        let mut class = JavaClass {
+            magic: MagicNumber::Cafebabe,
+            version: JavaVersion { major: 52, minor: 0 },
+            constant_pool: vec![
+                Constant::Class(ConstantPoolIndex(3)),
+                Constant::Class(ConstantPoolIndex(4)),
+                Constant::Utf8("Example".into()),
+                Constant::Utf8("java/lang/Object".into()),
+                Constant::Utf8("m".into()),
+                Constant::Utf8("(I)I".into()),
+                Constant::Utf8("Code".into()),
+            ]
+            .into(),
+            access_flags: AccessFlags::PUBLIC,
+            this_class: ConstantPoolIndex(1),
+            super_class: ConstantPoolIndex(2),
+            interfaces: Vec::new().into(),
+            fields: Vec::new().into(),
+            methods: vec![Method {
+                access_flags: AccessFlags::STATIC,
+                name: ConstantPoolIndex(5),
+                descriptor: ConstantPoolIndex(6),
+                attributes: vec![Attribute::Code {
+                    name: ConstantPoolIndex(7),
+                    max_stack: Compatibility::Current(1),
+                    max_locals: Compatibility::Current(1),
+                    code: Compatibility::Current(
+                        vec![
+                            Instruction::ILoad0,
+                            Instruction::IfEq { offset: 5 },
+                            Instruction::IConst1,
+                            Instruction::IReturn,
+                            Instruction::IConst0,
+                            Instruction::IReturn,
+                        ]
+                        .into(),
+                    ),
+                    exception_table: Vec::new().into(),
+                    attributes: Vec::new().into(),
+                }]
+                .into(),
+            }]
+            .into(),
+            attributes: Vec::new().into(),
+        };
+        let hierarchy = StaticHierarchy::default();
+        let options = WriteOptions {
+            compute_max_values: true,
+            compute_frames: Some(&hierarchy),
+        };
+        let mut data = Vec::new();
+        class.write_with_options(&mut data, options)?;
+        assert_eq!(code_values(&class).2, Some(vec![StackMapFrame::Same { frame_type: 6 }]));
+
+        let reader = ClassReader::new(&data)?;
+        let mut writer = ClassWriter::with_constant_pool(reader.constant_pool().clone()).with_options(options);
+        reader.accept(&mut InsertPushes(&mut writer))?;
+        let (code, max_stack, frames) = code_values(&writer.into_class()?);
+        assert_eq!(code[..4], [Instruction::IConst0, Instruction::IConst0, Instruction::Pop2, Instruction::ILoad0]);
+        assert_eq!(max_stack, 2);
+        assert_eq!(frames, Some(vec![StackMapFrame::Same { frame_type: 9 }]));
+
+        // Without the options the stale frames are dropped and the visited max values kept
+        let mut writer = ClassWriter::with_constant_pool(reader.constant_pool().clone());
+        reader.accept(&mut InsertPushes(&mut writer))?;
+        let (_, max_stack, frames) = code_values(&writer.into_class()?);
+        assert_eq!(max_stack, 1);
+        assert_eq!(frames, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+use java_rs_base::error::Error;
+
+use crate::attribute::{AnnotationInfo, Attribute, Compatibility};
+use crate::instruction_list::InstructionList;
+use crate::visitor::{AnnotationVisitor, ClassVisitor, MethodVisitor};
+use crate::{ConstantPool, ConstantPoolIndex, JavaClass};
+
+/// Drives visitors through a class. The class is parsed when the reader is created, method visitors
+/// receive the code as the nodes of an [`InstructionList`]
+#[derive(Debug)]
+pub struct ClassReader {
+    class: JavaClass,
+}
+
+impl ClassReader {
+    pub fn new(data: &[u8]) -> Result<Self, Error> {
+        Ok(ClassReader {
+            class: JavaClass::read(&mut &*data)?,
+        })
+    }
+
+    /// The pool the indices of visited attributes and instructions refer to
+    pub fn constant_pool(&self) -> &ConstantPool {
+        &self.class.constant_pool
+    }
+
+    pub fn accept(&self, visitor: &mut dyn ClassVisitor) -> Result<(), Error> {
+        let class = &self.class;
+        let constant_pool = &class.constant_pool;
+        let class_name = |index: ConstantPoolIndex| constant_pool.resolve_class_name(index);
+
+        let super_name = match class.super_class.0 {
+            0 => None,
+            _ => Some(class_name(class.super_class)?),
+        };
+        let interfaces = class
+            .interfaces
+            .iter()
+            .map(|index| class_name(*index))
+            .collect::<Result<Vec<_>, _>>()?;
+        visitor.visit(
+            &class.version,
+            class.access_flags,
+            class_name(class.this_class)?,
+            signature(constant_pool, &class.attributes)?,
+            super_name,
+            &interfaces,
+        );
+
+        for attribute in class.attributes.iter() {
+            match attribute {
+                Attribute::SourceFile { sourcefile, .. } => {
+                    visitor.visit_source(constant_pool.resolve_utf8(*sourcefile)?)
+                }
+                Attribute::Signature { .. } => {}
+                attribute => match annotations(constant_pool, attribute)? {
+                    Some((visible, annotations)) => {
+                        for annotation in annotations {
+                            accept_annotation(&annotation, visitor.visit_annotation(&annotation.ty, visible));
+                        }
+                    }
+                    None => visitor.visit_attribute(attribute),
+                },
+            }
+        }
+
+        for field in class.fields.iter() {
+            let mut field_visitor = match visitor.visit_field(
+                field.access_flags,
+                constant_pool.resolve_utf8(field.name)?,
+                constant_pool.resolve_utf8(field.descriptor)?,
+                signature(constant_pool, &field.attributes)?,
+            ) {
+                Some(field_visitor) => field_visitor,
+                None => continue,
+            };
+
+            for attribute in field.attributes.iter() {
+                match annotations(constant_pool, attribute)? {
+                    Some((visible, annotations)) => {
+                        for annotation in annotations {
+                            accept_annotation(&annotation, field_visitor.visit_annotation(&annotation.ty, visible));
+                        }
+                    }
+                    None if matches!(attribute, Attribute::Signature { .. }) => {}
+                    None => field_visitor.visit_attribute(attribute),
+                }
+            }
+            field_visitor.visit_end();
+        }
+
+        for method in class.methods.iter() {
+            let exceptions = match method.attributes.iter().find_map(|attribute| match attribute {
+                Attribute::Exceptions {
+                    exception_index_table, ..
+                } => Some(exception_index_table),
+                _ => None,
+            }) {
+                Some(exceptions) => exceptions
+                    .iter()
+                    .map(|index| class_name(*index))
+                    .collect::<Result<Vec<_>, _>>()?,
+                None => Vec::new(),
+            };
+            let mut method_visitor = match visitor.visit_method(
+                method.access_flags,
+                constant_pool.resolve_utf8(method.name)?,
+                constant_pool.resolve_utf8(method.descriptor)?,
+                signature(constant_pool, &method.attributes)?,
+                &exceptions,
+            ) {
+                Some(method_visitor) => method_visitor,
+                None => continue,
+            };
+
+            // Code comes last, after every other attribute of the method
+            let mut code = None;
+            for attribute in method.attributes.iter() {
+                match attribute {
+                    Attribute::Code { .. } => code = Some(attribute),
+                    Attribute::Signature { .. } | Attribute::Exceptions { .. } => {}
+                    attribute => match annotations(constant_pool, attribute)? {
+                        Some((visible, annotations)) => {
+                            for annotation in annotations {
+                                let annotation_visitor = method_visitor.visit_annotation(&annotation.ty, visible);
+                                accept_annotation(&annotation, annotation_visitor);
+                            }
+                        }
+                        None => method_visitor.visit_attribute(attribute),
+                    },
+                }
+            }
+            if let Some(code) = code {
+                accept_code(code, method_visitor.as_mut())?;
+            }
+            method_visitor.visit_end();
+        }
+
+        visitor.visit_end();
+        Ok(())
+    }
+}
+
+impl From<JavaClass> for ClassReader {
+    fn from(class: JavaClass) -> Self {
+        ClassReader { class }
+    }
+}
+
+fn signature<'a>(constant_pool: &'a ConstantPool, attributes: &[Attribute]) -> Result<Option<&'a str>, Error> {
+    match attributes.iter().find_map(|attribute| match attribute {
+        Attribute::Signature { signature, .. } => Some(*signature),
+        _ => None,
+    }) {
+        Some(signature) => Ok(Some(constant_pool.resolve_utf8(signature)?)),
+        None => Ok(None),
+    }
+}
+
+/// The resolved annotations of a `RuntimeVisibleAnnotations` or `RuntimeInvisibleAnnotations` attribute and
+/// whether they are visible, `None` for other attributes
+fn annotations(
+    constant_pool: &ConstantPool,
+    attribute: &Attribute,
+) -> Result<Option<(bool, Vec<AnnotationInfo>)>, Error> {
+    let (visible, annotations) = match attribute {
+        Attribute::RuntimeVisibleAnnotations { annotations, .. } => (true, annotations),
+        Attribute::RuntimeInvisibleAnnotations { annotations, .. } => (false, annotations),
+        _ => return Ok(None),
+    };
+    let annotations = annotations
+        .iter()
+        .map(|annotation| annotation.resolve(constant_pool))
+        .collect::<Result<_, _>>()?;
+
+    Ok(Some((visible, annotations)))
+}
+
+fn accept_annotation(annotation: &AnnotationInfo, visitor: Option<Box<dyn AnnotationVisitor + '_>>) {
+    if let Some(mut visitor) = visitor {
+        for (name, value) in annotation.elements.iter() {
+            visitor.visit(name, value);
+        }
+        visitor.visit_end();
+    }
+}
+
+fn accept_code(code: &Attribute, visitor: &mut dyn MethodVisitor) -> Result<(), Error> {
+    let (max_stack, max_locals, attributes) = match code {
+        Attribute::Code {
+            max_stack,
+            max_locals,
+            attributes,
+            ..
+        } => (max_stack, max_locals, attributes),
+        _ => return Ok(()),
+    };
+    let list = InstructionList::from_code(code)?;
+    let value = |value: &Compatibility<u8, u16>| match value {
+        Compatibility::PreJava1(value) => u16::from(*value),
+        Compatibility::Current(value) => *value,
+    };
+
+    visitor.visit_code();
+    for node in list.nodes.iter() {
+        visitor.visit_node(node);
+    }
+    for block in list.try_catch_blocks.iter() {
+        visitor.visit_try_catch_block(block);
+    }
+    for line_number in list.line_numbers.iter() {
+        visitor.visit_line_number(line_number);
+    }
+    for variable in list.local_variables.iter() {
+        visitor.visit_local_variable(variable);
+    }
+    for variable in list.local_variable_types.iter() {
+        visitor.visit_local_variable_type(variable);
+    }
+    for annotation in list.type_annotations.iter() {
+        visitor.visit_code_type_annotation(annotation);
+    }
+    for attribute in attributes.iter() {
+        if !matches!(
+            attribute,
+            Attribute::LineNumberTable { .. }
+                | Attribute::LocalVariableTable { .. }
+                | Attribute::LocalVariableTypeTable { .. }
+                | Attribute::RuntimeVisibleTypeAnnotations { .. }
+                | Attribute::RuntimeInvisibleTypeAnnotations { .. }
+        ) {
+            visitor.visit_code_attribute(attribute);
+        }
+    }
+    visitor.visit_maxs(value(max_stack), value(max_locals));
+
+    Ok(())
+}
@@ -0,0 +1,456 @@
+use std::convert::TryFrom;
+
+use java_rs_base::error::{ConstantPoolError, Error};
+
+use crate::analysis::frames::compute_frames;
+use crate::analysis::max_values::compute_max_values;
+use crate::attribute::{AnnotationElement, AnnotationInfo, Attribute, Compatibility};
+use crate::instruction_list::{CodeTypeAnnotation, InstructionList, LineNumber, LocalVariable, Node, TryCatchBlock};
+use crate::visitor::{AnnotationVisitor, ClassVisitor, FieldVisitor, MethodVisitor};
+use crate::{
+    AccessFlags, ConstantPool, ConstantPoolBuilder, ConstantPoolIndex, Field, JavaClass, JavaVersion, MagicNumber,
+    Method, SizedVec, WriteOptions,
+};
+
+/// Builds a class from the visitor calls. Attributes and instructions are copied as they are, so a writer fed
+/// by a [`ClassReader`](crate::visitor::ClassReader) has to start from the reader's constant pool. Names which
+/// are not in the pool yet are added, constants which end up unused can be dropped with
+/// [`JavaClass::compact_constant_pool`].
+///
+/// Code is lowered again from the visited nodes, so visited stack map tables are dropped as their offsets
+/// may no longer match. [`ClassWriter::with_options`] recomputes the frames and the max values, otherwise
+/// the values passed to `visit_maxs` are kept
+pub struct ClassWriter<'a> {
+    constant_pool: ConstantPool,
+    version: JavaVersion,
+    access_flags: AccessFlags,
+    this_class: ConstantPoolIndex,
+    super_class: ConstantPoolIndex,
+    interfaces: Vec<ConstantPoolIndex>,
+    fields: Vec<Field>,
+    methods: Vec<Method>,
+    attributes: Vec<Attribute>,
+    annotations: Vec<(bool, AnnotationInfo)>,
+    options: WriteOptions<'a>,
+    error: Option<Error>,
+}
+
+impl<'a> ClassWriter<'a> {
+    pub fn new() -> Self {
+        Self::with_constant_pool(ConstantPool(Vec::new()))
+    }
+
+    pub fn with_constant_pool(constant_pool: ConstantPool) -> Self {
+        ClassWriter {
+            constant_pool,
+            version: JavaVersion { major: 45, minor: 3 },
+            access_flags: AccessFlags::empty(),
+            this_class: ConstantPoolIndex(0),
+            super_class: ConstantPoolIndex(0),
+            interfaces: Vec::new(),
+            fields: Vec::new(),
+            methods: Vec::new(),
+            attributes: Vec::new(),
+            annotations: Vec::new(),
+            options: WriteOptions::default(),
+            error: None,
+        }
+    }
+
+    /// Computes the frames and max values of the written code like [`JavaClass::write_with_options`]
+    pub fn with_options(mut self, options: WriteOptions<'a>) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The written class, or the first error which occurred while visiting it
+    pub fn into_class(mut self) -> Result<JavaClass, Error> {
+        let annotations = std::mem::take(&mut self.annotations);
+        let attributes = self.annotation_attributes(annotations);
+        self.attributes.extend(attributes);
+
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(JavaClass {
+                magic: MagicNumber::Cafebabe,
+                version: self.version,
+                constant_pool: self.constant_pool,
+                access_flags: self.access_flags,
+                this_class: self.this_class,
+                super_class: self.super_class,
+                interfaces: self.interfaces.into(),
+                fields: self.fields.into(),
+                methods: self.methods.into(),
+                attributes: self.attributes.into(),
+            }),
+        }
+    }
+
+    /// Adds a constant, errors are kept for [`ClassWriter::into_class`] and give index zero
+    fn constant(
+        &mut self,
+        insert: impl FnOnce(&mut ConstantPoolBuilder) -> Result<ConstantPoolIndex, ConstantPoolError>,
+    ) -> ConstantPoolIndex {
+        let result = insert(&mut ConstantPoolBuilder::new(&mut self.constant_pool));
+        self.record(result.map_err(Error::from)).unwrap_or(ConstantPoolIndex(0))
+    }
+
+    fn utf8(&mut self, value: &str) -> ConstantPoolIndex {
+        self.constant(|constants| constants.utf8(value))
+    }
+
+    fn class(&mut self, name: &str) -> ConstantPoolIndex {
+        self.constant(|constants| constants.class(name))
+    }
+
+    fn record<T>(&mut self, result: Result<T, Error>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.error.get_or_insert(error);
+                None
+            }
+        }
+    }
+
+    fn signature(&mut self, signature: &str) -> Attribute {
+        Attribute::Signature {
+            name: self.utf8("Signature"),
+            signature: self.utf8(signature),
+        }
+    }
+
+    fn annotation_attributes(&mut self, annotations: Vec<(bool, AnnotationInfo)>) -> Vec<Attribute> {
+        let mut visible = Vec::new();
+        let mut invisible = Vec::new();
+        for (is_visible, annotation) in annotations {
+            let mut constants = ConstantPoolBuilder::new(&mut self.constant_pool);
+            let built = annotation.build(&mut constants).map_err(Error::from);
+            if let Some(built) = self.record(built) {
+                match is_visible {
+                    true => visible.push(built),
+                    false => invisible.push(built),
+                }
+            }
+        }
+
+        let mut attributes = Vec::new();
+        if !visible.is_empty() {
+            attributes.push(Attribute::RuntimeVisibleAnnotations {
+                name: self.utf8("RuntimeVisibleAnnotations"),
+                annotations: visible.into(),
+            });
+        }
+        if !invisible.is_empty() {
+            attributes.push(Attribute::RuntimeInvisibleAnnotations {
+                name: self.utf8("RuntimeInvisibleAnnotations"),
+                annotations: invisible.into(),
+            });
+        }
+        attributes
+    }
+}
+
+impl Default for ClassWriter<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClassVisitor for ClassWriter<'_> {
+    fn visit(
+        &mut self,
+        version: &JavaVersion,
+        access_flags: AccessFlags,
+        name: &str,
+        signature: Option<&str>,
+        super_name: Option<&str>,
+        interfaces: &[&str],
+    ) {
+        self.version = version.clone();
+        self.access_flags = access_flags;
+        self.this_class = self.class(name);
+        self.super_class = match super_name {
+            Some(super_name) => self.class(super_name),
+            None => ConstantPoolIndex(0),
+        };
+        self.interfaces = interfaces.iter().map(|interface| self.class(interface)).collect();
+        if let Some(signature) = signature {
+            let signature = self.signature(signature);
+            self.attributes.push(signature);
+        }
+    }
+
+    fn visit_source(&mut self, source: &str) {
+        let source_file = Attribute::SourceFile {
+            name: self.utf8("SourceFile"),
+            sourcefile: self.utf8(source),
+        };
+        self.attributes.push(source_file);
+    }
+
+    fn visit_annotation(&mut self, descriptor: &str, visible: bool) -> Option<Box<dyn AnnotationVisitor + '_>> {
+        Some(Box::new(AnnotationWriter::new(descriptor, visible, &mut self.annotations)))
+    }
+
+    fn visit_attribute(&mut self, attribute: &Attribute) {
+        self.attributes.push(attribute.clone());
+    }
+
+    fn visit_field(
+        &mut self,
+        access_flags: AccessFlags,
+        name: &str,
+        descriptor: &str,
+        signature: Option<&str>,
+    ) -> Option<Box<dyn FieldVisitor + '_>> {
+        let mut field = Field {
+            access_flags,
+            name: self.utf8(name),
+            descriptor: self.utf8(descriptor),
+            attributes: SizedVec::new(),
+        };
+        if let Some(signature) = signature {
+            field.attributes.push(self.signature(signature));
+        }
+
+        Some(Box::new(FieldWriter {
+            writer: self,
+            field: Some(field),
+            annotations: Vec::new(),
+        }))
+    }
+
+    fn visit_method(
+        &mut self,
+        access_flags: AccessFlags,
+        name: &str,
+        descriptor: &str,
+        signature: Option<&str>,
+        exceptions: &[&str],
+    ) -> Option<Box<dyn MethodVisitor + '_>> {
+        let mut method = Method {
+            access_flags,
+            name: self.utf8(name),
+            descriptor: self.utf8(descriptor),
+            attributes: SizedVec::new(),
+        };
+        if let Some(signature) = signature {
+            method.attributes.push(self.signature(signature));
+        }
+        if !exceptions.is_empty() {
+            let exceptions = Attribute::Exceptions {
+                name: self.utf8("Exceptions"),
+                exception_index_table: exceptions
+                    .iter()
+                    .map(|exception| self.class(exception))
+                    .collect::<Vec<_>>()
+                    .into(),
+            };
+            method.attributes.push(exceptions);
+        }
+
+        Some(Box::new(MethodWriter {
+            writer: self,
+            method: Some(method),
+            annotations: Vec::new(),
+            code: None,
+        }))
+    }
+}
+
+/// Adds the field to the class on `visit_end`
+struct FieldWriter<'a, 'b> {
+    writer: &'a mut ClassWriter<'b>,
+    field: Option<Field>,
+    annotations: Vec<(bool, AnnotationInfo)>,
+}
+
+impl FieldVisitor for FieldWriter<'_, '_> {
+    fn visit_annotation(&mut self, descriptor: &str, visible: bool) -> Option<Box<dyn AnnotationVisitor + '_>> {
+        Some(Box::new(AnnotationWriter::new(descriptor, visible, &mut self.annotations)))
+    }
+
+    fn visit_attribute(&mut self, attribute: &Attribute) {
+        if let Some(field) = self.field.as_mut() {
+            field.attributes.push(attribute.clone());
+        }
+    }
+
+    fn visit_end(&mut self) {
+        if let Some(mut field) = self.field.take() {
+            let annotations = std::mem::take(&mut self.annotations);
+            field.attributes.extend(self.writer.annotation_attributes(annotations));
+            self.writer.fields.push(field);
+        }
+    }
+}
+
+#[derive(Default)]
+struct CodeWriter {
+    list: InstructionList,
+    max_stack: u16,
+    max_locals: u16,
+    attributes: Vec<Attribute>,
+}
+
+/// Adds the method to the class on `visit_end`, its code is lowered from the visited nodes
+struct MethodWriter<'a, 'b> {
+    writer: &'a mut ClassWriter<'b>,
+    method: Option<Method>,
+    annotations: Vec<(bool, AnnotationInfo)>,
+    code: Option<CodeWriter>,
+}
+
+impl MethodWriter<'_, '_> {
+    fn code(&mut self) -> &mut CodeWriter {
+        self.code.get_or_insert_with(CodeWriter::default)
+    }
+
+    fn lower(&mut self, code: CodeWriter, method: &Method) -> Result<Attribute, Error> {
+        let pre_java_1 = !self.writer.version.supports(45, 3);
+        let value = |value: u16| -> Result<Compatibility<u8, u16>, Error> {
+            match pre_java_1 {
+                true => u8::try_from(value).map(Compatibility::PreJava1).map_err(|_| Error::PreJava1MaxValue(value)),
+                false => Ok(Compatibility::Current(value)),
+            }
+        };
+
+        let mut attribute = Attribute::Code {
+            name: self.writer.utf8("Code"),
+            max_stack: value(code.max_stack)?,
+            max_locals: value(code.max_locals)?,
+            code: match pre_java_1 {
+                true => Compatibility::PreJava1(SizedVec::new()),
+                false => Compatibility::Current(SizedVec::new()),
+            },
+            exception_table: SizedVec::new(),
+            attributes: code
+                .attributes
+                .into_iter()
+                .filter(|attribute| !matches!(attribute, Attribute::StackMapTable { .. }))
+                .collect::<Vec<_>>()
+                .into(),
+        };
+        code.list.write_code(&mut attribute, &mut self.writer.constant_pool)?;
+
+        let options = self.writer.options;
+        let constant_pool = &self.writer.constant_pool;
+        let descriptor = constant_pool.resolve_utf8(method.descriptor)?.to_string();
+        if let Some(hierarchy) = options.compute_frames.filter(|_| self.writer.version.major >= 50) {
+            let class = constant_pool.resolve_class_name(self.writer.this_class)?.to_string();
+            let name = constant_pool.resolve_utf8(method.name)?.to_string();
+            compute_frames(
+                &mut attribute,
+                &class,
+                &name,
+                &descriptor,
+                method.access_flags,
+                &mut self.writer.constant_pool,
+                hierarchy,
+            )?;
+        }
+        if options.compute_max_values {
+            compute_max_values(&mut attribute, &descriptor, method.access_flags, &self.writer.constant_pool)?;
+        }
+        Ok(attribute)
+    }
+}
+
+impl MethodVisitor for MethodWriter<'_, '_> {
+    fn visit_annotation(&mut self, descriptor: &str, visible: bool) -> Option<Box<dyn AnnotationVisitor + '_>> {
+        Some(Box::new(AnnotationWriter::new(descriptor, visible, &mut self.annotations)))
+    }
+
+    fn visit_attribute(&mut self, attribute: &Attribute) {
+        if let Some(method) = self.method.as_mut() {
+            method.attributes.push(attribute.clone());
+        }
+    }
+
+    fn visit_code(&mut self) {
+        self.code();
+    }
+
+    fn visit_node(&mut self, node: &Node) {
+        self.code().list.nodes.push(node.clone());
+    }
+
+    fn visit_try_catch_block(&mut self, block: &TryCatchBlock) {
+        self.code().list.try_catch_blocks.push(*block);
+    }
+
+    fn visit_line_number(&mut self, line_number: &LineNumber) {
+        self.code().list.line_numbers.push(*line_number);
+    }
+
+    fn visit_local_variable(&mut self, variable: &LocalVariable) {
+        self.code().list.local_variables.push(*variable);
+    }
+
+    fn visit_local_variable_type(&mut self, variable: &LocalVariable) {
+        self.code().list.local_variable_types.push(*variable);
+    }
+
+    fn visit_code_type_annotation(&mut self, annotation: &CodeTypeAnnotation) {
+        self.code().list.type_annotations.push(annotation.clone());
+    }
+
+    fn visit_code_attribute(&mut self, attribute: &Attribute) {
+        self.code().attributes.push(attribute.clone());
+    }
+
+    fn visit_maxs(&mut self, max_stack: u16, max_locals: u16) {
+        let code = self.code();
+        code.max_stack = max_stack;
+        code.max_locals = max_locals;
+    }
+
+    fn visit_end(&mut self) {
+        let mut method = match self.method.take() {
+            Some(method) => method,
+            None => return,
+        };
+
+        let annotations = std::mem::take(&mut self.annotations);
+        method.attributes.extend(self.writer.annotation_attributes(annotations));
+        if let Some(code) = self.code.take() {
+            let code = self.lower(code, &method);
+            if let Some(code) = self.writer.record(code) {
+                method.attributes.push(code);
+            }
+        }
+        self.writer.methods.push(method);
+    }
+}
+
+/// Collects the element values and hands the annotation to its owner on `visit_end`
+struct AnnotationWriter<'a> {
+    annotation: Option<AnnotationInfo>,
+    visible: bool,
+    annotations: &'a mut Vec<(bool, AnnotationInfo)>,
+}
+
+impl<'a> AnnotationWriter<'a> {
+    fn new(descriptor: &str, visible: bool, annotations: &'a mut Vec<(bool, AnnotationInfo)>) -> Self {
+        AnnotationWriter {
+            annotation: Some(AnnotationInfo::new(descriptor)),
+            visible,
+            annotations,
+        }
+    }
+}
+
+impl AnnotationVisitor for AnnotationWriter<'_> {
+    fn visit(&mut self, name: &str, value: &AnnotationElement) {
+        if let Some(annotation) = self.annotation.as_mut() {
+            annotation.elements.push((name.to_string(), value.clone()));
+        }
+    }
+
+    fn visit_end(&mut self) {
+        if let Some(annotation) = self.annotation.take() {
+            self.annotations.push((self.visible, annotation));
+        }
+    }
+}
@@ -0,0 +1,24 @@
+import java.lang.annotation.ElementType;
+import java.lang.annotation.Retention;
+import java.lang.annotation.RetentionPolicy;
+import java.lang.annotation.Target;
+import java.util.ArrayList;
+import java.util.List;
+
+public class TypeAnnotated {
+    @Retention(RetentionPolicy.RUNTIME)
+    @Target(ElementType.TYPE_USE)
+    @interface A {
+        int value() default 0;
+    }
+
+    List<@A(1) String @A(2) []> field;
+
+    Object method(@A(3) String text, Object value) throws @A(4) IllegalStateException {
+        @A(5) List<String> local = new @A(6) ArrayList<>();
+        if (value instanceof @A(7) String) {
+            local.add((@A(8) String) value);
+        }
+        return local;
+    }
+}
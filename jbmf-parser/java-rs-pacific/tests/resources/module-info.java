@@ -0,0 +1,15 @@
+module com.example.app {
+    requires transitive java.logging;
+    requires static java.sql;
+    requires java.desktop;
+
+    exports com.example.app.api;
+    exports com.example.app.spi to java.logging, java.desktop;
+
+    opens com.example.app.internal to java.desktop;
+
+    uses com.example.app.spi.Plugin;
+    uses java.sql.Driver;
+
+    provides com.example.app.spi.Plugin with com.example.app.internal.DefaultPlugin, com.example.app.internal.OtherPlugin;
+}
@@ -0,0 +1,220 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Cursor, Read, Seek, Write};
+
+use java_rs_pacific::JavaClass;
+use jbmf_error::{Error, ParserError};
+use zip::result::ZipError;
+use zip::write::FileOptions;
+pub use zip::{CompressionMethod, DateTime};
+use zip::{ZipArchive, ZipWriter};
+
+pub const MANIFEST: &str = "META-INF/MANIFEST.MF";
+
+#[derive(Debug)]
+pub enum EntryContent {
+    Directory,
+    /// An entry whose name ends in `.class`
+    Class(Box<JavaClass>),
+    Manifest(Vec<u8>),
+    Resource(Vec<u8>),
+}
+
+#[derive(Debug)]
+pub struct Entry {
+    pub name: String,
+    pub last_modified: DateTime,
+    /// Entries which are not stored are deflated when they are written
+    pub compression: CompressionMethod,
+    pub unix_mode: Option<u32>,
+    pub content: EntryContent,
+}
+
+/// The entries of a JAR or ZIP file in the order of its central directory
+#[derive(Debug, Default)]
+pub struct Archive {
+    pub entries: Vec<Entry>,
+    pub comment: Vec<u8>,
+}
+
+impl Archive {
+    pub fn open(path: &str) -> Result<Self, Error> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn read<R: Read + Seek>(reader: R) -> Result<Self, Error> {
+        let mut zip = ZipArchive::new(reader).map_err(zip_error)?;
+        let mut entries = Vec::with_capacity(zip.len());
+
+        for index in 0..zip.len() {
+            let mut file = zip.by_index(index).map_err(zip_error)?;
+            let name = file.name().to_string();
+
+            let content = if file.is_dir() {
+                EntryContent::Directory
+            } else {
+                let mut data = Vec::new();
+                file.read_to_end(&mut data)?;
+
+                if name.eq_ignore_ascii_case(MANIFEST) {
+                    EntryContent::Manifest(data)
+                } else if name.ends_with(".class") {
+                    let class = JavaClass::read(&mut Cursor::new(data)).map_err(|source| ParserError::ArchiveEntry {
+                        name: name.clone(),
+                        source,
+                    })?;
+                    EntryContent::Class(Box::new(class))
+                } else {
+                    EntryContent::Resource(data)
+                }
+            };
+
+            entries.push(Entry {
+                last_modified: file.last_modified(),
+                compression: file.compression(),
+                unix_mode: file.unix_mode(),
+                name,
+                content,
+            });
+        }
+
+        Ok(Archive {
+            entries,
+            comment: zip.comment().to_vec(),
+        })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes the entries in their order, classes are serialized again
+    pub fn write<W: Write + Seek>(&self, writer: W) -> Result<(), Error> {
+        let mut zip = ZipWriter::new(writer);
+        zip.set_raw_comment(self.comment.clone());
+
+        for entry in self.entries.iter() {
+            let compression = match entry.compression {
+                CompressionMethod::Stored => CompressionMethod::Stored,
+                _ => CompressionMethod::Deflated,
+            };
+            let mut options = FileOptions::default()
+                .compression_method(compression)
+                .last_modified_time(entry.last_modified);
+            if let Some(mode) = entry.unix_mode {
+                options = options.unix_permissions(mode);
+            }
+
+            match &entry.content {
+                EntryContent::Directory => zip.add_directory(entry.name.as_str(), options).map_err(zip_error)?,
+                EntryContent::Class(class) => {
+                    let mut data = Vec::new();
+                    class.write(&mut data)?;
+                    zip.start_file(entry.name.as_str(), options).map_err(zip_error)?;
+                    zip.write_all(&data)?;
+                }
+                EntryContent::Manifest(data) | EntryContent::Resource(data) => {
+                    zip.start_file(entry.name.as_str(), options).map_err(zip_error)?;
+                    zip.write_all(data)?;
+                }
+            }
+        }
+
+        zip.finish().map_err(zip_error)?;
+        Ok(())
+    }
+
+    pub fn manifest(&self) -> Option<&[u8]> {
+        self.entries.iter().find_map(|entry| match &entry.content {
+            EntryContent::Manifest(data) => Some(data.as_slice()),
+            _ => None,
+        })
+    }
+
+    pub fn classes(&self) -> impl Iterator<Item = &JavaClass> {
+        self.entries.iter().filter_map(|entry| match &entry.content {
+            EntryContent::Class(class) => Some(class.as_ref()),
+            _ => None,
+        })
+    }
+
+    pub fn classes_mut(&mut self) -> impl Iterator<Item = &mut JavaClass> {
+        self.entries.iter_mut().filter_map(|entry| match &mut entry.content {
+            EntryContent::Class(class) => Some(class.as_mut()),
+            _ => None,
+        })
+    }
+}
+
+fn zip_error(error: ZipError) -> Error {
+    match error {
+        ZipError::Io(error) => error.into(),
+        error => ParserError::InvalidArchive(error.to_string()).into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use java_rs_pacific::{AccessFlags, Constant, ConstantPoolIndex, JavaVersion, MagicNumber, SizedVec};
+
+    use super::*;
+
+    fn entry(name: &str, compression: CompressionMethod, content: EntryContent) -> Entry {
+        Entry {
+            name: name.to_string(),
+            last_modified: DateTime::from_date_and_time(2020, 5, 17, 13, 45, 10).unwrap(),
+            compression,
+            unix_mode: None,
+            content,
+        }
+    }
+
+    #[test]
+    fn round_trip() -> Result<(), Error> {
+        let class = JavaClass {
+            magic: MagicNumber::Cafebabe,
+            version: JavaVersion { major: 52, minor: 0 },
+            constant_pool: vec![
+                Constant::Class(ConstantPoolIndex(3)),
+                Constant::Class(ConstantPoolIndex(4)),
+                Constant::Utf8("a/A".into()),
+                Constant::Utf8("java/lang/Object".into()),
+            ]
+            .into(),
+            access_flags: AccessFlags::NONE,
+            this_class: ConstantPoolIndex(1),
+            super_class: ConstantPoolIndex(2),
+            interfaces: SizedVec::new(),
+            fields: SizedVec::new(),
+            methods: SizedVec::new(),
+            attributes: SizedVec::new(),
+        };
+        let archive = Archive {
+            entries: vec![
+                entry(MANIFEST, CompressionMethod::Deflated, EntryContent::Manifest(b"Manifest-Version: 1.0\r\n".to_vec())),
+                entry("a/", CompressionMethod::Stored, EntryContent::Directory),
+                entry("a/A.class", CompressionMethod::Deflated, EntryContent::Class(Box::new(class))),
+                entry("a/data.bin", CompressionMethod::Stored, EntryContent::Resource(vec![0, 1, 2])),
+            ],
+            comment: b"comment".to_vec(),
+        };
+
+        let mut buffer = Cursor::new(Vec::new());
+        archive.write(&mut buffer)?;
+        let read = Archive::read(Cursor::new(buffer.into_inner()))?;
+
+        assert_eq!(read.comment, archive.comment);
+        assert_eq!(read.manifest(), archive.manifest());
+        assert_eq!(read.classes().collect::<Vec<_>>(), archive.classes().collect::<Vec<_>>());
+        assert_eq!(read.entries.len(), archive.entries.len());
+        for (read, written) in read.entries.iter().zip(archive.entries.iter()) {
+            assert_eq!(read.name, written.name);
+            assert_eq!(read.compression, written.compression);
+            assert_eq!(read.last_modified.datepart(), written.last_modified.datepart());
+            assert_eq!(read.last_modified.timepart(), written.last_modified.timepart());
+        }
+        Ok(())
+    }
+}
@@ -2,11 +2,26 @@ pub use java_rs_base;
 pub use java_rs_derive;
 pub use java_rs_pacific;
 
+use archive::Archive;
 use java_rs_pacific::JavaClass;
+use jbmf_error::Error;
+use recovery::{recover, Recovery};
 use std::fs::File;
 use std::io::BufReader;
 
-pub fn parse_class_file(path: &str) -> anyhow::Result<JavaClass> {
-    let mut reader = BufReader::new(File::open(path).unwrap());
+pub mod archive;
+pub mod recovery;
+
+pub fn parse_class_file(path: &str) -> Result<JavaClass, Error> {
+    let mut reader = BufReader::new(File::open(path)?);
     Ok(JavaClass::read(&mut reader)?)
 }
+
+pub fn parse_archive(path: &str) -> Result<Archive, Error> {
+    Archive::open(path)
+}
+
+/// Reads an archive in recovery mode, see [`recovery::recover`]
+pub fn recover_archive(path: &str) -> Result<Recovery, Error> {
+    recover(&std::fs::read(path)?)
+}
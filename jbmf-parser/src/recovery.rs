@@ -0,0 +1,557 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+
+use byteorder::{ByteOrder, LittleEndian};
+use flate2::read::DeflateDecoder;
+use java_rs_pacific::JavaClass;
+use jbmf_error::{Error, ParserError};
+
+use crate::archive::{Archive, CompressionMethod, DateTime, Entry, EntryContent, MANIFEST};
+
+const END_SIGNATURE: u32 = 0x0605_4B50;
+const END_LENGTH: usize = 22;
+const CENTRAL_SIGNATURE: u32 = 0x0201_4B50;
+const CENTRAL_LENGTH: usize = 46;
+const LOCAL_SIGNATURE: u32 = 0x0403_4B50;
+const LOCAL_LENGTH: usize = 30;
+const DATA_DESCRIPTOR_FLAG: u16 = 1 << 3;
+
+/// A deviation from the ZIP format found while recovering an archive
+#[derive(Debug, Clone, Eq, PartialEq, thiserror::Error)]
+pub enum Anomaly {
+    #[error("{0} end of central directory records found, using the one at {1}")]
+    MultipleEnds(usize, u64),
+
+    #[error("{0} bytes precede the first entry")]
+    PrefixedData(u64),
+
+    #[error("Central directory declares {declared} entries, but contains {found}")]
+    EntryCount { declared: u64, found: u64 },
+
+    #[error("Central directory ends prematurely after {0} entries")]
+    TruncatedCentralDirectory(u64),
+
+    #[error("{0} is a ZIP64 entry, which is not supported")]
+    Zip64(String),
+
+    #[error("{0} appears more than once, the last occurrence is used")]
+    DuplicateEntry(String),
+
+    #[error("Local header of {0} is missing or truncated")]
+    InvalidLocalHeader(String),
+
+    #[error("Local header of {name} has {field} {local}, but the central directory has {central}")]
+    LocalHeaderMismatch {
+        name: String,
+        field: &'static str,
+        local: String,
+        central: String,
+    },
+
+    #[error("{0} uses compression method {1}, which is not supported")]
+    UnsupportedCompression(String, u16),
+
+    #[error("Data of {0} is corrupt: {1}")]
+    CorruptData(String, String),
+
+    #[error("{name} has CRC {found:08X}, but {expected:08X} is declared")]
+    CrcMismatch { name: String, expected: u32, found: u32 },
+
+    #[error("{name} has {found} bytes, but {expected} are declared")]
+    SizeMismatch { name: String, expected: u64, found: u64 },
+
+    #[error("Directory {0} has content and is read as a class")]
+    ClassDirectory(String),
+
+    #[error("{0} is not a valid class: {1}")]
+    InvalidClass(String, String),
+
+    #[error("{0} has {1} bytes after the end of the class")]
+    TrailingData(String, u64),
+}
+
+/// An archive recovered from a possibly malformed ZIP file, with every anomaly that was found
+#[derive(Debug)]
+pub struct Recovery {
+    pub archive: Archive,
+    pub anomalies: Vec<Anomaly>,
+}
+
+struct CentralEntry {
+    name: String,
+    flags: u16,
+    method: u16,
+    time: u16,
+    date: u16,
+    crc: u32,
+    compressed_size: u64,
+    size: u64,
+    local_offset: u64,
+    unix_mode: Option<u32>,
+}
+
+/// Reads an archive like the JVM does: the central directory is found from the end of the file, local
+/// headers are only used to locate the entry data, and later duplicates shadow earlier ones. Entries which
+/// can not be read are reported and skipped, classes which can not be parsed are kept as resources
+pub fn recover(data: &[u8]) -> Result<Recovery, Error> {
+    let mut anomalies = Vec::new();
+
+    let ends = find_ends(data);
+    let end = match ends.first() {
+        Some(end) => *end,
+        None => return Err(invalid("No end of central directory record found".to_string())),
+    };
+    if ends.len() > 1 {
+        anomalies.push(Anomaly::MultipleEnds(ends.len(), end as u64));
+    }
+
+    let declared = LittleEndian::read_u16(&data[end + 10..]) as u64;
+    let central_length = LittleEndian::read_u32(&data[end + 12..]) as usize;
+    let central_offset = LittleEndian::read_u32(&data[end + 16..]) as usize;
+    let comment_length = LittleEndian::read_u16(&data[end + 20..]) as usize;
+    let comment = data[end + END_LENGTH..(end + END_LENGTH + comment_length).min(data.len())].to_vec();
+
+    // The JVM locates the central directory relative to its end and treats anything before the first
+    // entry as a prefix, like the stub of a self extracting archive
+    let central_start = match end.checked_sub(central_length) {
+        Some(start) => start,
+        None => {
+            return Err(invalid(format!(
+                "Central directory of {} bytes does not fit before its end",
+                central_length
+            )))
+        }
+    };
+    let base = match central_start.checked_sub(central_offset) {
+        Some(base) => base,
+        None => {
+            return Err(invalid(format!(
+                "Central directory offset {} is past its position",
+                central_offset
+            )))
+        }
+    };
+    if base > 0 {
+        anomalies.push(Anomaly::PrefixedData(base as u64));
+    }
+
+    let central = read_central_directory(&data[central_start..end], &mut anomalies);
+    if central.len() as u64 != declared {
+        anomalies.push(Anomaly::EntryCount {
+            declared,
+            found: central.len() as u64,
+        });
+    }
+
+    let mut last = HashMap::new();
+    for (index, entry) in central.iter().enumerate() {
+        if last.insert(entry.name.as_str(), index).is_some() {
+            anomalies.push(Anomaly::DuplicateEntry(entry.name.clone()));
+        }
+    }
+
+    let mut entries = Vec::with_capacity(last.len());
+    for (index, entry) in central.iter().enumerate() {
+        if last[entry.name.as_str()] != index {
+            continue;
+        }
+
+        let content = match read_data(data, base, entry, &mut anomalies) {
+            Some(content) => content,
+            None => continue,
+        };
+
+        let mut name = entry.name.clone();
+        let content = if name.ends_with('/') {
+            // Looking up a missing class falls back to the directory with the same name
+            let file = &name[..name.len() - 1];
+            if content.is_empty() || !file.ends_with(".class") || last.contains_key(file) {
+                EntryContent::Directory
+            } else {
+                anomalies.push(Anomaly::ClassDirectory(name.clone()));
+                name = file.to_string();
+                classify(&name, content, &mut anomalies)
+            }
+        } else {
+            classify(&name, content, &mut anomalies)
+        };
+
+        entries.push(Entry {
+            name,
+            last_modified: DateTime::from_msdos(entry.date, entry.time),
+            compression: match entry.method {
+                0 => CompressionMethod::Stored,
+                _ => CompressionMethod::Deflated,
+            },
+            unix_mode: entry.unix_mode,
+            content,
+        });
+    }
+
+    Ok(Recovery {
+        archive: Archive { entries, comment },
+        anomalies,
+    })
+}
+
+fn invalid(reason: String) -> Error {
+    ParserError::InvalidArchive(reason).into()
+}
+
+/// Start positions of every end of central directory record, the one whose comment reaches the end of
+/// the file exactly comes first
+fn find_ends(data: &[u8]) -> Vec<usize> {
+    if data.len() < END_LENGTH {
+        return Vec::new();
+    }
+
+    let lowest = data.len().saturating_sub(END_LENGTH + u16::MAX as usize);
+    let mut ends: Vec<usize> = (lowest..=data.len() - END_LENGTH)
+        .rev()
+        .filter(|position| LittleEndian::read_u32(&data[*position..]) == END_SIGNATURE)
+        .collect();
+
+    let exact = ends.iter().position(|position| {
+        position + END_LENGTH + LittleEndian::read_u16(&data[position + 20..]) as usize == data.len()
+    });
+    if let Some(exact) = exact {
+        let position = ends.remove(exact);
+        ends.insert(0, position);
+    }
+    ends
+}
+
+fn read_central_directory(data: &[u8], anomalies: &mut Vec<Anomaly>) -> Vec<CentralEntry> {
+    let mut entries = Vec::new();
+    let mut position = 0;
+
+    while position < data.len() {
+        let header = &data[position..];
+        if header.len() < CENTRAL_LENGTH || LittleEndian::read_u32(header) != CENTRAL_SIGNATURE {
+            anomalies.push(Anomaly::TruncatedCentralDirectory(entries.len() as u64));
+            break;
+        }
+
+        let name_length = LittleEndian::read_u16(&header[28..]) as usize;
+        let extra_length = LittleEndian::read_u16(&header[30..]) as usize;
+        let comment_length = LittleEndian::read_u16(&header[32..]) as usize;
+        let length = CENTRAL_LENGTH + name_length + extra_length + comment_length;
+        if header.len() < length {
+            anomalies.push(Anomaly::TruncatedCentralDirectory(entries.len() as u64));
+            break;
+        }
+
+        let made_by = LittleEndian::read_u16(&header[4..]);
+        let external = LittleEndian::read_u32(&header[38..]);
+        let entry = CentralEntry {
+            name: String::from_utf8_lossy(&header[CENTRAL_LENGTH..CENTRAL_LENGTH + name_length]).into_owned(),
+            flags: LittleEndian::read_u16(&header[8..]),
+            method: LittleEndian::read_u16(&header[10..]),
+            time: LittleEndian::read_u16(&header[12..]),
+            date: LittleEndian::read_u16(&header[14..]),
+            crc: LittleEndian::read_u32(&header[16..]),
+            compressed_size: LittleEndian::read_u32(&header[20..]) as u64,
+            size: LittleEndian::read_u32(&header[24..]) as u64,
+            local_offset: LittleEndian::read_u32(&header[42..]) as u64,
+            unix_mode: match made_by >> 8 {
+                3 if external >> 16 != 0 => Some(external >> 16),
+                _ => None,
+            },
+        };
+
+        if [entry.compressed_size, entry.size, entry.local_offset].contains(&(u32::MAX as u64)) {
+            anomalies.push(Anomaly::Zip64(entry.name.clone()));
+        } else {
+            entries.push(entry);
+        }
+        position += length;
+    }
+
+    entries
+}
+
+/// The uncompressed data of an entry, `None` if it can not be read at all
+fn read_data(data: &[u8], base: usize, entry: &CentralEntry, anomalies: &mut Vec<Anomaly>) -> Option<Vec<u8>> {
+    let name = &entry.name;
+
+    let local = base as u64 + entry.local_offset;
+    let header = match data.get(local as usize..local as usize + LOCAL_LENGTH) {
+        Some(header) if LittleEndian::read_u32(header) == LOCAL_SIGNATURE => header,
+        _ => {
+            anomalies.push(Anomaly::InvalidLocalHeader(name.clone()));
+            return None;
+        }
+    };
+
+    let name_length = LittleEndian::read_u16(&header[26..]) as usize;
+    let extra_length = LittleEndian::read_u16(&header[28..]) as usize;
+    let start = local as usize + LOCAL_LENGTH;
+
+    let mut mismatch = |field: &'static str, local: String, central: String| {
+        if local != central {
+            anomalies.push(Anomaly::LocalHeaderMismatch {
+                name: name.clone(),
+                field,
+                local,
+                central,
+            });
+        }
+    };
+    let local_name = data
+        .get(start..start + name_length)
+        .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+        .unwrap_or_default();
+    mismatch("name", local_name, name.clone());
+    mismatch("compression method", LittleEndian::read_u16(&header[8..]).to_string(), entry.method.to_string());
+    if entry.flags & DATA_DESCRIPTOR_FLAG == 0 {
+        mismatch("CRC", format!("{:08X}", LittleEndian::read_u32(&header[14..])), format!("{:08X}", entry.crc));
+        mismatch(
+            "compressed size",
+            LittleEndian::read_u32(&header[18..]).to_string(),
+            entry.compressed_size.to_string(),
+        );
+        mismatch("size", LittleEndian::read_u32(&header[22..]).to_string(), entry.size.to_string());
+    }
+
+    let data_start = start + name_length + extra_length;
+    let data_end = data_start as u64 + entry.compressed_size;
+    let compressed = match data.get(data_start..data_end as usize) {
+        Some(compressed) => compressed,
+        None => {
+            anomalies.push(Anomaly::CorruptData(
+                name.clone(),
+                format!("data ends at {}, after the end of the file", data_end),
+            ));
+            return None;
+        }
+    };
+
+    let content = match entry.method {
+        0 => compressed.to_vec(),
+        8 => {
+            let mut content = Vec::new();
+            if let Err(error) = DeflateDecoder::new(compressed).read_to_end(&mut content) {
+                anomalies.push(Anomaly::CorruptData(name.clone(), error.to_string()));
+                return None;
+            }
+            content
+        }
+        method => {
+            anomalies.push(Anomaly::UnsupportedCompression(name.clone(), method));
+            return None;
+        }
+    };
+
+    if content.len() as u64 != entry.size {
+        anomalies.push(Anomaly::SizeMismatch {
+            name: name.clone(),
+            expected: entry.size,
+            found: content.len() as u64,
+        });
+    }
+    let crc = crc32fast::hash(&content);
+    if crc != entry.crc {
+        anomalies.push(Anomaly::CrcMismatch {
+            name: name.clone(),
+            expected: entry.crc,
+            found: crc,
+        });
+    }
+
+    Some(content)
+}
+
+fn classify(name: &str, data: Vec<u8>, anomalies: &mut Vec<Anomaly>) -> EntryContent {
+    if name.eq_ignore_ascii_case(MANIFEST) {
+        return EntryContent::Manifest(data);
+    }
+    if !name.ends_with(".class") {
+        return EntryContent::Resource(data);
+    }
+
+    let mut reader = Cursor::new(data.as_slice());
+    match JavaClass::read(&mut reader) {
+        Ok(class) => {
+            let trailing = data.len() as u64 - reader.position();
+            if trailing > 0 {
+                anomalies.push(Anomaly::TrailingData(name.to_string(), trailing));
+            }
+            EntryContent::Class(Box::new(class))
+        }
+        Err(error) => {
+            anomalies.push(Anomaly::InvalidClass(name.to_string(), error.to_string()));
+            EntryContent::Resource(data)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::WriteBytesExt;
+    use java_rs_pacific::attribute::Attribute;
+
+    use super::*;
+
+    const CLASS: &[u8] = &[
+        0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 52, 0, 5, 7, 0, 3, 7, 0, 4, 1, 0, 1, b'A', 1, 0, 16, b'j', b'a', b'v', b'a',
+        b'/', b'l', b'a', b'n', b'g', b'/', b'O', b'b', b'j', b'e', b'c', b't', 0, 0, 0, 1, 0, 2, 0, 0, 0, 0, 0, 0, 0,
+        0,
+    ];
+
+    /// A stored entry with the given local name, central name and declared CRC
+    struct Stored<'a> {
+        local_name: &'a str,
+        name: &'a str,
+        crc: u32,
+        data: &'a [u8],
+    }
+
+    fn zip(entries: &[Stored]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut central = Vec::new();
+
+        for entry in entries {
+            let offset = data.len() as u32;
+            data.write_u32::<LittleEndian>(LOCAL_SIGNATURE).unwrap();
+            for value in [20u16, 0, 0, 0, 0] {
+                data.write_u16::<LittleEndian>(value).unwrap();
+            }
+            for value in [entry.crc, entry.data.len() as u32, entry.data.len() as u32] {
+                data.write_u32::<LittleEndian>(value).unwrap();
+            }
+            data.write_u16::<LittleEndian>(entry.local_name.len() as u16).unwrap();
+            data.write_u16::<LittleEndian>(0).unwrap();
+            data.extend_from_slice(entry.local_name.as_bytes());
+            data.extend_from_slice(entry.data);
+
+            central.write_u32::<LittleEndian>(CENTRAL_SIGNATURE).unwrap();
+            for value in [20u16, 20, 0, 0, 0, 0] {
+                central.write_u16::<LittleEndian>(value).unwrap();
+            }
+            for value in [entry.crc, entry.data.len() as u32, entry.data.len() as u32] {
+                central.write_u32::<LittleEndian>(value).unwrap();
+            }
+            for value in [entry.name.len() as u16, 0, 0, 0, 0] {
+                central.write_u16::<LittleEndian>(value).unwrap();
+            }
+            central.write_u32::<LittleEndian>(0).unwrap();
+            central.write_u32::<LittleEndian>(offset).unwrap();
+            central.extend_from_slice(entry.name.as_bytes());
+        }
+
+        let central_offset = data.len() as u32;
+        data.extend_from_slice(&central);
+        data.write_u32::<LittleEndian>(END_SIGNATURE).unwrap();
+        for value in [0u16, 0, entries.len() as u16, entries.len() as u16] {
+            data.write_u16::<LittleEndian>(value).unwrap();
+        }
+        data.write_u32::<LittleEndian>(central.len() as u32).unwrap();
+        data.write_u32::<LittleEndian>(central_offset).unwrap();
+        data.write_u16::<LittleEndian>(0).unwrap();
+        data
+    }
+
+    #[test]
+    fn recovers_mangled_archive() -> Result<(), Error> {
+        let mut trailing = CLASS.to_vec();
+        trailing.extend_from_slice(b"garbage");
+
+        let data = zip(&[
+            Stored {
+                local_name: "A.class",
+                name: "A.class",
+                crc: 0,
+                data: b"not a class",
+            },
+            Stored {
+                local_name: "fake",
+                name: "A.class",
+                crc: crc32fast::hash(CLASS),
+                data: CLASS,
+            },
+            Stored {
+                local_name: "B.class/",
+                name: "B.class/",
+                crc: crc32fast::hash(&trailing),
+                data: &trailing,
+            },
+        ]);
+
+        let recovery = recover(&data)?;
+
+        assert_eq!(
+            recovery.anomalies,
+            vec![
+                Anomaly::DuplicateEntry("A.class".to_string()),
+                Anomaly::LocalHeaderMismatch {
+                    name: "A.class".to_string(),
+                    field: "name",
+                    local: "fake".to_string(),
+                    central: "A.class".to_string(),
+                },
+                Anomaly::ClassDirectory("B.class/".to_string()),
+                Anomaly::TrailingData("B.class".to_string(), 7),
+            ]
+        );
+        let names: Vec<_> = recovery.archive.entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, vec!["A.class", "B.class"]);
+        assert_eq!(recovery.archive.classes().count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn recovers_class_with_attribute_name_index_0() -> Result<(), Error> {
+        let mut poisoned = CLASS[..CLASS.len() - 2].to_vec();
+        poisoned.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 1, 42]);
+
+        let data = zip(&[
+            Stored {
+                local_name: "A.class",
+                name: "A.class",
+                crc: crc32fast::hash(&poisoned),
+                data: &poisoned,
+            },
+            Stored {
+                local_name: "B.class",
+                name: "B.class",
+                crc: crc32fast::hash(CLASS),
+                data: CLASS,
+            },
+        ]);
+
+        let recovery = recover(&data)?;
+
+        assert_eq!(recovery.anomalies, Vec::new());
+        assert_eq!(recovery.archive.classes().count(), 2);
+        match &recovery.archive.entries[0].content {
+            EntryContent::Class(class) => {
+                assert!(matches!(class.attributes[0], Attribute::IllegalNameReference(_)))
+            }
+            content => panic!("Expected a class, got {:?}", content),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reports_bad_crc() -> Result<(), Error> {
+        let data = zip(&[Stored {
+            local_name: "data.bin",
+            name: "data.bin",
+            crc: 1,
+            data: &[1, 2, 3],
+        }]);
+
+        let recovery = recover(&data)?;
+
+        assert_eq!(
+            recovery.anomalies,
+            vec![Anomaly::CrcMismatch {
+                name: "data.bin".to_string(),
+                expected: 1,
+                found: crc32fast::hash(&[1, 2, 3]),
+            }]
+        );
+        assert!(matches!(recovery.archive.entries[0].content, EntryContent::Resource(_)));
+        Ok(())
+    }
+}